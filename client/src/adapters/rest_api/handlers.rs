@@ -2,4 +2,6 @@ pub mod block;
 pub mod keys;
 pub mod mint;
 pub mod preimage;
+pub mod swap;
 pub mod transfer;
+pub mod withdraw;
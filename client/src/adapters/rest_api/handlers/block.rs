@@ -1,16 +1,48 @@
 use common::structs::Block;
 
-use axum::{extract::State, http::StatusCode, Json};
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use curves::{pallas::PallasConfig, vesta::VestaConfig};
 
 use crate::adapters::rest_api::rest_api_entry::AppState;
 use crate::domain::Fr;
-use crate::ports::storage::{PreimageDB, TreeDB};
+use crate::usecase::{
+    self,
+    sync_blocks::{BlockError, BlockIngestion},
+};
 
-#[tracing::instrument(name = "New Block", skip(db, block))]
-pub async fn handle_block(State(db): State<AppState>, Json(block): Json<Block<Fr>>) -> StatusCode {
-    let mut db = db.state_db.lock().await;
-    db.update_preimages(block.clone());
+impl IntoResponse for BlockError {
+    fn into_response(self) -> Response {
+        let status = match self {
+            BlockError::InconsistentCommitmentRoot(_) | BlockError::InconsistentHeader(_) => {
+                StatusCode::UNPROCESSABLE_ENTITY
+            }
+            BlockError::ConflictingBlock(_) | BlockError::UnknownParent(_) => StatusCode::CONFLICT,
+            BlockError::PendingBlocksFull(_) => StatusCode::SERVICE_UNAVAILABLE,
+            BlockError::StorageError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (status, Json(self)).into_response()
+    }
+}
 
-    db.add_block_leaves(block.commitments, block.block_number);
-    StatusCode::CREATED
+#[tracing::instrument(name = "New Block", skip(db, block))]
+pub async fn handle_block(
+    State(db): State<AppState>,
+    Json(block): Json<Block<Fr>>,
+) -> Result<StatusCode, BlockError> {
+    let ingestion = usecase::sync_blocks::ingest_block_process::<PallasConfig, VestaConfig, _, _>(
+        db.state_db.clone(),
+        db.pending_blocks.clone(),
+        block,
+    )
+    .await?;
+    Ok(match ingestion {
+        BlockIngestion::Applied => StatusCode::CREATED,
+        BlockIngestion::Replayed => StatusCode::OK,
+        BlockIngestion::Queued => StatusCode::ACCEPTED,
+    })
 }
@@ -1,12 +1,17 @@
-use axum::{extract::State, Json};
-use curves::pallas::PallasConfig;
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use curves::pallas::{Fq, PallasConfig};
 
-use crate::domain::StoredPreimageInfoVector;
+use crate::domain::{Balance, PreimageFilter, StoredPreimageInfoVector};
 use crate::ports::committable::Committable;
 use crate::ports::storage::PreimageDB;
 
 use crate::adapters::rest_api::rest_api_entry::{AppError, AppState};
-use crate::adapters::rest_api::structs::PreimageResponse;
+use crate::adapters::rest_api::structs::{
+    BalanceQuery, CommitmentPath, NotesQuery, PreimageResponse,
+};
 
 #[tracing::instrument(name = "Requesting Preimages", skip(db))]
 pub async fn get_preimages(
@@ -14,18 +19,57 @@ pub async fn get_preimages(
 ) -> Result<Json<Vec<PreimageResponse<PallasConfig>>>, AppError> {
     let db_locked = db.state_db.lock().await;
     let preimages: StoredPreimageInfoVector<PallasConfig> = db_locked.get_all_preimages();
+    Ok(Json(to_preimage_responses(preimages)?))
+}
+
+#[tracing::instrument(name = "Requesting Balance", skip(db, query))]
+pub async fn get_balance(
+    State(db): State<AppState>,
+    Query(query): Query<BalanceQuery<PallasConfig>>,
+) -> Result<Json<Balance<Fq>>, AppError> {
+    let db_locked = db.state_db.lock().await;
+    Ok(Json(db_locked.get_balance(query.owner, query.token_id)))
+}
+
+#[tracing::instrument(name = "Requesting Notes", skip(db, query))]
+pub async fn get_notes(
+    State(db): State<AppState>,
+    Query(query): Query<NotesQuery<PallasConfig>>,
+) -> Result<Json<Vec<PreimageResponse<PallasConfig>>>, AppError> {
+    let db_locked = db.state_db.lock().await;
+    let preimages = db_locked.query_preimages(&PreimageFilter::from(query));
+    Ok(Json(to_preimage_responses(preimages)?))
+}
+
+#[tracing::instrument(name = "Requesting Preimage", skip(db, path))]
+pub async fn get_preimage(
+    State(db): State<AppState>,
+    Path(path): Path<CommitmentPath<PallasConfig>>,
+) -> Result<Json<PreimageResponse<PallasConfig>>, AppError> {
+    let db_locked = db.state_db.lock().await;
+    let stored_preimage = db_locked
+        .get_preimage(path.commitment)
+        .ok_or(AppError::NotFound)?;
+    Ok(Json(PreimageResponse {
+        stored_preimage,
+        commitment_hash: path.commitment,
+    }))
+}
+
+fn to_preimage_responses(
+    preimages: StoredPreimageInfoVector<PallasConfig>,
+) -> Result<Vec<PreimageResponse<PallasConfig>>, AppError> {
     let keys = preimages
         .iter()
         .map(|x| x.preimage.commitment_hash())
         .collect::<Result<Vec<_>, _>>()
         .map_err(|_| AppError::TxError)?;
-    let preimage_response = preimages
+    Ok(preimages
         .into_iter()
         .zip(keys)
         .map(|(x, y)| PreimageResponse {
             stored_preimage: x,
             commitment_hash: y.0,
         })
-        .collect::<Vec<_>>();
-    Ok(Json(preimage_response))
+        .collect::<Vec<_>>())
 }
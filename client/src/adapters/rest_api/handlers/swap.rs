@@ -0,0 +1,19 @@
+use crate::adapters::rest_api::rest_api_entry::{AppError, AppState};
+use crate::adapters::rest_api::structs::SwapInput;
+use crate::usecase;
+use axum::{extract::State, Json};
+use common::structs::Transaction;
+use curves::{pallas::PallasConfig, vesta::VestaConfig};
+
+#[tracing::instrument(name = "Creating new swap transaction", skip(db, swap_details))]
+pub async fn create_swap(
+    State(db): State<AppState>,
+    Json(swap_details): Json<SwapInput<PallasConfig>>,
+) -> Result<Json<Transaction<VestaConfig>>, AppError> {
+    let transaction =
+        usecase::swap::swap_process(db.state_db, db.prover, db.notifier, swap_details)
+            .await
+            .map_err(|_| AppError::TxError)?;
+
+    Ok(Json(transaction))
+}
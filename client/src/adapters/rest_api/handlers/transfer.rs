@@ -4,16 +4,24 @@ use crate::usecase;
 use axum::{extract::State, Json};
 use common::structs::Transaction;
 use curves::{pallas::PallasConfig, vesta::VestaConfig};
+use tracing_log::log;
 
 #[tracing::instrument(name = "Creating new transfer transaction", skip(db, transfer_details))]
 pub async fn create_transfer(
     State(db): State<AppState>,
     Json(transfer_details): Json<TransferInput<PallasConfig>>,
-) -> Result<Json<Transaction<VestaConfig>>, AppError> {
-    let transaction =
+) -> Result<Json<Vec<Transaction<VestaConfig>>>, AppError> {
+    let transactions =
         usecase::transfer::transfer_process(db.state_db, db.prover, db.notifier, transfer_details)
             .await
-            .map_err(|_| AppError::TxError)?;
+            .map_err(|e| {
+                log::error!("{}", e);
+                if e.sent.is_empty() {
+                    AppError::TxError
+                } else {
+                    AppError::PartialTransfer(e.sent)
+                }
+            })?;
 
-    Ok(Json(transaction))
+    Ok(Json(transactions))
 }
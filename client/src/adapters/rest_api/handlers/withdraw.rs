@@ -0,0 +1,19 @@
+use crate::adapters::rest_api::rest_api_entry::{AppError, AppState};
+use crate::adapters::rest_api::structs::WithdrawInput;
+use crate::usecase;
+use axum::{extract::State, Json};
+use common::structs::Transaction;
+use curves::{pallas::PallasConfig, vesta::VestaConfig};
+
+#[tracing::instrument(name = "Creating new withdraw transaction", skip(db, withdraw_details))]
+pub async fn create_withdraw(
+    State(db): State<AppState>,
+    Json(withdraw_details): Json<WithdrawInput<PallasConfig>>,
+) -> Result<Json<Transaction<VestaConfig>>, AppError> {
+    let transaction =
+        usecase::withdraw::withdraw_process(db.state_db, db.prover, db.notifier, withdraw_details)
+            .await
+            .map_err(|_| AppError::TxError)?;
+
+    Ok(Json(transaction))
+}
@@ -5,8 +5,10 @@ pub mod rest_api_entry {
     use super::handlers::block::handle_block;
     use super::handlers::keys::create_keys;
     use super::handlers::mint::create_mint;
-    use super::handlers::preimage::get_preimages;
+    use super::handlers::preimage::{get_balance, get_notes, get_preimage, get_preimages};
+    use super::handlers::swap::create_swap;
     use super::handlers::transfer::create_transfer;
+    use super::handlers::withdraw::create_withdraw;
     use anyhow::anyhow;
     use axum::{
         http::StatusCode,
@@ -23,34 +25,45 @@ pub mod rest_api_entry {
     use tokio::sync::Mutex;
     use tracing_log::log;
 
+    use crate::domain::{Fr, PendingBlocks};
     use crate::services::{
-        prover::in_memory_prover::InMemProver, storage::in_mem_storage::InMemStorage,
+        prover::in_memory_prover::InMemProver, storage::client_storage::ClientStorage,
     };
     use common::configuration::ApplicationSettings;
 
     pub enum AppError {
         TxError,
+        NotFound,
+        // Transactions of a chained transfer sent before one of them failed
+        PartialTransfer(Vec<Transaction<VestaConfig>>),
     }
     impl IntoResponse for AppError {
         fn into_response(self) -> axum::response::Response {
-            let (status, error_msg) = match self {
-                AppError::TxError => (StatusCode::INTERNAL_SERVER_ERROR, "Internal Error"),
+            let (status, body) = match self {
+                AppError::TxError => (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    json!({ "error": "Internal Error" }),
+                ),
+                AppError::NotFound => (StatusCode::NOT_FOUND, json!({ "error": "Not Found" })),
+                AppError::PartialTransfer(transactions) => (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    json!({ "error": "Partial Transfer", "transactions": transactions }),
+                ),
             };
-            let body = Json(json!({
-                "error": error_msg,
-            }));
-            (status, body).into_response()
+            (status, Json(body)).into_response()
         }
     }
 
     // type WriteDatabase = Arc<Mutex<dyn PreimageDB<E = PallasConfig> + Send + Sync>>;
-    type WriteDatabase = Arc<Mutex<InMemStorage<PallasConfig, curves::pallas::Fq>>>;
+    type WriteDatabase = Arc<Mutex<ClientStorage<PallasConfig, curves::pallas::Fq>>>;
 
     #[derive(Clone)]
     pub struct AppState {
         pub state_db: WriteDatabase,
         pub prover: Arc<Mutex<InMemProver<PallasConfig, VestaConfig, VestaConfig>>>,
         pub notifier: Arc<Mutex<HttpNotifier<Transaction<VestaConfig>>>>,
+        // Pushed blocks waiting for the blocks before them
+        pub pending_blocks: Arc<Mutex<PendingBlocks<Fr>>>,
     }
 
     pub struct Application {
@@ -65,10 +78,12 @@ pub mod rest_api_entry {
     }
 
     impl Application {
+        // `pending_blocks` is shared with the block sync, see `run_block_sync`
         pub async fn build(
             db: WriteDatabase,
             prover: Arc<Mutex<InMemProver<PallasConfig, VestaConfig, VestaConfig>>>,
             notifier: Arc<Mutex<HttpNotifier<Transaction<VestaConfig>>>>,
+            pending_blocks: Arc<Mutex<PendingBlocks<Fr>>>,
             configuration: ApplicationSettings,
         ) -> Result<Application, anyhow::Error> {
             let address = format!("{}:{}", configuration.host, configuration.port);
@@ -77,8 +92,14 @@ pub mod rest_api_entry {
                 .map_err(|_| anyhow!("Unable to start application"))?;
             let port = listener.local_addr().unwrap().port();
 
-            let server: axum::serve::Serve<Router, Router> =
-                run_api(listener, db.clone(), prover.clone(), notifier.clone()).await;
+            let server: axum::serve::Serve<Router, Router> = run_api(
+                listener,
+                db.clone(),
+                prover.clone(),
+                notifier.clone(),
+                pending_blocks,
+            )
+            .await;
             log::trace!("Launching server at {}:{}", configuration.host, port);
 
             Ok(Application {
@@ -105,19 +126,26 @@ pub mod rest_api_entry {
         db_state: WriteDatabase,
         prover: Arc<Mutex<InMemProver<PallasConfig, VestaConfig, VestaConfig>>>,
         notifier: Arc<Mutex<HttpNotifier<Transaction<VestaConfig>>>>,
+        pending_blocks: Arc<Mutex<PendingBlocks<Fr>>>,
     ) -> axum::serve::Serve<Router, Router> {
         dotenv().ok();
         let app_state = AppState {
             state_db: db_state,
             prover,
             notifier,
+            pending_blocks,
         };
         let app = Router::new()
             .route("/health", get(|| async { StatusCode::OK }))
             .route("/mint", post(create_mint))
             .route("/keys", post(create_keys))
             .route("/transfer", post(create_transfer))
+            .route("/swap", post(create_swap))
+            .route("/withdraw", post(create_withdraw))
             .route("/preimages", get(get_preimages))
+            .route("/preimages/:commitment", get(get_preimage))
+            .route("/notes", get(get_notes))
+            .route("/balance", get(get_balance))
             .route("/block", post(handle_block))
             .with_state(app_state);
 
@@ -1,9 +1,12 @@
 use ark_ec::short_weierstrass::{Affine, SWCurveConfig};
+use ark_serialize::CanonicalDeserialize;
 use common::serialize::{ark_de, ark_se, vec_ark_de, vec_ark_se};
 use derivative::Derivative;
 use serde::{Deserialize, Serialize};
 
-use crate::domain::StoredPreimageInfo;
+use crate::domain::{
+    PreimageFilter, PreimageStatus, StoredPreimageInfo, DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE,
+};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct MintInput {
@@ -27,8 +30,16 @@ pub struct MnemonicInput {
     Hash(bound = "P: SWCurveConfig")
 )]
 pub struct TransferInput<P: SWCurveConfig> {
-    #[serde(serialize_with = "vec_ark_se", deserialize_with = "vec_ark_de")]
+    // Selected automatically from the sender's spendable commitments when empty
+    #[serde(
+        default,
+        serialize_with = "vec_ark_se",
+        deserialize_with = "vec_ark_de"
+    )]
     pub commitments_to_use: Vec<P::BaseField>,
+    // Required when commitments_to_use is empty
+    #[serde(default, serialize_with = "ark_se", deserialize_with = "ark_de")]
+    pub token_id: Option<P::BaseField>,
     #[serde(serialize_with = "ark_se", deserialize_with = "ark_de")]
     pub recipient: Affine<P>,
     #[serde(serialize_with = "ark_se", deserialize_with = "ark_de")]
@@ -39,6 +50,53 @@ pub struct TransferInput<P: SWCurveConfig> {
     pub sender: Affine<P>,
 }
 
+/// One half of a private swap. The commitment is sent as is to the counterparty, who must
+/// submit the matching half paying `incoming_value` of `incoming_token_id` in return.
+#[derive(Derivative, Default, Deserialize, Serialize)]
+#[derivative(
+    Clone(bound = "P: SWCurveConfig"),
+    Debug(bound = "P: SWCurveConfig"),
+    PartialEq(bound = "P: SWCurveConfig"),
+    Eq(bound = "P: SWCurveConfig")
+)]
+pub struct SwapInput<P: SWCurveConfig> {
+    #[serde(serialize_with = "ark_se", deserialize_with = "ark_de")]
+    pub commitment_to_use: P::BaseField,
+    // Counterparty public key
+    #[serde(serialize_with = "ark_se", deserialize_with = "ark_de")]
+    pub recipient: Affine<P>,
+    #[serde(serialize_with = "ark_se", deserialize_with = "ark_de")]
+    pub incoming_value: P::BaseField,
+    #[serde(serialize_with = "ark_se", deserialize_with = "ark_de")]
+    pub incoming_token_id: P::BaseField,
+    // Leaf index of the counterparty commitment, used as salt of the incoming commitment
+    pub counterparty_leaf_index: usize,
+    #[serde(default, serialize_with = "ark_se", deserialize_with = "ark_de")]
+    pub eph_key: Option<P::BaseField>,
+    #[serde(serialize_with = "ark_se", deserialize_with = "ark_de")]
+    pub sender: Affine<P>,
+}
+
+/// Takes `withdraw_amount` out of the shielded pool to a public `recipient` address.
+/// Whatever is left in the selected commitments goes back to the sender as change.
+#[derive(Derivative, Default, Deserialize, Serialize)]
+#[derivative(
+    Clone(bound = "P: SWCurveConfig"),
+    Debug(bound = "P: SWCurveConfig"),
+    PartialEq(bound = "P: SWCurveConfig"),
+    Eq(bound = "P: SWCurveConfig")
+)]
+pub struct WithdrawInput<P: SWCurveConfig> {
+    #[serde(serialize_with = "vec_ark_se", deserialize_with = "vec_ark_de")]
+    pub commitments_to_use: Vec<P::BaseField>,
+    #[serde(serialize_with = "ark_se", deserialize_with = "ark_de")]
+    pub withdraw_amount: P::BaseField,
+    #[serde(serialize_with = "ark_se", deserialize_with = "ark_de")]
+    pub recipient: P::BaseField,
+    #[serde(serialize_with = "ark_se", deserialize_with = "ark_de")]
+    pub sender: Affine<P>,
+}
+
 #[derive(Default, Debug, Deserialize, Serialize)]
 pub struct PreimageResponse<EmbedCurve: SWCurveConfig> {
     #[serde(serialize_with = "ark_se", deserialize_with = "ark_de")]
@@ -46,3 +104,48 @@ pub struct PreimageResponse<EmbedCurve: SWCurveConfig> {
     #[serde(serialize_with = "ark_se", deserialize_with = "ark_de")]
     pub commitment_hash: EmbedCurve::BaseField,
 }
+
+#[derive(Deserialize)]
+pub struct BalanceQuery<P: SWCurveConfig> {
+    #[serde(deserialize_with = "ark_de")]
+    pub owner: Affine<P>,
+    #[serde(deserialize_with = "ark_de")]
+    pub token_id: P::BaseField,
+}
+
+#[derive(Deserialize)]
+pub struct NotesQuery<P: SWCurveConfig> {
+    #[serde(default, deserialize_with = "ark_de_option")]
+    pub owner: Option<Affine<P>>,
+    #[serde(default, deserialize_with = "ark_de_option")]
+    pub token_id: Option<P::BaseField>,
+    pub status: Option<PreimageStatus>,
+    pub offset: Option<usize>,
+    pub limit: Option<usize>,
+}
+
+impl<P: SWCurveConfig> From<NotesQuery<P>> for PreimageFilter<P> {
+    fn from(query: NotesQuery<P>) -> Self {
+        Self {
+            owner: query.owner,
+            token_id: query.token_id,
+            status: query.status,
+            offset: query.offset.unwrap_or_default(),
+            limit: query.limit.unwrap_or(DEFAULT_PAGE_SIZE).min(MAX_PAGE_SIZE),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct CommitmentPath<P: SWCurveConfig> {
+    #[serde(deserialize_with = "ark_de")]
+    pub commitment: P::BaseField,
+}
+
+// Optional query parameters are only deserialized when present
+fn ark_de_option<'de, D, A: CanonicalDeserialize>(data: D) -> Result<Option<A>, D::Error>
+where
+    D: serde::de::Deserializer<'de>,
+{
+    ark_de(data).map(Some)
+}
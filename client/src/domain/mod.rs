@@ -1,3 +1,5 @@
+mod pending_blocks;
+mod query;
 mod stored_preimage;
 mod transaction;
 pub mod primitives {
@@ -5,6 +7,8 @@ pub mod primitives {
     pub use common::curves::*;
     pub use common::serialize::{ark_de, ark_de_std, ark_se, ark_se_std, vec_ark_de, vec_ark_se};
 }
+pub use self::pending_blocks::*;
+pub use self::query::*;
 pub use self::stored_preimage::*;
 pub use self::transaction::*;
 pub use primitives::*;
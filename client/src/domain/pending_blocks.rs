@@ -0,0 +1,54 @@
+use std::collections::BTreeMap;
+
+use ark_ff::Field;
+use common::structs::Block;
+
+pub const MAX_PENDING_BLOCKS: usize = 256;
+
+/// Blocks received ahead of the next block to apply, by block number. They are
+/// applied once the blocks before them arrive.
+#[derive(Debug, Clone)]
+pub struct PendingBlocks<F: Field> {
+    capacity: usize,
+    blocks: BTreeMap<u64, Block<F>>,
+}
+
+impl<F: Field> Default for PendingBlocks<F> {
+    fn default() -> Self {
+        Self::new(MAX_PENDING_BLOCKS)
+    }
+}
+
+impl<F: Field> PendingBlocks<F> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            blocks: BTreeMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+
+    // Queues the block unless the queue is full. A block already queued is
+    // replaced.
+    pub fn insert(&mut self, block: Block<F>) -> bool {
+        if self.blocks.len() >= self.capacity && !self.blocks.contains_key(&block.block_number) {
+            return false;
+        }
+        self.blocks.insert(block.block_number, block);
+        true
+    }
+
+    // Takes the block numbered `next`, dropping the blocks before it, which
+    // were applied some other way
+    pub fn take_next(&mut self, next: u64) -> Option<Block<F>> {
+        self.blocks = self.blocks.split_off(&next);
+        self.blocks.remove(&next)
+    }
+}
@@ -0,0 +1,71 @@
+use ark_ec::short_weierstrass::{Affine, SWCurveConfig};
+use ark_ff::PrimeField;
+use derivative::Derivative;
+use serde::{Deserialize, Serialize};
+
+use crate::domain::{ark_de, ark_se, PreimageStatus, StoredPreimageInfo};
+
+pub const DEFAULT_PAGE_SIZE: usize = 100;
+pub const MAX_PAGE_SIZE: usize = 1000;
+
+/// Value held by an owner for a single token.
+/// - confirmed: unspent notes included in a block
+/// - pending: unspent notes not yet included in a block
+/// - locked: notes used by a transfer that hasn't been confirmed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Balance<F: PrimeField> {
+    #[serde(serialize_with = "ark_se", deserialize_with = "ark_de")]
+    pub confirmed: F,
+    #[serde(serialize_with = "ark_se", deserialize_with = "ark_de")]
+    pub pending: F,
+    #[serde(serialize_with = "ark_se", deserialize_with = "ark_de")]
+    pub locked: F,
+}
+
+impl<F: PrimeField> Balance<F> {
+    pub fn add<E: SWCurveConfig<BaseField = F>>(&mut self, stored: &StoredPreimageInfo<E>) {
+        let value = stored.preimage.value;
+        match (stored.status, stored.block_number) {
+            (PreimageStatus::Unspent, Some(_)) => self.confirmed += value,
+            (PreimageStatus::Unspent, None) => self.pending += value,
+            (PreimageStatus::Locked, _) => self.locked += value,
+            (PreimageStatus::Spent, _) => {}
+        }
+    }
+}
+
+/// Selects a page of stored preimages. Unset fields match every preimage.
+#[derive(Derivative)]
+#[derivative(
+    Copy(bound = "E: SWCurveConfig"),
+    Clone(bound = "E: SWCurveConfig"),
+    Debug(bound = "E: SWCurveConfig")
+)]
+pub struct PreimageFilter<E: SWCurveConfig> {
+    pub owner: Option<Affine<E>>,
+    pub token_id: Option<E::BaseField>,
+    pub status: Option<PreimageStatus>,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+impl<E: SWCurveConfig> Default for PreimageFilter<E> {
+    fn default() -> Self {
+        Self {
+            owner: None,
+            token_id: None,
+            status: None,
+            offset: 0,
+            limit: DEFAULT_PAGE_SIZE,
+        }
+    }
+}
+
+impl<E: SWCurveConfig> PreimageFilter<E> {
+    pub fn matches(&self, stored: &StoredPreimageInfo<E>) -> bool {
+        let preimage = &stored.preimage;
+        (self.owner.is_none() || self.owner == Some(preimage.public_key.as_affine()))
+            && (self.token_id.is_none() || self.token_id == Some(preimage.token_id))
+            && (self.status.is_none() || self.status == Some(stored.status))
+    }
+}
@@ -1,5 +1,8 @@
+use crate::domain::PendingBlocks;
 use crate::services::{
-    prover::in_memory_prover::InMemProver, storage::in_mem_storage::InMemStorage,
+    block_source::HttpBlockSource,
+    prover::{in_memory_prover::InMemProver, key_store::FileKeyStore},
+    storage::client_storage::ClientStorage,
 };
 use adapters::rest_api::rest_api_entry::Application;
 use common::services::notifier::HttpNotifier;
@@ -8,6 +11,7 @@ use curves::{
     pallas::{Fq, PallasConfig},
     vesta::VestaConfig,
 };
+use plonk_prover::setup::shared_setup;
 use std::sync::Arc;
 use tracing_log::log;
 pub mod adapters;
@@ -25,28 +29,60 @@ fn main() -> anyhow::Result<()> {
         std::io::stdout,
     );
     log::trace!("Initializing");
-    let db: InMemStorage<PallasConfig, Fq> = InMemStorage::new();
+    let db: ClientStorage<PallasConfig, Fq> =
+        ClientStorage::from_settings(&configuration.client.storage)?;
     let thread_safe_db = std::sync::Arc::new(tokio::sync::Mutex::new(db));
     let mut prover: InMemProver<PallasConfig, VestaConfig, _> = InMemProver::new();
-    let notifier = HttpNotifier::new(configuration.sequencer);
+    if let Some(path) = &configuration.client.key_store_path {
+        prover = prover.with_key_store(FileKeyStore::open(path)?);
+    }
+    let notifier = HttpNotifier::new(configuration.sequencer.clone());
+    let block_source = HttpBlockSource::new(configuration.sequencer);
+    let status_source = block_source.clone();
 
+    let srs = shared_setup::<VestaConfig, VestaConfig>()
+        .map_err(|e| anyhow::anyhow!("Failed to derive setup: {:?}", e))?;
     utils::circuits::init_client_circuits::<PallasConfig, VestaConfig, VestaConfig, _>(
         &mut prover,
+        &srs,
     )?;
 
     let thread_safe_prover = Arc::new(tokio::sync::Mutex::new(prover));
     let _test_mnemonic = "pact gun essay three dash seat page silent slogan hole huge harvest awesome fault cute alter boss thank click menu service quarter gaze salmon";
     let thread_safe_notifier = Arc::new(tokio::sync::Mutex::new(notifier));
+    // Pushed and synced blocks go through the same queue, so either can fill
+    // the gap the other is waiting on
+    let pending_blocks = Arc::new(tokio::sync::Mutex::new(PendingBlocks::default()));
     let async_rt = tokio::runtime::Builder::new_multi_thread()
         .enable_io()
         .enable_time()
         .build()
         .unwrap();
     async_rt.block_on(async {
+        tokio::spawn(usecase::release_inputs::run_input_release(
+            thread_safe_db.clone(),
+            status_source,
+            configuration.client.block_sync.interval(),
+        ));
+        if configuration.client.block_sync.enabled {
+            tokio::spawn(usecase::sync_blocks::run_block_sync::<
+                PallasConfig,
+                VestaConfig,
+                _,
+                _,
+                _,
+            >(
+                thread_safe_db.clone(),
+                pending_blocks.clone(),
+                block_source,
+                configuration.client.block_sync.clone(),
+            ));
+        }
         let application = Application::build(
             thread_safe_db,
             thread_safe_prover,
             thread_safe_notifier,
+            pending_blocks,
             configuration.client,
         )
         .await
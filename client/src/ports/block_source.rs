@@ -0,0 +1,13 @@
+use ark_ff::Field;
+use async_trait::async_trait;
+use common::structs::Block;
+
+// Where the client pulls the blocks it missed from
+#[async_trait]
+pub trait BlockSource {
+    type F: Field;
+
+    // Blocks numbered from `from` up to, but excluding, `to`. Sources may
+    // return fewer blocks than asked for, and none past their latest block.
+    async fn get_blocks(&self, from: u64, to: u64) -> anyhow::Result<Vec<Block<Self::F>>>;
+}
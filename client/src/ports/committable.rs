@@ -16,7 +16,7 @@ pub trait Committable<F: PrimeField> {
 }
 
 pub trait Nullifiable<F: PrimeField>: Committable<F> {
-    fn nullifier_hash<Hasher>(&self) -> Result<F, Self::Error>;
+    fn nullifier_hash(&self, nullifier_key: &F) -> Result<F, Self::Error>;
 }
 
 // Implementation of how a Preimage can be committed to.
@@ -37,3 +37,15 @@ where
         }
     }
 }
+
+// Mirrors the nullifier computed in the transfer circuit: H(nullifier_key, commitment)
+impl<E, F> Nullifiable<F> for Preimage<E>
+where
+    E: SWCurveConfig<BaseField = F>,
+    F: PrimeField + PoseidonParams<Field = F>,
+{
+    fn nullifier_hash(&self, nullifier_key: &F) -> Result<F, Self::Error> {
+        let commitment = self.commitment_hash()?;
+        Poseidon::<F>::new().hash(vec![*nullifier_key, commitment.0])
+    }
+}
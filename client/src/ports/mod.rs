@@ -1,4 +1,6 @@
+pub mod block_source;
 pub mod committable;
 pub mod keys;
 pub mod prover;
 pub mod storage;
+pub mod transaction_status;
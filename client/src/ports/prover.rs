@@ -38,4 +38,10 @@ pub trait Prover<P, V, VSW> {
 
     fn get_pk(&self, circuit_type: CircuitType) -> Option<&ProvingKey<V>>;
     fn store_pk(&mut self, circuit_type: CircuitType, pk: ProvingKey<V>);
+    fn get_circuit_types(&self) -> Vec<CircuitType>;
+
+    // Durable keys: load a previously persisted pk generated for a circuit with
+    // the given hash, and persist the pk currently held for a circuit type.
+    fn load_pk(&mut self, circuit_type: CircuitType, circuit_hash: &str) -> bool;
+    fn persist_pk(&self, circuit_type: CircuitType, circuit_hash: &str) -> anyhow::Result<()>;
 }
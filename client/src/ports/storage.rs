@@ -1,4 +1,6 @@
-use crate::domain::{StoredPreimageInfo, StoredPreimageInfoVector};
+use crate::domain::{
+    Balance, PreimageFilter, PreimageStatus, StoredPreimageInfo, StoredPreimageInfoVector,
+};
 use ark_ec::{
     short_weierstrass::{Affine, SWCurveConfig},
     CurveConfig,
@@ -18,6 +20,15 @@ pub trait PreimageDB {
     ) -> Option<StoredPreimageInfo<Self::E>>;
     fn get_spendable(&self) -> Option<StoredPreimageInfoVector<Self::E>>;
     fn get_all_preimages(&self) -> StoredPreimageInfoVector<Self::E>;
+    fn get_balance(
+        &self,
+        owner: Affine<Self::E>,
+        token_id: <Self::E as CurveConfig>::BaseField,
+    ) -> Balance<<Self::E as CurveConfig>::BaseField>;
+    fn query_preimages(
+        &self,
+        filter: &PreimageFilter<Self::E>,
+    ) -> StoredPreimageInfoVector<Self::E>;
     fn get_preimage(
         &self,
         key: <Self::E as CurveConfig>::BaseField,
@@ -28,7 +39,32 @@ pub trait PreimageDB {
         key: <Self::E as CurveConfig>::BaseField,
         preimage: StoredPreimageInfo<Self::E>,
     ) -> Option<()>;
-    fn update_preimages(&mut self, block: Block<<Self::E as CurveConfig>::BaseField>);
+    fn set_preimage_status(
+        &mut self,
+        key: <Self::E as CurveConfig>::BaseField,
+        status: PreimageStatus,
+    ) -> Option<()>;
+    fn remove_preimage(
+        &mut self,
+        key: <Self::E as CurveConfig>::BaseField,
+    ) -> Option<StoredPreimageInfo<Self::E>>;
+    fn update_preimages(&mut self, block: Block<<Self::E as CurveConfig>::BaseField>)
+        -> Option<()>;
+
+    // Inputs locked by the transactions sent to the sequencer, by transaction
+    // hash, until the sequencer reports whether they were included
+    fn insert_locking_transaction(
+        &mut self,
+        transaction_hash: String,
+        keys: Vec<<Self::E as CurveConfig>::BaseField>,
+    ) -> Option<()>;
+    fn remove_locking_transaction(
+        &mut self,
+        transaction_hash: &str,
+    ) -> Option<Vec<<Self::E as CurveConfig>::BaseField>>;
+    fn get_locking_transactions(&self) -> Vec<String>;
+    // Seconds since the unix epoch at which the locking transaction was recorded
+    fn get_locking_time(&self, transaction_hash: &str) -> Option<u64>;
 }
 
 pub trait TreeDB {
@@ -38,13 +74,27 @@ pub trait TreeDB {
         block_number: &u64,
         leaf_index: usize,
     ) -> Option<MembershipPath<Self::F>>;
-    fn add_block_leaves(&mut self, leaves: Vec<Self::F>, block_number: u64) -> Option<()>;
+    fn add_block_leaves(
+        &mut self,
+        leaves: Vec<Self::F>,
+        block_number: u64,
+        block_hash: String,
+    ) -> Option<()>;
     fn get_root(&self, block_number: &u64) -> Option<Self::F>;
+    // Header hash of an applied block, unknown for blocks applied before
+    // headers were stored
+    fn get_block_hash(&self, block_number: &u64) -> Option<String>;
+
+    // Last block applied together with every block before it. Leaves are
+    // added once the rest of a block is applied, so a block with a tree is
+    // fully processed. Stored as blocks are added rather than looked up.
+    fn get_synced_block_number(&self) -> Option<u64>;
 }
 
 pub trait KeyDB {
     type E: SWCurveConfig;
     type Key: FullKey<Self::E>;
     fn get_key(&self, public_key: Affine<Self::E>) -> Option<Self::Key>;
+    fn get_all_keys(&self) -> Vec<Self::Key>;
     fn insert_key(&mut self, key: Affine<Self::E>, value: Self::Key) -> Option<()>;
 }
@@ -0,0 +1,12 @@
+use async_trait::async_trait;
+use common::structs::TransactionStatus;
+
+// Where the client learns what became of the transactions it sent
+#[async_trait]
+pub trait TransactionStatusSource: Send + Sync {
+    // None when the source doesn't know the transaction
+    async fn get_transaction_status(
+        &self,
+        transaction_hash: &str,
+    ) -> anyhow::Result<Option<TransactionStatus>>;
+}
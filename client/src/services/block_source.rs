@@ -0,0 +1,85 @@
+use ark_ff::Field;
+use async_trait::async_trait;
+use common::configuration::ApplicationSettings;
+use common::structs::{Block, TransactionStatus};
+use reqwest::StatusCode;
+use serde::{de::DeserializeOwned, Deserialize};
+use std::marker::PhantomData;
+
+use crate::ports::{block_source::BlockSource, transaction_status::TransactionStatusSource};
+
+#[derive(Deserialize)]
+struct TransactionStatusResponse {
+    status: TransactionStatus,
+}
+
+// Reads blocks and transaction statuses from the block explorer API of the sequencer
+#[derive(Clone, Debug)]
+pub struct HttpBlockSource<F> {
+    pub base_url: String,
+    timeout: std::time::Duration,
+    _marker: PhantomData<F>,
+}
+
+impl<F> HttpBlockSource<F> {
+    pub fn new(settings: ApplicationSettings) -> Self {
+        let timeout = settings.timeout();
+        Self {
+            base_url: settings.base_url,
+            timeout,
+            _marker: PhantomData,
+        }
+    }
+}
+
+#[async_trait]
+impl<F> BlockSource for HttpBlockSource<F>
+where
+    F: Field,
+    Block<F>: DeserializeOwned,
+{
+    type F = F;
+
+    #[tracing::instrument(name = "Fetch blocks", skip(self))]
+    async fn get_blocks(&self, from: u64, to: u64) -> anyhow::Result<Vec<Block<F>>> {
+        let client = reqwest::Client::builder().timeout(self.timeout).build()?;
+        let blocks = client
+            .get(format!("{}/blocks", self.base_url))
+            .query(&[("from", from), ("to", to)])
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<Vec<Block<F>>>()
+            .await?;
+        Ok(blocks)
+    }
+}
+
+#[async_trait]
+impl<F> TransactionStatusSource for HttpBlockSource<F>
+where
+    F: Send + Sync,
+{
+    #[tracing::instrument(name = "Fetch transaction status", skip(self))]
+    async fn get_transaction_status(
+        &self,
+        transaction_hash: &str,
+    ) -> anyhow::Result<Option<TransactionStatus>> {
+        let client = reqwest::Client::builder().timeout(self.timeout).build()?;
+        let response = client
+            .get(format!(
+                "{}/transactions/{}",
+                self.base_url, transaction_hash
+            ))
+            .send()
+            .await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let response = response
+            .error_for_status()?
+            .json::<TransactionStatusResponse>()
+            .await?;
+        Ok(Some(response.status))
+    }
+}
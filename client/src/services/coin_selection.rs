@@ -0,0 +1,144 @@
+use anyhow::anyhow;
+use ark_ff::PrimeField;
+
+/// Number of (commitments, nullifiers) of a registered transfer circuit
+pub type TransferShape = (usize, usize);
+
+/// Commitments spent by a single transfer transaction and the amount it sends
+/// to the recipient. Whatever is left goes back to the sender as change.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SelectedTransfer<F: PrimeField> {
+    pub commitments_to_use: Vec<F>,
+    pub transfer_amount: F,
+    pub n_commitments: usize,
+}
+
+/// Picks the smallest registered transfer circuit spending exactly `n_inputs` commitments.
+/// Circuits with a change output can also be used for exact payments with a zero value change.
+pub fn select_shape(
+    shapes: &[TransferShape],
+    n_inputs: usize,
+    has_change: bool,
+) -> Option<TransferShape> {
+    let min_commitments = if has_change { 2 } else { 1 };
+    shapes
+        .iter()
+        .filter(|(c, n)| *n == n_inputs && *c >= min_commitments)
+        .min_by_key(|(c, _)| *c)
+        .copied()
+}
+
+/// Selects commitments from `candidates` (commitment, value) to pay `amount`, largest values first.
+/// When more commitments are needed than the largest registered circuit can spend, the payment is
+/// split into several transactions to the same recipient and only the last one returns change.
+pub fn select_transfers<F: PrimeField>(
+    mut candidates: Vec<(F, F)>,
+    amount: F,
+    shapes: &[TransferShape],
+) -> anyhow::Result<Vec<SelectedTransfer<F>>> {
+    if amount.is_zero() {
+        return Err(anyhow!("Transfer amount must be greater than zero"));
+    }
+    let max_inputs = shapes
+        .iter()
+        .map(|(_, n)| *n)
+        .max()
+        .ok_or(anyhow!("No transfer circuits registered"))?;
+
+    candidates.sort_by_key(|(_, value)| std::cmp::Reverse(value.into_bigint()));
+    let mut selected = Vec::new();
+    let mut selected_value = F::zero();
+    for candidate in candidates {
+        if selected_value.into_bigint() >= amount.into_bigint() {
+            break;
+        }
+        selected_value += candidate.1;
+        selected.push(candidate);
+    }
+    if selected_value.into_bigint() < amount.into_bigint() {
+        return Err(anyhow!("Insufficient funds"));
+    }
+
+    let chunks = selected.chunks(max_inputs).collect::<Vec<_>>();
+    let mut remaining = amount;
+    let mut transfers = Vec::with_capacity(chunks.len());
+    for (i, chunk) in chunks.iter().enumerate() {
+        let chunk_value = chunk.iter().fold(F::zero(), |acc, (_, value)| acc + value);
+        // Only the last chunk can exceed what is left to pay
+        let transfer_amount = if i + 1 == chunks.len() {
+            remaining
+        } else {
+            chunk_value
+        };
+        let has_change = chunk_value != transfer_amount;
+        let (n_commitments, _) = select_shape(shapes, chunk.len(), has_change).ok_or(anyhow!(
+            "No registered transfer circuit spends {} commitments",
+            chunk.len()
+        ))?;
+        remaining -= transfer_amount;
+        transfers.push(SelectedTransfer {
+            commitments_to_use: chunk.iter().map(|(commitment, _)| *commitment).collect(),
+            transfer_amount,
+            n_commitments,
+        });
+    }
+
+    Ok(transfers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use curves::pallas::Fq;
+
+    const SHAPES: [TransferShape; 5] = [(1, 1), (2, 1), (1, 2), (2, 2), (2, 3)];
+
+    fn candidates(values: &[u64]) -> Vec<(Fq, Fq)> {
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (Fq::from(i as u64 + 100), Fq::from(*v)))
+            .collect()
+    }
+
+    #[test]
+    fn test_select_shape() {
+        assert_eq!(select_shape(&SHAPES, 1, false), Some((1, 1)));
+        assert_eq!(select_shape(&SHAPES, 1, true), Some((2, 1)));
+        // No (1, 3) circuit, so an exact payment pads a zero change
+        assert_eq!(select_shape(&SHAPES, 3, false), Some((2, 3)));
+        assert_eq!(select_shape(&SHAPES, 4, false), None);
+    }
+
+    #[test]
+    fn test_select_single_transfer_with_change() {
+        let transfers =
+            select_transfers(candidates(&[5, 50, 20]), Fq::from(30u64), &SHAPES).unwrap();
+        assert_eq!(
+            transfers,
+            vec![SelectedTransfer {
+                commitments_to_use: vec![Fq::from(101u64)],
+                transfer_amount: Fq::from(30u64),
+                n_commitments: 2,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_select_chained_transfers() {
+        let transfers =
+            select_transfers(candidates(&[10, 10, 10, 10, 10]), Fq::from(45u64), &SHAPES).unwrap();
+        assert_eq!(transfers.len(), 2);
+        assert_eq!(transfers[0].commitments_to_use.len(), 3);
+        assert_eq!(transfers[0].transfer_amount, Fq::from(30u64));
+        assert_eq!(transfers[1].commitments_to_use.len(), 2);
+        assert_eq!(transfers[1].transfer_amount, Fq::from(15u64));
+        assert_eq!(transfers[1].n_commitments, 2);
+    }
+
+    #[test]
+    fn test_select_transfers_insufficient_funds() {
+        assert!(select_transfers(candidates(&[10, 20]), Fq::from(31u64), &SHAPES).is_err());
+        assert!(select_transfers(vec![], Fq::from(1u64), &SHAPES).is_err());
+    }
+}
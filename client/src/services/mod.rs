@@ -1,3 +1,5 @@
+pub mod block_source;
+pub mod coin_selection;
 pub mod prover;
 pub mod storage;
 pub mod user_keys;
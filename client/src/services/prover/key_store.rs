@@ -0,0 +1,142 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::anyhow;
+use ark_ec::pairing::Pairing;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Compress, Validate};
+use common::{files::write_atomically, hash::content_hash, structs::CircuitType};
+use jf_plonk::nightfall::ipa_structs::ProvingKey;
+use tracing_log::log;
+
+// Proving keys stored on disk, one file per circuit type. Each record carries
+// the hash of the circuit it was generated for, so a key is only reused while
+// the circuit definition is unchanged, and a hash of its own bytes, so a
+// truncated or corrupted file is discarded instead of deserialized.
+#[derive(Clone, Debug)]
+pub struct FileKeyStore {
+    path: PathBuf,
+}
+
+// (circuit hash, content hash, serialized proving key)
+type KeyRecord = (String, String, Vec<u8>);
+
+impl FileKeyStore {
+    pub fn open(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        fs::create_dir_all(&path)?;
+        Ok(Self { path })
+    }
+
+    pub fn load<V: Pairing>(
+        &self,
+        circuit_type: &CircuitType,
+        circuit_hash: &str,
+    ) -> Option<ProvingKey<V>> {
+        let path = self.key_path(circuit_type);
+        let bytes = fs::read(&path).ok()?;
+        let (stored_circuit_hash, stored_content_hash, key) =
+            match <KeyRecord as CanonicalDeserialize>::deserialize_with_mode(
+                &*bytes,
+                Compress::No,
+                Validate::Yes,
+            ) {
+                Ok(record) => record,
+                Err(e) => {
+                    log::warn!("Discarding unreadable proving key {:?}: {e}", path);
+                    return None;
+                }
+            };
+        if stored_circuit_hash != circuit_hash {
+            log::info!("Circuit {:?} changed, proving key is stale", circuit_type);
+            return None;
+        }
+        if stored_content_hash != content_hash(&key) {
+            log::warn!("Discarding corrupted proving key {:?}", path);
+            return None;
+        }
+        // The content hash already vouches for the bytes we wrote
+        CanonicalDeserialize::deserialize_with_mode(&*key, Compress::No, Validate::No)
+            .map_err(|e| log::warn!("Discarding undecodable proving key {:?}: {e}", path))
+            .ok()
+    }
+
+    pub fn store<V: Pairing>(
+        &self,
+        circuit_type: &CircuitType,
+        circuit_hash: &str,
+        pk: &ProvingKey<V>,
+    ) -> anyhow::Result<()> {
+        let mut key = Vec::new();
+        pk.serialize_with_mode(&mut key, Compress::No)
+            .map_err(|e| anyhow!("Failed to serialize proving key: {e}"))?;
+        let record: KeyRecord = (circuit_hash.to_string(), content_hash(&key), key);
+        let mut bytes = Vec::new();
+        record
+            .serialize_with_mode(&mut bytes, Compress::No)
+            .map_err(|e| anyhow!("Failed to serialize proving key record: {e}"))?;
+        write_atomically(&self.key_path(circuit_type), &bytes)?;
+        Ok(())
+    }
+
+    fn key_path(&self, circuit_type: &CircuitType) -> PathBuf {
+        self.path.join(format!("{}.pk", circuit_type.key_name()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use curves::{pallas::PallasConfig, vesta::VestaConfig};
+    use plonk_prover::client::{circuits::mint::MintCircuit, ClientPlonkCircuit};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_store_and_load_pk() {
+        let dir = TempDir::new().unwrap();
+        let store = FileKeyStore::open(dir.path()).unwrap();
+        let circuit = MintCircuit::<1>::new().as_circuit::<PallasConfig, VestaConfig, _>();
+        let (pk, _) = circuit.generate_keys().unwrap();
+        let circuit_type = circuit.get_circuit_type();
+
+        store.store(&circuit_type, "hash", &pk).unwrap();
+        let loaded = store.load::<VestaConfig>(&circuit_type, "hash");
+        assert_eq!(loaded, Some(pk));
+    }
+
+    #[test]
+    fn test_load_pk_with_changed_circuit() {
+        let dir = TempDir::new().unwrap();
+        let store = FileKeyStore::open(dir.path()).unwrap();
+        let circuit = MintCircuit::<1>::new().as_circuit::<PallasConfig, VestaConfig, _>();
+        let (pk, _) = circuit.generate_keys().unwrap();
+        let circuit_type = circuit.get_circuit_type();
+
+        store.store(&circuit_type, "hash", &pk).unwrap();
+        assert!(store
+            .load::<VestaConfig>(&circuit_type, "other_hash")
+            .is_none());
+        assert!(store
+            .load::<VestaConfig>(&CircuitType::Mint(2), "hash")
+            .is_none());
+    }
+
+    #[test]
+    fn test_load_corrupted_pk() {
+        let dir = TempDir::new().unwrap();
+        let store = FileKeyStore::open(dir.path()).unwrap();
+        let circuit = MintCircuit::<1>::new().as_circuit::<PallasConfig, VestaConfig, _>();
+        let (pk, _) = circuit.generate_keys().unwrap();
+        let circuit_type = circuit.get_circuit_type();
+
+        store.store(&circuit_type, "hash", &pk).unwrap();
+        let key_path = dir.path().join("mint_1.pk");
+        let mut bytes = fs::read(&key_path).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xff;
+        fs::write(&key_path, bytes).unwrap();
+
+        assert!(store.load::<VestaConfig>(&circuit_type, "hash").is_none());
+    }
+}
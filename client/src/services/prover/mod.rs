@@ -1,3 +1,5 @@
+pub mod key_store;
+
 pub mod in_memory_prover {
     use ark_ec::{
         pairing::Pairing,
@@ -19,6 +21,7 @@ pub mod in_memory_prover {
     use rand_chacha::ChaChaRng;
     use std::{collections::HashMap, time::Instant};
 
+    use super::key_store::FileKeyStore;
     use crate::ports::prover::Prover;
     use ark_ff::PrimeField;
     use common::crypto::poseidon::constants::PoseidonParams;
@@ -32,6 +35,7 @@ pub mod in_memory_prover {
     #[client_bounds]
     pub struct InMemProver<P, V, VSW> {
         pub key_storage: HashMap<CircuitType, ProvingKey<V>>,
+        key_store: Option<FileKeyStore>,
         _marker: PhantomData<(P, VSW)>,
     }
 
@@ -40,9 +44,15 @@ pub mod in_memory_prover {
         pub fn new() -> Self {
             Self {
                 key_storage: HashMap::new(),
+                key_store: None,
                 _marker: PhantomData,
             }
         }
+
+        pub fn with_key_store(mut self, key_store: FileKeyStore) -> Self {
+            self.key_store = Some(key_store);
+            self
+        }
     }
 
     #[client_bounds]
@@ -50,6 +60,7 @@ pub mod in_memory_prover {
         fn default() -> Self {
             Self {
                 key_storage: HashMap::new(),
+                key_store: None,
                 _marker: PhantomData,
             }
         }
@@ -111,6 +122,29 @@ pub mod in_memory_prover {
         fn store_pk(&mut self, circuit_type: CircuitType, pk: ProvingKey<V>) {
             self.key_storage.entry(circuit_type).or_insert(pk);
         }
+        fn get_circuit_types(&self) -> Vec<CircuitType> {
+            self.key_storage.keys().cloned().collect()
+        }
+
+        fn load_pk(&mut self, circuit_type: CircuitType, circuit_hash: &str) -> bool {
+            let Some(pk) = self
+                .key_store
+                .as_ref()
+                .and_then(|store| store.load::<V>(&circuit_type, circuit_hash))
+            else {
+                return false;
+            };
+            self.key_storage.insert(circuit_type, pk);
+            true
+        }
+
+        fn persist_pk(&self, circuit_type: CircuitType, circuit_hash: &str) -> anyhow::Result<()> {
+            let (Some(store), Some(pk)) = (&self.key_store, self.key_storage.get(&circuit_type))
+            else {
+                return Ok(());
+            };
+            store.store(&circuit_type, circuit_hash, pk)
+        }
     }
 }
 
@@ -215,8 +249,11 @@ mod tests {
 
         assert!(is_valid, "Verification should succeed for a valid proof");
 
-        circuits::init_client_circuits::<PallasConfig, VestaConfig, VestaConfig, _>(&mut prover)
-            .expect("Error initializing client circuits");
+        circuits::init_client_circuits::<PallasConfig, VestaConfig, VestaConfig, _>(
+            &mut prover,
+            circuits::test_setup(),
+        )
+        .expect("Error initializing client circuits");
 
         let stored_pk = prover
             .get_pk(
@@ -0,0 +1,248 @@
+use std::fmt::Debug;
+
+use anyhow::anyhow;
+use ark_ec::short_weierstrass::{Affine, SWCurveConfig};
+use ark_ff::PrimeField;
+use common::{
+    configuration::{StorageBackend, StorageSettings},
+    crypto::poseidon::constants::PoseidonParams,
+    structs::Block,
+};
+use trees::MembershipPath;
+
+use super::{file_storage::FileStorage, in_mem_storage::InMemStorage};
+use crate::{
+    domain::{
+        Balance, PreimageFilter, PreimageStatus, StoredPreimageInfo, StoredPreimageInfoVector,
+    },
+    ports::storage::{KeyDB, PreimageDB, TreeDB},
+    services::user_keys::UserKeys,
+};
+
+/// Storage backend selected at startup from `StorageSettings`.
+pub enum ClientStorage<VSW, F>
+where
+    VSW: SWCurveConfig<BaseField = F>,
+    F: PrimeField,
+{
+    InMem(InMemStorage<VSW, F>),
+    File(FileStorage<VSW, F>),
+}
+
+impl<VSW, F> ClientStorage<VSW, F>
+where
+    VSW: SWCurveConfig<BaseField = F>,
+    F: PrimeField + PoseidonParams<Field = F>,
+{
+    pub fn from_settings(settings: &StorageSettings) -> anyhow::Result<Self> {
+        match settings.backend {
+            StorageBackend::InMemory => Ok(Self::InMem(InMemStorage::new())),
+            StorageBackend::File => {
+                let path = settings
+                    .path
+                    .as_ref()
+                    .ok_or(anyhow!("File storage requires a storage path"))?;
+                Ok(Self::File(FileStorage::open(path)?))
+            }
+        }
+    }
+}
+
+impl<VSW, F> PreimageDB for ClientStorage<VSW, F>
+where
+    VSW: SWCurveConfig<BaseField = F>,
+    F: PrimeField + PoseidonParams<Field = F>,
+{
+    type E = VSW;
+
+    fn get_value(&self, value: VSW::BaseField) -> Option<StoredPreimageInfo<VSW>> {
+        match self {
+            Self::InMem(db) => db.get_value(value),
+            Self::File(db) => db.get_value(value),
+        }
+    }
+
+    fn get_spendable(&self) -> Option<StoredPreimageInfoVector<VSW>> {
+        match self {
+            Self::InMem(db) => db.get_spendable(),
+            Self::File(db) => db.get_spendable(),
+        }
+    }
+
+    fn get_all_preimages(&self) -> StoredPreimageInfoVector<VSW> {
+        match self {
+            Self::InMem(db) => db.get_all_preimages(),
+            Self::File(db) => db.get_all_preimages(),
+        }
+    }
+
+    fn get_balance(&self, owner: Affine<VSW>, token_id: VSW::BaseField) -> Balance<F> {
+        match self {
+            Self::InMem(db) => db.get_balance(owner, token_id),
+            Self::File(db) => db.get_balance(owner, token_id),
+        }
+    }
+
+    fn query_preimages(&self, filter: &PreimageFilter<VSW>) -> StoredPreimageInfoVector<VSW> {
+        match self {
+            Self::InMem(db) => db.query_preimages(filter),
+            Self::File(db) => db.query_preimages(filter),
+        }
+    }
+
+    fn get_preimage(&self, key: VSW::BaseField) -> Option<StoredPreimageInfo<VSW>> {
+        match self {
+            Self::InMem(db) => db.get_preimage(key),
+            Self::File(db) => db.get_preimage(key),
+        }
+    }
+
+    fn insert_preimage(
+        &mut self,
+        key: VSW::BaseField,
+        preimage: StoredPreimageInfo<VSW>,
+    ) -> Option<()> {
+        match self {
+            Self::InMem(db) => db.insert_preimage(key, preimage),
+            Self::File(db) => db.insert_preimage(key, preimage),
+        }
+    }
+
+    fn set_preimage_status(&mut self, key: VSW::BaseField, status: PreimageStatus) -> Option<()> {
+        match self {
+            Self::InMem(db) => db.set_preimage_status(key, status),
+            Self::File(db) => db.set_preimage_status(key, status),
+        }
+    }
+
+    fn remove_preimage(&mut self, key: VSW::BaseField) -> Option<StoredPreimageInfo<VSW>> {
+        match self {
+            Self::InMem(db) => db.remove_preimage(key),
+            Self::File(db) => db.remove_preimage(key),
+        }
+    }
+
+    fn update_preimages(&mut self, block: Block<F>) -> Option<()> {
+        match self {
+            Self::InMem(db) => db.update_preimages(block),
+            Self::File(db) => db.update_preimages(block),
+        }
+    }
+
+    fn insert_locking_transaction(
+        &mut self,
+        transaction_hash: String,
+        keys: Vec<VSW::BaseField>,
+    ) -> Option<()> {
+        match self {
+            Self::InMem(db) => db.insert_locking_transaction(transaction_hash, keys),
+            Self::File(db) => db.insert_locking_transaction(transaction_hash, keys),
+        }
+    }
+
+    fn remove_locking_transaction(
+        &mut self,
+        transaction_hash: &str,
+    ) -> Option<Vec<VSW::BaseField>> {
+        match self {
+            Self::InMem(db) => db.remove_locking_transaction(transaction_hash),
+            Self::File(db) => db.remove_locking_transaction(transaction_hash),
+        }
+    }
+
+    fn get_locking_transactions(&self) -> Vec<String> {
+        match self {
+            Self::InMem(db) => db.get_locking_transactions(),
+            Self::File(db) => db.get_locking_transactions(),
+        }
+    }
+
+    fn get_locking_time(&self, transaction_hash: &str) -> Option<u64> {
+        match self {
+            Self::InMem(db) => db.get_locking_time(transaction_hash),
+            Self::File(db) => db.get_locking_time(transaction_hash),
+        }
+    }
+}
+
+impl<VSW, F> TreeDB for ClientStorage<VSW, F>
+where
+    VSW: SWCurveConfig<BaseField = F>,
+    F: PrimeField + PoseidonParams<Field = F>,
+{
+    type F = F;
+
+    fn get_sibling_path(
+        &self,
+        block_number: &u64,
+        leaf_index: usize,
+    ) -> Option<MembershipPath<Self::F>> {
+        match self {
+            Self::InMem(db) => db.get_sibling_path(block_number, leaf_index),
+            Self::File(db) => db.get_sibling_path(block_number, leaf_index),
+        }
+    }
+
+    fn add_block_leaves(
+        &mut self,
+        leaves: Vec<Self::F>,
+        block_number: u64,
+        block_hash: String,
+    ) -> Option<()> {
+        match self {
+            Self::InMem(db) => db.add_block_leaves(leaves, block_number, block_hash),
+            Self::File(db) => db.add_block_leaves(leaves, block_number, block_hash),
+        }
+    }
+
+    fn get_root(&self, block_number: &u64) -> Option<Self::F> {
+        match self {
+            Self::InMem(db) => db.get_root(block_number),
+            Self::File(db) => db.get_root(block_number),
+        }
+    }
+
+    fn get_block_hash(&self, block_number: &u64) -> Option<String> {
+        match self {
+            Self::InMem(db) => db.get_block_hash(block_number),
+            Self::File(db) => db.get_block_hash(block_number),
+        }
+    }
+
+    fn get_synced_block_number(&self) -> Option<u64> {
+        match self {
+            Self::InMem(db) => db.get_synced_block_number(),
+            Self::File(db) => db.get_synced_block_number(),
+        }
+    }
+}
+
+impl<VSW, F> KeyDB for ClientStorage<VSW, F>
+where
+    VSW: SWCurveConfig<BaseField = F> + Debug,
+    F: PrimeField + PoseidonParams<Field = F>,
+{
+    type E = VSW;
+    type Key = UserKeys<VSW>;
+
+    fn get_key(&self, public_key: Affine<VSW>) -> Option<Self::Key> {
+        match self {
+            Self::InMem(db) => db.get_key(public_key),
+            Self::File(db) => db.get_key(public_key),
+        }
+    }
+
+    fn get_all_keys(&self) -> Vec<Self::Key> {
+        match self {
+            Self::InMem(db) => db.get_all_keys(),
+            Self::File(db) => db.get_all_keys(),
+        }
+    }
+
+    fn insert_key(&mut self, key: Affine<VSW>, value: Self::Key) -> Option<()> {
+        match self {
+            Self::InMem(db) => db.insert_key(key, value),
+            Self::File(db) => db.insert_key(key, value),
+        }
+    }
+}
@@ -0,0 +1,633 @@
+use std::{
+    fmt::Debug,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::anyhow;
+use ark_ec::short_weierstrass::{Affine, SWCurveConfig};
+use ark_ff::PrimeField;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use common::{
+    crypto::poseidon::constants::PoseidonParams,
+    files::{write_atomically, write_atomically_private},
+    structs::Block,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tracing_log::log;
+use trees::{
+    membership_tree::Tree,
+    tree::{AppendTree, Position},
+    MembershipPath,
+};
+
+use super::{in_mem_storage::InMemStorage, unix_time};
+use crate::{
+    domain::{
+        vec_ark_de, vec_ark_se, Balance, PreimageFilter, PreimageStatus, StoredPreimageInfo,
+        StoredPreimageInfoVector,
+    },
+    ports::storage::{KeyDB, PreimageDB, TreeDB},
+    services::user_keys::UserKeys,
+};
+
+pub const PREIMAGE_RECORD_VERSION: u32 = 1;
+pub const KEY_RECORD_VERSION: u32 = 1;
+pub const TREE_RECORD_VERSION: u32 = 1;
+pub const LOCK_RECORD_VERSION: u32 = 1;
+pub const SYNC_RECORD_VERSION: u32 = 1;
+
+const PREIMAGES_FILE: &str = "preimages.json";
+// Holds the users' private keys unencrypted, so it is only readable by its
+// owner on unix
+const KEYS_FILE: &str = "keys.json";
+const TREES_FILE: &str = "commitment_trees.json";
+const LOCKS_FILE: &str = "locking_transactions.json";
+const SYNC_FILE: &str = "synced_block.json";
+
+// A migration upgrades a single record by one version. Entry i upgrades
+// records stored at version i + 1 to version i + 2.
+type Migration = fn(Value) -> anyhow::Result<Value>;
+
+const PREIMAGE_MIGRATIONS: &[Migration] = &[];
+const KEY_MIGRATIONS: &[Migration] = &[];
+const TREE_MIGRATIONS: &[Migration] = &[];
+const LOCK_MIGRATIONS: &[Migration] = &[];
+const SYNC_MIGRATIONS: &[Migration] = &[];
+
+#[derive(Serialize, Deserialize)]
+struct VersionedRecords<T> {
+    version: u32,
+    records: Vec<T>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+struct PreimageRecord<E: SWCurveConfig> {
+    key: String,
+    info: StoredPreimageInfo<E>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct TreeRecord<F: CanonicalSerialize + CanonicalDeserialize> {
+    block_number: u64,
+    // Empty for blocks applied before headers were stored
+    #[serde(default)]
+    block_hash: String,
+    #[serde(serialize_with = "vec_ark_se", deserialize_with = "vec_ark_de")]
+    leaves: Vec<F>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SyncRecord {
+    block_number: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct LockRecord<F: CanonicalSerialize + CanonicalDeserialize> {
+    transaction_hash: String,
+    #[serde(serialize_with = "vec_ark_se", deserialize_with = "vec_ark_de")]
+    keys: Vec<F>,
+    // Records written before lock times were stored count from when they
+    // are loaded
+    #[serde(default = "unix_time")]
+    locked_at: u64,
+}
+
+/// Durable client storage. Every record lives in an in memory cache that is
+/// written through to a directory of versioned JSON files. Files are replaced
+/// atomically so a crash leaves either the previous or the new snapshot.
+///
+/// Known limit: every write rewrites the whole file of the record type it
+/// touches, so the cost of a write grows with the number of stored records.
+pub struct FileStorage<VSW, F>
+where
+    VSW: SWCurveConfig<BaseField = F>,
+    F: PrimeField,
+{
+    path: PathBuf,
+    cache: InMemStorage<VSW, F>,
+}
+
+impl<VSW, F> FileStorage<VSW, F>
+where
+    VSW: SWCurveConfig<BaseField = F>,
+    F: PrimeField + PoseidonParams<Field = F>,
+{
+    /// Opens the storage at `path`, creating the directory if needed and
+    /// migrating any records written by an older version.
+    pub fn open<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        fs::create_dir_all(&path)?;
+        let mut cache = InMemStorage::new();
+
+        let preimages = read_records(
+            &path.join(PREIMAGES_FILE),
+            PREIMAGE_RECORD_VERSION,
+            PREIMAGE_MIGRATIONS,
+        )?;
+        for record in preimages {
+            let record: PreimageRecord<VSW> = serde_json::from_value(record)?;
+            cache.insert_preimage_entry(record.key, record.info);
+        }
+
+        // Key files written before they were made private keep the default mode
+        #[cfg(unix)]
+        if path.join(KEYS_FILE).exists() {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(path.join(KEYS_FILE), fs::Permissions::from_mode(0o600))?;
+        }
+        let keys = read_records(&path.join(KEYS_FILE), KEY_RECORD_VERSION, KEY_MIGRATIONS)?;
+        for record in keys {
+            let keys: UserKeys<VSW> = serde_json::from_value(record)?;
+            cache.key_db.insert(keys.public_key, keys);
+        }
+
+        let trees = read_records(&path.join(TREES_FILE), TREE_RECORD_VERSION, TREE_MIGRATIONS)?;
+        for record in trees {
+            let record: TreeRecord<F> = serde_json::from_value(record)?;
+            cache
+                .commitment_tree_db
+                .insert(record.block_number, Tree::from_leaves(record.leaves));
+            if !record.block_hash.is_empty() {
+                cache
+                    .block_hash_db
+                    .insert(record.block_number, record.block_hash);
+            }
+        }
+
+        let synced = read_records(&path.join(SYNC_FILE), SYNC_RECORD_VERSION, SYNC_MIGRATIONS)?;
+        for record in synced {
+            let record: SyncRecord = serde_json::from_value(record)?;
+            cache.synced_block_number = Some(record.block_number);
+        }
+        // Trees are written before the synced block number, so trees stored
+        // after the last record was written are picked up here
+        cache.advance_synced_block_number();
+
+        let locks = read_records(&path.join(LOCKS_FILE), LOCK_RECORD_VERSION, LOCK_MIGRATIONS)?;
+        for record in locks {
+            let record: LockRecord<F> = serde_json::from_value(record)?;
+            cache
+                .locking_time_db
+                .insert(record.transaction_hash.clone(), record.locked_at);
+            cache
+                .locking_db
+                .insert(record.transaction_hash, record.keys);
+        }
+
+        Ok(Self { path, cache })
+    }
+
+    fn persist_preimages(&self) -> anyhow::Result<()> {
+        let records = self
+            .cache
+            .preimage_db
+            .iter()
+            .map(|(key, info)| PreimageRecord {
+                key: key.clone(),
+                info: *info,
+            })
+            .collect::<Vec<_>>();
+        write_records(
+            &self.path.join(PREIMAGES_FILE),
+            PREIMAGE_RECORD_VERSION,
+            records,
+        )
+    }
+
+    fn persist_keys(&self) -> anyhow::Result<()> {
+        let records = self.cache.key_db.values().cloned().collect::<Vec<_>>();
+        let bytes = encode_records(KEY_RECORD_VERSION, records)?;
+        write_atomically_private(&self.path.join(KEYS_FILE), &bytes)?;
+        Ok(())
+    }
+
+    fn persist_trees(&self) -> anyhow::Result<()> {
+        let records = self
+            .cache
+            .commitment_tree_db
+            .iter()
+            .map(|(block_number, tree)| TreeRecord {
+                block_number: *block_number,
+                block_hash: self
+                    .cache
+                    .block_hash_db
+                    .get(block_number)
+                    .cloned()
+                    .unwrap_or_default(),
+                leaves: (0..tree.leaf_count() as usize)
+                    .map(|i| tree.get_node(Position::new(i, 0)))
+                    .collect(),
+            })
+            .collect::<Vec<_>>();
+        write_records(&self.path.join(TREES_FILE), TREE_RECORD_VERSION, records)
+    }
+
+    fn persist_locks(&self) -> anyhow::Result<()> {
+        let records = self
+            .cache
+            .locking_db
+            .iter()
+            .map(|(transaction_hash, keys)| LockRecord {
+                transaction_hash: transaction_hash.clone(),
+                keys: keys.clone(),
+                locked_at: self
+                    .cache
+                    .get_locking_time(transaction_hash)
+                    .unwrap_or_else(unix_time),
+            })
+            .collect::<Vec<_>>();
+        write_records(&self.path.join(LOCKS_FILE), LOCK_RECORD_VERSION, records)
+    }
+
+    fn persist_synced_block_number(&self) -> anyhow::Result<()> {
+        let records = self
+            .cache
+            .synced_block_number
+            .map(|block_number| SyncRecord { block_number })
+            .into_iter()
+            .collect::<Vec<_>>();
+        write_records(&self.path.join(SYNC_FILE), SYNC_RECORD_VERSION, records)
+    }
+}
+
+fn read_records(
+    path: &Path,
+    current_version: u32,
+    migrations: &[Migration],
+) -> anyhow::Result<Vec<Value>> {
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    let stored: VersionedRecords<Value> = serde_json::from_slice(&fs::read(path)?)?;
+    if stored.version == 0 || stored.version > current_version {
+        return Err(anyhow!(
+            "Unsupported record version {} in {:?}. Expected at most {}",
+            stored.version,
+            path,
+            current_version
+        ));
+    }
+
+    let mut records = stored.records;
+    for version in stored.version..current_version {
+        let migration = migrations
+            .get(version as usize - 1)
+            .ok_or(anyhow!("Missing migration from record version {version}"))?;
+        records = records
+            .into_iter()
+            .map(migration)
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        log::info!("Migrated {:?} to record version {}", path, version + 1);
+    }
+    Ok(records)
+}
+
+fn encode_records<T: Serialize>(version: u32, records: Vec<T>) -> anyhow::Result<Vec<u8>> {
+    Ok(serde_json::to_vec(&VersionedRecords { version, records })?)
+}
+
+fn write_records<T: Serialize>(path: &Path, version: u32, records: Vec<T>) -> anyhow::Result<()> {
+    write_atomically(path, &encode_records(version, records)?)?;
+    Ok(())
+}
+
+impl<VSW, F> PreimageDB for FileStorage<VSW, F>
+where
+    VSW: SWCurveConfig<BaseField = F>,
+    F: PrimeField + PoseidonParams<Field = F>,
+{
+    type E = VSW;
+
+    fn get_value(&self, value: VSW::BaseField) -> Option<StoredPreimageInfo<VSW>> {
+        self.cache.get_value(value)
+    }
+
+    fn get_spendable(&self) -> Option<StoredPreimageInfoVector<VSW>> {
+        self.cache.get_spendable()
+    }
+
+    fn get_all_preimages(&self) -> StoredPreimageInfoVector<VSW> {
+        self.cache.get_all_preimages()
+    }
+
+    fn get_balance(&self, owner: Affine<VSW>, token_id: VSW::BaseField) -> Balance<F> {
+        self.cache.get_balance(owner, token_id)
+    }
+
+    fn query_preimages(&self, filter: &PreimageFilter<VSW>) -> StoredPreimageInfoVector<VSW> {
+        self.cache.query_preimages(filter)
+    }
+
+    fn get_preimage(&self, key: VSW::BaseField) -> Option<StoredPreimageInfo<VSW>> {
+        self.cache.get_preimage(key)
+    }
+
+    fn insert_preimage(
+        &mut self,
+        key: VSW::BaseField,
+        preimage: StoredPreimageInfo<VSW>,
+    ) -> Option<()> {
+        let previous = self.cache.get_preimage(key);
+        self.cache.insert_preimage(key, preimage)?;
+        if let Err(e) = self.persist_preimages() {
+            log::error!("Couldn't persist preimage {}: {e}", key);
+            self.cache.remove_preimage_entry(&key.to_string());
+            if let Some(previous) = previous {
+                self.cache.insert_preimage_entry(key.to_string(), previous);
+            }
+            return None;
+        }
+        Some(())
+    }
+
+    fn set_preimage_status(&mut self, key: VSW::BaseField, status: PreimageStatus) -> Option<()> {
+        let previous = self.cache.get_preimage(key)?.status;
+        self.cache.set_preimage_status(key, status)?;
+        if let Err(e) = self.persist_preimages() {
+            log::error!("Couldn't persist status of preimage {}: {e}", key);
+            self.cache.set_preimage_status(key, previous);
+            return None;
+        }
+        Some(())
+    }
+
+    fn remove_preimage(&mut self, key: VSW::BaseField) -> Option<StoredPreimageInfo<VSW>> {
+        let preimage = self.cache.remove_preimage(key)?;
+        if let Err(e) = self.persist_preimages() {
+            log::error!("Couldn't persist removal of preimage {}: {e}", key);
+            self.cache.insert_preimage_entry(key.to_string(), preimage);
+            return None;
+        }
+        Some(preimage)
+    }
+
+    fn update_preimages(&mut self, block: Block<F>) -> Option<()> {
+        let previous = self.cache.preimage_db.clone();
+        self.cache.update_preimages(block)?;
+        if let Err(e) = self.persist_preimages() {
+            log::error!("Couldn't persist updated preimages: {e}");
+            self.cache.preimage_db = previous;
+            return None;
+        }
+        Some(())
+    }
+
+    fn insert_locking_transaction(
+        &mut self,
+        transaction_hash: String,
+        keys: Vec<VSW::BaseField>,
+    ) -> Option<()> {
+        self.cache
+            .insert_locking_transaction(transaction_hash.clone(), keys)?;
+        if let Err(e) = self.persist_locks() {
+            log::error!("Couldn't persist locking transaction {transaction_hash}: {e}");
+            self.cache.remove_locking_transaction(&transaction_hash);
+            return None;
+        }
+        Some(())
+    }
+
+    fn remove_locking_transaction(
+        &mut self,
+        transaction_hash: &str,
+    ) -> Option<Vec<VSW::BaseField>> {
+        let locked_at = self.cache.get_locking_time(transaction_hash);
+        let keys = self.cache.remove_locking_transaction(transaction_hash)?;
+        if let Err(e) = self.persist_locks() {
+            log::error!("Couldn't persist removal of locking transaction {transaction_hash}: {e}");
+            self.cache
+                .locking_db
+                .insert(transaction_hash.to_string(), keys);
+            if let Some(locked_at) = locked_at {
+                self.cache
+                    .locking_time_db
+                    .insert(transaction_hash.to_string(), locked_at);
+            }
+            return None;
+        }
+        Some(keys)
+    }
+
+    fn get_locking_transactions(&self) -> Vec<String> {
+        self.cache.get_locking_transactions()
+    }
+
+    fn get_locking_time(&self, transaction_hash: &str) -> Option<u64> {
+        self.cache.get_locking_time(transaction_hash)
+    }
+}
+
+impl<VSW, F> TreeDB for FileStorage<VSW, F>
+where
+    VSW: SWCurveConfig<BaseField = F>,
+    F: PrimeField + PoseidonParams<Field = F>,
+{
+    type F = F;
+
+    fn get_sibling_path(
+        &self,
+        block_number: &u64,
+        leaf_index: usize,
+    ) -> Option<MembershipPath<Self::F>> {
+        self.cache.get_sibling_path(block_number, leaf_index)
+    }
+
+    fn add_block_leaves(
+        &mut self,
+        leaves: Vec<Self::F>,
+        block_number: u64,
+        block_hash: String,
+    ) -> Option<()> {
+        let synced_block_number = self.cache.synced_block_number;
+        self.cache
+            .add_block_leaves(leaves, block_number, block_hash)?;
+        if let Err(e) = self.persist_trees() {
+            log::error!("Couldn't persist commitment tree for block {block_number}: {e}");
+            self.cache.commitment_tree_db.remove(&block_number);
+            self.cache.block_hash_db.remove(&block_number);
+            self.cache.synced_block_number = synced_block_number;
+            return None;
+        }
+        // The tree is already stored, so a stale record is caught up on open
+        if self.cache.synced_block_number != synced_block_number {
+            if let Err(e) = self.persist_synced_block_number() {
+                log::error!("Couldn't persist synced block number: {e}");
+            }
+        }
+        Some(())
+    }
+
+    fn get_root(&self, block_number: &u64) -> Option<Self::F> {
+        self.cache.get_root(block_number)
+    }
+
+    fn get_block_hash(&self, block_number: &u64) -> Option<String> {
+        self.cache.get_block_hash(block_number)
+    }
+
+    fn get_synced_block_number(&self) -> Option<u64> {
+        self.cache.get_synced_block_number()
+    }
+}
+
+impl<VSW, F> KeyDB for FileStorage<VSW, F>
+where
+    VSW: SWCurveConfig<BaseField = F> + Debug,
+    F: PrimeField + PoseidonParams<Field = F>,
+{
+    type E = VSW;
+    type Key = UserKeys<VSW>;
+
+    fn get_key(&self, public_key: Affine<VSW>) -> Option<Self::Key> {
+        self.cache.get_key(public_key)
+    }
+
+    fn get_all_keys(&self) -> Vec<Self::Key> {
+        self.cache.get_all_keys()
+    }
+
+    fn insert_key(&mut self, key: Affine<VSW>, value: Self::Key) -> Option<()> {
+        let previous = self.cache.key_db.get(&key).cloned();
+        self.cache.insert_key(key, value)?;
+        if let Err(e) = self.persist_keys() {
+            log::error!("Couldn't persist user keys: {e}");
+            match previous {
+                Some(keys) => self.cache.key_db.insert(key, keys),
+                None => self.cache.key_db.remove(&key),
+            };
+            return None;
+        }
+        Some(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::Preimage;
+    use ark_ec::AffineRepr;
+    use ark_ff::UniformRand;
+    use bip32::Mnemonic;
+    use common::keypair::PublicKey;
+    use curves::pallas::{Fq, PallasConfig};
+    use jf_utils::test_rng;
+    use tempfile::TempDir;
+
+    use crate::services::user_keys::generate_user_keys;
+
+    #[test]
+    fn test_records_survive_reopen() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path();
+        let mnemonic_str = "pact gun essay three dash seat page silent slogan hole huge harvest awesome fault cute alter boss thank click menu service quarter gaze salmon";
+        let mnemonic = Mnemonic::new(mnemonic_str, bip32::Language::English).unwrap();
+        let keys = generate_user_keys::<PallasConfig>(mnemonic).unwrap();
+
+        let rng = &mut test_rng();
+        let preimage = StoredPreimageInfo {
+            preimage: Preimage::new(
+                Fq::from(10u32),
+                Fq::from(1u32),
+                PublicKey::from_affine(keys.public_key),
+                Fq::rand(rng),
+            ),
+            block_number: Some(0),
+            leaf_index: Some(1),
+            nullifier: Fq::rand(rng),
+            status: Default::default(),
+        };
+        let preimage_key = Fq::rand(rng);
+        let leaves = vec![Fq::rand(rng), preimage_key];
+
+        {
+            let mut db: FileStorage<PallasConfig, Fq> = FileStorage::open(path).unwrap();
+            db.insert_key(keys.public_key, keys).unwrap();
+            db.insert_preimage(preimage_key, preimage).unwrap();
+            db.add_block_leaves(leaves.clone(), 0, "hash".to_string())
+                .unwrap();
+        }
+
+        let db: FileStorage<PallasConfig, Fq> = FileStorage::open(path).unwrap();
+        assert_eq!(db.get_key(keys.public_key), Some(keys));
+        assert_eq!(db.get_preimage(preimage_key), Some(preimage));
+        assert_eq!(
+            db.get_root(&0),
+            Some(Tree::<Fq, 8>::from_leaves(leaves.clone()).root())
+        );
+        assert!(db.get_sibling_path(&0, 1).is_some());
+        assert_eq!(db.get_block_hash(&0), Some("hash".to_string()));
+        assert_eq!(db.get_synced_block_number(), Some(0));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_keys_file_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new().unwrap();
+        let mnemonic_str = "pact gun essay three dash seat page silent slogan hole huge harvest awesome fault cute alter boss thank click menu service quarter gaze salmon";
+        let mnemonic = Mnemonic::new(mnemonic_str, bip32::Language::English).unwrap();
+        let keys = generate_user_keys::<PallasConfig>(mnemonic).unwrap();
+
+        let mut db: FileStorage<PallasConfig, Fq> = FileStorage::open(dir.path()).unwrap();
+        db.insert_key(keys.public_key, keys).unwrap();
+
+        let mode = fs::metadata(dir.path().join(KEYS_FILE))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[test]
+    fn test_newer_record_version_is_rejected() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path();
+        let stored = VersionedRecords::<Value> {
+            version: KEY_RECORD_VERSION + 1,
+            records: vec![],
+        };
+        fs::write(path.join(KEYS_FILE), serde_json::to_vec(&stored).unwrap()).unwrap();
+
+        let db = FileStorage::<PallasConfig, Fq>::open(path);
+        assert!(db.is_err());
+    }
+
+    #[test]
+    fn test_failed_persist_keeps_previous_records() {
+        let dir = TempDir::new().unwrap();
+        let rng = &mut test_rng();
+        let stored = |value: u32| StoredPreimageInfo::<PallasConfig> {
+            preimage: Preimage::new(
+                Fq::from(value),
+                Fq::from(1u32),
+                PublicKey::from_affine(Affine::<PallasConfig>::generator()),
+                Fq::from(value + 100),
+            ),
+            block_number: None,
+            leaf_index: None,
+            nullifier: Fq::from(value + 200),
+            status: Default::default(),
+        };
+        let (kept, lost) = (Fq::rand(rng), Fq::rand(rng));
+
+        let mut db: FileStorage<PallasConfig, Fq> =
+            FileStorage::open(dir.path().join("storage")).unwrap();
+        db.insert_preimage(kept, stored(1)).unwrap();
+        // Writes fail once the storage directory is gone
+        fs::remove_dir_all(dir.path().join("storage")).unwrap();
+
+        assert!(db.insert_preimage(lost, stored(2)).is_none());
+        assert!(db
+            .add_block_leaves(vec![kept], 0, "hash".to_string())
+            .is_none());
+        assert_eq!(db.get_preimage(kept), Some(stored(1)));
+        assert_eq!(db.get_preimage(lost), None);
+        assert_eq!(db.get_root(&0), None);
+        assert_eq!(db.get_block_hash(&0), None);
+        assert_eq!(db.get_synced_block_number(), None);
+    }
+}
@@ -1,8 +1,20 @@
+pub mod client_storage;
+pub mod file_storage;
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Seconds since the unix epoch, zero if the clock is set before it
+pub(crate) fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
 pub mod in_mem_storage {
     use std::{collections::HashMap, fmt::Debug};
 
     use ark_ec::short_weierstrass::{Affine, SWCurveConfig};
-    use ark_ff::PrimeField;
+    use ark_ff::{PrimeField, Zero};
     use common::{crypto::poseidon::constants::PoseidonParams, structs::Block};
     use trees::{
         membership_tree::{MembershipTree, Tree},
@@ -11,7 +23,9 @@ pub mod in_mem_storage {
     };
 
     use crate::{
-        domain::{PreimageStatus, StoredPreimageInfo, StoredPreimageInfoVector},
+        domain::{
+            Balance, PreimageFilter, PreimageStatus, StoredPreimageInfo, StoredPreimageInfoVector,
+        },
         ports::storage::{KeyDB, PreimageDB, TreeDB},
         services::user_keys::UserKeys,
     };
@@ -22,7 +36,17 @@ pub mod in_mem_storage {
         F: PrimeField,
     {
         pub preimage_db: HashMap<String, StoredPreimageInfo<VSW>>,
+        // Nullifier -> preimage key, to find the notes spent in a block
+        pub nullifier_db: HashMap<String, String>,
         pub commitment_tree_db: HashMap<u64, Tree<F, 8>>,
+        // Block number -> header hash of the block
+        pub block_hash_db: HashMap<u64, String>,
+        // Last block with a tree that follows a tree for every block before it
+        pub synced_block_number: Option<u64>,
+        // Transaction hash -> keys of the inputs it locked
+        pub locking_db: HashMap<String, Vec<F>>,
+        // Transaction hash -> unix time at which it was recorded
+        pub locking_time_db: HashMap<String, u64>,
         pub key_db: HashMap<Affine<VSW>, UserKeys<VSW>>,
     }
 
@@ -34,10 +58,45 @@ pub mod in_mem_storage {
         pub fn new() -> Self {
             Self {
                 preimage_db: HashMap::new(),
+                nullifier_db: HashMap::new(),
                 commitment_tree_db: HashMap::new(),
+                block_hash_db: HashMap::new(),
+                synced_block_number: None,
+                locking_db: HashMap::new(),
+                locking_time_db: HashMap::new(),
                 key_db: HashMap::new(),
             }
         }
+
+        pub(crate) fn insert_preimage_entry(
+            &mut self,
+            key: String,
+            preimage: StoredPreimageInfo<VSW>,
+        ) {
+            if !preimage.nullifier.is_zero() {
+                self.nullifier_db
+                    .insert(preimage.nullifier.to_string(), key.clone());
+            }
+            self.preimage_db.insert(key, preimage);
+        }
+
+        pub(crate) fn remove_preimage_entry(
+            &mut self,
+            key: &str,
+        ) -> Option<StoredPreimageInfo<VSW>> {
+            let preimage = self.preimage_db.remove(key)?;
+            self.nullifier_db.remove(&preimage.nullifier.to_string());
+            Some(preimage)
+        }
+
+        // Moves the synced block number past every consecutive block with a tree
+        pub(crate) fn advance_synced_block_number(&mut self) {
+            let mut next = self.synced_block_number.map_or(0, |n| n + 1);
+            while self.commitment_tree_db.contains_key(&next) {
+                self.synced_block_number = Some(next);
+                next += 1;
+            }
+        }
     }
     impl<VSW, F> Default for InMemStorage<VSW, F>
     where
@@ -56,15 +115,56 @@ pub mod in_mem_storage {
     {
         type E = VSW;
 
-        fn get_value(&self, _value: VSW::BaseField) -> Option<StoredPreimageInfo<VSW>> {
-            todo!()
+        fn get_value(&self, value: VSW::BaseField) -> Option<StoredPreimageInfo<VSW>> {
+            // Any spendable note of exactly this value
+            self.preimage_db
+                .values()
+                .find(|p| {
+                    p.preimage.value == value
+                        && p.block_number.is_some()
+                        && p.status == PreimageStatus::Unspent
+                })
+                .cloned()
+        }
+
+        fn get_balance(&self, owner: Affine<VSW>, token_id: VSW::BaseField) -> Balance<F> {
+            let filter = PreimageFilter {
+                owner: Some(owner),
+                token_id: Some(token_id),
+                ..Default::default()
+            };
+            self.preimage_db
+                .values()
+                .filter(|p| filter.matches(p))
+                .fold(Balance::default(), |mut balance, p| {
+                    balance.add(p);
+                    balance
+                })
+        }
+
+        fn query_preimages(&self, filter: &PreimageFilter<VSW>) -> StoredPreimageInfoVector<VSW> {
+            let mut preimages = self
+                .preimage_db
+                .iter()
+                .filter(|(_, p)| filter.matches(p))
+                .collect::<Vec<_>>();
+            // Stable order for pagination: included notes by position, then pending ones
+            preimages.sort_by_key(|(key, p)| {
+                (p.block_number.is_none(), p.block_number, p.leaf_index, *key)
+            });
+            preimages
+                .into_iter()
+                .skip(filter.offset)
+                .take(filter.limit)
+                .map(|(_, p)| *p)
+                .collect()
         }
 
         fn get_spendable(&self) -> Option<StoredPreimageInfoVector<VSW>> {
             let spendable = self
                 .preimage_db
                 .values()
-                .filter(|p| p.block_number.is_some())
+                .filter(|p| p.block_number.is_some() && p.status == PreimageStatus::Unspent)
                 .cloned()
                 .collect::<Vec<_>>();
             if spendable.is_empty() {
@@ -87,18 +187,31 @@ pub mod in_mem_storage {
             if self.preimage_db.contains_key(&key.to_string()) {
                 return None;
             }
-            // Insert returns None if there wasnt a key.
-            let _insert = self.preimage_db.insert(key.to_string(), preimage);
+            self.insert_preimage_entry(key.to_string(), preimage);
             Some(())
         }
 
+        fn set_preimage_status(
+            &mut self,
+            key: VSW::BaseField,
+            status: PreimageStatus,
+        ) -> Option<()> {
+            let preimage = self.preimage_db.get_mut(&key.to_string())?;
+            preimage.status = status;
+            Some(())
+        }
+
+        fn remove_preimage(&mut self, key: VSW::BaseField) -> Option<StoredPreimageInfo<VSW>> {
+            self.remove_preimage_entry(&key.to_string())
+        }
+
         fn get_all_preimages(&self) -> StoredPreimageInfoVector<VSW> {
             let mut v = Vec::new();
             self.preimage_db.values().for_each(|&x| v.push(x));
             v
         }
 
-        fn update_preimages(&mut self, block: Block<F>) {
+        fn update_preimages(&mut self, block: Block<F>) -> Option<()> {
             // Find all commitments in block and set to them to spendable
             block.commitments.iter().enumerate().for_each(|(i, c)| {
                 if let Some(preimage) = self.preimage_db.get_mut(&c.to_string()) {
@@ -107,13 +220,49 @@ pub mod in_mem_storage {
                 }
             });
             // Find all nullifiers in block and set to them to spent
-            block.nullifiers.iter().for_each(|&n| {
-                if let Some(preimage) = self.preimage_db.get_mut(&n.to_string()) {
-                    if preimage.nullifier == n {
-                        preimage.status = PreimageStatus::Spent;
-                    }
+            block.nullifiers.iter().for_each(|n| {
+                // Mints publish zero nullifiers
+                if n.is_zero() {
+                    return;
+                }
+                let Some(key) = self.nullifier_db.get(&n.to_string()) else {
+                    return;
+                };
+                if let Some(preimage) = self.preimage_db.get_mut(key) {
+                    preimage.status = PreimageStatus::Spent;
                 }
             });
+            Some(())
+        }
+
+        fn insert_locking_transaction(
+            &mut self,
+            transaction_hash: String,
+            keys: Vec<VSW::BaseField>,
+        ) -> Option<()> {
+            if self.locking_db.contains_key(&transaction_hash) {
+                return None;
+            }
+            self.locking_time_db
+                .insert(transaction_hash.clone(), super::unix_time());
+            self.locking_db.insert(transaction_hash, keys);
+            Some(())
+        }
+
+        fn remove_locking_transaction(
+            &mut self,
+            transaction_hash: &str,
+        ) -> Option<Vec<VSW::BaseField>> {
+            self.locking_time_db.remove(transaction_hash);
+            self.locking_db.remove(transaction_hash)
+        }
+
+        fn get_locking_transactions(&self) -> Vec<String> {
+            self.locking_db.keys().cloned().collect()
+        }
+
+        fn get_locking_time(&self, transaction_hash: &str) -> Option<u64> {
+            self.locking_time_db.get(transaction_hash).copied()
         }
     }
 
@@ -134,12 +283,19 @@ pub mod in_mem_storage {
                 .and_then(|t| t.membership_witness(leaf_index))
         }
 
-        fn add_block_leaves(&mut self, leaves: Vec<Self::F>, block_number: u64) -> Option<()> {
+        fn add_block_leaves(
+            &mut self,
+            leaves: Vec<Self::F>,
+            block_number: u64,
+            block_hash: String,
+        ) -> Option<()> {
             let tree = Tree::from_leaves(leaves);
             if self.commitment_tree_db.contains_key(&block_number) {
                 return None;
             }
             self.commitment_tree_db.insert(block_number, tree);
+            self.block_hash_db.insert(block_number, block_hash);
+            self.advance_synced_block_number();
             Some(())
         }
 
@@ -149,6 +305,14 @@ pub mod in_mem_storage {
             }
             self.commitment_tree_db.get(block_number).map(|t| t.root())
         }
+
+        fn get_block_hash(&self, block_number: &u64) -> Option<String> {
+            self.block_hash_db.get(block_number).cloned()
+        }
+
+        fn get_synced_block_number(&self) -> Option<u64> {
+            self.synced_block_number
+        }
     }
 
     impl<VSW, F> KeyDB for InMemStorage<VSW, F>
@@ -163,6 +327,10 @@ pub mod in_mem_storage {
             self.key_db.get(&public_key).cloned()
         }
 
+        fn get_all_keys(&self) -> Vec<Self::Key> {
+            self.key_db.values().cloned().collect()
+        }
+
         fn insert_key(&mut self, key: Affine<VSW>, value: Self::Key) -> Option<()> {
             if self.key_db.contains_key(&key) {
                 return None;
@@ -171,4 +339,68 @@ pub mod in_mem_storage {
             Some(())
         }
     }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::domain::Preimage;
+        use ark_ec::AffineRepr;
+        use common::keypair::PublicKey;
+        use curves::pallas::{Fq, PallasConfig};
+
+        fn stored_preimage(value: u64, nullifier: u64) -> StoredPreimageInfo<PallasConfig> {
+            StoredPreimageInfo {
+                preimage: Preimage::new(
+                    Fq::from(value),
+                    Fq::from(1u64),
+                    PublicKey::from_affine(Affine::<PallasConfig>::generator()),
+                    Fq::from(value + 100),
+                ),
+                block_number: None,
+                leaf_index: None,
+                nullifier: Fq::from(nullifier),
+                status: PreimageStatus::Unspent,
+            }
+        }
+
+        #[test]
+        fn test_block_nullifiers_mark_notes_spent() {
+            let mut db = InMemStorage::<PallasConfig, Fq>::new();
+            let (spent, kept) = (Fq::from(1u64), Fq::from(2u64));
+            db.insert_preimage(spent, stored_preimage(10, 11)).unwrap();
+            db.insert_preimage(kept, stored_preimage(20, 21)).unwrap();
+
+            db.update_preimages(Block {
+                block_number: 3,
+                commitments: vec![kept],
+                // Zero nullifiers of mints and unknown nullifiers are skipped
+                nullifiers: vec![Fq::zero(), Fq::from(11u64), Fq::from(99u64)],
+                ..Default::default()
+            })
+            .unwrap();
+
+            assert_eq!(
+                db.get_preimage(spent).unwrap().status,
+                PreimageStatus::Spent
+            );
+            let kept = db.get_preimage(kept).unwrap();
+            assert_eq!(kept.status, PreimageStatus::Unspent);
+            assert_eq!(kept.block_number, Some(3));
+            assert_eq!(kept.leaf_index, Some(0));
+        }
+
+        #[test]
+        fn test_removed_notes_leave_the_nullifier_index() {
+            let mut db = InMemStorage::<PallasConfig, Fq>::new();
+            let key = Fq::from(1u64);
+            db.insert_preimage(key, stored_preimage(10, 11)).unwrap();
+            assert_eq!(
+                db.nullifier_db.get(&Fq::from(11u64).to_string()),
+                Some(&key.to_string())
+            );
+
+            db.remove_preimage_entry(&key.to_string());
+            assert!(db.nullifier_db.is_empty());
+        }
+    }
 }
@@ -0,0 +1,166 @@
+use crate::domain::{Preimage, PreimageStatus, StoredPreimageInfo};
+use crate::ports::committable::{Committable, Nullifiable};
+use crate::ports::storage::{KeyDB, PreimageDB};
+use crate::services::user_keys::UserKeys;
+use ark_ec::{
+    pairing::Pairing,
+    short_weierstrass::{Affine, Projective, SWCurveConfig},
+    CurveConfig,
+};
+use ark_ff::PrimeField;
+use common::crypto::poseidon::constants::PoseidonParams;
+use common::keypair::PublicKey;
+use common::structs::{BlockTransaction, Commitment};
+use jf_primitives::rescue::RescueParameter;
+use jf_relation::gadgets::ecc::SWToTEConParam;
+use plonk_prover::primitives::circuits::kem_dem::{native, KemDemParams};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tracing_log::log;
+use zk_macros::client_bounds;
+
+// Transfers encrypt (value, token_id, salt) of the first commitment to its recipient
+const PLAINTEXT_LEN: usize = 3;
+const EPHEMERAL_KEY_LEN: usize = 2;
+
+/// Trial decrypts the note of every transaction with every stored key and stores
+/// the preimages that belong to us. Returns the commitments that were discovered.
+#[client_bounds]
+pub async fn discover_notes_process<
+    P,
+    V,
+    VSW,
+    Storage: PreimageDB<E = P> + KeyDB<E = P, Key = UserKeys<P>>,
+>(
+    db: Arc<Mutex<Storage>>,
+    transactions: &[BlockTransaction<V::ScalarField>],
+) -> anyhow::Result<Vec<Commitment<P::BaseField>>> {
+    let mut db = db.lock().await;
+    let user_keys = db.get_all_keys();
+    let mut discovered = Vec::new();
+
+    for transaction in transactions {
+        let Some(commitment) = transaction.commitments.first() else {
+            continue;
+        };
+        for keys in user_keys.iter() {
+            let Some(stored_preimage) = decrypt_note::<P>(
+                *commitment,
+                &transaction.eph_pub_key,
+                &transaction.ciphertexts,
+                keys,
+            ) else {
+                continue;
+            };
+            // Already known notes (e.g. transfers to ourselves) are kept untouched
+            if db.insert_preimage(*commitment, stored_preimage).is_some() {
+                log::debug!("Discovered incoming note {}", commitment);
+                discovered.push(Commitment(*commitment));
+            }
+            break;
+        }
+    }
+
+    Ok(discovered)
+}
+
+/// Attempts to open a note encrypted to `user_keys`. The note is only accepted if the
+/// decrypted preimage hashes to the published commitment.
+pub(crate) fn decrypt_note<P>(
+    commitment: P::BaseField,
+    eph_pub_key: &[P::BaseField],
+    ciphertexts: &[P::BaseField],
+    user_keys: &UserKeys<P>,
+) -> Option<StoredPreimageInfo<P>>
+where
+    P: SWCurveConfig,
+    <P as CurveConfig>::BaseField: PrimeField + KemDemParams<Field = P::BaseField>,
+{
+    if eph_pub_key.len() != EPHEMERAL_KEY_LEN || ciphertexts.len() != PLAINTEXT_LEN {
+        return None;
+    }
+    // Mints publish a zeroed ephemeral key, which is not a valid point
+    let eph_pub_key = Affine::<P>::new_unchecked(eph_pub_key[0], eph_pub_key[1]);
+    if !eph_pub_key.is_on_curve() || !eph_pub_key.is_in_correct_subgroup_assuming_on_curve() {
+        return None;
+    }
+
+    let plaintext =
+        native::decrypt::<P, P::BaseField>(user_keys.private_key, eph_pub_key, ciphertexts).ok()?;
+    let preimage = Preimage::new(
+        plaintext[0],
+        plaintext[1],
+        PublicKey::from_affine(user_keys.public_key),
+        plaintext[2],
+    );
+    if preimage.commitment_hash().ok()?.0 != commitment {
+        return None;
+    }
+    let nullifier = preimage.nullifier_hash(&user_keys.nullifier_key).ok()?;
+
+    Some(StoredPreimageInfo {
+        preimage,
+        block_number: None,
+        leaf_index: None,
+        nullifier,
+        status: PreimageStatus::Unspent,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::user_keys::generate_user_keys;
+    use ark_ec::CurveGroup;
+    use ark_std::UniformRand;
+    use bip32::Mnemonic;
+    use curves::pallas::{Fq, Fr, PallasConfig};
+
+    fn user_keys(mnemonic_str: &str) -> UserKeys<PallasConfig> {
+        let mnemonic = Mnemonic::new(mnemonic_str, bip32::Language::English).unwrap();
+        generate_user_keys::<PallasConfig>(mnemonic).unwrap()
+    }
+
+    #[test]
+    fn test_decrypt_note_only_for_recipient() {
+        let mut rng = ark_std::test_rng();
+        let recipient = user_keys("pact gun essay three dash seat page silent slogan hole huge harvest awesome fault cute alter boss thank click menu service quarter gaze salmon");
+        let other = user_keys("abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon art");
+
+        let preimage = Preimage::<PallasConfig>::new(
+            Fq::from(5u32),
+            Fq::from(1u32),
+            PublicKey::from_affine(recipient.public_key),
+            Fq::from(3u32),
+        );
+        let commitment = preimage.commitment_hash().unwrap().0;
+        let ephemeral_key = Fr::rand(&mut rng);
+        let eph_pub = (PallasConfig::GENERATOR * ephemeral_key).into_affine();
+        let ciphertexts = native::encrypt::<PallasConfig, Fq>(
+            ephemeral_key,
+            recipient.public_key,
+            &[Fq::from(5u32), Fq::from(1u32), Fq::from(3u32)],
+        )
+        .unwrap();
+
+        let stored = decrypt_note(
+            commitment,
+            &[eph_pub.x, eph_pub.y],
+            &ciphertexts,
+            &recipient,
+        )
+        .expect("Recipient should decrypt the note");
+        assert_eq!(stored.preimage, preimage);
+        assert_eq!(stored.status, PreimageStatus::Unspent);
+
+        assert!(decrypt_note(commitment, &[eph_pub.x, eph_pub.y], &ciphertexts, &other).is_none());
+        // Mint transactions carry zeroed keys and ciphertexts
+        assert!(decrypt_note(
+            commitment,
+            &[Fq::from(0u32), Fq::from(0u32)],
+            &[Fq::from(0u32); 3],
+            &recipient
+        )
+        .is_none());
+    }
+}
@@ -1,7 +1,7 @@
 use super::MintPreimage;
 use crate::domain::Preimage;
 use crate::domain::{PreimageStatus, StoredPreimageInfo};
-use crate::ports::committable::Committable;
+use crate::ports::committable::{Committable, Nullifiable};
 use anyhow::Context;
 use ark_ec::{
     pairing::Pairing,
@@ -10,27 +10,38 @@ use ark_ec::{
 };
 use ark_ff::PrimeField;
 use common::crypto::poseidon::constants::PoseidonParams;
-use common::structs::Transaction;
 use jf_primitives::rescue::RescueParameter;
 use jf_relation::gadgets::ecc::SWToTEConParam;
 use plonk_prover::client::circuits::circuit_inputs::CircuitInputs;
 use plonk_prover::primitives::circuits::kem_dem::KemDemParams;
 use zk_macros::client_bounds;
 
-#[client_bounds]
-pub(crate) fn compute_mint_preimages<P, V, VSW>(
+/// Builds the preimages of the minted notes owned by one of our keys. `nullifier_keys[i]` is the
+/// nullifier key of the owner of `mint_details[i]`, if we hold it.
+pub(crate) fn compute_mint_preimages<P>(
     mint_details: Vec<Preimage<P>>,
-    transaction: &Transaction<V>,
-) -> anyhow::Result<Vec<MintPreimage<P>>> {
+    nullifier_keys: &[Option<P::BaseField>],
+) -> anyhow::Result<Vec<MintPreimage<P>>>
+where
+    P: SWCurveConfig,
+    <P as CurveConfig>::BaseField: PrimeField + PoseidonParams<Field = P::BaseField>,
+{
     let mut stored_preimages = Vec::new();
-    for mint_detail in mint_details {
+    for (mint_detail, nullifier_key) in mint_details.into_iter().zip(nullifier_keys) {
+        let Some(nullifier_key) = nullifier_key else {
+            // Notes minted to someone else can't be spent by us
+            continue;
+        };
         let preimage_key = mint_detail
             .commitment_hash()
             .context("Failed to compute commitment hash")?;
+        let nullifier = mint_detail
+            .nullifier_hash(nullifier_key)
+            .context("Failed to compute nullifier hash")?;
 
         let new_preimage = StoredPreimageInfo {
             preimage: mint_detail,
-            nullifier: transaction.nullifiers[0].0,
+            nullifier,
             block_number: None,
             leaf_index: None,
             status: PreimageStatus::Unspent,
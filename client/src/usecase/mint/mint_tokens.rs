@@ -28,7 +28,7 @@ pub(crate) fn mint_tokens<P, V, VSW, Proof: Prover<P, V, VSW>>(
     let client_pub_inputs: ClientPubInput<<V as Pairing>::ScalarField> =
         ClientPubInput::new(pub_inputs, commitments_nullifiers_count)?;
 
-    let transaction = Transaction::new(
+    let mut transaction = Transaction::new(
         client_pub_inputs
             .commitments
             .into_iter()
@@ -46,5 +46,6 @@ pub(crate) fn mint_tokens<P, V, VSW, Proof: Prover<P, V, VSW>>(
         client_pub_inputs.swap_field,
         CircuitType::Mint(commitments_nullifiers_count.0),
     );
+    transaction.set_commitment_root(client_pub_inputs.commitment_root);
     Ok(transaction)
 }
@@ -1,5 +1,9 @@
 use crate::domain::{Preimage, StoredPreimageInfo};
-use crate::ports::{prover::Prover, storage::PreimageDB};
+use crate::ports::{
+    prover::Prover,
+    storage::{KeyDB, PreimageDB},
+};
+use crate::services::user_keys::UserKeys;
 use crate::utils;
 use ark_ec::{
     pairing::Pairing,
@@ -40,7 +44,7 @@ pub async fn mint_process<
     V,
     VSW,
     Proof: Prover<P, V, VSW>,
-    Storage: PreimageDB<E = P>,
+    Storage: PreimageDB<E = P> + KeyDB<E = P, Key = UserKeys<P>>,
     Comms: Notifier<Info = Transaction<V>>,
 >(
     db: Arc<Mutex<Storage>>,
@@ -49,10 +53,18 @@ pub async fn mint_process<
     mint_details: Vec<Preimage<P>>,
 ) -> anyhow::Result<Transaction<V>> {
     let (proving_key, circuit) = get_circuit_and_pk(prover, &mint_details).await?;
-    let (transaction, preimages) =
-        spawn_mint::<_, _, _, Proof>(circuit, mint_details, proving_key).await?;
+    let transaction =
+        spawn_mint::<_, _, _, Proof>(circuit, mint_details.clone(), proving_key).await?;
     let mut db = db.lock().await;
 
+    let nullifier_keys = mint_details
+        .iter()
+        .map(|preimage| {
+            db.get_key(preimage.public_key.as_affine())
+                .map(|keys| keys.nullifier_key)
+        })
+        .collect::<Vec<_>>();
+    let preimages = compute_mint_preimages(mint_details, &nullifier_keys)?;
     for mint_preimage in preimages {
         db.insert_preimage(mint_preimage.key.0, mint_preimage.preimage)
             .ok_or(anyhow::anyhow!("Error inserting mint preimage"))?;
@@ -106,16 +118,14 @@ async fn spawn_mint<P, V, VSW, Proof: Prover<P, V, VSW>>(
     mint_circuit: Box<dyn ClientPlonkCircuit<P, V, VSW>>,
     mint_details: Vec<Preimage<P>>,
     proving_key: ProvingKey<V>,
-) -> anyhow::Result<(Transaction<V>, Vec<MintPreimage<P>>)> {
-    let (transaction, preimages) =
-        tokio::task::spawn_blocking(move || -> anyhow::Result<(_, _)> {
-            let inputs = build_mint_inputs::<P, V, VSW>(mint_details.clone())?;
-            let transaction = mint_tokens::<P, V, _, Proof>(mint_circuit, inputs, &proving_key)
-                .map_err(|_| anyhow::anyhow!("Error minting tokens",))?;
-            let preimages = compute_mint_preimages(mint_details, &transaction)?;
+) -> anyhow::Result<Transaction<V>> {
+    let transaction = tokio::task::spawn_blocking(move || -> anyhow::Result<_> {
+        let inputs = build_mint_inputs::<P, V, VSW>(mint_details)?;
+        let transaction = mint_tokens::<P, V, _, Proof>(mint_circuit, inputs, &proving_key)
+            .map_err(|_| anyhow::anyhow!("Error minting tokens",))?;
 
-            Ok((transaction, preimages))
-        })
-        .await??;
-    Ok((transaction, preimages))
+        Ok(transaction)
+    })
+    .await??;
+    Ok(transaction)
 }
@@ -1,3 +1,8 @@
 pub mod create_keys;
+pub mod discover_notes;
 pub mod mint;
+pub mod release_inputs;
+pub mod swap;
+pub mod sync_blocks;
 pub mod transfer;
+pub mod withdraw;
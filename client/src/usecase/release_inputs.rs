@@ -0,0 +1,256 @@
+use crate::domain::PreimageStatus;
+use crate::ports::storage::PreimageDB;
+use crate::ports::transaction_status::TransactionStatusSource;
+use crate::services::storage::unix_time;
+use ark_ec::short_weierstrass::SWCurveConfig;
+use common::structs::TransactionStatus;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::MissedTickBehavior;
+use tracing_log::log;
+
+// How long a transaction the sequencer doesn't know keeps its inputs locked.
+// The sequencer only records a transaction once its proof is verified, so it
+// has no status for one it is still verifying.
+pub const UNKNOWN_TRANSACTION_GRACE_PERIOD: Duration = Duration::from_secs(600);
+
+/// Asks `source` what became of the transactions holding locked inputs. The
+/// inputs of rejected transactions, and of those the source still doesn't know
+/// `grace_period` after they were recorded, are made spendable again and their
+/// pending change is dropped. Included transactions stop being tracked, as
+/// their block marks the inputs spent. Returns how many transactions were
+/// released.
+pub async fn release_rejected_inputs_process<P, Storage, Source>(
+    db: Arc<Mutex<Storage>>,
+    source: &Source,
+    grace_period: Duration,
+) -> anyhow::Result<usize>
+where
+    P: SWCurveConfig,
+    Storage: PreimageDB<E = P>,
+    Source: TransactionStatusSource,
+{
+    let transaction_hashes = db.lock().await.get_locking_transactions();
+    let mut released = 0;
+    for transaction_hash in transaction_hashes {
+        let status = source.get_transaction_status(&transaction_hash).await?;
+        if status.is_none()
+            && within_grace_period(&*db.lock().await, &transaction_hash, grace_period)
+        {
+            continue;
+        }
+        match status {
+            Some(TransactionStatus::Pending) => {}
+            Some(TransactionStatus::Included { .. }) => {
+                db.lock()
+                    .await
+                    .remove_locking_transaction(&transaction_hash);
+            }
+            status => {
+                log::info!(
+                    "Releasing inputs of transaction {}: {:?}",
+                    transaction_hash,
+                    status
+                );
+                let mut db = db.lock().await;
+                let Some(keys) = db.remove_locking_transaction(&transaction_hash) else {
+                    continue;
+                };
+                for key in keys {
+                    let Some(preimage) = db.get_preimage(key) else {
+                        continue;
+                    };
+                    if preimage.status == PreimageStatus::Locked {
+                        db.set_preimage_status(key, PreimageStatus::Unspent)
+                            .ok_or(anyhow::anyhow!("Error unlocking preimage {}", key))?;
+                    } else if preimage.block_number.is_none() {
+                        // Change of the transaction, which will never be included
+                        db.remove_preimage(key)
+                            .ok_or(anyhow::anyhow!("Error removing change preimage {}", key))?;
+                    }
+                }
+                released += 1;
+            }
+        }
+    }
+    Ok(released)
+}
+
+fn within_grace_period<Storage: PreimageDB>(
+    db: &Storage,
+    transaction_hash: &str,
+    grace_period: Duration,
+) -> bool {
+    db.get_locking_time(transaction_hash)
+        .is_some_and(|locked_at| {
+            Duration::from_secs(unix_time().saturating_sub(locked_at)) < grace_period
+        })
+}
+
+// Releases the inputs of rejected transactions in the background. Runs whether
+// or not blocks are synced, as transactions can be rejected either way.
+pub async fn run_input_release<P, Storage, Source>(
+    db: Arc<Mutex<Storage>>,
+    source: Source,
+    period: Duration,
+) where
+    P: SWCurveConfig,
+    Storage: PreimageDB<E = P>,
+    Source: TransactionStatusSource,
+{
+    let mut interval = tokio::time::interval(period);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        interval.tick().await;
+        match release_rejected_inputs_process(db.clone(), &source, UNKNOWN_TRANSACTION_GRACE_PERIOD)
+            .await
+        {
+            Ok(0) => {}
+            Ok(released) => log::info!("Released inputs of {} rejected transactions", released),
+            Err(e) => log::error!("Checking locked inputs failed: {:?}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{Preimage, StoredPreimageInfo};
+    use crate::services::storage::in_mem_storage::InMemStorage;
+    use ark_ec::{short_weierstrass::Affine, AffineRepr};
+    use async_trait::async_trait;
+    use common::keypair::PublicKey;
+    use curves::pallas::{Fq, PallasConfig};
+    use std::collections::HashMap;
+
+    struct MapStatusSource(HashMap<String, TransactionStatus>);
+
+    #[async_trait]
+    impl TransactionStatusSource for MapStatusSource {
+        async fn get_transaction_status(
+            &self,
+            transaction_hash: &str,
+        ) -> anyhow::Result<Option<TransactionStatus>> {
+            Ok(self.0.get(transaction_hash).cloned())
+        }
+    }
+
+    fn locked_preimage(value: u32) -> StoredPreimageInfo<PallasConfig> {
+        StoredPreimageInfo {
+            preimage: Preimage::new(
+                Fq::from(value),
+                Fq::from(1u32),
+                PublicKey::from_affine(Affine::<PallasConfig>::generator()),
+                Fq::from(value + 100),
+            ),
+            block_number: Some(0),
+            leaf_index: Some(value as usize),
+            nullifier: Fq::from(value + 200),
+            status: PreimageStatus::Locked,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_only_rejected_transactions_release_their_inputs() {
+        let mut storage = InMemStorage::<PallasConfig, Fq>::new();
+        for (value, transaction_hash) in [(1u32, "pending"), (2, "included"), (3, "rejected")] {
+            let key = Fq::from(value);
+            storage
+                .insert_preimage(key, locked_preimage(value))
+                .unwrap();
+            storage
+                .insert_locking_transaction(transaction_hash.to_string(), vec![key])
+                .unwrap();
+        }
+        // Pending change of the rejected transaction
+        let change = Fq::from(4u32);
+        let mut change_preimage = locked_preimage(4);
+        change_preimage.block_number = None;
+        change_preimage.status = PreimageStatus::Unspent;
+        storage.insert_preimage(change, change_preimage).unwrap();
+        storage.remove_locking_transaction("rejected");
+        storage
+            .insert_locking_transaction("rejected".to_string(), vec![Fq::from(3u32), change])
+            .unwrap();
+        let db = Arc::new(Mutex::new(storage));
+        let source = MapStatusSource(HashMap::from([
+            ("pending".to_string(), TransactionStatus::Pending),
+            (
+                "included".to_string(),
+                TransactionStatus::Included { block_number: 1 },
+            ),
+            (
+                "rejected".to_string(),
+                TransactionStatus::Rejected {
+                    reason: "evicted".to_string(),
+                },
+            ),
+        ]));
+
+        let released =
+            release_rejected_inputs_process(db.clone(), &source, UNKNOWN_TRANSACTION_GRACE_PERIOD)
+                .await
+                .unwrap();
+        assert_eq!(released, 1);
+
+        let db = db.lock().await;
+        let status = |value: u32| db.get_preimage(Fq::from(value)).unwrap().status;
+        assert_eq!(status(1), PreimageStatus::Locked);
+        assert_eq!(status(2), PreimageStatus::Locked);
+        assert_eq!(status(3), PreimageStatus::Unspent);
+        assert_eq!(db.get_preimage(Fq::from(4u32)), None);
+        assert_eq!(db.get_locking_transactions(), vec!["pending".to_string()]);
+    }
+
+    fn unknown_transaction_db() -> Arc<Mutex<InMemStorage<PallasConfig, Fq>>> {
+        let mut storage = InMemStorage::<PallasConfig, Fq>::new();
+        storage
+            .insert_preimage(Fq::from(1u32), locked_preimage(1))
+            .unwrap();
+        storage
+            .insert_locking_transaction("forgotten".to_string(), vec![Fq::from(1u32)])
+            .unwrap();
+        Arc::new(Mutex::new(storage))
+    }
+
+    #[tokio::test]
+    async fn test_unknown_transactions_release_their_inputs() {
+        let db = unknown_transaction_db();
+
+        let released = release_rejected_inputs_process(
+            db.clone(),
+            &MapStatusSource(HashMap::new()),
+            Duration::ZERO,
+        )
+        .await
+        .unwrap();
+        assert_eq!(released, 1);
+        assert_eq!(
+            db.lock().await.get_preimage(Fq::from(1u32)).unwrap().status,
+            PreimageStatus::Unspent
+        );
+    }
+
+    #[tokio::test]
+    async fn test_recent_unknown_transactions_keep_their_inputs() {
+        // The sequencer may still be verifying the proof
+        let db = unknown_transaction_db();
+
+        let released = release_rejected_inputs_process(
+            db.clone(),
+            &MapStatusSource(HashMap::new()),
+            UNKNOWN_TRANSACTION_GRACE_PERIOD,
+        )
+        .await
+        .unwrap();
+        assert_eq!(released, 0);
+        let db = db.lock().await;
+        assert_eq!(
+            db.get_preimage(Fq::from(1u32)).unwrap().status,
+            PreimageStatus::Locked
+        );
+        assert_eq!(db.get_locking_transactions(), vec!["forgotten".to_string()]);
+    }
+}
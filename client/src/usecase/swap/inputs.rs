@@ -0,0 +1,82 @@
+use crate::adapters::rest_api::structs::SwapInput;
+use crate::ports::keys::FullKey;
+use crate::ports::storage::{KeyDB, PreimageDB, TreeDB};
+use crate::services::user_keys::UserKeys;
+use ark_ec::{
+    pairing::Pairing,
+    short_weierstrass::{Affine, Projective, SWCurveConfig},
+    CurveConfig,
+};
+use ark_ff::PrimeField;
+use ark_ff::UniformRand;
+use common::crypto::poseidon::constants::PoseidonParams;
+use common::keypair::PublicKey;
+use jf_primitives::rescue::RescueParameter;
+use jf_relation::gadgets::ecc::SWToTEConParam;
+use plonk_prover::client::circuits::circuit_inputs::CircuitInputs;
+use plonk_prover::primitives::circuits::kem_dem::KemDemParams;
+use rand::SeedableRng;
+use rand_chacha::ChaChaRng;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use zk_macros::client_bounds;
+
+#[client_bounds]
+pub async fn build_swap_inputs<
+    P,
+    V,
+    VSW,
+    Storage: PreimageDB<E = P> + TreeDB<F = <P as CurveConfig>::BaseField> + KeyDB<E = P, Key = UserKeys<P>>,
+>(
+    db: Arc<Mutex<Storage>>,
+    swap_details: SwapInput<P>,
+) -> anyhow::Result<CircuitInputs<P>> {
+    let db_locked = db.lock().await;
+    let stored_preimage = db_locked
+        .get_preimage(swap_details.commitment_to_use)
+        .ok_or(anyhow::anyhow!("Preimage not found during swap request"))?;
+    let preimage = stored_preimage.preimage;
+    if preimage.public_key.as_affine() != swap_details.sender {
+        return Err(anyhow::anyhow!("Commitment is not owned by the sender"));
+    }
+
+    let (block_number, leaf_index) = stored_preimage
+        .block_number
+        .zip(stored_preimage.leaf_index)
+        .ok_or(anyhow::anyhow!("Commitment not included in a block yet"))?;
+    let sibling_path = db_locked
+        .get_sibling_path(&block_number, leaf_index)
+        .ok_or(anyhow::anyhow!("Error retrieving sibling path"))?;
+    let commitment_root = db_locked
+        .get_root(&block_number)
+        .ok_or(anyhow::anyhow!("Error retrieving commitment roots"))?;
+
+    let root_key: <P as CurveConfig>::BaseField = db_locked
+        .get_key(swap_details.sender)
+        .ok_or(anyhow::anyhow!("Error retrieving key"))?
+        .get_private_key();
+
+    let eph_key = swap_details.eph_key.unwrap_or_else(|| {
+        let mut rng = ChaChaRng::from_entropy();
+        <P as CurveConfig>::BaseField::rand(&mut rng)
+    });
+
+    let circuit_inputs = CircuitInputs::<P>::new()
+        .add_old_token_values(vec![preimage.value])
+        .add_old_token_salts(vec![preimage.salt])
+        .add_old_token_ids(vec![preimage.token_id])
+        .add_membership_path(vec![sibling_path])
+        .add_membership_path_index(vec![<P as CurveConfig>::BaseField::from(leaf_index as u64)])
+        .add_commitment_tree_root(vec![commitment_root])
+        .add_token_values(vec![swap_details.incoming_value])
+        .add_token_salts(vec![<P as CurveConfig>::BaseField::from(
+            swap_details.counterparty_leaf_index as u64,
+        )])
+        .add_token_ids(vec![swap_details.incoming_token_id])
+        .add_recipients(vec![PublicKey(swap_details.recipient)])
+        .add_root_key(root_key)
+        .add_ephemeral_key(eph_key)
+        .build();
+
+    Ok(circuit_inputs)
+}
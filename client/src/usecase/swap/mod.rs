@@ -0,0 +1,58 @@
+use crate::adapters::rest_api::structs::SwapInput;
+use crate::ports::prover::Prover;
+use crate::ports::storage::{KeyDB, PreimageDB, TreeDB};
+use crate::services::user_keys::UserKeys;
+use crate::usecase::transfer::prove_and_send;
+use crate::utils;
+use ark_ec::{
+    pairing::Pairing,
+    short_weierstrass::{Affine, Projective, SWCurveConfig},
+    CurveConfig,
+};
+use ark_ff::PrimeField;
+use common::crypto::poseidon::constants::PoseidonParams;
+use common::ports::notifier::Notifier;
+use common::structs::Transaction;
+use jf_primitives::rescue::RescueParameter;
+use jf_relation::gadgets::ecc::SWToTEConParam;
+use plonk_prover::primitives::circuits::kem_dem::KemDemParams;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use zk_macros::client_bounds;
+
+pub mod inputs;
+
+use inputs::*;
+
+/// Proves and sends one half of a swap. The commitment sent stays locked until a block
+/// including both halves confirms its nullifier. The incoming commitment is discovered
+/// from the counterparty half once it is published.
+#[client_bounds]
+pub async fn swap_process<
+    P,
+    V,
+    VSW,
+    Proof: Prover<P, V, VSW>,
+    Storage: PreimageDB<E = P> + TreeDB<F = <P as CurveConfig>::BaseField> + KeyDB<E = P, Key = UserKeys<P>>,
+    Comms: Notifier<Info = Transaction<V>>,
+>(
+    db: Arc<Mutex<Storage>>,
+    prover: Arc<Mutex<Proof>>,
+    notifier: Arc<Mutex<Comms>>,
+    swap_details: SwapInput<P>,
+) -> anyhow::Result<Transaction<V>> {
+    let swap_inputs =
+        build_swap_inputs::<P, V, VSW, Storage>(db.clone(), swap_details.clone()).await?;
+
+    let circuit = utils::circuits::get_swap_circuit::<P, V, VSW>();
+    prove_and_send::<P, V, VSW, Proof, Storage, Comms>(
+        &db,
+        prover,
+        notifier,
+        circuit,
+        swap_inputs,
+        &[swap_details.commitment_to_use],
+        vec![],
+    )
+    .await
+}
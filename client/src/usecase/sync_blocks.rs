@@ -0,0 +1,442 @@
+use crate::domain::PendingBlocks;
+use crate::ports::block_source::BlockSource;
+use crate::ports::storage::{KeyDB, PreimageDB, TreeDB};
+use crate::services::user_keys::UserKeys;
+use ark_ec::{
+    pairing::Pairing,
+    short_weierstrass::{Affine, Projective, SWCurveConfig},
+    CurveConfig,
+};
+use ark_ff::PrimeField;
+use common::configuration::BlockSyncSettings;
+use common::crypto::poseidon::constants::PoseidonParams;
+use common::structs::{transactions_root, Block, BlockHeader};
+use jf_primitives::rescue::RescueParameter;
+use jf_relation::gadgets::ecc::SWToTEConParam;
+use plonk_prover::primitives::circuits::kem_dem::KemDemParams;
+use serde::Serialize;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio::time::MissedTickBehavior;
+use tracing_log::log;
+use trees::{membership_tree::Tree, tree::AppendTree};
+use zk_macros::client_bounds;
+
+use super::discover_notes::discover_notes_process;
+
+// Blocks requested from the source at a time
+pub const SYNC_BATCH_SIZE: u64 = 100;
+
+// What happened to a block handed to `ingest_block_process`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockIngestion {
+    // The block and any queued blocks following it were applied
+    Applied,
+    // The block was applied before
+    Replayed,
+    // Blocks before it are missing, so it waits for them
+    Queued,
+}
+
+// Reasons a block is refused
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "reason", content = "detail", rename_all = "snake_case")]
+pub enum BlockError {
+    // The commitment root doesn't match the commitments of the block
+    InconsistentCommitmentRoot(u64),
+    // The header doesn't match the number or the transactions of the block
+    InconsistentHeader(u64),
+    // The block doesn't extend the last applied block
+    UnknownParent(u64),
+    // A different block with the same number was applied
+    ConflictingBlock(u64),
+    // Too many blocks are waiting for missing ones
+    PendingBlocksFull(u64),
+    StorageError(String),
+}
+
+/// Applies blocks in block number order. Blocks already applied are ignored
+/// and blocks ahead of the next one are queued in `pending` until the gap is
+/// filled, either by a later push or by a sync.
+#[client_bounds]
+pub async fn ingest_block_process<
+    P,
+    V,
+    VSW,
+    Storage: PreimageDB<E = P> + KeyDB<E = P, Key = UserKeys<P>> + TreeDB<F = P::BaseField>,
+>(
+    db: Arc<Mutex<Storage>>,
+    pending: Arc<Mutex<PendingBlocks<V::ScalarField>>>,
+    block: Block<V::ScalarField>,
+) -> Result<BlockIngestion, BlockError> {
+    let block_number = block.block_number;
+    let root = Tree::<P::BaseField, 8>::from_leaves(block.commitments.clone()).root();
+    if root != block.commitment_root {
+        return Err(BlockError::InconsistentCommitmentRoot(block_number));
+    }
+    if block.header.block_number != block_number
+        || block.header.transactions_root != transactions_root(&block.transactions)
+    {
+        return Err(BlockError::InconsistentHeader(block_number));
+    }
+
+    // Holding the queue serializes ingestion, so blocks are applied in order
+    let mut pending = pending.lock().await;
+    let next = next_block_number(&*db.lock().await);
+    if block_number < next {
+        let db = db.lock().await;
+        let conflicting = db
+            .get_root(&block_number)
+            .is_some_and(|stored_root| stored_root != root)
+            || db
+                .get_block_hash(&block_number)
+                .is_some_and(|stored_hash| stored_hash != block.hash());
+        if conflicting {
+            return Err(BlockError::ConflictingBlock(block_number));
+        }
+        return Ok(BlockIngestion::Replayed);
+    }
+    if block_number > next {
+        if !pending.insert(block) {
+            return Err(BlockError::PendingBlocksFull(block_number));
+        }
+        log::debug!("Queued block {} until block {} arrives", block_number, next);
+        return Ok(BlockIngestion::Queued);
+    }
+
+    check_parent(&*db.lock().await, &block.header)?;
+    let mut next_block = Some(block);
+    while let Some(block) = next_block {
+        let block_number = block.block_number;
+        apply_block_process::<P, V, VSW, Storage>(db.clone(), block)
+            .await
+            .map_err(|e| BlockError::StorageError(e.to_string()))?;
+        next_block = pending.take_next(block_number + 1);
+        if let Some(queued) = &next_block {
+            if let Err(e) = check_parent(&*db.lock().await, &queued.header) {
+                log::warn!("Dropping queued block: {:?}", e);
+                next_block = None;
+            }
+        }
+    }
+    Ok(BlockIngestion::Applied)
+}
+
+// The first block has no parent, every other block must reference the header
+// of the block applied before it
+fn check_parent<Storage: TreeDB>(db: &Storage, header: &BlockHeader) -> Result<(), BlockError> {
+    let parent_hash = match header.block_number.checked_sub(1) {
+        Some(parent) => db.get_block_hash(&parent),
+        None => Some(String::new()),
+    };
+    match parent_hash {
+        Some(parent_hash) if parent_hash != header.parent_hash => {
+            Err(BlockError::UnknownParent(header.block_number))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn next_block_number<Storage: TreeDB>(db: &Storage) -> u64 {
+    db.get_synced_block_number().map_or(0, |n| n + 1)
+}
+
+// Applies a block to the local state: stores the notes sent to us, updates the
+// notes it includes or spends and records its commitment tree. Returns whether
+// the block was applied, as blocks that already have a tree are skipped.
+#[client_bounds]
+async fn apply_block_process<
+    P,
+    V,
+    VSW,
+    Storage: PreimageDB<E = P> + KeyDB<E = P, Key = UserKeys<P>> + TreeDB<F = P::BaseField>,
+>(
+    db: Arc<Mutex<Storage>>,
+    block: Block<V::ScalarField>,
+) -> anyhow::Result<bool> {
+    if db.lock().await.get_root(&block.block_number).is_some() {
+        log::debug!("Block {} already applied", block.block_number);
+        return Ok(false);
+    }
+    // Notes sent to us must be stored before the block marks them as included
+    discover_notes_process::<P, V, VSW, Storage>(db.clone(), &block.transactions).await?;
+
+    let mut db = db.lock().await;
+    db.update_preimages(block.clone()).ok_or(anyhow::anyhow!(
+        "Unable to store the notes of block {}",
+        block.block_number
+    ))?;
+    // Added last, as it marks the block as applied
+    db.add_block_leaves(block.commitments, block.block_number, block.header.hash())
+        .ok_or(anyhow::anyhow!(
+            "Unable to store the commitment tree of block {}",
+            block.block_number
+        ))?;
+    Ok(true)
+}
+
+/// Pulls every block after the last synced one from `source` and applies them in
+/// order, along with the pushed blocks they unblock in `pending`. Returns by how
+/// many blocks the client advanced.
+#[client_bounds]
+pub async fn sync_blocks_process<
+    P,
+    V,
+    VSW,
+    Storage: PreimageDB<E = P> + KeyDB<E = P, Key = UserKeys<P>> + TreeDB<F = P::BaseField>,
+    Source: BlockSource<F = V::ScalarField>,
+>(
+    db: Arc<Mutex<Storage>>,
+    pending: Arc<Mutex<PendingBlocks<V::ScalarField>>>,
+    source: &Source,
+) -> anyhow::Result<u64> {
+    let start = next_block_number(&*db.lock().await);
+    loop {
+        let from = next_block_number(&*db.lock().await);
+        let mut blocks = source.get_blocks(from, from + SYNC_BATCH_SIZE).await?;
+        if blocks.is_empty() {
+            return Ok(from - start);
+        }
+        blocks.sort_by_key(|block| block.block_number);
+        for block in blocks {
+            ingest_block_process::<P, V, VSW, Storage>(db.clone(), pending.clone(), block)
+                .await
+                .map_err(|e| anyhow::anyhow!("Unable to apply synced block: {:?}", e))?;
+        }
+        if next_block_number(&*db.lock().await) == from {
+            return Err(anyhow::anyhow!("Block source skipped block {}", from));
+        }
+    }
+}
+
+// Catches up with the blocks missed while the client was down, then keeps
+// syncing in the background in case a pushed block gets lost. `pending` must be
+// the queue pushed blocks are ingested through, so synced blocks apply them.
+#[client_bounds]
+pub async fn run_block_sync<
+    P,
+    V,
+    VSW,
+    Storage: PreimageDB<E = P> + KeyDB<E = P, Key = UserKeys<P>> + TreeDB<F = P::BaseField>,
+    Source: BlockSource<F = V::ScalarField>,
+>(
+    db: Arc<Mutex<Storage>>,
+    pending: Arc<Mutex<PendingBlocks<V::ScalarField>>>,
+    source: Source,
+    settings: BlockSyncSettings,
+) {
+    let mut interval = tokio::time::interval(settings.interval());
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        interval.tick().await;
+        match sync_blocks_process::<P, V, VSW, Storage, Source>(
+            db.clone(),
+            pending.clone(),
+            &source,
+        )
+        .await
+        {
+            Ok(0) => {}
+            Ok(applied) => log::info!("Synced {} missed blocks", applied),
+            Err(e) => log::error!("Block sync failed: {:?}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::storage::in_mem_storage::InMemStorage;
+    use async_trait::async_trait;
+    use common::structs::{BlockTransaction, CircuitType};
+    use curves::{
+        pallas::{Fq, PallasConfig},
+        vesta::VestaConfig,
+    };
+
+    type TestDB = Arc<Mutex<InMemStorage<PallasConfig, Fq>>>;
+
+    struct VecBlockSource(Vec<Block<Fq>>);
+
+    #[async_trait]
+    impl BlockSource for VecBlockSource {
+        type F = Fq;
+
+        async fn get_blocks(&self, from: u64, to: u64) -> anyhow::Result<Vec<Block<Fq>>> {
+            Ok(self
+                .0
+                .iter()
+                .filter(|block| (from..to).contains(&block.block_number))
+                .cloned()
+                .collect())
+        }
+    }
+
+    fn block(block_number: u64) -> Block<Fq> {
+        let commitments = vec![Fq::from(block_number + 1)];
+        let parent_hash = match block_number.checked_sub(1) {
+            Some(parent) => block(parent).header.hash(),
+            None => String::new(),
+        };
+        Block {
+            block_number,
+            header: BlockHeader {
+                block_number,
+                parent_hash,
+                transactions_root: transactions_root::<Fq>(&[]),
+                ..Default::default()
+            },
+            commitment_root: Tree::<Fq, 8>::from_leaves(commitments.clone()).root(),
+            commitments,
+            ..Default::default()
+        }
+    }
+
+    fn new_db() -> (TestDB, Arc<Mutex<PendingBlocks<Fq>>>) {
+        (
+            Arc::new(Mutex::new(InMemStorage::new())),
+            Arc::new(Mutex::new(PendingBlocks::default())),
+        )
+    }
+
+    async fn ingest(
+        db: &TestDB,
+        pending: &Arc<Mutex<PendingBlocks<Fq>>>,
+        block: Block<Fq>,
+    ) -> Result<BlockIngestion, BlockError> {
+        ingest_block_process::<PallasConfig, VestaConfig, _, _>(db.clone(), pending.clone(), block)
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_out_of_order_blocks_are_queued() {
+        let (db, pending) = new_db();
+        assert_eq!(
+            ingest(&db, &pending, block(2)).await,
+            Ok(BlockIngestion::Queued)
+        );
+        assert_eq!(
+            ingest(&db, &pending, block(1)).await,
+            Ok(BlockIngestion::Queued)
+        );
+        assert_eq!(db.lock().await.get_synced_block_number(), None);
+
+        assert_eq!(
+            ingest(&db, &pending, block(0)).await,
+            Ok(BlockIngestion::Applied)
+        );
+        assert_eq!(db.lock().await.get_synced_block_number(), Some(2));
+        assert!(pending.lock().await.is_empty());
+        assert_eq!(
+            ingest(&db, &pending, block(1)).await,
+            Ok(BlockIngestion::Replayed)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_inconsistent_blocks_are_rejected() {
+        let (db, pending) = new_db();
+        let mut tampered = block(0);
+        tampered.commitments.push(Fq::from(7u32));
+        assert_eq!(
+            ingest(&db, &pending, tampered).await,
+            Err(BlockError::InconsistentCommitmentRoot(0))
+        );
+
+        ingest(&db, &pending, block(0)).await.unwrap();
+        let mut conflicting = block(1);
+        conflicting.block_number = 0;
+        conflicting.header = block(0).header;
+        assert_eq!(
+            ingest(&db, &pending, conflicting).await,
+            Err(BlockError::ConflictingBlock(0))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_blocks_must_match_their_header() {
+        let (db, pending) = new_db();
+        let mut renumbered = block(0);
+        renumbered.header.block_number = 1;
+        assert_eq!(
+            ingest(&db, &pending, renumbered).await,
+            Err(BlockError::InconsistentHeader(0))
+        );
+
+        // The transactions root no longer matches once a transaction is added
+        let mut padded = block(0);
+        padded.transactions.push(BlockTransaction {
+            commitments: vec![],
+            nullifiers: vec![],
+            eph_pub_key: vec![],
+            ciphertexts: vec![],
+            swap_field: false,
+            circuit_type: CircuitType::Mint(1),
+        });
+        assert_eq!(
+            ingest(&db, &pending, padded).await,
+            Err(BlockError::InconsistentHeader(0))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_blocks_must_extend_the_applied_chain() {
+        let (db, pending) = new_db();
+        ingest(&db, &pending, block(0)).await.unwrap();
+
+        let mut forked = block(1);
+        forked.header.parent_hash = "other".to_string();
+        assert_eq!(
+            ingest(&db, &pending, forked.clone()).await,
+            Err(BlockError::UnknownParent(1))
+        );
+
+        // A queued block is dropped once its parent turns out different
+        forked.block_number = 2;
+        forked.header.block_number = 2;
+        assert_eq!(
+            ingest(&db, &pending, forked).await,
+            Ok(BlockIngestion::Queued)
+        );
+        assert_eq!(
+            ingest(&db, &pending, block(1)).await,
+            Ok(BlockIngestion::Applied)
+        );
+        assert_eq!(db.lock().await.get_synced_block_number(), Some(1));
+        assert!(pending.lock().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_sync_fills_missed_blocks() {
+        let (db, pending) = new_db();
+        // Block 1 was pushed while block 0 was missed
+        assert_eq!(
+            ingest(&db, &pending, block(1)).await,
+            Ok(BlockIngestion::Queued)
+        );
+
+        let source = VecBlockSource((0..3).map(block).collect());
+        let synced = sync_blocks_process::<PallasConfig, VestaConfig, _, _, _>(
+            db.clone(),
+            pending.clone(),
+            &source,
+        )
+        .await
+        .unwrap();
+        assert_eq!(synced, 3);
+        assert_eq!(db.lock().await.get_synced_block_number(), Some(2));
+        assert!(pending.lock().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_sync_fails_on_missing_block() {
+        let (db, pending) = new_db();
+        let source = VecBlockSource(vec![block(0), block(2)]);
+        let synced =
+            sync_blocks_process::<PallasConfig, VestaConfig, _, _, _>(db.clone(), pending, &source)
+                .await;
+        assert!(synced.is_err());
+        assert_eq!(db.lock().await.get_synced_block_number(), Some(0));
+    }
+}
@@ -10,8 +10,8 @@ use ark_ec::{
     CurveConfig,
 };
 use ark_ff::PrimeField;
-use ark_ff::UniformRand;
-use common::crypto::poseidon::constants::PoseidonParams;
+use ark_ff::{UniformRand, Zero};
+use common::crypto::poseidon::{constants::PoseidonParams, Poseidon};
 use common::keypair::PublicKey;
 use jf_primitives::rescue::RescueParameter;
 use jf_relation::gadgets::ecc::SWToTEConParam;
@@ -34,6 +34,22 @@ pub async fn build_transfer_inputs<
 >(
     db: Arc<Mutex<Storage>>,
     transfer_details: TransferInput<P>,
+) -> anyhow::Result<CircuitInputs<P>> {
+    build_transfer_inputs_with_change::<P, V, VSW, Storage>(db, transfer_details, false).await
+}
+
+/// Builds the inputs of a transfer. With `force_change` a change commitment is added even
+/// if it carries no value, so that circuits with a change output can be used for exact payments.
+#[client_bounds]
+pub async fn build_transfer_inputs_with_change<
+    P,
+    V,
+    VSW,
+    Storage: PreimageDB<E = P> + TreeDB<F = <P as CurveConfig>::BaseField> + KeyDB<E = P, Key = UserKeys<P>>,
+>(
+    db: Arc<Mutex<Storage>>,
+    transfer_details: TransferInput<P>,
+    force_change: bool,
 ) -> anyhow::Result<CircuitInputs<P>> {
     let mut circuit_inputs = CircuitInputs::<P>::new();
 
@@ -65,7 +81,6 @@ pub async fn build_transfer_inputs<
         .collect::<Option<_>>()
         .ok_or(anyhow::anyhow!("Error building path indices"))?;
     circuit_inputs.add_membership_path_index(sibling_path_indices.clone());
-    circuit_inputs.add_token_salts(sibling_path_indices);
 
     let sibling_paths: Vec<MembershipPath<<P as CurveConfig>::BaseField>> = stored_preimages
         .iter()
@@ -111,11 +126,35 @@ pub async fn build_transfer_inputs<
         old_token_salts.push(x.salt);
     });
 
+    // Whatever is not sent to the recipient goes back to the sender as a change commitment
+    let old_value_sum = old_token_values
+        .iter()
+        .fold(<P as CurveConfig>::BaseField::zero(), |acc, x| acc + x);
+    if transfer_details.transfer_amount.into_bigint() > old_value_sum.into_bigint() {
+        return Err(anyhow::anyhow!(
+            "Transfer amount exceeds the value of the selected commitments"
+        ));
+    }
+    let change = old_value_sum - transfer_details.transfer_amount;
+
+    // The recipient salt must match the index of the first commitment spent
+    let mut token_values = vec![transfer_details.transfer_amount];
+    let mut token_salts = vec![sibling_path_indices[0]];
+    if force_change || !change.is_zero() {
+        // Derived from the ephemeral key so that the same request rebuilds the same inputs
+        let change_salt = Poseidon::<<P as CurveConfig>::BaseField>::new()
+            .hash(vec![root_key, eph_key])
+            .map_err(|e| anyhow::anyhow!(e.to_string()))?;
+        token_values.push(change);
+        token_salts.push(change_salt);
+    }
+
     circuit_inputs
         .add_token_ids(vec![token_id])
         .add_old_token_values(old_token_values)
         .add_old_token_salts(old_token_salts)
-        .add_token_values(vec![transfer_details.transfer_amount])
+        .add_token_values(token_values)
+        .add_token_salts(token_salts)
         .build();
     check_inputs::<P, V>(
         &circuit_inputs,
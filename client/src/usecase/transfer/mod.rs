@@ -1,4 +1,5 @@
 use crate::adapters::rest_api::structs::TransferInput;
+use crate::domain::StoredPreimageInfo;
 use crate::ports::prover::Prover;
 use crate::ports::storage::{KeyDB, PreimageDB, TreeDB};
 use crate::services::user_keys::UserKeys;
@@ -6,28 +7,61 @@ use crate::utils;
 use ark_ec::{
     pairing::Pairing,
     short_weierstrass::{Affine, Projective, SWCurveConfig},
-    CurveConfig,
+    CurveConfig, CurveGroup,
 };
 use ark_ff::PrimeField;
 use common::crypto::poseidon::constants::PoseidonParams;
-use common::ports::notifier::Notifier;
-use common::structs::Transaction;
-use jf_plonk::nightfall::ipa_structs::ProvingKey;
+use common::ports::notifier::{Notifier, Rejected};
+use common::structs::{transaction_hash, Transaction};
 use jf_primitives::rescue::RescueParameter;
 use jf_relation::gadgets::ecc::SWToTEConParam;
 use plonk_prover::client::circuits::circuit_inputs::CircuitInputs;
 use plonk_prover::client::ClientPlonkCircuit;
 use plonk_prover::primitives::circuits::kem_dem::KemDemParams;
+use std::fmt;
 use std::sync::Arc;
 use tokio::sync::Mutex;
+use tracing_log::log;
 use zk_macros::client_bounds;
 
 pub mod inputs;
+pub(crate) mod preimages;
+mod selection;
 pub mod transfer_tokens;
 
 use transfer_tokens::*;
 
 use inputs::*;
+use preimages::*;
+use selection::*;
+
+/// A transfer that failed after `sent` transactions of its chain were sent. Their
+/// inputs stay locked until the sequencer settles them, the transfers that were
+/// not sent leave their inputs spendable.
+#[derive(Debug)]
+pub struct TransferError<V>
+where
+    V: Pairing,
+    <<V as Pairing>::G1 as CurveGroup>::Config: SWCurveConfig,
+{
+    pub sent: Vec<Transaction<V>>,
+    pub error: anyhow::Error,
+}
+
+impl<V> fmt::Display for TransferError<V>
+where
+    V: Pairing,
+    <<V as Pairing>::G1 as CurveGroup>::Config: SWCurveConfig,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Transfer failed after sending {} transactions: {}",
+            self.sent.len(),
+            self.error
+        )
+    }
+}
 
 #[client_bounds]
 pub async fn transfer_process<
@@ -42,52 +76,147 @@ pub async fn transfer_process<
     prover: Arc<Mutex<Proof>>,
     notifier: Arc<Mutex<Comms>>,
     transfer_details: TransferInput<P>,
+) -> Result<Vec<Transaction<V>>, TransferError<V>> {
+    let transfers = plan_transfers::<P, V, VSW, Proof, Storage>(&db, &prover, transfer_details)
+        .await
+        .map_err(|error| TransferError {
+            sent: vec![],
+            error,
+        })?;
+
+    let mut transactions = Vec::with_capacity(transfers.len());
+    for (transfer_details, force_change) in transfers {
+        match single_transfer::<P, V, VSW, Proof, Storage, Comms>(
+            db.clone(),
+            prover.clone(),
+            notifier.clone(),
+            transfer_details,
+            force_change,
+        )
+        .await
+        {
+            Ok(transaction) => transactions.push(transaction),
+            Err(error) => {
+                return Err(TransferError {
+                    sent: transactions,
+                    error,
+                })
+            }
+        }
+    }
+
+    Ok(transactions)
+}
+
+#[client_bounds]
+async fn single_transfer<
+    P,
+    V,
+    VSW,
+    Proof: Prover<P, V, VSW>,
+    Storage: PreimageDB<E = P> + TreeDB<F = <P as CurveConfig>::BaseField> + KeyDB<E = P, Key = UserKeys<P>>,
+    Comms: Notifier<Info = Transaction<V>>,
+>(
+    db: Arc<Mutex<Storage>>,
+    prover: Arc<Mutex<Proof>>,
+    notifier: Arc<Mutex<Comms>>,
+    transfer_details: TransferInput<P>,
+    force_change: bool,
 ) -> anyhow::Result<Transaction<V>> {
-    let transfer_inputs = build_transfer_inputs::<P, V, VSW, Storage>(db, transfer_details).await?;
-    let (proving_key, circuit) = get_circuit_and_pk(prover, &transfer_inputs).await?;
+    let transfer_inputs = build_transfer_inputs_with_change::<P, V, VSW, Storage>(
+        db.clone(),
+        transfer_details.clone(),
+        force_change,
+    )
+    .await?;
+    let circuit = utils::circuits::get_transfer_circuit_from_params::<P, V, _>(
+        transfer_inputs.token_values.len(),
+        transfer_inputs.old_token_values.len(),
+    )?;
+    let change = transfer_change_preimages(&db, transfer_details.sender, &transfer_inputs).await?;
 
-    // transfer tokens
-    let transaction =
-        spawn_transfer::<P, V, _, Proof>(circuit, transfer_inputs, proving_key).await?;
+    prove_and_send::<P, V, VSW, Proof, Storage, Comms>(
+        &db,
+        prover,
+        notifier,
+        circuit,
+        transfer_inputs,
+        &transfer_details.commitments_to_use,
+        change,
+    )
+    .await
+}
 
-    let notifier = notifier.lock().await;
-    notifier.send_info(transaction.clone()).await?;
+/// Locks the `spent` inputs, proves the transaction and records it as pending before
+/// sending it. If the sequencer rejects it, the inputs are unlocked and the change is
+/// dropped. If the sequencer doesn't answer, both stay as they are until
+/// `release_rejected_inputs_process` learns what became of the transaction.
+#[client_bounds]
+pub(crate) async fn prove_and_send<
+    P,
+    V,
+    VSW,
+    Proof: Prover<P, V, VSW>,
+    Storage: PreimageDB<E = P>,
+    Comms: Notifier<Info = Transaction<V>>,
+>(
+    db: &Arc<Mutex<Storage>>,
+    prover: Arc<Mutex<Proof>>,
+    notifier: Arc<Mutex<Comms>>,
+    circuit: Box<dyn ClientPlonkCircuit<P, V, VSW>>,
+    circuit_inputs: CircuitInputs<P>,
+    spent: &[P::BaseField],
+    change: Vec<(P::BaseField, StoredPreimageInfo<P>)>,
+) -> anyhow::Result<Transaction<V>> {
+    // inputs stay locked until a block confirms their nullifiers
+    lock_preimages(db, spent).await?;
+    let transaction = match prove::<P, V, VSW, Proof>(prover, circuit, circuit_inputs).await {
+        Ok(transaction) => transaction,
+        Err(e) => {
+            unlock_preimages(db, spent).await;
+            return Err(e);
+        }
+    };
+    if let Err(e) = record_pending_transaction(db, &transaction, spent, &change).await {
+        unlock_preimages(db, spent).await;
+        return Err(e);
+    }
 
+    let sent = notifier.lock().await.send_info(transaction.clone()).await;
+    if let Err(e) = sent {
+        if e.is::<Rejected>() {
+            discard_pending_transaction(db, &transaction, spent, &change).await;
+        } else {
+            log::warn!(
+                "No answer for transaction {}. Its inputs stay locked until its status is known",
+                transaction_hash(&transaction)
+            );
+        }
+        return Err(e);
+    }
     Ok(transaction)
 }
 
 #[client_bounds]
-async fn get_circuit_and_pk<P, V, VSW, Proof: Prover<P, V, VSW>>(
+async fn prove<P, V, VSW, Proof: Prover<P, V, VSW>>(
     prover: Arc<Mutex<Proof>>,
-    transfer_details: &CircuitInputs<P>,
-) -> anyhow::Result<(ProvingKey<V>, Box<dyn ClientPlonkCircuit<P, V, VSW>>)> {
-    let prover_guard = prover.lock().await;
-    let circuit = utils::circuits::get_transfer_circuit_from_params::<P, V, _>(
-        transfer_details.token_values.len(),
-        transfer_details.old_token_values.len(),
-    )?;
-    let pk = prover_guard
-        .get_pk(circuit.get_circuit_type())
+    circuit: Box<dyn ClientPlonkCircuit<P, V, VSW>>,
+    circuit_inputs: CircuitInputs<P>,
+) -> anyhow::Result<Transaction<V>> {
+    let circuit_type = circuit.get_circuit_type();
+    let proving_key = prover
+        .lock()
+        .await
+        .get_pk(circuit_type.clone())
         .ok_or(anyhow::anyhow!(
-            "Error in minting process. Circuit Id {:?} not registered",
-            circuit.get_circuit_type()
+            "Circuit Id {:?} not registered",
+            circuit_type
         ))?
         .clone();
-    Ok((pk, circuit))
-}
 
-#[client_bounds]
-async fn spawn_transfer<P, V, VSW, Proof: Prover<P, V, VSW>>(
-    transfer_circuit: Box<dyn ClientPlonkCircuit<P, V, VSW>>,
-    transfer_inputs: CircuitInputs<P>,
-    proving_key: ProvingKey<V>,
-) -> anyhow::Result<Transaction<V>> {
     let transaction = tokio::task::spawn_blocking(move || -> anyhow::Result<Transaction<V>> {
-        let transaction =
-            transfer_tokens::<P, V, _, Proof>(transfer_circuit, &transfer_inputs, &proving_key)
-                .map_err(|_| anyhow::anyhow!("Error transfer tokens",))?;
-
-        Ok(transaction)
+        transfer_tokens::<P, V, _, Proof>(circuit, &circuit_inputs, &proving_key)
+            .map_err(|_| anyhow::anyhow!("Error proving {:?} transaction", circuit_type))
     })
     .await??;
     Ok(transaction)
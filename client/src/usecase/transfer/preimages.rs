@@ -0,0 +1,196 @@
+use crate::domain::{Preimage, PreimageStatus, StoredPreimageInfo};
+use crate::ports::committable::{Committable, Nullifiable};
+use crate::ports::storage::{KeyDB, PreimageDB};
+use crate::services::user_keys::UserKeys;
+use anyhow::anyhow;
+use ark_ec::{
+    pairing::Pairing,
+    short_weierstrass::{Affine, SWCurveConfig},
+    CurveGroup,
+};
+use ark_ff::PrimeField;
+use common::crypto::poseidon::constants::PoseidonParams;
+use common::keypair::PublicKey;
+use common::structs::{transaction_hash, Transaction};
+use plonk_prover::client::circuits::circuit_inputs::CircuitInputs;
+use std::collections::HashSet;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tracing_log::log;
+
+/// Marks the commitments consumed by a transfer as `Locked`. Fails without touching
+/// the db if any of them is repeated or is not `Unspent`.
+pub(crate) async fn lock_preimages<P, Storage>(
+    db: &Arc<Mutex<Storage>>,
+    keys: &[P::BaseField],
+) -> anyhow::Result<()>
+where
+    P: SWCurveConfig,
+    Storage: PreimageDB<E = P>,
+{
+    let mut db = db.lock().await;
+    if keys.iter().collect::<HashSet<_>>().len() != keys.len() {
+        return Err(anyhow!("Repeated commitments in transfer request"));
+    }
+    for key in keys {
+        let stored = db
+            .get_preimage(*key)
+            .ok_or(anyhow!("Preimage not found during transfer request"))?;
+        if stored.status != PreimageStatus::Unspent {
+            return Err(anyhow!("Commitment {} is not spendable", key));
+        }
+    }
+    for key in keys {
+        db.set_preimage_status(*key, PreimageStatus::Locked)
+            .ok_or(anyhow!("Error locking preimage {}", key))?;
+    }
+    Ok(())
+}
+
+/// Returns commitments locked by a failed transfer to `Unspent`
+pub(crate) async fn unlock_preimages<P, Storage>(db: &Arc<Mutex<Storage>>, keys: &[P::BaseField])
+where
+    P: SWCurveConfig,
+    Storage: PreimageDB<E = P>,
+{
+    let mut db = db.lock().await;
+    for key in keys {
+        if db
+            .set_preimage_status(*key, PreimageStatus::Unspent)
+            .is_none()
+        {
+            log::error!("Error unlocking preimage {}", key);
+        }
+    }
+}
+
+/// Stores the change of `transaction` as pending and records that the transaction
+/// locks its inputs, before it is sent: its block may arrive before the sequencer
+/// answers. The change keys are tracked with the inputs, so the change is dropped
+/// if the transaction is later rejected. Leaves the db untouched on failure.
+pub(crate) async fn record_pending_transaction<P, V, Storage>(
+    db: &Arc<Mutex<Storage>>,
+    transaction: &Transaction<V>,
+    spent: &[P::BaseField],
+    change: &[(P::BaseField, StoredPreimageInfo<P>)],
+) -> anyhow::Result<()>
+where
+    P: SWCurveConfig,
+    V: Pairing<ScalarField = P::BaseField>,
+    <<V as Pairing>::G1 as CurveGroup>::Config: SWCurveConfig,
+    Storage: PreimageDB<E = P>,
+{
+    if change
+        .iter()
+        .any(|(key, _)| !transaction.commitments.iter().any(|c| c.0 == *key))
+    {
+        return Err(anyhow!("Change commitment doesn't match transaction"));
+    }
+
+    let mut db = db.lock().await;
+    let mut stored = Vec::with_capacity(change.len());
+    for (key, preimage) in change {
+        if db.insert_preimage(*key, *preimage).is_none() {
+            for key in stored {
+                db.remove_preimage(key);
+            }
+            return Err(anyhow!("Error storing change preimage"));
+        }
+        stored.push(*key);
+    }
+    let keys = spent.iter().chain(stored.iter()).copied().collect();
+    if db
+        .insert_locking_transaction(transaction_hash(transaction), keys)
+        .is_none()
+    {
+        for key in stored {
+            db.remove_preimage(key);
+        }
+        return Err(anyhow!("Error tracking the transaction locking the inputs"));
+    }
+    Ok(())
+}
+
+/// Undoes `record_pending_transaction` for a transaction the sequencer refused and
+/// returns its inputs to `Unspent`
+pub(crate) async fn discard_pending_transaction<P, V, Storage>(
+    db: &Arc<Mutex<Storage>>,
+    transaction: &Transaction<V>,
+    spent: &[P::BaseField],
+    change: &[(P::BaseField, StoredPreimageInfo<P>)],
+) where
+    P: SWCurveConfig,
+    V: Pairing,
+    <<V as Pairing>::G1 as CurveGroup>::Config: SWCurveConfig,
+    Storage: PreimageDB<E = P>,
+{
+    {
+        let mut db = db.lock().await;
+        db.remove_locking_transaction(&transaction_hash(transaction));
+        for (key, _) in change {
+            if db.remove_preimage(*key).is_none() {
+                log::error!("Error removing change preimage {}", key);
+            }
+        }
+    }
+    unlock_preimages(db, spent).await;
+}
+
+/// The pending preimage of a change note owned by `keys`, keyed by its commitment
+pub(crate) fn change_preimage<P>(
+    keys: &UserKeys<P>,
+    value: P::BaseField,
+    token_id: P::BaseField,
+    salt: P::BaseField,
+) -> anyhow::Result<(P::BaseField, StoredPreimageInfo<P>)>
+where
+    P: SWCurveConfig,
+    P::BaseField: PrimeField + PoseidonParams<Field = P::BaseField>,
+{
+    let preimage = Preimage::<P>::new(
+        value,
+        token_id,
+        PublicKey::from_affine(keys.public_key),
+        salt,
+    );
+    let commitment = preimage.commitment_hash()?;
+    let nullifier = preimage.nullifier_hash(&keys.nullifier_key)?;
+    Ok((
+        commitment.0,
+        StoredPreimageInfo {
+            preimage,
+            block_number: None,
+            leaf_index: None,
+            nullifier,
+            status: PreimageStatus::Unspent,
+        },
+    ))
+}
+
+/// The change notes of a transfer (outputs 1..C), which are owned by the sender
+pub(crate) async fn transfer_change_preimages<P, Storage>(
+    db: &Arc<Mutex<Storage>>,
+    sender: Affine<P>,
+    circuit_inputs: &CircuitInputs<P>,
+) -> anyhow::Result<Vec<(P::BaseField, StoredPreimageInfo<P>)>>
+where
+    P: SWCurveConfig,
+    P::BaseField: PrimeField + PoseidonParams<Field = P::BaseField>,
+    Storage: KeyDB<E = P, Key = UserKeys<P>>,
+{
+    let keys = db
+        .lock()
+        .await
+        .get_key(sender)
+        .ok_or(anyhow!("Error retrieving key"))?;
+    (1..circuit_inputs.token_values.len())
+        .map(|i| {
+            change_preimage(
+                &keys,
+                circuit_inputs.token_values[i],
+                circuit_inputs.token_ids[0],
+                circuit_inputs.token_salts[i],
+            )
+        })
+        .collect()
+}
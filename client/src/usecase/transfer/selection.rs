@@ -0,0 +1,200 @@
+use crate::adapters::rest_api::structs::TransferInput;
+use crate::domain::StoredPreimageInfoVector;
+use crate::ports::committable::Committable;
+use crate::ports::prover::Prover;
+use crate::ports::storage::PreimageDB;
+use crate::services::coin_selection::{select_transfers, TransferShape};
+use anyhow::anyhow;
+use ark_ec::{
+    pairing::Pairing,
+    short_weierstrass::{Affine, Projective, SWCurveConfig},
+    CurveConfig,
+};
+use ark_ff::PrimeField;
+use common::crypto::poseidon::constants::PoseidonParams;
+use common::structs::CircuitType;
+use jf_primitives::rescue::RescueParameter;
+use jf_relation::gadgets::ecc::SWToTEConParam;
+use plonk_prover::primitives::circuits::kem_dem::KemDemParams;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use zk_macros::client_bounds;
+
+/// Splits a transfer request into the transactions needed to pay it. Requests with explicit
+/// commitments are sent as is. Otherwise commitments owned by the sender are selected and
+/// each transaction is paired with whether it must carry a change output.
+#[client_bounds]
+pub(crate) async fn plan_transfers<
+    P,
+    V,
+    VSW,
+    Proof: Prover<P, V, VSW>,
+    Storage: PreimageDB<E = P>,
+>(
+    db: &Arc<Mutex<Storage>>,
+    prover: &Arc<Mutex<Proof>>,
+    transfer_details: TransferInput<P>,
+) -> anyhow::Result<Vec<(TransferInput<P>, bool)>> {
+    let shapes: Vec<TransferShape> = prover
+        .lock()
+        .await
+        .get_circuit_types()
+        .into_iter()
+        .filter_map(|circuit_type| match circuit_type {
+            CircuitType::Transfer(c, n) => Some((c, n)),
+            _ => None,
+        })
+        .collect();
+    let spendable = db.lock().await.get_spendable().unwrap_or_default();
+    plan_from_spendable(transfer_details, &shapes, spendable)
+}
+
+fn plan_from_spendable<P, F>(
+    transfer_details: TransferInput<P>,
+    shapes: &[TransferShape],
+    spendable: StoredPreimageInfoVector<P>,
+) -> anyhow::Result<Vec<(TransferInput<P>, bool)>>
+where
+    P: SWCurveConfig<BaseField = F>,
+    F: PrimeField + PoseidonParams<Field = F>,
+{
+    if !transfer_details.commitments_to_use.is_empty() {
+        return Ok(vec![(transfer_details, false)]);
+    }
+    let token_id = transfer_details
+        .token_id
+        .ok_or(anyhow!("Token id required to select commitments"))?;
+
+    let candidates = spendable
+        .into_iter()
+        .filter(|p| {
+            p.preimage.get_public_key().as_affine() == transfer_details.sender
+                && *p.preimage.get_token_id() == token_id
+        })
+        .map(|p| Ok((p.preimage.commitment_hash()?.0, *p.preimage.get_value())))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let transfers = select_transfers(candidates, transfer_details.transfer_amount, shapes)?;
+    Ok(transfers
+        .into_iter()
+        .enumerate()
+        .map(|(i, transfer)| {
+            let request = TransferInput {
+                commitments_to_use: transfer.commitments_to_use,
+                transfer_amount: transfer.transfer_amount,
+                token_id: Some(token_id),
+                // A fixed ephemeral key can only be used once
+                eph_key: if i == 0 {
+                    transfer_details.eph_key
+                } else {
+                    None
+                },
+                ..transfer_details.clone()
+            };
+            (request, transfer.n_commitments > 1)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{Preimage, PreimageStatus, StoredPreimageInfo};
+    use ark_ec::{AffineRepr, CurveGroup};
+    use common::keypair::PublicKey;
+    use curves::pallas::{Fq, Fr, PallasConfig};
+
+    const SHAPES: [TransferShape; 3] = [(1, 1), (2, 1), (2, 2)];
+
+    fn account(seed: u64) -> Affine<PallasConfig> {
+        (Affine::<PallasConfig>::generator() * Fr::from(seed)).into_affine()
+    }
+
+    fn spendable(
+        owner: Affine<PallasConfig>,
+        token_id: u64,
+        values: &[u64],
+    ) -> StoredPreimageInfoVector<PallasConfig> {
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, value)| StoredPreimageInfo {
+                preimage: Preimage::new(
+                    Fq::from(*value),
+                    Fq::from(token_id),
+                    PublicKey::from_affine(owner),
+                    Fq::from(i as u64 + 1),
+                ),
+                block_number: Some(0),
+                leaf_index: Some(i),
+                nullifier: Fq::from(i as u64 + 1000),
+                status: PreimageStatus::Unspent,
+            })
+            .collect()
+    }
+
+    fn request(amount: u64) -> TransferInput<PallasConfig> {
+        TransferInput {
+            commitments_to_use: vec![],
+            token_id: Some(Fq::from(1u64)),
+            recipient: account(2),
+            transfer_amount: Fq::from(amount),
+            eph_key: Some(Fq::from(7u64)),
+            sender: account(1),
+        }
+    }
+
+    #[test]
+    fn test_explicit_commitments_are_sent_as_is() {
+        let mut transfer = request(10);
+        transfer.commitments_to_use = vec![Fq::from(3u64)];
+        let plan = plan_from_spendable(transfer.clone(), &SHAPES, vec![]).unwrap();
+        assert_eq!(plan.len(), 1);
+        assert_eq!(plan[0].0.commitments_to_use, transfer.commitments_to_use);
+        assert!(!plan[0].1);
+    }
+
+    #[test]
+    fn test_plan_requires_token_id() {
+        let mut transfer = request(10);
+        transfer.token_id = None;
+        let spendable = spendable(account(1), 1, &[10]);
+        assert!(plan_from_spendable(transfer, &SHAPES, spendable).is_err());
+    }
+
+    #[test]
+    fn test_plan_only_spends_sender_notes_of_the_token() {
+        let mut notes = spendable(account(1), 1, &[4]);
+        notes.extend(spendable(account(3), 1, &[100]));
+        notes.extend(spendable(account(1), 2, &[100]));
+        assert!(plan_from_spendable(request(10), &SHAPES, notes.clone()).is_err());
+
+        let plan = plan_from_spendable(request(3), &SHAPES, notes.clone()).unwrap();
+        assert_eq!(plan.len(), 1);
+        let (transfer, with_change) = &plan[0];
+        assert_eq!(
+            transfer.commitments_to_use,
+            vec![notes[0].preimage.commitment_hash().unwrap().0]
+        );
+        assert_eq!(transfer.transfer_amount, Fq::from(3u64));
+        assert!(with_change);
+    }
+
+    #[test]
+    fn test_chained_plan_uses_the_ephemeral_key_once() {
+        let notes = spendable(account(1), 1, &[10, 10, 10]);
+        let plan = plan_from_spendable(request(25), &SHAPES, notes).unwrap();
+        assert_eq!(plan.len(), 2);
+        assert_eq!(plan[0].0.commitments_to_use.len(), 2);
+        assert_eq!(plan[0].0.transfer_amount, Fq::from(20u64));
+        assert_eq!(plan[0].0.eph_key, Some(Fq::from(7u64)));
+        // There is no (1, 2) circuit, so the exact payment pads a zero change
+        assert!(plan[0].1);
+        assert_eq!(plan[1].0.transfer_amount, Fq::from(5u64));
+        assert_eq!(plan[1].0.eph_key, None);
+        assert!(plan[1].1);
+        assert!(plan
+            .iter()
+            .all(|(transfer, _)| transfer.recipient == account(2)));
+    }
+}
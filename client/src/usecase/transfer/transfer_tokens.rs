@@ -4,7 +4,6 @@ use ark_ec::{
     short_weierstrass::{Affine, Projective, SWCurveConfig},
 };
 use common::crypto::poseidon::constants::PoseidonParams;
-use common::structs::CircuitType;
 use common::structs::Transaction;
 use jf_plonk::nightfall::ipa_structs::ProvingKey;
 use jf_primitives::rescue::RescueParameter;
@@ -41,7 +40,7 @@ where
 
     let client_pub_inputs = ClientPubInput::new(pub_inputs, commitment_nullifier_count)?;
 
-    let transaction = Transaction::new(
+    let mut transaction = Transaction::new(
         client_pub_inputs
             .commitments
             .into_iter()
@@ -57,8 +56,9 @@ where
         g_polys,
         client_pub_inputs.ephemeral_public_key,
         client_pub_inputs.swap_field,
-        CircuitType::Transfer(commitment_nullifier_count.0, commitment_nullifier_count.1),
+        transfer_circuit.get_circuit_type(),
     );
+    transaction.set_commitment_root(client_pub_inputs.commitment_root);
 
     Ok(transaction)
 }
@@ -0,0 +1,107 @@
+use crate::adapters::rest_api::structs::WithdrawInput;
+use crate::domain::{Preimage, StoredPreimageInfoVector};
+use crate::ports::keys::FullKey;
+use crate::ports::storage::{KeyDB, PreimageDB, TreeDB};
+use crate::services::user_keys::UserKeys;
+use ark_ec::{
+    pairing::Pairing,
+    short_weierstrass::{Affine, Projective, SWCurveConfig},
+    CurveConfig,
+};
+use ark_ff::PrimeField;
+use ark_ff::Zero;
+use common::crypto::poseidon::{constants::PoseidonParams, Poseidon};
+use jf_primitives::rescue::RescueParameter;
+use jf_relation::gadgets::ecc::SWToTEConParam;
+use plonk_prover::client::circuits::circuit_inputs::CircuitInputs;
+use plonk_prover::primitives::circuits::kem_dem::KemDemParams;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use trees::MembershipPath;
+use zk_macros::client_bounds;
+
+#[client_bounds]
+pub async fn build_withdraw_inputs<
+    P,
+    V,
+    VSW,
+    Storage: PreimageDB<E = P> + TreeDB<F = <P as CurveConfig>::BaseField> + KeyDB<E = P, Key = UserKeys<P>>,
+>(
+    db: Arc<Mutex<Storage>>,
+    withdraw_details: WithdrawInput<P>,
+) -> anyhow::Result<CircuitInputs<P>> {
+    if withdraw_details.commitments_to_use.is_empty() {
+        return Err(anyhow::anyhow!("No commitments selected for withdrawal"));
+    }
+    let db_locked = db.lock().await;
+    let stored_preimages: StoredPreimageInfoVector<P> = withdraw_details
+        .commitments_to_use
+        .iter()
+        .map(|key| db_locked.get_preimage(*key))
+        .collect::<Option<_>>()
+        .ok_or(anyhow::anyhow!(
+            "Preimage not found during withdraw request"
+        ))?;
+    let old_preimages: Vec<Preimage<P>> = stored_preimages.iter().map(|x| x.preimage).collect();
+
+    let sibling_path_indices: Vec<<P as CurveConfig>::BaseField> = stored_preimages
+        .iter()
+        .map(|x| {
+            x.leaf_index
+                .map(|x| <P as CurveConfig>::BaseField::from(x as u64))
+        })
+        .collect::<Option<_>>()
+        .ok_or(anyhow::anyhow!("Error building path indices"))?;
+    let sibling_paths: Vec<MembershipPath<<P as CurveConfig>::BaseField>> = stored_preimages
+        .iter()
+        .map(|x| db_locked.get_sibling_path(&x.block_number?, x.leaf_index?))
+        .collect::<Option<Vec<_>>>()
+        .ok_or(anyhow::anyhow!("Error retrieving sibling path"))?;
+    let commitment_roots: Vec<<P as CurveConfig>::BaseField> = stored_preimages
+        .iter()
+        .map(|x| db_locked.get_root(&x.block_number?))
+        .collect::<Option<_>>()
+        .ok_or(anyhow::anyhow!("Error retrieving commitment roots"))?;
+
+    let root_key: <P as CurveConfig>::BaseField = db_locked
+        .get_key(withdraw_details.sender)
+        .ok_or(anyhow::anyhow!("Error retrieving key"))?
+        .get_private_key();
+
+    let token_id = old_preimages[0].token_id;
+    if !old_preimages.iter().all(|x| x.token_id == token_id) {
+        return Err(anyhow::anyhow!("Token Ids must be equal"));
+    }
+    let old_token_values: Vec<_> = old_preimages.iter().map(|x| x.value).collect();
+    let old_token_salts: Vec<_> = old_preimages.iter().map(|x| x.salt).collect();
+
+    let old_value_sum = old_token_values
+        .iter()
+        .fold(<P as CurveConfig>::BaseField::zero(), |acc, x| acc + x);
+    if withdraw_details.withdraw_amount.into_bigint() > old_value_sum.into_bigint() {
+        return Err(anyhow::anyhow!(
+            "Withdraw amount exceeds the value of the selected commitments"
+        ));
+    }
+    let change = old_value_sum - withdraw_details.withdraw_amount;
+    // Derived like the change salt of transfers, from a value unique to the request,
+    // so a lost change note can be rebuilt from the keys and the spent note
+    let change_salt = Poseidon::<<P as CurveConfig>::BaseField>::new()
+        .hash(vec![root_key, withdraw_details.commitments_to_use[0]])
+        .map_err(|e| anyhow::anyhow!(e.to_string()))?;
+
+    let circuit_inputs = CircuitInputs::<P>::new()
+        .add_old_token_values(old_token_values)
+        .add_old_token_salts(old_token_salts)
+        .add_membership_path(sibling_paths)
+        .add_membership_path_index(sibling_path_indices)
+        .add_commitment_tree_root(commitment_roots)
+        .add_token_values(vec![withdraw_details.withdraw_amount, change])
+        .add_token_salts(vec![change_salt])
+        .add_token_ids(vec![token_id])
+        .add_root_key(root_key)
+        .add_withdraw_address(withdraw_details.recipient)
+        .build();
+
+    Ok(circuit_inputs)
+}
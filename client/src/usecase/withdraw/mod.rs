@@ -0,0 +1,90 @@
+use crate::adapters::rest_api::structs::WithdrawInput;
+use crate::domain::StoredPreimageInfo;
+use crate::ports::prover::Prover;
+use crate::ports::storage::{KeyDB, PreimageDB, TreeDB};
+use crate::services::user_keys::UserKeys;
+use crate::usecase::transfer::preimages::change_preimage;
+use crate::usecase::transfer::prove_and_send;
+use crate::utils;
+use anyhow::anyhow;
+use ark_ec::{
+    pairing::Pairing,
+    short_weierstrass::{Affine, Projective, SWCurveConfig},
+    CurveConfig,
+};
+use ark_ff::{PrimeField, Zero};
+use common::crypto::poseidon::constants::PoseidonParams;
+use common::ports::notifier::Notifier;
+use common::structs::Transaction;
+use jf_primitives::rescue::RescueParameter;
+use jf_relation::gadgets::ecc::SWToTEConParam;
+use plonk_prover::client::circuits::circuit_inputs::CircuitInputs;
+use plonk_prover::primitives::circuits::kem_dem::KemDemParams;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use zk_macros::client_bounds;
+
+pub mod inputs;
+
+use inputs::*;
+
+#[client_bounds]
+pub async fn withdraw_process<
+    P,
+    V,
+    VSW,
+    Proof: Prover<P, V, VSW>,
+    Storage: PreimageDB<E = P> + TreeDB<F = <P as CurveConfig>::BaseField> + KeyDB<E = P, Key = UserKeys<P>>,
+    Comms: Notifier<Info = Transaction<V>>,
+>(
+    db: Arc<Mutex<Storage>>,
+    prover: Arc<Mutex<Proof>>,
+    notifier: Arc<Mutex<Comms>>,
+    withdraw_details: WithdrawInput<P>,
+) -> anyhow::Result<Transaction<V>> {
+    let withdraw_inputs =
+        build_withdraw_inputs::<P, V, VSW, Storage>(db.clone(), withdraw_details.clone()).await?;
+    let circuit = utils::circuits::get_withdraw_circuit_from_params::<P, V, VSW>(
+        withdraw_inputs.old_token_values.len(),
+    )?;
+    let change = withdraw_change_preimage(&db, withdraw_details.sender, &withdraw_inputs).await?;
+
+    prove_and_send::<P, V, VSW, Proof, Storage, Comms>(
+        &db,
+        prover,
+        notifier,
+        circuit,
+        withdraw_inputs,
+        &withdraw_details.commitments_to_use,
+        change,
+    )
+    .await
+}
+
+/// The change note of a withdrawal, which is the only commitment it outputs
+async fn withdraw_change_preimage<P, Storage>(
+    db: &Arc<Mutex<Storage>>,
+    sender: Affine<P>,
+    circuit_inputs: &CircuitInputs<P>,
+) -> anyhow::Result<Vec<(P::BaseField, StoredPreimageInfo<P>)>>
+where
+    P: SWCurveConfig,
+    P::BaseField: PrimeField + PoseidonParams<Field = P::BaseField>,
+    Storage: KeyDB<E = P, Key = UserKeys<P>>,
+{
+    let change = circuit_inputs.token_values[1];
+    if change.is_zero() {
+        return Ok(vec![]);
+    }
+    let keys = db
+        .lock()
+        .await
+        .get_key(sender)
+        .ok_or(anyhow!("Error retrieving key"))?;
+    Ok(vec![change_preimage(
+        &keys,
+        change,
+        circuit_inputs.token_ids[0],
+        circuit_inputs.token_salts[0],
+    )?])
+}
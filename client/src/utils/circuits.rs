@@ -6,14 +6,20 @@ use ark_ec::{
     CurveConfig,
 };
 use ark_ff::PrimeField;
+use ark_serialize::CanonicalSerialize;
 use common::crypto::poseidon::constants::PoseidonParams;
+use common::hash::content_hash;
 use jf_primitives::rescue::RescueParameter;
-use jf_relation::gadgets::ecc::SWToTEConParam;
+use jf_relation::{gadgets::ecc::SWToTEConParam, Arithmetization, Circuit};
 use plonk_prover::client::{
-    circuits::{mint::MintCircuit, transfer::TransferCircuit},
+    circuits::{
+        mint::MintCircuit, swap::SwapCircuit, transfer::TransferCircuit, withdraw::WithdrawCircuit,
+    },
     ClientPlonkCircuit,
 };
 use plonk_prover::primitives::circuits::kem_dem::KemDemParams;
+use plonk_prover::setup::UniversalSrs;
+use tracing_log::log;
 use zk_macros::client_bounds;
 
 const DEPTH: usize = 8;
@@ -27,28 +33,129 @@ where
         Box::new(MintCircuit::<1>::new()),
         Box::new(MintCircuit::<2>::new()),
         Box::new(TransferCircuit::<1, 1, DEPTH>::new()),
+        Box::new(TransferCircuit::<2, 1, DEPTH>::new()),
         Box::new(TransferCircuit::<1, 2, DEPTH>::new()),
         Box::new(TransferCircuit::<2, 2, DEPTH>::new()),
         Box::new(TransferCircuit::<2, 3, DEPTH>::new()),
+        Box::new(SwapCircuit::<DEPTH>::new()),
+        Box::new(WithdrawCircuit::<1, DEPTH>::new()),
+        Box::new(WithdrawCircuit::<2, DEPTH>::new()),
     ];
     circuit_info
 }
 
+// Smallest setup degree every client circuit fits in, below the shared
+// `SRS_DEGREE` which also bounds the rollup circuits
 #[client_bounds]
-pub fn init_client_circuits<P, V, VSW, PR: Prover<P, V, VSW>>(prover: &mut PR) -> Result<()>
+pub fn client_srs_degree<P, V, VSW>() -> Result<usize>
 where
     <V as Pairing>::BaseField: PrimeField + PoseidonParams<Field = V::BaseField>,
 {
+    select_client_circuits::<P, V, VSW>()
+        .iter()
+        .map(|c| {
+            let inputs = c
+                .generate_random_inputs(None)
+                .map_err(|e| anyhow!("Failed to generate inputs: {:?}", e))?;
+            c.to_plonk_circuit(inputs)
+                .and_then(|circuit| circuit.srs_size())
+                .map_err(|e| anyhow!("Failed to build circuit: {:?}", e))
+        })
+        .try_fold(0, |degree, size| Ok(degree.max(size?)))
+}
+
+// Client tests only need a setup for the client circuits. It is derived once
+// per test binary and shared.
+#[cfg(test)]
+pub(crate) fn test_setup() -> &'static UniversalSrs<curves::vesta::VestaConfig> {
+    use curves::{pallas::PallasConfig, vesta::VestaConfig};
+    use once_cell::sync::Lazy;
+    use plonk_prover::setup::universal_setup;
+
+    static SETUP: Lazy<UniversalSrs<VestaConfig>> = Lazy::new(|| {
+        let degree = client_srs_degree::<PallasConfig, VestaConfig, VestaConfig>().unwrap();
+        universal_setup::<VestaConfig, VestaConfig>(degree).unwrap()
+    });
+    &SETUP
+}
+
+#[client_bounds]
+pub fn init_client_circuits<P, V, VSW, PR: Prover<P, V, VSW>>(
+    prover: &mut PR,
+    srs: &UniversalSrs<V>,
+) -> Result<()>
+where
+    <V as Pairing>::BaseField: PrimeField + PoseidonParams<Field = V::BaseField>,
+{
+    let setup_hash = setup_hash::<P, V, VSW>(srs)?;
     let circuit_info = select_client_circuits::<P, V, VSW>();
     for c in circuit_info {
+        let circuit_type = c.get_circuit_type();
+        let hash = circuit_key_hash(&*c, &setup_hash)?;
+        if prover.load_pk(circuit_type.clone(), &hash) {
+            log::debug!("Loaded proving key for {:?}", circuit_type);
+            continue;
+        }
+        log::info!("Generating proving key for {:?}", circuit_type);
         let keys = c
-            .generate_keys()
+            .generate_keys_with_srs(srs)
             .map_err(|e| anyhow!("Failed to generate keys: {:?}", e))?;
-        prover.store_pk(c.get_circuit_type(), keys.0);
+        prover.store_pk(circuit_type.clone(), keys.0);
+        prover.persist_pk(circuit_type, &hash)?;
     }
     Ok(())
 }
 
+// Persisted keys are only valid for the circuit and the setup they were
+// derived from, so both are part of the hash they are stored under.
+#[client_bounds]
+pub fn circuit_key_hash<P, V, VSW>(
+    circuit: &dyn ClientPlonkCircuit<P, V, VSW>,
+    setup_hash: &str,
+) -> Result<String> {
+    let hash = circuit_hash(circuit)?;
+    Ok(content_hash(format!("{hash}:{setup_hash}").as_bytes()))
+}
+
+#[client_bounds]
+pub fn setup_hash<P, V, VSW>(srs: &UniversalSrs<V>) -> Result<String> {
+    let mut bytes = Vec::new();
+    srs.serialize_compressed(&mut bytes)
+        .map_err(|e| anyhow!("Failed to serialize setup: {e}"))?;
+    Ok(content_hash(&bytes))
+}
+
+// Fingerprint of a circuit definition. Selector and permutation polynomials
+// only depend on the constraint system, so the hash changes whenever the
+// circuit does, regardless of the witness used to build it.
+#[client_bounds]
+pub fn circuit_hash<P, V, VSW>(circuit: &dyn ClientPlonkCircuit<P, V, VSW>) -> Result<String> {
+    let inputs = circuit
+        .generate_random_inputs(None)
+        .map_err(|e| anyhow!("Failed to generate inputs: {:?}", e))?;
+    let plonk_circuit = circuit
+        .to_plonk_circuit(inputs)
+        .map_err(|e| anyhow!("Failed to build circuit: {:?}", e))?;
+
+    let mut bytes = format!(
+        "{:?}:{}:{}",
+        circuit.get_circuit_type(),
+        plonk_circuit.num_inputs(),
+        plonk_circuit.num_gates()
+    )
+    .into_bytes();
+    let selectors = plonk_circuit
+        .compute_selector_polynomials()
+        .map_err(|e| anyhow!("Failed to compute selectors: {:?}", e))?;
+    let permutation = plonk_circuit
+        .compute_extended_permutation_polynomials()
+        .map_err(|e| anyhow!("Failed to compute permutation: {:?}", e))?;
+    (selectors, permutation)
+        .serialize_compressed(&mut bytes)
+        .map_err(|e| anyhow!("Failed to serialize circuit: {e}"))?;
+    Ok(content_hash(&bytes))
+}
+
 #[client_bounds]
 pub fn get_mint_circuit_from_params<P, V, VSW>(
     c: usize,
@@ -78,6 +185,7 @@ where
 {
     let circuit = match (c, n) {
         (1, 1) => TransferCircuit::<1, 1, DEPTH>::new().as_circuit::<P, V, VSW>(),
+        (2, 1) => TransferCircuit::<2, 1, DEPTH>::new().as_circuit::<P, V, VSW>(),
         (1, 2) => TransferCircuit::<1, 2, DEPTH>::new().as_circuit::<P, V, VSW>(),
         (2, 2) => TransferCircuit::<2, 2, DEPTH>::new().as_circuit::<P, V, VSW>(),
         (2, 3) => TransferCircuit::<2, 3, DEPTH>::new().as_circuit::<P, V, VSW>(),
@@ -91,15 +199,39 @@ where
     Ok(circuit)
 }
 
+#[client_bounds]
+pub fn get_withdraw_circuit_from_params<P, V, VSW>(
+    n: usize,
+) -> Result<Box<dyn ClientPlonkCircuit<P, V, VSW>>> {
+    let circuit = match n {
+        1 => WithdrawCircuit::<1, DEPTH>::new().as_circuit::<P, V, VSW>(),
+        2 => WithdrawCircuit::<2, DEPTH>::new().as_circuit::<P, V, VSW>(),
+        _ => {
+            return Err(anyhow!(
+                "Withdraw circuit with {n} nullifiers is not registered"
+            ))
+        }
+    };
+    Ok(circuit)
+}
+
+#[client_bounds]
+pub fn get_swap_circuit<P, V, VSW>() -> Box<dyn ClientPlonkCircuit<P, V, VSW>> {
+    SwapCircuit::<DEPTH>::new().as_circuit::<P, V, VSW>()
+}
+
 #[cfg(test)]
 mod tests {
     use crate::ports::prover::Prover;
     use crate::services::prover::in_memory_prover::InMemProver;
+    use crate::services::prover::key_store::FileKeyStore;
     use crate::utils::circuits;
     use curves::pallas::PallasConfig;
     use curves::vesta::VestaConfig;
     use plonk_prover::client::circuits::mint::MintCircuit;
+    use plonk_prover::client::circuits::swap::SwapCircuit;
     use plonk_prover::client::circuits::transfer::TransferCircuit;
+    use plonk_prover::client::circuits::withdraw::WithdrawCircuit;
     use plonk_prover::client::ClientPlonkCircuit;
 
     #[test]
@@ -114,20 +246,71 @@ mod tests {
 
         assert!(pk.is_none());
 
-        circuits::init_client_circuits::<PallasConfig, VestaConfig, VestaConfig, _>(&mut prover)
-            .expect("Error initializing client circuits");
+        let srs = circuits::test_setup();
+        circuits::init_client_circuits::<PallasConfig, VestaConfig, VestaConfig, _>(
+            &mut prover,
+            srs,
+        )
+        .expect("Error initializing client circuits");
 
         let circuit_info: Vec<Box<dyn ClientPlonkCircuit<PallasConfig, VestaConfig, VestaConfig>>> = vec![
             Box::new(MintCircuit::<1>::new()),
             Box::new(MintCircuit::<2>::new()),
             Box::new(TransferCircuit::<1, 1, DEPTH>::new()),
+            Box::new(TransferCircuit::<2, 1, DEPTH>::new()),
             Box::new(TransferCircuit::<1, 2, DEPTH>::new()),
             Box::new(TransferCircuit::<2, 2, DEPTH>::new()),
             Box::new(TransferCircuit::<2, 3, DEPTH>::new()),
+            Box::new(SwapCircuit::<DEPTH>::new()),
+            Box::new(WithdrawCircuit::<1, DEPTH>::new()),
+            Box::new(WithdrawCircuit::<2, DEPTH>::new()),
         ];
         circuit_info.iter().for_each(|c| {
             let pk = prover.get_pk(c.get_circuit_type());
             assert!(pk.is_some())
         });
     }
+
+    #[test]
+    fn test_init_client_circuit_from_key_store() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path();
+
+        let srs = circuits::test_setup();
+        let setup_hash =
+            circuits::setup_hash::<PallasConfig, VestaConfig, VestaConfig>(srs).unwrap();
+
+        let mut prover: InMemProver<PallasConfig, VestaConfig, _> =
+            InMemProver::new().with_key_store(FileKeyStore::open(path).unwrap());
+        circuits::init_client_circuits::<PallasConfig, VestaConfig, VestaConfig, _>(
+            &mut prover,
+            srs,
+        )
+        .expect("Error initializing client circuits");
+
+        // A restarted client reloads the same keys instead of generating new ones
+        let mut restarted: InMemProver<PallasConfig, VestaConfig, _> =
+            InMemProver::new().with_key_store(FileKeyStore::open(path).unwrap());
+        circuits::select_client_circuits::<PallasConfig, VestaConfig, VestaConfig>()
+            .iter()
+            .for_each(|c| {
+                let hash = circuits::circuit_key_hash(&**c, &setup_hash).unwrap();
+                assert!(restarted.load_pk(c.get_circuit_type(), &hash));
+                assert_eq!(
+                    restarted.get_pk(c.get_circuit_type()),
+                    prover.get_pk(c.get_circuit_type())
+                );
+            });
+    }
+
+    #[test]
+    fn test_circuit_hash() {
+        let mint_1 = MintCircuit::<1>::new().as_circuit::<PallasConfig, VestaConfig, _>();
+        let mint_2 = MintCircuit::<2>::new().as_circuit::<PallasConfig, VestaConfig, _>();
+        let hash = circuits::circuit_hash(&*mint_1).unwrap();
+
+        // Independent of the random witness, but not of the circuit
+        assert_eq!(hash, circuits::circuit_hash(&*mint_1).unwrap());
+        assert_ne!(hash, circuits::circuit_hash(&*mint_2).unwrap());
+    }
 }
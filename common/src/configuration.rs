@@ -1,5 +1,7 @@
 use serde_aux::field_attributes::deserialize_number_from_string;
 
+use crate::structs::CircuitType;
+
 pub enum Environment {
     Local,
     Production,
@@ -53,6 +55,129 @@ pub struct ApplicationSettings {
     pub host: String,
     pub base_url: String,
     pub timeout_milliseconds: u64,
+    #[serde(default)]
+    pub storage: StorageSettings,
+    // Directory where proving keys are persisted between restarts
+    #[serde(default)]
+    pub key_store_path: Option<String>,
+    // Maximum number of pending transactions kept by the sequencer
+    #[serde(default)]
+    pub mempool_capacity: Option<usize>,
+    #[serde(default)]
+    pub block_production: BlockProductionSettings,
+    #[serde(default)]
+    pub block_sync: BlockSyncSettings,
+    #[serde(default)]
+    pub notifications: NotificationSettings,
+    // Circuit types of the transactions in each base rollup the sequencer
+    // builds blocks from. Rollup keys are generated for each at startup.
+    #[serde(default)]
+    pub rollup_shapes: Vec<Vec<CircuitType>>,
+}
+
+#[derive(serde::Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageBackend {
+    #[default]
+    InMemory,
+    File,
+}
+
+#[derive(serde::Deserialize, Clone, Debug, Default)]
+pub struct StorageSettings {
+    #[serde(default)]
+    pub backend: StorageBackend,
+    // Directory where the file backend keeps its records
+    #[serde(default)]
+    pub path: Option<String>,
+}
+
+// Background block production. A block is built once `batch_size` transactions
+// are pending or the oldest pending one has waited `max_latency_milliseconds`.
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct BlockProductionSettings {
+    pub enabled: bool,
+    pub batch_size: usize,
+    pub max_latency_milliseconds: u64,
+    pub poll_interval_milliseconds: u64,
+}
+
+impl Default for BlockProductionSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            batch_size: 8,
+            max_latency_milliseconds: 30000,
+            poll_interval_milliseconds: 1000,
+        }
+    }
+}
+
+impl BlockProductionSettings {
+    pub fn max_latency(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.max_latency_milliseconds)
+    }
+
+    pub fn poll_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.poll_interval_milliseconds)
+    }
+}
+
+// Catch-up sync of the client. Blocks missed while the client was down are
+// pulled from the sequencer at startup and then every `interval_milliseconds`.
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct BlockSyncSettings {
+    pub enabled: bool,
+    pub interval_milliseconds: u64,
+}
+
+impl Default for BlockSyncSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_milliseconds: 10000,
+        }
+    }
+}
+
+impl BlockSyncSettings {
+    pub fn interval(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.interval_milliseconds)
+    }
+}
+
+// Delivery of new blocks to the clients subscribed to the sequencer. Each
+// subscriber has a queue of up to `queue_capacity` blocks. A failed delivery is
+// retried `max_retries` times, waiting twice as long after each attempt.
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct NotificationSettings {
+    pub max_subscribers: usize,
+    pub queue_capacity: usize,
+    pub max_retries: u32,
+    pub retry_backoff_milliseconds: u64,
+    // Callback urls subscribed at startup
+    pub subscribers: Vec<String>,
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        Self {
+            max_subscribers: 64,
+            queue_capacity: 16,
+            max_retries: 5,
+            retry_backoff_milliseconds: 500,
+            subscribers: vec![],
+        }
+    }
+}
+
+impl NotificationSettings {
+    pub fn retry_backoff(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.retry_backoff_milliseconds)
+    }
 }
 
 pub fn get_configuration() -> Result<Settings, config::ConfigError> {
@@ -0,0 +1,48 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{ErrorKind, Write},
+    path::Path,
+};
+
+// Write to a temporary file and rename it over the target, so readers never
+// observe a partially written file. The parent directory is synced as well,
+// otherwise the rename itself may not survive a crash.
+pub fn write_atomically(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    write_atomically_with(path, bytes, false)
+}
+
+// Same as `write_atomically`, but on unix the file is only readable and
+// writable by its owner (mode 0600). Use it for files holding secrets.
+pub fn write_atomically_private(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    write_atomically_with(path, bytes, true)
+}
+
+fn write_atomically_with(path: &Path, bytes: &[u8], private: bool) -> std::io::Result<()> {
+    let tmp_path = path.with_extension("tmp");
+    // The mode only applies when the file is created, so a temporary file left
+    // by a crash must not be reused
+    match fs::remove_file(&tmp_path) {
+        Err(e) if e.kind() != ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+    {
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        if private {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        #[cfg(not(unix))]
+        let _ = private;
+        let mut file = options.open(&tmp_path)?;
+        file.write_all(bytes)?;
+        file.sync_all()?;
+    }
+    fs::rename(&tmp_path, path)?;
+    #[cfg(unix)]
+    if let Some(parent) = path.parent() {
+        File::open(parent)?.sync_all()?;
+    }
+    Ok(())
+}
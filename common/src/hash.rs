@@ -0,0 +1,12 @@
+use sha2::{Digest, Sha256};
+
+// Lowercase hex encoding, as used by every hash we expose
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+// SHA-256 of `bytes`, hex encoded. Stored records carry it to detect
+// truncated or corrupted files.
+pub fn content_hash(bytes: &[u8]) -> String {
+    to_hex(&Sha256::digest(bytes))
+}
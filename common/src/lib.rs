@@ -2,6 +2,8 @@ pub mod configuration;
 pub mod crypto;
 pub mod curves;
 pub mod derived_keys;
+pub mod files;
+pub mod hash;
 pub mod keypair;
 pub mod ports;
 pub mod serialize;
@@ -1,4 +1,5 @@
 use async_trait::async_trait;
+use thiserror::Error;
 
 #[async_trait]
 pub trait Notifier: Clone + Send + Sync + 'static {
@@ -6,3 +7,9 @@ pub trait Notifier: Clone + Send + Sync + 'static {
 
     async fn send_info(&self, info: Self::Info) -> anyhow::Result<()>;
 }
+
+/// Returned by `send_info` when the receiver answered and refused the info. Any
+/// other error leaves open whether the info was received.
+#[derive(Error, Debug)]
+#[error("Rejected by receiver: {0}")]
+pub struct Rejected(pub String);
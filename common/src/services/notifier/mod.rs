@@ -1,8 +1,9 @@
-use crate::structs::Block;
 use crate::structs::Transaction;
-use crate::{configuration::ApplicationSettings, ports::notifier::Notifier};
+use crate::{
+    configuration::ApplicationSettings,
+    ports::notifier::{Notifier, Rejected},
+};
 use ark_ec::{pairing::Pairing, short_weierstrass::SWCurveConfig, CurveGroup};
-use ark_ff::Field;
 use async_trait::async_trait;
 use reqwest::header::CONTENT_TYPE;
 use std::marker::PhantomData;
@@ -10,14 +11,16 @@ use std::marker::PhantomData;
 #[derive(Clone, Debug)]
 pub struct HttpNotifier<V> {
     pub base_url: String,
+    timeout: std::time::Duration,
     _marker: std::marker::PhantomData<V>,
 }
 
 impl<V> HttpNotifier<V> {
     pub fn new(settings: ApplicationSettings) -> Self {
-        let _timeout = settings.timeout();
+        let timeout = settings.timeout();
         Self {
             base_url: settings.base_url,
+            timeout,
             _marker: PhantomData,
         }
     }
@@ -35,7 +38,7 @@ where
     #[tracing::instrument(name = "Send notification", skip(self, transaction))]
     async fn send_info(&self, transaction: Transaction<V>) -> anyhow::Result<()> {
         let base_url = self.base_url.clone();
-        let client = reqwest::Client::new();
+        let client = reqwest::Client::builder().timeout(self.timeout).build()?;
         let cbor_data = serde_cbor::to_vec(&transaction)
             .map_err(|_| anyhow::anyhow!("Transaction couldnt be serialized"))?;
         let res = client
@@ -45,27 +48,10 @@ where
             .send()
             .await;
         ark_std::println!("Got response {:?} from {}", res, base_url);
-        Ok(())
-    }
-}
-
-#[async_trait]
-impl<F> Notifier for HttpNotifier<Block<F>>
-where
-    F: Field,
-{
-    type Info = Block<F>;
-
-    #[tracing::instrument(name = "Send notification", skip(self, block))]
-    async fn send_info(&self, block: Block<F>) -> anyhow::Result<()> {
-        let base_url = self.base_url.clone();
-        let client = reqwest::Client::new();
-        let res = client
-            .post(format!("{}/block", base_url))
-            .json(&block)
-            .send()
-            .await;
-        ark_std::println!("Got response {:?}", res);
+        // Only an error status is a rejection. After a timeout the sequencer may
+        // still have admitted the transaction.
+        res?.error_for_status()
+            .map_err(|e| Rejected(format!("Transaction rejected by sequencer: {e}")))?;
         Ok(())
     }
 }
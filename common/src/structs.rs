@@ -1,19 +1,231 @@
+use crate::hash::to_hex;
 use ark_ec::{pairing::Pairing, short_weierstrass::SWCurveConfig, CurveGroup};
 use ark_ff::{BigInt, BigInteger, Field, PrimeField};
 use ark_poly::univariate::DensePolynomial;
+use ark_serialize::CanonicalSerialize;
 use jf_plonk::nightfall::ipa_structs::Proof;
 use jf_utils::canonical;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sha2::{Digest, Sha256};
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Block<F: Field> {
     pub block_number: u64,
+    #[serde(default)]
+    pub header: BlockHeader,
     #[serde(with = "canonical")]
     pub commitments: Vec<F>,
     #[serde(with = "canonical")]
     pub nullifiers: Vec<F>,
     #[serde(with = "canonical")]
     pub commitment_root: F,
+    // Per transaction data, in the order transactions were included
+    #[serde(default)]
+    pub transactions: Vec<BlockTransaction<F>>,
+    // Value taken out of the pool by the withdraw transactions of the block
+    #[serde(default)]
+    pub withdrawals: Vec<Withdrawal<F>>,
+    #[serde(default)]
+    pub proof: Option<BlockProof>,
+}
+
+impl<F: PrimeField> Block<F> {
+    pub fn hash(&self) -> String {
+        self.header.hash()
+    }
+}
+
+/// Links a block to its parent and to the global state it moves from and to. The
+/// global trees live on the other curve of the cycle, so their roots are kept hex
+/// encoded in their canonical serialization.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BlockHeader {
+    pub block_number: u64,
+    // Hash of the previous block, empty for the first block
+    pub parent_hash: String,
+    pub previous_state: GlobalState,
+    pub new_state: GlobalState,
+    pub vk_root: String,
+    // See `transactions_root`
+    pub transactions_root: String,
+}
+
+impl BlockHeader {
+    // Canonical hash of the block. The header commits to the block contents
+    // through its transactions root.
+    pub fn hash(&self) -> String {
+        let mut hasher = Sha256::new().chain_update(self.block_number.to_le_bytes());
+        for state in [&self.previous_state, &self.new_state] {
+            hash_str(&mut hasher, &state.commitment_root);
+            hash_str(&mut hasher, &state.nullifier_root);
+            hasher.update(state.nullifier_leaf_count.to_le_bytes());
+        }
+        for value in [&self.parent_hash, &self.vk_root, &self.transactions_root] {
+            hash_str(&mut hasher, value);
+        }
+        to_hex(&hasher.finalize())
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct GlobalState {
+    pub commitment_root: String,
+    pub nullifier_root: String,
+    pub nullifier_leaf_count: u64,
+}
+
+impl GlobalState {
+    pub fn new<G: CanonicalSerialize>(
+        commitment_root: &G,
+        nullifier_root: &G,
+        nullifier_leaf_count: u64,
+    ) -> Self {
+        Self {
+            commitment_root: canonical_hex(commitment_root),
+            nullifier_root: canonical_hex(nullifier_root),
+            nullifier_leaf_count,
+        }
+    }
+}
+
+pub fn canonical_hex<G: CanonicalSerialize>(value: &G) -> String {
+    let mut bytes = Vec::new();
+    value
+        .serialize_compressed(&mut bytes)
+        .expect("Serializing into a vector doesn't fail");
+    to_hex(&bytes)
+}
+
+// Root of a SHA-256 Merkle tree over the transaction hashes, in inclusion
+// order. The last hash of an odd level moves up alone, so no two lists of
+// transactions share a root.
+pub fn transactions_root<F: PrimeField>(transactions: &[BlockTransaction<F>]) -> String {
+    let mut level = transactions
+        .iter()
+        .map(|transaction| transaction.hash())
+        .collect::<Vec<_>>();
+    if level.is_empty() {
+        return to_hex(&Sha256::digest(b""));
+    }
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => Sha256::new()
+                    .chain_update(left)
+                    .chain_update(right)
+                    .finalize()
+                    .into(),
+                _ => pair[0],
+            })
+            .collect();
+    }
+    to_hex(&level[0])
+}
+
+fn hash_str(hasher: &mut Sha256, value: &str) {
+    hasher.update((value.len() as u64).to_le_bytes());
+    hasher.update(value.as_bytes());
+}
+
+/// Rollup proof attesting to a block. The proof and its public inputs live on the other
+/// curve of the cycle, so they are kept in their canonical serialization and decoded by
+/// `plonk_prover::rollup::block::verify_block`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BlockProof {
+    // Rollup circuit the proof was generated for
+    pub circuit_type: CircuitType,
+    // Hash of the verifying key the proof checks against
+    pub vk_hash: String,
+    pub rollup_proof: Vec<u8>,
+    // Polynomials of the accumulators the proof outputs, to decide them
+    #[serde(default)]
+    pub accumulators: Vec<u8>,
+    // Number of base rollups merged by the proof, one per batch of transactions
+    #[serde(default)]
+    pub base_rollups: u64,
+}
+
+/// Public data of a transaction included in a block. It holds everything needed to
+/// reconstruct notes from blocks alone, but not the client proof.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BlockTransaction<F: Field> {
+    #[serde(with = "canonical")]
+    pub commitments: Vec<F>,
+    #[serde(with = "canonical")]
+    pub nullifiers: Vec<F>,
+    #[serde(with = "canonical")]
+    pub eph_pub_key: Vec<F>,
+    #[serde(with = "canonical")]
+    pub ciphertexts: Vec<F>,
+    pub swap_field: bool,
+    pub circuit_type: CircuitType,
+}
+
+impl<F: PrimeField> BlockTransaction<F> {
+    // Same statement as `transaction_hash`, without the commitment roots the
+    // transaction was proven against
+    pub fn hash(&self) -> [u8; 32] {
+        statement_hash(
+            &self.circuit_type,
+            self.swap_field,
+            &[
+                &self.commitments,
+                &self.nullifiers,
+                &self.eph_pub_key,
+                &self.ciphertexts,
+            ],
+        )
+    }
+}
+
+// Hash of the public values of a transaction, each list prefixed with its length
+fn statement_hash<F: PrimeField>(
+    circuit_type: &CircuitType,
+    swap_field: bool,
+    fields: &[&[F]],
+) -> [u8; 32] {
+    let circuit_type =
+        serde_cbor::to_vec(circuit_type).expect("Circuit type is always serializable");
+    let mut hasher = Sha256::new()
+        .chain_update((circuit_type.len() as u64).to_le_bytes())
+        .chain_update(circuit_type)
+        .chain_update([swap_field as u8]);
+    for values in fields {
+        hasher.update((values.len() as u64).to_le_bytes());
+        for value in values.iter() {
+            hasher.update(value.into_bigint().to_bytes_le());
+        }
+    }
+    hasher.finalize().into()
+}
+
+/// Public outputs of a withdraw transaction. Withdrawals publish no encrypted note, so they
+/// are carried in the ciphertext slots of the transaction as [value, token_id, recipient].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Withdrawal<F: Field> {
+    #[serde(with = "canonical")]
+    pub value: F,
+    #[serde(with = "canonical")]
+    pub token_id: F,
+    #[serde(with = "canonical")]
+    pub recipient: F,
+}
+
+impl<F: Field> Withdrawal<F> {
+    pub fn from_block_transaction(transaction: &BlockTransaction<F>) -> Option<Self> {
+        match (
+            &transaction.circuit_type,
+            transaction.ciphertexts.as_slice(),
+        ) {
+            (CircuitType::Withdraw(_), [value, token_id, recipient]) => Some(Withdrawal {
+                value: *value,
+                token_id: *token_id,
+                recipient: *recipient,
+            }),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Clone, Deserialize, Serialize, Default, Debug, PartialEq)]
@@ -109,6 +321,9 @@ where
     pub eph_pub_key: Vec<P::ScalarField>,
     pub swap_field: bool,
     pub circuit_type: CircuitType,
+    // Commitment tree roots the spent notes were proven against, one per nullifier slot
+    #[serde(with = "canonical", default)]
+    pub commitment_root: Vec<P::ScalarField>,
     #[serde(with = "canonical")]
     pub proof: Proof<P>,
     //#[serde(with = "canonical")]
@@ -143,20 +358,125 @@ where
             eph_pub_key,
             swap_field,
             circuit_type,
+            commitment_root: Vec::new(),
         }
     }
     pub fn set_proof(&mut self, proof: Proof<P>) -> &mut Self {
         self.proof = proof;
         self
     }
+    pub fn set_commitment_root(&mut self, commitment_root: Vec<P::ScalarField>) -> &mut Self {
+        self.commitment_root = commitment_root;
+        self
+    }
+}
+
+impl<P: Pairing> From<&Transaction<P>> for BlockTransaction<P::ScalarField>
+where
+    <<P as Pairing>::G1 as CurveGroup>::Config: SWCurveConfig,
+{
+    fn from(transaction: &Transaction<P>) -> Self {
+        Self {
+            commitments: transaction.commitments.iter().map(|c| c.0).collect(),
+            nullifiers: transaction.nullifiers.iter().map(|n| n.0).collect(),
+            eph_pub_key: transaction.eph_pub_key.clone(),
+            ciphertexts: transaction.ciphertexts.clone(),
+            swap_field: transaction.swap_field,
+            circuit_type: transaction.circuit_type.clone(),
+        }
+    }
+}
+
+// Hash of the statement a transaction proves. Proofs are randomized, so the
+// same transaction proven twice has the same hash.
+pub fn transaction_hash<P>(transaction: &Transaction<P>) -> String
+where
+    P: Pairing,
+    <<P as Pairing>::G1 as CurveGroup>::Config: SWCurveConfig,
+{
+    let commitments = transaction
+        .commitments
+        .iter()
+        .map(|c| c.0)
+        .collect::<Vec<_>>();
+    let nullifiers = transaction
+        .nullifiers
+        .iter()
+        .map(|n| n.0)
+        .collect::<Vec<_>>();
+    to_hex(&statement_hash(
+        &transaction.circuit_type,
+        transaction.swap_field,
+        &[
+            &commitments,
+            &nullifiers,
+            &transaction.commitment_root,
+            &transaction.eph_pub_key,
+            &transaction.ciphertexts,
+        ],
+    ))
+}
+
+// Where a transaction submitted to the sequencer ended up
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum TransactionStatus {
+    Pending,
+    Included { block_number: u64 },
+    Rejected { reason: String },
 }
 
 #[derive(Debug, Clone, Eq, Hash, PartialEq, Serialize, Deserialize)]
 pub enum CircuitType {
     Mint(usize),
     Transfer(usize, usize),
+    Swap,
+    Withdraw(usize),
     BaseRollup,
     BounceRollup,
     MergeRollup,
     BounceMergeRollup,
 }
+
+impl CircuitType {
+    // Name of the files holding keys for this circuit
+    pub fn key_name(&self) -> String {
+        match self {
+            CircuitType::Mint(c) => format!("mint_{c}"),
+            CircuitType::Transfer(c, n) => format!("transfer_{c}_{n}"),
+            CircuitType::Swap => "swap".to_string(),
+            CircuitType::Withdraw(n) => format!("withdraw_{n}"),
+            other => format!("{other:?}").to_lowercase(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use curves::vesta::Fr;
+
+    fn transaction(commitment: u64) -> BlockTransaction<Fr> {
+        BlockTransaction {
+            commitments: vec![Fr::from(commitment)],
+            nullifiers: vec![],
+            eph_pub_key: vec![],
+            ciphertexts: vec![],
+            swap_field: false,
+            circuit_type: CircuitType::Mint(1),
+        }
+    }
+
+    #[test]
+    fn test_transactions_root_commits_to_every_transaction() {
+        let transactions = (0..3).map(transaction).collect::<Vec<_>>();
+        let root = transactions_root(&transactions);
+
+        let mut repeated = transactions.clone();
+        repeated.push(transaction(2));
+        assert_ne!(transactions_root(&repeated), root);
+        assert_ne!(transactions_root(&transactions[..2]), root);
+        assert_ne!(transactions_root(&transactions[1..]), root);
+        assert_eq!(transactions_root(&transactions), root);
+    }
+}
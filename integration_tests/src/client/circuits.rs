@@ -2,6 +2,7 @@ use curves::{pallas::PallasConfig, vesta::VestaConfig};
 use jf_plonk::nightfall::ipa_structs::ProvingKey;
 
 use super::test_app::ClientTestApp;
+use crate::common::setup::VESTA_SRS;
 use anyhow::anyhow;
 use client::ports::prover::Prover;
 use common::structs::CircuitType;
@@ -15,7 +16,7 @@ impl ClientTestApp {
         let mut prover = self.prover.lock().await;
         for c in circuits {
             let keys = c
-                .generate_keys()
+                .generate_keys_with_srs(&VESTA_SRS)
                 .map_err(|e| anyhow!("Failed to generate keys: {:?}", e))?;
             prover.store_pk(c.get_circuit_type(), keys.0);
         }
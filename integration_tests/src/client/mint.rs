@@ -1,3 +1,4 @@
+use crate::common::setup::VESTA_SRS;
 use crate::common::utils::decimal_to_hex;
 
 use super::test_app::ClientTestApp;
@@ -138,7 +139,7 @@ impl ClientTestApp {
                 preimage.len(),
             )
             .unwrap();
-        let (_, vk) = circuit.generate_keys().unwrap();
+        let (_, vk) = circuit.generate_keys_with_srs(&VESTA_SRS).unwrap();
         let circuit_inputs = build_mint_inputs::<PallasConfig, VestaConfig, _>(preimage).unwrap();
         let mint_circuit = circuit.to_plonk_circuit(circuit_inputs).unwrap();
         let public_inputs = mint_circuit.public_input().unwrap();
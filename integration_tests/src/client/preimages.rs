@@ -3,6 +3,7 @@ use client::domain::{StoredPreimageInfo, StoredPreimageInfoVector};
 use client::ports::committable::Committable;
 use client::ports::storage::PreimageDB;
 use curves::pallas::PallasConfig;
+use reqwest::Response;
 
 impl ClientTestApp {
     pub async fn get_preimages(&self) -> StoredPreimageInfoVector<PallasConfig> {
@@ -24,4 +25,30 @@ impl ClientTestApp {
         }
         Ok(())
     }
+
+    pub async fn get_notes_request(&self, query: &[(&str, &str)]) -> Response {
+        self.api_client
+            .get(format!("{}/notes", self.address))
+            .query(query)
+            .send()
+            .await
+            .unwrap()
+    }
+
+    pub async fn get_balance_request(&self, owner: &str, token_id: &str) -> Response {
+        self.api_client
+            .get(format!("{}/balance", self.address))
+            .query(&[("owner", owner), ("token_id", token_id)])
+            .send()
+            .await
+            .unwrap()
+    }
+
+    pub async fn get_preimage_request(&self, commitment: &str) -> Response {
+        self.api_client
+            .get(format!("{}/preimages/{}", self.address, commitment))
+            .send()
+            .await
+            .unwrap()
+    }
 }
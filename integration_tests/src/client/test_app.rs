@@ -1,6 +1,8 @@
 use client::adapters::rest_api::rest_api_entry::Application;
+use client::domain::PendingBlocks;
 use client::services::{
-    prover::in_memory_prover::InMemProver, storage::in_mem_storage::InMemStorage,
+    prover::in_memory_prover::InMemProver,
+    storage::{client_storage::ClientStorage, in_mem_storage::InMemStorage},
 };
 use common::configuration;
 use common::services::notifier::HttpNotifier;
@@ -21,7 +23,7 @@ pub struct ClientTestApp {
     pub address: String,
     pub port: u16,
     pub prover: Arc<Mutex<InMemProver<PallasConfig, VestaConfig, VestaConfig>>>,
-    pub db: Arc<Mutex<InMemStorage<PallasConfig, Fq>>>,
+    pub db: Arc<Mutex<ClientStorage<PallasConfig, Fq>>>,
     pub notifier: Arc<Mutex<HttpNotifier<Transaction<VestaConfig>>>>,
     pub api_client: reqwest::Client,
     pub user_keys: Option<UserKeysResponseBody>,
@@ -56,7 +58,7 @@ pub async fn spawn_client_app() -> ClientTestApp {
         c
     };
 
-    let db: InMemStorage<PallasConfig, Fq> = InMemStorage::new();
+    let db: ClientStorage<PallasConfig, Fq> = ClientStorage::InMem(InMemStorage::new());
     let thread_safe_db = std::sync::Arc::new(tokio::sync::Mutex::new(db));
     let prover: InMemProver<PallasConfig, VestaConfig, _> = InMemProver::new();
     let thread_safe_prover = Arc::new(tokio::sync::Mutex::new(prover));
@@ -68,6 +70,7 @@ pub async fn spawn_client_app() -> ClientTestApp {
         thread_safe_db.clone(),
         thread_safe_prover.clone(),
         thread_safe_notifier.clone(),
+        Arc::new(Mutex::new(PendingBlocks::default())),
         configuration.client.clone(),
     )
     .await
@@ -1,4 +1,5 @@
 use super::test_app::ClientTestApp;
+use crate::common::setup::VESTA_SRS;
 use ark_ff::UniformRand;
 use client::adapters::rest_api::structs::TransferInput;
 use client::domain::StoredPreimageInfo;
@@ -47,6 +48,7 @@ impl ClientTestApp {
         let transfer_request = TransferInput {
             transfer_amount: Fq::from_str(transfer_amount).unwrap(),
             commitments_to_use,
+            token_id: None,
             sender: user_keys.public_key,
             recipient: user_keys.public_key,
             eph_key: Some(Fq::rand(&mut rng)),
@@ -77,7 +79,7 @@ impl ClientTestApp {
         let circuit =
             utils::circuits::get_transfer_circuit_from_params::<PallasConfig, VestaConfig, _>(1, 1)
                 .unwrap();
-        let (_, vk) = circuit.generate_keys().unwrap();
+        let (_, vk) = circuit.generate_keys_with_srs(&VESTA_SRS).unwrap();
 
         let circuit_inputs = build_transfer_inputs::<PallasConfig, VestaConfig, _, _>(
             self.db.clone(),
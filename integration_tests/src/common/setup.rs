@@ -0,0 +1,14 @@
+use curves::{pallas::PallasConfig, vesta::VestaConfig};
+use once_cell::sync::Lazy;
+use plonk_prover::setup::{universal_setup, UniversalSrs, SRS_DEGREE};
+
+// Client and sequencer test apps share the same setup, as separate processes
+// deriving the setup would.
+pub static VESTA_SRS: Lazy<UniversalSrs<VestaConfig>> = Lazy::new(|| {
+    universal_setup::<VestaConfig, VestaConfig>(SRS_DEGREE).expect("Failed to derive Vesta setup")
+});
+
+pub static PALLAS_SRS: Lazy<UniversalSrs<PallasConfig>> = Lazy::new(|| {
+    universal_setup::<PallasConfig, PallasConfig>(SRS_DEGREE)
+        .expect("Failed to derive Pallas setup")
+});
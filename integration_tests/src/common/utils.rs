@@ -1,8 +1,8 @@
 use anyhow::Result;
 use client::adapters::rest_api::structs::TransferInput;
 use client::domain::StoredPreimageInfo;
-use common::structs::Block;
 use common::structs::Transaction;
+use common::structs::{transactions_root, Block};
 use curves::pallas::PallasConfig;
 use curves::vesta::VestaConfig;
 use std::fs::File;
@@ -67,10 +67,15 @@ pub fn read_preimage_from_file(path: &str) -> Result<StoredPreimageInfo<PallasCo
         .map_err(|_| anyhow::anyhow!("Error deserializing Preimage"))
 }
 
+// Recorded blocks predate block headers, so their header is filled in from
+// their contents
 pub fn read_block_from_file(path: &str) -> Result<Block<curves::vesta::Fr>> {
     let block = read_from_file(path)?;
-    serde_json::from_str::<Block<curves::vesta::Fr>>(&block)
-        .map_err(|_| anyhow::anyhow!("Error deserializing Block"))
+    let mut block = serde_json::from_str::<Block<curves::vesta::Fr>>(&block)
+        .map_err(|_| anyhow::anyhow!("Error deserializing Block"))?;
+    block.header.block_number = block.block_number;
+    block.header.transactions_root = transactions_root(&block.transactions);
+    Ok(block)
 }
 
 pub fn decimal_to_hex(decimal_str: &str) -> Result<String, Box<dyn std::error::Error>> {
@@ -1,16 +1,20 @@
 use super::test_app::SequencerTestApp;
+use crate::common::setup::{PALLAS_SRS, VESTA_SRS};
 use curves::{pallas::PallasConfig, vesta::VestaConfig};
 use plonk_prover::client::ClientPlonkCircuit;
 use plonk_prover::utils::vk_tree::build_vk_tree;
 use sequencer::ports::storage::GlobalStateStorage;
 use sequencer::services::prover::in_mem_sequencer_prover::InMemProver;
-use sequencer::services::prover::{generate_and_store_cks, generate_and_store_client_circuit_vks};
+use sequencer::services::prover::{
+    generate_and_store_cks, generate_and_store_client_circuit_vks, generate_and_store_rollup_pks,
+};
 
 impl SequencerTestApp {
-    pub async fn add_client_circuits(
+    // Only the verifying keys, enough to admit transactions of these circuits
+    pub async fn add_client_verifying_keys(
         &mut self,
-        circuits: Vec<Box<dyn ClientPlonkCircuit<PallasConfig, VestaConfig, VestaConfig>>>,
-    ) -> anyhow::Result<()> {
+        circuits: &[Box<dyn ClientPlonkCircuit<PallasConfig, VestaConfig, VestaConfig>>],
+    ) {
         let mut prover = self.prover.lock().await;
         let mut db = self.db.lock().await;
 
@@ -20,10 +24,18 @@ impl SequencerTestApp {
             _,
             _,
             InMemProver<VestaConfig, _, PallasConfig, _>,
-        >(&mut prover, &circuits);
+        >(&mut prover, circuits, &VESTA_SRS);
 
         let vk_tree = build_vk_tree(&vks);
         db.store_vk_tree(vk_tree);
+    }
+
+    pub async fn add_client_circuits(
+        &mut self,
+        circuits: Vec<Box<dyn ClientPlonkCircuit<PallasConfig, VestaConfig, VestaConfig>>>,
+    ) -> anyhow::Result<()> {
+        self.add_client_verifying_keys(&circuits).await;
+        let mut prover = self.prover.lock().await;
 
         generate_and_store_cks::<
             VestaConfig,
@@ -31,7 +43,14 @@ impl SequencerTestApp {
             PallasConfig,
             _,
             InMemProver<VestaConfig, _, PallasConfig, _>,
-        >(&mut prover);
+        >(&mut prover, &VESTA_SRS, &PALLAS_SRS);
+
+        // Blocks of two transactions of the same circuit
+        let shapes = circuits
+            .iter()
+            .map(|c| vec![c.get_circuit_type(), c.get_circuit_type()])
+            .collect::<Vec<_>>();
+        generate_and_store_rollup_pks(&mut *prover, &circuits, &shapes, None)?;
 
         Ok(())
     }
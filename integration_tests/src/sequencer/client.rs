@@ -25,17 +25,19 @@ impl SequencerTestApp {
         Ok(block)
     }
 
+    // Blocks are delivered in the background, so wait a bit for them to arrive
     pub async fn get_client_requests_as_bytes(&self) -> Result<Vec<u8>> {
-        let requests = self
-            .client_server
-            .received_requests()
-            .await
-            .ok_or(anyhow::anyhow!("Error retrieving sequencer requests"))?;
-
-        Ok(requests
-            .last()
-            .ok_or(anyhow::anyhow!("Error. No client requests received"))?
-            .body
-            .clone())
+        for _ in 0..50 {
+            let requests = self
+                .client_server
+                .received_requests()
+                .await
+                .ok_or(anyhow::anyhow!("Error retrieving sequencer requests"))?;
+            if let Some(request) = requests.last() {
+                return Ok(request.body.clone());
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        }
+        Err(anyhow::anyhow!("Error. No client requests received"))
     }
 }
@@ -1,12 +1,12 @@
 use crate::sequencer::test_app::SequencerTestApp;
 use anyhow::Result;
 use ark_ff::Zero;
-use common::structs::{Block, Transaction};
+use common::structs::{Block, BlockTransaction, Transaction, Withdrawal};
 use curves::{pallas::Fq, vesta::VestaConfig};
 use jf_utils::field_switching;
 use plonk_prover::rollup::circuits::client_input;
-use sequencer::ports::storage::BlockStorage;
-use sequencer::ports::storage::GlobalStateStorage;
+use sequencer::ports::storage::{BlockStorage, TransactionStorage};
+use sequencer::ports::storage::{GlobalStateStorage, StateTransition};
 use trees::{AppendTree, Tree};
 
 impl SequencerTestApp {
@@ -59,16 +59,33 @@ impl SequencerTestApp {
         let block_count = db_locked.get_block_count();
         let mut global_commitment_tree = db_locked.get_global_commitment_tree();
         global_commitment_tree.append_leaf(field_switching(&local_commitment_tree_root));
-        //db_locked.store_global_commitment_tree(global_commitment_tree);
+        db_locked.apply_state_transition(StateTransition {
+            commitment_tree: global_commitment_tree,
+            nullifier_tree,
+        });
 
+        let block_transactions: Vec<_> = transactions.iter().map(BlockTransaction::from).collect();
         let block = Block {
             block_number: block_count,
             commitments,
             nullifiers,
             commitment_root: local_commitment_tree_root,
+            withdrawals: block_transactions
+                .iter()
+                .filter_map(Withdrawal::from_block_transaction)
+                .collect(),
+            transactions: block_transactions,
+            proof: None,
+            header: Default::default(),
         };
         db_locked.insert_block(block.clone());
-        db_locked.past_txs.append(&mut transactions.to_vec());
+        // Record the transactions as included, as sequencing them would
+        for transaction in transactions {
+            db_locked
+                .insert_transaction(transaction.clone())
+                .map_err(|e| anyhow::anyhow!("{e:?}"))?;
+        }
+        db_locked.flush_mempool_transactions(block_count, transactions);
 
         Ok(block)
     }
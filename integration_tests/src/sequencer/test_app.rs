@@ -1,13 +1,13 @@
 use common::configuration;
-use common::services::notifier::HttpNotifier;
-use common::structs::Block;
 use common::telemetry;
 use curves::{pallas::PallasConfig, vesta::VestaConfig};
 
 use once_cell::sync::Lazy;
 use sequencer::adapters::rest_api::sequencer_api::Application;
 use sequencer::services::{
-    prover::in_mem_sequencer_prover::InMemProver, storage::in_mem_sequencer_storage::InMemStorage,
+    notifier::block_notifier::{configured_subscribers, BlockNotifier},
+    prover::in_mem_sequencer_prover::InMemProver,
+    storage::{in_mem_sequencer_storage::InMemStorage, sequencer_storage::SequencerStorage},
 };
 use std::sync::Arc;
 use tokio::sync::Mutex;
@@ -17,8 +17,8 @@ pub struct SequencerTestApp {
     pub address: String,
     pub port: u16,
     pub prover: Arc<Mutex<InMemProver<VestaConfig, VestaConfig, PallasConfig, PallasConfig>>>,
-    pub db: Arc<Mutex<InMemStorage>>,
-    pub notifier: Arc<Mutex<HttpNotifier<Block<curves::vesta::Fr>>>>,
+    pub db: Arc<Mutex<SequencerStorage>>,
+    pub notifier: Arc<Mutex<BlockNotifier<curves::vesta::Fr>>>,
     pub api_client: reqwest::Client,
     pub client_server: MockServer,
 }
@@ -47,16 +47,21 @@ pub async fn spawn_sequencer_app() -> SequencerTestApp {
     let configuration = {
         let mut c = configuration::get_configuration().expect("Failed to read configuration");
         c.sequencer.port = 0;
-        c.client.base_url = client_server.uri();
+        c.sequencer.notifications.subscribers = vec![format!("{}/block", client_server.uri())];
         c
     };
 
-    let db: InMemStorage = InMemStorage::new();
+    let db = SequencerStorage::InMem(InMemStorage::new());
     let thread_safe_db = std::sync::Arc::new(tokio::sync::Mutex::new(db));
     let prover: InMemProver<VestaConfig, VestaConfig, PallasConfig, PallasConfig> =
         InMemProver::new();
     let thread_safe_prover = Arc::new(tokio::sync::Mutex::new(prover));
-    let notifier = HttpNotifier::new(configuration.client);
+    let mut notifier = BlockNotifier::new(&configuration.sequencer);
+    for callback_url in configured_subscribers(&configuration) {
+        notifier
+            .subscribe(callback_url)
+            .expect("Invalid subscriber");
+    }
     let thread_safe_notifier = Arc::new(tokio::sync::Mutex::new(notifier));
 
     let application = Application::build(
@@ -1,21 +1,52 @@
+use crate::common::setup::VESTA_SRS;
 use crate::sequencer::test_app::SequencerTestApp;
 use anyhow::Result;
-use common::structs::Transaction;
-use curves::vesta::VestaConfig;
-use reqwest::header::CONTENT_TYPE;
+use ark_ff::{One, UniformRand};
+use client::adapters::rest_api::structs::TransferInput;
+use client::domain::{Preimage, PreimageStatus, StoredPreimageInfo};
+use client::ports::committable::{Committable, Nullifiable};
+use client::ports::prover::Prover;
+use client::ports::storage::{KeyDB, PreimageDB, TreeDB};
+use client::services::prover::in_memory_prover::InMemProver;
+use client::services::storage::in_mem_storage::InMemStorage;
+use client::services::user_keys::{generate_user_keys, UserKeys};
+use client::usecase::mint::inputs::build_mint_inputs;
+use client::usecase::transfer::inputs::build_transfer_inputs;
+use client::usecase::transfer::transfer_tokens::transfer_tokens;
+use common::keypair::PublicKey;
+use common::structs::{Block, Transaction};
+use curves::{
+    pallas::{Fq, PallasConfig},
+    vesta::VestaConfig,
+};
+use plonk_prover::client::circuits::{
+    circuit_inputs::CircuitInputs, mint::MintCircuit, transfer::TransferCircuit,
+};
+use plonk_prover::client::ClientPlonkCircuit;
+use rand::SeedableRng;
+use rand_chacha::ChaChaRng;
+use reqwest::{header::CONTENT_TYPE, Response};
+use std::sync::Arc;
+use tokio::sync::Mutex;
 
 impl SequencerTestApp {
-    pub async fn post_transaction(&self, transaction: &Transaction<VestaConfig>) -> Result<()> {
+    pub async fn post_transaction_request(
+        &self,
+        transaction: &Transaction<VestaConfig>,
+    ) -> Result<Response> {
         let cbor_data = serde_cbor::to_vec(&transaction)
             .map_err(|_| anyhow::anyhow!("Transaction couldnt be serialized"))?;
-        let response = self
-            .api_client
+        self.api_client
             .post(format!("{}/transactions", self.address))
             .header(CONTENT_TYPE, "application/cbor")
             .body(cbor_data)
             .send()
             .await
-            .unwrap();
+            .map_err(|_| anyhow::anyhow!("Error sending transaction to sequencer"))
+    }
+
+    pub async fn post_transaction(&self, transaction: &Transaction<VestaConfig>) -> Result<()> {
+        let response = self.post_transaction_request(transaction).await?;
 
         assert!(
             response.status().is_success(),
@@ -47,3 +78,130 @@ impl SequencerTestApp {
         Ok(transactions)
     }
 }
+
+// Proves transactions against the shared test setup the way a client would, so
+// sequencer tests post transactions that pass every admission check.
+pub struct TransactionFactory {
+    keys: UserKeys<PallasConfig>,
+    db: Arc<Mutex<InMemStorage<PallasConfig, Fq>>>,
+    prover: InMemProver<PallasConfig, VestaConfig, VestaConfig>,
+    rng: ChaChaRng,
+}
+
+impl TransactionFactory {
+    pub fn new(mnemonic: &str) -> Result<Self> {
+        let mnemonic = bip32::Mnemonic::new(mnemonic, bip32::Language::English)
+            .map_err(|e| anyhow::anyhow!("Invalid mnemonic: {e}"))?;
+        let keys = generate_user_keys::<PallasConfig>(mnemonic)
+            .map_err(|e| anyhow::anyhow!("Error generating user keys: {e}"))?;
+        let mut db = InMemStorage::new();
+        db.insert_key(keys.public_key, keys)
+            .ok_or(anyhow::anyhow!("Error storing user keys"))?;
+
+        let mut prover = InMemProver::new();
+        for circuit in [
+            Box::new(MintCircuit::<1>::new()) as Box<dyn ClientPlonkCircuit<_, _, _>>,
+            Box::new(TransferCircuit::<1, 1, 8>::new()),
+        ] {
+            let (pk, _) = circuit.generate_keys_with_srs(&VESTA_SRS)?;
+            prover.store_pk(circuit.get_circuit_type(), pk);
+        }
+
+        Ok(Self {
+            keys,
+            db: Arc::new(Mutex::new(db)),
+            prover,
+            rng: ChaChaRng::seed_from_u64(0),
+        })
+    }
+
+    // A Mint(1) of `value` to our keys. Returns the transaction and the commitment
+    // it creates, which can be spent once a block includes it.
+    pub async fn mint(&mut self, value: u64) -> Result<(Transaction<VestaConfig>, Fq)> {
+        let preimage = Preimage::new(
+            Fq::from(value),
+            Fq::one(),
+            PublicKey(self.keys.public_key),
+            Fq::rand(&mut self.rng),
+        );
+        let commitment = preimage
+            .commitment_hash()
+            .map_err(|e| anyhow::anyhow!(e.to_string()))?
+            .0;
+        let nullifier = preimage
+            .nullifier_hash(&self.keys.nullifier_key)
+            .map_err(|e| anyhow::anyhow!(e.to_string()))?;
+        self.db
+            .lock()
+            .await
+            .insert_preimage(
+                commitment,
+                StoredPreimageInfo {
+                    preimage,
+                    nullifier,
+                    block_number: None,
+                    leaf_index: None,
+                    status: PreimageStatus::Unspent,
+                },
+            )
+            .ok_or(anyhow::anyhow!("Error storing mint preimage"))?;
+
+        let inputs = build_mint_inputs::<PallasConfig, VestaConfig, VestaConfig>(vec![preimage])?;
+        let transaction = self.prove(Box::new(MintCircuit::<1>::new()), &inputs)?;
+        Ok((transaction, commitment))
+    }
+
+    // Makes the commitments of a sequenced block spendable
+    pub async fn apply_block(&self, block: &Block<Fq>) -> Result<()> {
+        let mut db = self.db.lock().await;
+        db.update_preimages(block.clone())
+            .ok_or(anyhow::anyhow!("Error updating preimages"))?;
+        db.add_block_leaves(
+            block.commitments.clone(),
+            block.block_number,
+            block.header.hash(),
+        )
+        .ok_or(anyhow::anyhow!("Error storing block leaves"))?;
+        Ok(())
+    }
+
+    // A Transfer(1, 1) spending the whole value of `commitment` back to our keys
+    pub async fn transfer(&mut self, commitment: Fq) -> Result<Transaction<VestaConfig>> {
+        let value = *self
+            .db
+            .lock()
+            .await
+            .get_preimage(commitment)
+            .ok_or(anyhow::anyhow!("Unknown commitment"))?
+            .preimage
+            .get_value();
+        let inputs = build_transfer_inputs::<PallasConfig, VestaConfig, VestaConfig, _>(
+            self.db.clone(),
+            TransferInput {
+                commitments_to_use: vec![commitment],
+                token_id: None,
+                transfer_amount: value,
+                sender: self.keys.public_key,
+                recipient: self.keys.public_key,
+                eph_key: None,
+            },
+        )
+        .await?;
+        self.prove(Box::new(TransferCircuit::<1, 1, 8>::new()), &inputs)
+    }
+
+    fn prove(
+        &self,
+        circuit: Box<dyn ClientPlonkCircuit<PallasConfig, VestaConfig, VestaConfig>>,
+        inputs: &CircuitInputs<PallasConfig>,
+    ) -> Result<Transaction<VestaConfig>> {
+        let pk = self
+            .prover
+            .get_pk(circuit.get_circuit_type())
+            .ok_or(anyhow::anyhow!("Circuit not registered"))?;
+        transfer_tokens::<_, _, _, InMemProver<PallasConfig, VestaConfig, VestaConfig>>(
+            circuit, inputs, pk,
+        )
+        .map_err(|e| anyhow::anyhow!(e))
+    }
+}
@@ -41,7 +41,9 @@ pub async fn spawn_app() -> TestApp {
         let mut client_notifier = test_app.client.notifier.lock().await;
         client_notifier.base_url = configuration.sequencer.base_url;
         let mut sequencer_notifier = test_app.sequencer.notifier.lock().await;
-        sequencer_notifier.base_url = configuration.client.base_url;
+        sequencer_notifier
+            .subscribe(format!("{}/block", configuration.client.base_url))
+            .expect("Invalid client url");
     }
 
     test_app
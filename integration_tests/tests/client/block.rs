@@ -82,3 +82,29 @@ async fn block_endpoint_updates_preimages_and_root() {
     assert_eq!(preimages[1].status, PreimageStatus::Unspent);
     assert!(root.is_some());
 }
+
+#[tokio::test]
+async fn block_endpoint_returns_200_for_replayed_block() {
+    let app = spawn_app().await;
+    let filename = "./tests/data/block0_2_mints_c1_v10_c1_v100.dat";
+    let block = read_block_from_file(filename).unwrap();
+    let body = json!(block);
+
+    let first = app
+        .api_client
+        .post(format!("{}/block", app.address))
+        .json(&body)
+        .send()
+        .await
+        .unwrap();
+    let replay = app
+        .api_client
+        .post(format!("{}/block", app.address))
+        .json(&body)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(first.status(), reqwest::StatusCode::CREATED);
+    assert_eq!(replay.status(), reqwest::StatusCode::OK);
+}
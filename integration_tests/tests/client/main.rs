@@ -2,4 +2,5 @@ pub mod block;
 pub mod health;
 pub mod keys;
 pub mod mint;
+pub mod preimages;
 pub mod transfer;
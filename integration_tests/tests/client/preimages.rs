@@ -0,0 +1,94 @@
+use ark_ff::Zero;
+use common::serialize::ark_se;
+use curves::pallas::Fq;
+use integration_tests::client::test_app::spawn_app;
+use serde_json::Value;
+
+const PREIMAGE_FILES: [&str; 2] = [
+    "./tests/data/mint_preimage_c1_v10.dat",
+    "./tests/data/mint_preimage_c1_v100.dat",
+];
+const BLOCK_FILES: [&str; 1] = ["./tests/data/block0_2_mints_c1_v10_c1_v100.dat"];
+
+fn to_hex<A: ark_serialize::CanonicalSerialize>(a: &A) -> String {
+    ark_se(a, serde_json::value::Serializer)
+        .unwrap()
+        .as_str()
+        .unwrap()
+        .to_string()
+}
+
+#[tokio::test]
+async fn notes_endpoint_returns_paginated_notes() {
+    let app = spawn_app().await;
+    app.set_initial_state(PREIMAGE_FILES.to_vec(), BLOCK_FILES.to_vec())
+        .await
+        .unwrap();
+
+    let response = app.get_notes_request(&[]).await;
+    assert!(response.status().is_success());
+    let notes: Vec<Value> = response.json().await.unwrap();
+    assert_eq!(notes.len(), 2);
+
+    let page: Vec<Value> = app
+        .get_notes_request(&[("offset", "1"), ("limit", "1")])
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert_eq!(page, vec![notes[1].clone()]);
+
+    let spent: Vec<Value> = app
+        .get_notes_request(&[("status", "Spent")])
+        .await
+        .json()
+        .await
+        .unwrap();
+    assert!(spent.is_empty());
+}
+
+#[tokio::test]
+async fn preimage_endpoint_looks_up_by_commitment() {
+    let app = spawn_app().await;
+    app.set_initial_state(PREIMAGE_FILES.to_vec(), BLOCK_FILES.to_vec())
+        .await
+        .unwrap();
+
+    let notes: Vec<Value> = app.get_notes_request(&[]).await.json().await.unwrap();
+    let commitment = notes[0]["commitment_hash"].as_str().unwrap();
+    let response = app.get_preimage_request(commitment).await;
+    assert!(response.status().is_success());
+    let note: Value = response.json().await.unwrap();
+    assert_eq!(note, notes[0]);
+
+    let response = app.get_preimage_request(&to_hex(&Fq::from(7u64))).await;
+    assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn balance_endpoint_adds_confirmed_notes() {
+    let app = spawn_app().await;
+    let preimages = app
+        .set_initial_state(PREIMAGE_FILES.to_vec(), BLOCK_FILES.to_vec())
+        .await
+        .unwrap();
+
+    let owner = preimages[0].preimage.get_public_key().as_affine();
+    let token_id = *preimages[0].preimage.get_token_id();
+    let expected = preimages
+        .iter()
+        .filter(|p| {
+            p.preimage.get_public_key().as_affine() == owner
+                && *p.preimage.get_token_id() == token_id
+        })
+        .fold(Fq::zero(), |acc, p| acc + p.preimage.get_value());
+
+    let response = app
+        .get_balance_request(&to_hex(&owner), &to_hex(&token_id))
+        .await;
+    assert!(response.status().is_success());
+    let balance: Value = response.json().await.unwrap();
+    assert_eq!(balance["confirmed"], to_hex(&expected).as_str());
+    assert_eq!(balance["pending"], to_hex(&Fq::zero()).as_str());
+    assert_eq!(balance["locked"], to_hex(&Fq::zero()).as_str());
+}
@@ -1,3 +1,4 @@
 pub mod health;
 pub mod sequence;
+pub mod subscriptions;
 pub mod transactions;
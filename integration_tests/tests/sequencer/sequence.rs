@@ -1,8 +1,14 @@
-use common::structs::Transaction;
-use integration_tests::common::utils;
+use integration_tests::client::keys::UserKeysRequestBody;
 use integration_tests::sequencer::test_app::spawn_app;
+use integration_tests::sequencer::transactions::TransactionFactory;
 use plonk_prover::client::circuits::mint::MintCircuit;
 use plonk_prover::client::circuits::transfer::TransferCircuit;
+use reqwest::StatusCode;
+
+fn transaction_factory() -> TransactionFactory {
+    TransactionFactory::new(&UserKeysRequestBody::default().mnemonic)
+        .expect("Error building transaction factory")
+}
 
 #[tokio::test]
 async fn post_sequence_after_posting_2_mint_transactions() {
@@ -11,16 +17,14 @@ async fn post_sequence_after_posting_2_mint_transactions() {
     app.add_client_circuits(vec![Box::new(MintCircuit::<1>::new())])
         .await
         .expect("Error adding new circuit");
+    let mut factory = transaction_factory();
 
     // Add first transaction
-    let mint_transaction =
-        utils::read_cbor_transaction_from_file("./tests/data/mint_transaction_c1_v10.dat").unwrap();
+    let (mint_transaction, _) = factory.mint(10).await.unwrap();
     app.post_transaction(&mint_transaction).await.unwrap();
 
     // Add second transaction
-    let mint_transaction =
-        utils::read_cbor_transaction_from_file("./tests/data/mint_transaction_c1_v100.dat")
-            .unwrap();
+    let (mint_transaction, _) = factory.mint(100).await.unwrap();
     app.post_transaction(&mint_transaction).await.unwrap();
 
     let block = app.post_sequence().await.unwrap();
@@ -42,33 +46,21 @@ async fn post_sequence_after_posting_2_transfer_transactions() {
     ])
     .await
     .expect("Error adding new circuit");
+    let mut factory = transaction_factory();
 
-    let mut transactions: Vec<Transaction<_>> = vec![];
-
-    transactions.push(
-        utils::read_cbor_transaction_from_file("./tests/data/mint_transaction_c1_v10.dat").unwrap(),
-    );
-    transactions.push(
-        utils::read_cbor_transaction_from_file("./tests/data/mint_transaction_c1_v100.dat")
-            .unwrap(),
-    );
-
-    app.new_block(&transactions).await.unwrap();
-    //app.post_transaction(&transactions[0]).await.unwrap();
-    //app.post_transaction(&transactions[1]).await.unwrap();
-    //let mut block = app.post_sequence().await.unwrap();
-
-    transactions[0] =
-        utils::read_cbor_transaction_from_file("./tests/data/transfer_transaction_c1_v10.dat")
-            .unwrap();
-    transactions[1] =
-        utils::read_cbor_transaction_from_file("./tests/data/transfer_transaction_c1_v100.dat")
-            .unwrap();
+    let (mint_10, commitment_10) = factory.mint(10).await.unwrap();
+    let (mint_100, commitment_100) = factory.mint(100).await.unwrap();
+    let block = app.new_block(&[mint_10, mint_100]).await.unwrap();
+    factory.apply_block(&block).await.unwrap();
 
-    app.post_transaction(&transactions[0]).await.unwrap();
-    app.post_transaction(&transactions[1]).await.unwrap();
+    let transfer_10 = factory.transfer(commitment_10).await.unwrap();
+    let transfer_100 = factory.transfer(commitment_100).await.unwrap();
+    app.post_transaction(&transfer_10).await.unwrap();
+    app.post_transaction(&transfer_100).await.unwrap();
 
-    app.post_sequence().await.unwrap();
+    let block = app.post_sequence().await.unwrap();
+    assert_eq!(block.block_number, 1);
+    assert_eq!(block.nullifiers.len(), 2);
 }
 
 #[tokio::test]
@@ -81,32 +73,24 @@ async fn post_sequence_after_posting_duplicated_transfer_transactions() {
     ])
     .await
     .expect("Error adding new circuit");
-
-    let mut transactions: Vec<Transaction<_>> = vec![];
-
-    transactions.push(
-        utils::read_cbor_transaction_from_file("./tests/data/mint_transaction_c1_v10.dat").unwrap(),
-    );
-    transactions.push(
-        utils::read_cbor_transaction_from_file("./tests/data/mint_transaction_c1_v100.dat")
-            .unwrap(),
+    let mut factory = transaction_factory();
+
+    let (mint_10, commitment_10) = factory.mint(10).await.unwrap();
+    let (mint_100, _) = factory.mint(100).await.unwrap();
+    let block = app.new_block(&[mint_10, mint_100]).await.unwrap();
+    factory.apply_block(&block).await.unwrap();
+
+    let transfer_10 = factory.transfer(commitment_10).await.unwrap();
+    app.post_transaction(&transfer_10).await.unwrap();
+    let response = app.post_transaction_request(&transfer_10).await.unwrap();
+    assert_eq!(
+        response.status(),
+        StatusCode::CONFLICT,
+        "Sequencer should reject a duplicated transaction with 409. Instead, it returned {}",
+        response.status()
     );
 
-    app.new_block(&transactions).await.unwrap();
-    //app.post_transaction(&transactions[0]).await.unwrap();
-    //app.post_transaction(&transactions[1]).await.unwrap();
-    //let mut block = app.post_sequence().await.unwrap();
-
-    transactions[0] =
-        utils::read_cbor_transaction_from_file("./tests/data/transfer_transaction_c1_v10.dat")
-            .unwrap();
-    transactions[1] =
-        utils::read_cbor_transaction_from_file("./tests/data/transfer_transaction_c1_v10.dat")
-            .unwrap();
-
-    app.post_transaction(&transactions[0]).await.unwrap();
-    app.post_transaction(&transactions[1]).await.unwrap();
-
+    // A single pending transaction is not enough for a block
     let response = app.post_sequence().await;
     assert!(response.is_err());
 }
@@ -121,53 +105,28 @@ async fn post_2_sequences_after_posting_4_transfer_transactions() {
     ])
     .await
     .expect("Error adding new circuit");
-
-    let mut transactions: Vec<Transaction<_>> = vec![];
-
-    transactions.push(
-        utils::read_cbor_transaction_from_file("./tests/data/mint_transaction_c1_v10.dat").unwrap(),
-    );
-    transactions.push(
-        utils::read_cbor_transaction_from_file("./tests/data/mint_transaction_c1_v100.dat")
-            .unwrap(),
-    );
-
-    app.new_block(&transactions).await.unwrap();
-    transactions.push(
-        utils::read_cbor_transaction_from_file("./tests/data/mint_transaction_c1_v1000.dat")
-            .unwrap(),
-    );
-    transactions.push(
-        utils::read_cbor_transaction_from_file("./tests/data/mint_transaction_c1_v2000.dat")
-            .unwrap(),
-    );
-
-    app.new_block(&transactions).await.unwrap();
-    //app.post_transaction(&transactions[0]).await.unwrap();
-    //app.post_transaction(&transactions[1]).await.unwrap();
-    //let mut block = app.post_sequence().await.unwrap();
-
-    transactions[0] =
-        utils::read_cbor_transaction_from_file("./tests/data/transfer_transaction_c1_v10.dat")
-            .unwrap();
-    transactions[1] =
-        utils::read_cbor_transaction_from_file("./tests/data/transfer_transaction_c1_v100.dat")
-            .unwrap();
-
-    app.post_transaction(&transactions[0]).await.unwrap();
-    app.post_transaction(&transactions[1]).await.unwrap();
-
-    app.post_sequence().await.unwrap();
-
-    transactions[0] =
-        utils::read_cbor_transaction_from_file("./tests/data/transfer_transaction_c1_v1000.dat")
-            .unwrap();
-    transactions[1] =
-        utils::read_cbor_transaction_from_file("./tests/data/transfer_transaction_c1_v2000.dat")
-            .unwrap();
-
-    app.post_transaction(&transactions[0]).await.unwrap();
-    app.post_transaction(&transactions[1]).await.unwrap();
-
-    app.post_sequence().await.unwrap();
+    let mut factory = transaction_factory();
+
+    let mut commitments = vec![];
+    for values in [[10, 100], [1000, 2000]] {
+        let mut mints = vec![];
+        for value in values {
+            let (mint, commitment) = factory.mint(value).await.unwrap();
+            mints.push(mint);
+            commitments.push(commitment);
+        }
+        let block = app.new_block(&mints).await.unwrap();
+        factory.apply_block(&block).await.unwrap();
+    }
+
+    for pair in commitments.chunks(2) {
+        for commitment in pair {
+            let transfer = factory.transfer(*commitment).await.unwrap();
+            app.post_transaction(&transfer).await.unwrap();
+        }
+        app.post_sequence().await.unwrap();
+    }
+
+    let block = app.post_sequence().await;
+    assert!(block.is_err(), "The mempool should be empty");
 }
@@ -0,0 +1,67 @@
+use integration_tests::sequencer::test_app::spawn_app;
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+
+#[tokio::test]
+async fn subscriptions_can_be_registered_and_removed() {
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+    let callback_url = "http://localhost:9000/block";
+
+    let response = client
+        .post(&format!("{}/subscriptions", app.address))
+        .json(&json!({ "callback_url": callback_url }))
+        .send()
+        .await
+        .expect("Failed execute request");
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let subscription: Value = response.json().await.unwrap();
+    let id = subscription["id"].as_str().unwrap().to_string();
+
+    let response = client
+        .post(&format!("{}/subscriptions", app.address))
+        .json(&json!({ "callback_url": callback_url }))
+        .send()
+        .await
+        .expect("Failed execute request");
+    assert_eq!(response.status(), StatusCode::CONFLICT);
+
+    let callback_urls: Vec<String> = client
+        .get(&format!("{}/subscriptions", app.address))
+        .send()
+        .await
+        .expect("Failed execute request")
+        .json()
+        .await
+        .unwrap();
+    // The mock client is subscribed at startup
+    assert_eq!(callback_urls.len(), 2);
+    assert!(callback_urls.iter().any(|url| url == callback_url));
+
+    let response = client
+        .delete(&format!("{}/subscriptions/{}", app.address, id))
+        .send()
+        .await
+        .expect("Failed execute request");
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let response = client
+        .delete(&format!("{}/subscriptions/{}", app.address, id))
+        .send()
+        .await
+        .expect("Failed execute request");
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn subscription_with_invalid_callback_url_is_rejected() {
+    let app = spawn_app().await;
+    let response = reqwest::Client::new()
+        .post(&format!("{}/subscriptions", app.address))
+        .json(&json!({ "callback_url": "localhost" }))
+        .send()
+        .await
+        .expect("Failed execute request");
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
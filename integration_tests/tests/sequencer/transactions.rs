@@ -1,14 +1,24 @@
-use integration_tests::common::utils;
+use integration_tests::client::keys::UserKeysRequestBody;
 use integration_tests::sequencer::test_app::spawn_app;
+use integration_tests::sequencer::transactions::TransactionFactory;
+use plonk_prover::client::circuits::mint::MintCircuit;
+use plonk_prover::client::circuits::transfer::TransferCircuit;
 use reqwest::StatusCode;
 
+fn transaction_factory() -> TransactionFactory {
+    TransactionFactory::new(&UserKeysRequestBody::default().mnemonic)
+        .expect("Error building transaction factory")
+}
+
 #[tokio::test]
 async fn post_correct_mint_transactions_returns_200() {
-    let app = spawn_app().await;
+    let mut app = spawn_app().await;
+    app.add_client_verifying_keys(&[Box::new(MintCircuit::<1>::new())])
+        .await;
+    let mut factory = transaction_factory();
 
     // Add first transaction
-    let mint_transaction =
-        utils::read_cbor_transaction_from_file("./tests/data/mint_transaction_c1_v10.dat").unwrap();
+    let (mint_transaction, _) = factory.mint(10).await.unwrap();
     app.post_transaction(&mint_transaction).await.unwrap();
 
     let transactions = app.get_transactions().await.unwrap();
@@ -20,9 +30,7 @@ async fn post_correct_mint_transactions_returns_200() {
     );
 
     // Add second transaction
-    let mint_transaction =
-        utils::read_cbor_transaction_from_file("./tests/data/mint_transaction_c1_v100.dat")
-            .unwrap();
+    let (mint_transaction, _) = factory.mint(100).await.unwrap();
     app.post_transaction(&mint_transaction).await.unwrap();
 
     let transactions = app.get_transactions().await.unwrap();
@@ -36,11 +44,20 @@ async fn post_correct_mint_transactions_returns_200() {
 
 #[tokio::test]
 async fn post_correct_transfer_transactions_returns_200() {
-    let app = spawn_app().await;
+    let mut app = spawn_app().await;
+    app.add_client_verifying_keys(&[
+        Box::new(MintCircuit::<1>::new()),
+        Box::new(TransferCircuit::<1, 1, 8>::new()),
+    ])
+    .await;
+    let mut factory = transaction_factory();
+
+    let (mint_10, commitment_10) = factory.mint(10).await.unwrap();
+    let (mint_100, commitment_100) = factory.mint(100).await.unwrap();
+    let block = app.new_block(&[mint_10, mint_100]).await.unwrap();
+    factory.apply_block(&block).await.unwrap();
 
-    let transfer_transaction =
-        utils::read_cbor_transaction_from_file("./tests/data/transfer_transaction_c1_v10.dat")
-            .unwrap();
+    let transfer_transaction = factory.transfer(commitment_10).await.unwrap();
     app.post_transaction(&transfer_transaction).await.unwrap();
 
     let transactions = app.get_transactions().await.unwrap();
@@ -51,9 +68,7 @@ async fn post_correct_transfer_transactions_returns_200() {
         transactions.len()
     );
 
-    let transfer_transaction =
-        utils::read_cbor_transaction_from_file("./tests/data/transfer_transaction_c1_v100.dat")
-            .unwrap();
+    let transfer_transaction = factory.transfer(commitment_100).await.unwrap();
     app.post_transaction(&transfer_transaction).await.unwrap();
 
     let transactions = app.get_transactions().await.unwrap();
@@ -65,6 +80,85 @@ async fn post_correct_transfer_transactions_returns_200() {
     );
 }
 
+#[tokio::test]
+async fn post_transaction_of_unknown_circuit_returns_400() {
+    let app = spawn_app().await;
+    let mut factory = transaction_factory();
+
+    let (mint_transaction, _) = factory.mint(10).await.unwrap();
+    let response = app
+        .post_transaction_request(&mint_transaction)
+        .await
+        .unwrap();
+    assert_eq!(
+        response.status(),
+        StatusCode::BAD_REQUEST,
+        "Sequencer should reject a transaction of an unregistered circuit with 400. Instead, it returned {}",
+        response.status()
+    );
+}
+
+#[tokio::test]
+async fn post_transfer_spending_a_pending_nullifier_returns_409() {
+    let mut app = spawn_app().await;
+    app.add_client_verifying_keys(&[
+        Box::new(MintCircuit::<1>::new()),
+        Box::new(TransferCircuit::<1, 1, 8>::new()),
+    ])
+    .await;
+    let mut factory = transaction_factory();
+
+    let (mint_transaction, commitment) = factory.mint(10).await.unwrap();
+    let block = app.new_block(&[mint_transaction]).await.unwrap();
+    factory.apply_block(&block).await.unwrap();
+
+    // Two different transfers of the same commitment share its nullifier
+    let first_transfer = factory.transfer(commitment).await.unwrap();
+    let second_transfer = factory.transfer(commitment).await.unwrap();
+    app.post_transaction(&first_transfer).await.unwrap();
+
+    let response = app
+        .post_transaction_request(&second_transfer)
+        .await
+        .unwrap();
+    assert_eq!(
+        response.status(),
+        StatusCode::CONFLICT,
+        "Sequencer should reject a transfer of a pending nullifier with 409. Instead, it returned {}",
+        response.status()
+    );
+}
+
+#[tokio::test]
+async fn post_transfer_spending_a_spent_nullifier_returns_409() {
+    let mut app = spawn_app().await;
+    app.add_client_verifying_keys(&[
+        Box::new(MintCircuit::<1>::new()),
+        Box::new(TransferCircuit::<1, 1, 8>::new()),
+    ])
+    .await;
+    let mut factory = transaction_factory();
+
+    let (mint_transaction, commitment) = factory.mint(10).await.unwrap();
+    let block = app.new_block(&[mint_transaction]).await.unwrap();
+    factory.apply_block(&block).await.unwrap();
+
+    let first_transfer = factory.transfer(commitment).await.unwrap();
+    let second_transfer = factory.transfer(commitment).await.unwrap();
+    app.new_block(&[first_transfer]).await.unwrap();
+
+    let response = app
+        .post_transaction_request(&second_transfer)
+        .await
+        .unwrap();
+    assert_eq!(
+        response.status(),
+        StatusCode::CONFLICT,
+        "Sequencer should reject a transfer of a spent nullifier with 409. Instead, it returned {}",
+        response.status()
+    );
+}
+
 #[tokio::test]
 async fn post_empty_transactions_returns_415() {
     let app = spawn_app().await;
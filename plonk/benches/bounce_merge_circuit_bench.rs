@@ -17,7 +17,7 @@ pub fn benchmark_bounce_merge<const D: usize>(c: &mut Criterion) {
     let stored_proof_merge = merge_circuit_helper_generator::<D>(&client_circuits);
     let (global_public_inputs, subtree_public_inputs, passthrough_instance, bounce_accs) =
         stored_proof_merge.pub_inputs;
-    let (bounce_circuit, _) = bounce_merge_circuit::<PallasConfig, VestaConfig>(
+    let (bounce_circuit, _, _) = bounce_merge_circuit::<PallasConfig, VestaConfig>(
         stored_proof_merge.vk,
         global_public_inputs.clone(),
         subtree_public_inputs.clone(),
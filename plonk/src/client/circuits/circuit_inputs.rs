@@ -32,6 +32,7 @@ where
     pub recipients: Vec<PublicKey<E>>,
     pub root_key: E::BaseField,
     pub ephemeral_key: E::BaseField,
+    pub withdraw_address: E::BaseField,
 }
 
 /*
@@ -69,6 +70,18 @@ Swap:
     root_key: P::ScalarField,
     ephemeral_key: P::ScalarField,
 
+Withdraw:
+    token_values: [P::ScalarField; 2], // Withdrawn value and change value
+    token_salts: P::ScalarField, // Salt of the change commitment
+    token_ids: P::ScalarField,
+    old_token_values: [P::ScalarField; N],
+    old_token_salts: [P::ScalarField; N],
+    commitment_tree_root: [P::ScalarField; N],
+    membership_path: [[P::ScalarField; D]; N],
+    membership_path_index: [P::ScalarField; N],
+    root_key: P::ScalarField,
+    withdraw_address: P::ScalarField,
+
 */
 impl<E> CircuitInputs<E>
 where
@@ -88,6 +101,7 @@ where
             recipients: Vec::new(),
             root_key: E::BaseField::zero(),
             ephemeral_key: E::BaseField::zero(),
+            withdraw_address: E::BaseField::zero(),
         }
     }
     pub fn build(&self) -> Self {
@@ -104,6 +118,7 @@ where
             recipients: self.recipients.clone(),
             root_key: self.root_key,
             ephemeral_key: self.ephemeral_key,
+            withdraw_address: self.withdraw_address,
         }
     }
     pub fn add_token_values(&mut self, token_value: Vec<E::BaseField>) -> &mut Self {
@@ -160,4 +175,8 @@ where
         self.root_key = root_key;
         self
     }
+    pub fn add_withdraw_address(&mut self, withdraw_address: E::BaseField) -> &mut Self {
+        self.withdraw_address = withdraw_address;
+        self
+    }
 }
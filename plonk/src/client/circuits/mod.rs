@@ -2,6 +2,7 @@ pub mod circuit_inputs;
 pub mod mint;
 pub mod swap;
 pub mod transfer;
+pub mod withdraw;
 
 pub mod structs {
     use ark_ec::pairing::Pairing;
@@ -0,0 +1,177 @@
+use ark_ec::{pairing::Pairing, short_weierstrass::SWCurveConfig, CurveConfig};
+use ark_ff::PrimeField;
+
+use jf_relation::{errors::CircuitError, Circuit, PlonkCircuit};
+
+use crate::client::circuits::circuit_inputs::CircuitInputs;
+use crate::client::circuits::transfer::{
+    PLAINTEXT_VAR_LEN, POSEIDON_STATE_VAR_LEN3, POSEIDON_STATE_VAR_LEN6, PRIVATE_KEY_LEN,
+};
+use crate::primitives::circuits::{
+    kem_dem::{KemDemGadget, KemDemParams, PlainTextVars},
+    merkle_tree::BinaryMerkleTreeGadget,
+    poseidon::{PoseidonGadget, PoseidonStateVar},
+};
+use common::derived_keys::{NULLIFIER_PREFIX, PRIVATE_KEY_PREFIX};
+use std::str::FromStr;
+
+// Inputs of one half of a swap:
+// - old_token_values[0], old_token_salts[0], old_token_ids[0]: commitment sent to the counterparty
+// - membership_path[0], membership_path_index[0], commitment_tree_root[0]: its membership proof
+// - token_values[0], token_ids[0]: value and token expected from the counterparty
+// - token_salts[0]: nonce of the incoming commitment, i.e. the counterparty's leaf index
+// - recipients[0]: counterparty public key
+// D: depth of the merkle tree
+pub fn swap_circuit<P, V, const D: usize>(
+    circuit_inputs: CircuitInputs<P>,
+) -> Result<PlonkCircuit<V::ScalarField>, CircuitError>
+where
+    P: SWCurveConfig,
+    V: Pairing<ScalarField = P::BaseField>,
+    <P as CurveConfig>::BaseField: PrimeField + KemDemParams<Field = V::ScalarField>,
+{
+    let mut circuit = PlonkCircuit::new_turbo_plonk();
+
+    // Swap_field = true
+    circuit.create_public_boolean_variable(true)?;
+
+    let private_key_domain = P::BaseField::from_str(PRIVATE_KEY_PREFIX)
+        .map_err(|_| CircuitError::NotSupported(String::from("Prefix")))?;
+    let nullifier_key_domain = P::BaseField::from_str(NULLIFIER_PREFIX)
+        .map_err(|_| CircuitError::NotSupported(String::from("Prefix")))?;
+    // Derive Keys - ToDo, remove this once we have HSM-compatible key derivation
+    let private_key_domain_var = circuit.create_constant_variable(private_key_domain)?;
+    let nullifier_key_domain_var = circuit.create_constant_variable(nullifier_key_domain)?;
+    let root_key_var = circuit.create_variable(circuit_inputs.root_key)?;
+
+    let private_key_var = PoseidonGadget::<
+        PoseidonStateVar<POSEIDON_STATE_VAR_LEN3>,
+        V::ScalarField,
+    >::hash(&mut circuit, &[root_key_var, private_key_domain_var])?;
+    let private_key_bits_var =
+        circuit.unpack(private_key_var, V::ScalarField::MODULUS_BIT_SIZE as usize)?;
+
+    let private_key_var_trunc = private_key_bits_var
+        .into_iter()
+        .take(PRIVATE_KEY_LEN)
+        .collect::<Vec<_>>();
+
+    let private_key_var_trunc_bits = private_key_var_trunc.as_slice();
+    let generator_point_var = &circuit.create_constant_sw_point_variable(P::GENERATOR.into())?;
+
+    // Implicit mod being done here
+    let public_key_var = circuit
+        .variable_base_binary_sw_scalar_mul::<P>(private_key_var_trunc_bits, generator_point_var)?;
+
+    let nullifier_key_var = PoseidonGadget::<
+        PoseidonStateVar<POSEIDON_STATE_VAR_LEN3>,
+        V::ScalarField,
+    >::hash(&mut circuit, &[root_key_var, nullifier_key_domain_var])?;
+
+    // Calculate the private old commitment hash and check the sibling path
+    let outgoing_token_id_var = circuit.create_variable(circuit_inputs.old_token_ids[0])?;
+    let commitment_root_var =
+        circuit.create_public_variable(circuit_inputs.commitment_tree_root[0])?;
+    let old_commitment_nonce_var = circuit.create_variable(circuit_inputs.old_token_salts[0])?;
+    let old_commitment_val_var = circuit.create_variable(circuit_inputs.old_token_values[0])?;
+
+    let old_commitment_hash_var =
+        PoseidonGadget::<PoseidonStateVar<POSEIDON_STATE_VAR_LEN6>, V::ScalarField>::hash(
+            &mut circuit,
+            &[
+                old_commitment_val_var,
+                outgoing_token_id_var,
+                old_commitment_nonce_var,
+                public_key_var.get_x(),
+                public_key_var.get_y(),
+            ],
+        )?;
+    // Check the sibling path
+    let calc_commitment_root_var = BinaryMerkleTreeGadget::<D, V::ScalarField>::calculate_root(
+        &mut circuit,
+        old_commitment_hash_var,
+        circuit_inputs.membership_path_index[0],
+        circuit_inputs.membership_path[0]
+            .clone()
+            .try_into()
+            .map_err(|_| {
+                CircuitError::ParameterError("Error converting membership path".to_string())
+            })?,
+    )?;
+    circuit.enforce_equal(calc_commitment_root_var, commitment_root_var)?;
+    // Calculate the public nullifier hash
+    let nullifier_hash_var =
+        PoseidonGadget::<PoseidonStateVar<POSEIDON_STATE_VAR_LEN3>, V::ScalarField>::hash(
+            &mut circuit,
+            &[nullifier_key_var, old_commitment_hash_var],
+        )?;
+    circuit.set_variable_public(nullifier_hash_var)?;
+
+    // Calculate the recipients commitment hash, this has an additional requirement
+    // Check that the commitment nonce is the same as the index of the old commitment
+    // Check that the recipients public key is set as the new owner
+    let recipient_commitment_nonce_var =
+        circuit.create_variable(circuit_inputs.membership_path_index[0])?;
+    let recipient_var =
+        circuit.create_sw_point_variable(circuit_inputs.recipients[0].as_affine().into())?;
+    // We are sending the exact token => same value
+    let recipient_commitment_hash_var =
+        PoseidonGadget::<PoseidonStateVar<POSEIDON_STATE_VAR_LEN6>, V::ScalarField>::hash(
+            &mut circuit,
+            &[
+                old_commitment_val_var,
+                outgoing_token_id_var,
+                recipient_commitment_nonce_var,
+                recipient_var.get_x(),
+                recipient_var.get_y(),
+            ],
+        )?;
+    circuit.set_variable_public(recipient_commitment_hash_var)?;
+
+    // Calculate the expected incoming commitment as agreed in the swap
+    let incoming_token_id_var = circuit.create_variable(circuit_inputs.token_ids[0])?;
+    let incoming_commitment_nonce_var = circuit.create_variable(circuit_inputs.token_salts[0])?;
+    let incoming_commitment_val_var = circuit.create_variable(circuit_inputs.token_values[0])?;
+
+    let incoming_commitment_hash_var =
+        PoseidonGadget::<PoseidonStateVar<POSEIDON_STATE_VAR_LEN6>, V::ScalarField>::hash(
+            &mut circuit,
+            &[
+                incoming_commitment_val_var,
+                incoming_token_id_var,
+                incoming_commitment_nonce_var,
+                public_key_var.get_x(),
+                public_key_var.get_y(),
+            ],
+        )?;
+    circuit.set_variable_public(incoming_commitment_hash_var)?;
+
+    // Check the encryption of secret information to the recipient
+    // This proves that they will be able to decrypt the information
+    let gen = circuit.create_constant_sw_point_variable(P::GENERATOR.into())?;
+    let ephemeral_key_var = circuit.create_variable(circuit_inputs.ephemeral_key)?;
+    let eph_key_bits =
+        circuit.unpack(ephemeral_key_var, P::BaseField::MODULUS_BIT_SIZE as usize)?;
+    let eph_public_key = circuit.variable_base_binary_sw_scalar_mul::<P>(&eph_key_bits, &gen)?;
+    circuit.set_variable_public(eph_public_key.get_x())?;
+    circuit.set_variable_public(eph_public_key.get_y())?;
+
+    let ciphertext_vars =
+        KemDemGadget::<PlainTextVars<PLAINTEXT_VAR_LEN>, P, V::ScalarField>::kem_dem(
+            &mut circuit,
+            ephemeral_key_var,
+            recipient_var,
+            [
+                old_commitment_val_var,
+                outgoing_token_id_var,
+                recipient_commitment_nonce_var,
+            ],
+        )?;
+    for ciphertext in ciphertext_vars {
+        circuit.set_variable_public(ciphertext)?;
+    }
+
+    circuit.check_circuit_satisfiability(&circuit.public_input()?)?;
+    circuit.finalize_for_arithmetization()?;
+    Ok(circuit)
+}
@@ -0,0 +1,76 @@
+use super::check_inputs;
+use crate::client::circuits::circuit_inputs::CircuitInputs;
+use crate::primitives::circuits::kem_dem::KemDemParams;
+use crate::utils::poseidon_utils::build_commitment_hash;
+use ark_ec::{
+    pairing::Pairing,
+    short_weierstrass::{Affine, Projective, SWCurveConfig},
+    CurveConfig,
+};
+use ark_ff::{PrimeField, UniformRand};
+use common::crypto::poseidon::constants::PoseidonParams;
+use common::derived_keys::DerivedKeys;
+use common::keypair::PublicKey;
+use jf_primitives::rescue::RescueParameter;
+use jf_relation::errors::CircuitError;
+use jf_relation::gadgets::ecc::SWToTEConParam;
+use rand::SeedableRng;
+use rand_chacha::ChaChaRng;
+use trees::{AppendTree, MembershipTree, Tree};
+use zk_macros::client_bounds;
+
+#[client_bounds]
+pub fn build_random_inputs<P, V, VSW, const D: usize>(
+    token_id: Option<V::ScalarField>,
+) -> Result<CircuitInputs<P>, CircuitError> {
+    let mut rng = ChaChaRng::from_entropy();
+
+    let root_key = V::ScalarField::rand(&mut rng);
+    let derived_keys = DerivedKeys::<P>::new(root_key).map_err(CircuitError::ParameterError)?;
+    let token_owner = derived_keys.public_key;
+
+    let outgoing_token_id = token_id.unwrap_or_else(|| V::ScalarField::rand(&mut rng));
+    let outgoing_value = V::ScalarField::from(u32::rand(&mut rng));
+    let outgoing_salt = V::ScalarField::rand(&mut rng);
+    let old_commitment_hash = build_commitment_hash([
+        outgoing_value,
+        outgoing_token_id,
+        outgoing_salt,
+        token_owner.x,
+        token_owner.y,
+    ])
+    .map_err(|_| {
+        CircuitError::ParameterError(
+            "Error generating hash commitment when building swap inputs".to_string(),
+        )
+    })?;
+    // The counterparty commitment is stored next to ours
+    let commitment_tree = Tree::<V::ScalarField, D>::from_leaves(vec![
+        old_commitment_hash,
+        V::ScalarField::rand(&mut rng),
+    ]);
+    let membership_path =
+        commitment_tree
+            .membership_witness(0)
+            .ok_or(CircuitError::ParameterError(
+                "Error computing membership witness".to_string(),
+            ))?;
+
+    let circuit_inputs = CircuitInputs::new()
+        .add_old_token_values(vec![outgoing_value])
+        .add_old_token_salts(vec![outgoing_salt])
+        .add_old_token_ids(vec![outgoing_token_id])
+        .add_membership_path(vec![membership_path])
+        .add_membership_path_index(vec![V::ScalarField::from(0u64)])
+        .add_commitment_tree_root(vec![commitment_tree.root()])
+        .add_token_values(vec![V::ScalarField::from(u32::rand(&mut rng))])
+        .add_token_salts(vec![V::ScalarField::from(1u64)])
+        .add_token_ids(vec![V::ScalarField::rand(&mut rng)])
+        .add_recipients(vec![PublicKey::from_affine(Affine::rand(&mut rng))])
+        .add_root_key(root_key)
+        .add_ephemeral_key(V::ScalarField::rand(&mut rng))
+        .build();
+    check_inputs::<P, V>(&circuit_inputs, D)?;
+
+    Ok(circuit_inputs)
+}
@@ -0,0 +1,181 @@
+use ark_ec::{
+    pairing::Pairing,
+    short_weierstrass::{Affine, Projective, SWCurveConfig},
+    CurveConfig,
+};
+use ark_ff::PrimeField;
+
+use super::circuit_inputs::CircuitInputs;
+use crate::client::ClientPlonkCircuit;
+use crate::primitives::circuits::kem_dem::KemDemParams;
+use common::crypto::poseidon::constants::PoseidonParams;
+use common::structs::CircuitType;
+use jf_primitives::rescue::RescueParameter;
+use jf_relation::gadgets::ecc::SWToTEConParam;
+use jf_relation::{errors::CircuitError, PlonkCircuit};
+use zk_macros::client_bounds;
+
+pub mod circuit;
+pub mod utils;
+
+pub use circuit::{withdraw_circuit, VALUE_BITS};
+pub use utils::build_random_inputs;
+
+/// Takes value out of the shielded pool. It nullifies N notes, publishes the withdrawn
+/// value, token id and withdraw address, and returns the remainder to the sender as change.
+#[derive(Debug, Hash)]
+pub struct WithdrawCircuit<const N: usize, const D: usize>;
+
+impl<const N: usize, const D: usize> WithdrawCircuit<N, D> {
+    pub fn new() -> Self {
+        WithdrawCircuit
+    }
+    pub fn get_circuit_type(&self) -> CircuitType {
+        CircuitType::Withdraw(N)
+    }
+    #[client_bounds]
+    pub fn as_circuit<P, V, VSW>(self) -> Box<dyn ClientPlonkCircuit<P, V, VSW>> {
+        Box::new(self)
+    }
+}
+
+impl<const N: usize, const D: usize> Default for WithdrawCircuit<N, D> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[client_bounds]
+impl<P, V, VSW, const N: usize, const D: usize> ClientPlonkCircuit<P, V, VSW>
+    for WithdrawCircuit<N, D>
+{
+    fn to_plonk_circuit(
+        &self,
+        circuit_inputs: CircuitInputs<P>,
+    ) -> Result<PlonkCircuit<V::ScalarField>, CircuitError> {
+        check_inputs::<P, V>(&circuit_inputs, N, D)?;
+        withdraw_circuit::<P, V, N, D>(circuit_inputs)
+    }
+    fn generate_random_inputs(
+        &self,
+        token_id: Option<V::ScalarField>,
+    ) -> Result<CircuitInputs<P>, CircuitError> {
+        utils::build_random_inputs::<P, V, VSW, N, D>(token_id)
+    }
+    fn get_circuit_type(&self) -> CircuitType {
+        self.get_circuit_type()
+    }
+    fn get_commitment_and_nullifier_count(&self) -> (usize, usize) {
+        (1, N)
+    }
+}
+
+#[allow(non_snake_case)]
+pub fn check_inputs<P, V>(
+    circuit_inputs: &CircuitInputs<P>,
+    N: usize,
+    D: usize,
+) -> Result<(), CircuitError>
+where
+    P: SWCurveConfig,
+    <P as CurveConfig>::BaseField: PrimeField + PoseidonParams<Field = V::ScalarField>,
+    V: Pairing<ScalarField = P::BaseField>,
+{
+    fn check_length(
+        field_name: &str,
+        actual_len: usize,
+        expected_len: usize,
+    ) -> Result<(), CircuitError> {
+        if actual_len != expected_len {
+            Err(CircuitError::ParameterError(format!(
+                "Incorrect length for {field_name}. Expected {expected_len}, Obtained {actual_len}"
+            )))
+        } else {
+            Ok(())
+        }
+    }
+
+    // Withdrawn value and change value
+    check_length("token_values", circuit_inputs.token_values.len(), 2)?;
+    check_length("token_salts", circuit_inputs.token_salts.len(), 1)?;
+    check_length("token_ids", circuit_inputs.token_ids.len(), 1)?;
+    check_length("old_token_values", circuit_inputs.old_token_values.len(), N)?;
+    check_length("old_token_salts", circuit_inputs.old_token_salts.len(), N)?;
+    check_length(
+        "commitment_tree_root",
+        circuit_inputs.commitment_tree_root.len(),
+        N,
+    )?;
+    check_length(
+        "membership_path_index",
+        circuit_inputs.membership_path_index.len(),
+        N,
+    )?;
+    check_length("membership_path", circuit_inputs.membership_path.len(), N)?;
+
+    if !circuit_inputs
+        .membership_path
+        .iter()
+        .all(|inner_vec| inner_vec.path_len() == D)
+    {
+        return Err(CircuitError::ParameterError(format!(
+            "Incorrect length for membership_path elements. Expected {D}",
+        )));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::client::structs::ClientPubInput;
+    use curves::pallas::PallasConfig;
+    use curves::vesta::VestaConfig;
+    use jf_relation::{errors::CircuitError, Circuit};
+
+    #[test]
+    fn test_generate_keys() {
+        WithdrawCircuit::<1, 8>::new()
+            .as_circuit::<PallasConfig, VestaConfig, _>()
+            .generate_keys()
+            .expect("Error generating key for withdraw circuit from random inputs");
+    }
+
+    #[test]
+    fn withdraw_test() -> Result<(), CircuitError> {
+        withdraw_test_helper::<1, 8>()?;
+        withdraw_test_helper::<2, 8>()
+    }
+
+    fn withdraw_test_helper<const N: usize, const D: usize>() -> Result<(), CircuitError> {
+        let inputs = build_random_inputs::<PallasConfig, VestaConfig, _, N, D>(None)?;
+        let withdraw_value = inputs.token_values[0];
+        let token_id = inputs.token_ids[0];
+        let withdraw_address = inputs.withdraw_address;
+        let circuit = WithdrawCircuit::<N, D>::new()
+            .as_circuit::<PallasConfig, VestaConfig, _>()
+            .to_plonk_circuit(inputs)?;
+
+        let public_inputs = circuit.public_input()?;
+        assert!(circuit.check_circuit_satisfiability(&public_inputs).is_ok());
+        let client_pub_inputs = ClientPubInput::new(public_inputs, (1, N)).unwrap();
+        assert!(!client_pub_inputs.swap_field);
+        assert_eq!(
+            client_pub_inputs.ciphertexts,
+            vec![withdraw_value, token_id, withdraw_address]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn withdraw_value_not_conserved_test() {
+        let mut inputs = build_random_inputs::<PallasConfig, VestaConfig, _, 1, 8>(None).unwrap();
+        let withdraw_value = inputs.token_values[0];
+        inputs.token_values[1] += withdraw_value;
+        assert!(WithdrawCircuit::<1, 8>::new()
+            .as_circuit::<PallasConfig, VestaConfig, _>()
+            .to_plonk_circuit(inputs)
+            .is_err());
+    }
+}
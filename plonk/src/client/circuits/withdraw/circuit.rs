@@ -0,0 +1,162 @@
+use ark_ec::{pairing::Pairing, short_weierstrass::SWCurveConfig, CurveConfig};
+use ark_ff::PrimeField;
+
+use jf_relation::{errors::CircuitError, Circuit, PlonkCircuit};
+
+use crate::client::circuits::circuit_inputs::CircuitInputs;
+use crate::client::circuits::transfer::{
+    POSEIDON_STATE_VAR_LEN3, POSEIDON_STATE_VAR_LEN6, PRIVATE_KEY_LEN,
+};
+use crate::primitives::circuits::{
+    kem_dem::KemDemParams,
+    merkle_tree::BinaryMerkleTreeGadget,
+    poseidon::{PoseidonGadget, PoseidonStateVar},
+};
+use common::derived_keys::{NULLIFIER_PREFIX, PRIVATE_KEY_PREFIX};
+use std::str::FromStr;
+
+// Withdrawn and change values are range checked so that they can't wrap around the field
+pub const VALUE_BITS: usize = 128;
+const EPHEMERAL_KEY_LEN: usize = 2;
+
+// Withdrawals reuse the client public input layout. There is no encrypted note, so the
+// ephemeral key is zero and the ciphertext slots carry [value, token_id, withdraw_address]
+// N: number of nullifiers
+// D: depth of the merkle tree
+pub fn withdraw_circuit<P, V, const N: usize, const D: usize>(
+    circuit_inputs: CircuitInputs<P>,
+) -> Result<PlonkCircuit<V::ScalarField>, CircuitError>
+where
+    P: SWCurveConfig,
+    V: Pairing<ScalarField = P::BaseField>,
+    <P as CurveConfig>::BaseField: PrimeField + KemDemParams<Field = V::ScalarField>,
+{
+    let mut circuit = PlonkCircuit::new_turbo_plonk();
+
+    // Swap_field = false
+    circuit.create_public_boolean_variable(false)?;
+
+    let private_key_domain = P::BaseField::from_str(PRIVATE_KEY_PREFIX)
+        .map_err(|_| CircuitError::NotSupported(String::from("Prefix")))?;
+    let nullifier_key_domain = P::BaseField::from_str(NULLIFIER_PREFIX)
+        .map_err(|_| CircuitError::NotSupported(String::from("Prefix")))?;
+    // Derive Keys - ToDo, remove this once we have HSM-compatible key derivation
+    let private_key_domain_var = circuit.create_constant_variable(private_key_domain)?;
+    let nullifier_key_domain_var = circuit.create_constant_variable(nullifier_key_domain)?;
+    let root_key_var = circuit.create_variable(circuit_inputs.root_key)?;
+
+    let private_key_var = PoseidonGadget::<
+        PoseidonStateVar<POSEIDON_STATE_VAR_LEN3>,
+        V::ScalarField,
+    >::hash(&mut circuit, &[root_key_var, private_key_domain_var])?;
+    let private_key_bits_var =
+        circuit.unpack(private_key_var, V::ScalarField::MODULUS_BIT_SIZE as usize)?;
+
+    let private_key_var_trunc = private_key_bits_var
+        .into_iter()
+        .take(PRIVATE_KEY_LEN)
+        .collect::<Vec<_>>();
+
+    let private_key_var_trunc_bits = private_key_var_trunc.as_slice();
+    let generator_point_var = &circuit.create_constant_sw_point_variable(P::GENERATOR.into())?;
+
+    // Implicit mod being done here
+    let public_key_var = circuit
+        .variable_base_binary_sw_scalar_mul::<P>(private_key_var_trunc_bits, generator_point_var)?;
+
+    let nullifier_key_var = PoseidonGadget::<
+        PoseidonStateVar<POSEIDON_STATE_VAR_LEN3>,
+        V::ScalarField,
+    >::hash(&mut circuit, &[root_key_var, nullifier_key_domain_var])?;
+
+    // Check conservation of value
+    // That is, sum of nullifiers = withdrawn value + change
+    let old_commitment_values_vars = circuit_inputs
+        .old_token_values
+        .iter()
+        .map(|v| circuit.create_variable(*v))
+        .collect::<Result<Vec<_>, _>>()?;
+    let withdraw_value_var = circuit.create_variable(circuit_inputs.token_values[0])?;
+    let change_value_var = circuit.create_variable(circuit_inputs.token_values[1])?;
+    circuit.unpack(withdraw_value_var, VALUE_BITS)?;
+    circuit.unpack(change_value_var, VALUE_BITS)?;
+
+    let nullifiers_sum_var = old_commitment_values_vars
+        .iter()
+        .try_fold(circuit.zero(), |acc, v| circuit.add(acc, *v))?;
+    let outputs_sum_var = circuit.add(withdraw_value_var, change_value_var)?;
+    circuit.enforce_equal(nullifiers_sum_var, outputs_sum_var)?;
+
+    // Calculate the private old commitment hash and check the sibling path
+    // Calculate the public nullifier hash
+    let token_id_var = circuit.create_variable(circuit_inputs.token_ids[0])?;
+    let commitment_roots_vars = circuit_inputs
+        .commitment_tree_root
+        .iter()
+        .map(|r| circuit.create_public_variable(*r))
+        .collect::<Result<Vec<_>, _>>()?;
+    for (i, &old_commitment_val_var) in old_commitment_values_vars.iter().enumerate() {
+        let old_commitment_nonce_var =
+            circuit.create_variable(circuit_inputs.old_token_salts[i])?;
+        let old_commitment_hash_var =
+            PoseidonGadget::<PoseidonStateVar<POSEIDON_STATE_VAR_LEN6>, V::ScalarField>::hash(
+                &mut circuit,
+                &[
+                    old_commitment_val_var,
+                    token_id_var,
+                    old_commitment_nonce_var,
+                    public_key_var.get_x(),
+                    public_key_var.get_y(),
+                ],
+            )?;
+        // Check the sibling path
+        let calc_commitment_root_var = BinaryMerkleTreeGadget::<D, V::ScalarField>::calculate_root(
+            &mut circuit,
+            old_commitment_hash_var,
+            circuit_inputs.membership_path_index[i],
+            circuit_inputs.membership_path[i]
+                .clone()
+                .try_into()
+                .map_err(|_| {
+                    CircuitError::ParameterError("Error converting membership path".to_string())
+                })?,
+        )?;
+        circuit.enforce_equal(calc_commitment_root_var, commitment_roots_vars[i])?;
+
+        let nullifier_hash_var =
+            PoseidonGadget::<PoseidonStateVar<POSEIDON_STATE_VAR_LEN3>, V::ScalarField>::hash(
+                &mut circuit,
+                &[nullifier_key_var, old_commitment_hash_var],
+            )?;
+        circuit.set_variable_public(nullifier_hash_var)?;
+    }
+
+    // The change commitment goes back to the sender
+    let change_nonce_var = circuit.create_variable(circuit_inputs.token_salts[0])?;
+    let change_commitment_hash_var =
+        PoseidonGadget::<PoseidonStateVar<POSEIDON_STATE_VAR_LEN6>, V::ScalarField>::hash(
+            &mut circuit,
+            &[
+                change_value_var,
+                token_id_var,
+                change_nonce_var,
+                public_key_var.get_x(),
+                public_key_var.get_y(),
+            ],
+        )?;
+    circuit.set_variable_public(change_commitment_hash_var)?;
+
+    for _ in 0..EPHEMERAL_KEY_LEN {
+        let eph_key_var = circuit.create_public_variable(V::ScalarField::from(0u64))?;
+        circuit.enforce_constant(eph_key_var, V::ScalarField::from(0u64))?;
+    }
+
+    // Withdrawal outputs
+    circuit.set_variable_public(withdraw_value_var)?;
+    circuit.set_variable_public(token_id_var)?;
+    circuit.create_public_variable(circuit_inputs.withdraw_address)?;
+
+    circuit.check_circuit_satisfiability(&circuit.public_input()?)?;
+    circuit.finalize_for_arithmetization()?;
+    Ok(circuit)
+}
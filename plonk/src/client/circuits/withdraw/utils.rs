@@ -0,0 +1,80 @@
+use super::check_inputs;
+use crate::client::circuits::circuit_inputs::CircuitInputs;
+use crate::client::circuits::mint;
+use crate::primitives::circuits::kem_dem::KemDemParams;
+use crate::utils::poseidon_utils::build_commitment_hash;
+use ark_ec::{
+    pairing::Pairing,
+    short_weierstrass::{Affine, Projective, SWCurveConfig},
+    CurveConfig,
+};
+use ark_ff::{PrimeField, UniformRand, Zero};
+use common::crypto::poseidon::constants::PoseidonParams;
+use common::derived_keys::DerivedKeys;
+use jf_primitives::rescue::RescueParameter;
+use jf_relation::errors::CircuitError;
+use jf_relation::gadgets::ecc::SWToTEConParam;
+use rand::SeedableRng;
+use rand_chacha::ChaChaRng;
+use trees::{AppendTree, MembershipPath, MembershipTree, Tree};
+use zk_macros::client_bounds;
+
+#[client_bounds]
+pub fn build_random_inputs<P, V, VSW, const N: usize, const D: usize>(
+    token_id: Option<V::ScalarField>,
+) -> Result<CircuitInputs<P>, CircuitError> {
+    let mut rng = ChaChaRng::from_entropy();
+    let mint_inputs = mint::utils::build_random_inputs::<P, V, _, N>(token_id)?;
+    let mut mint_commitment_hashes = Vec::with_capacity(N);
+    let mut total_value = V::ScalarField::zero();
+    let mut indices = Vec::<V::ScalarField>::with_capacity(N);
+
+    let root_key = V::ScalarField::rand(&mut rng);
+    let derived_keys = DerivedKeys::<P>::new(root_key).map_err(CircuitError::ParameterError)?;
+    let token_owner = derived_keys.public_key;
+
+    for i in 0..N {
+        let hash = build_commitment_hash([
+            mint_inputs.token_values[i],
+            mint_inputs.token_ids[i],
+            mint_inputs.token_salts[i],
+            token_owner.x,
+            token_owner.y,
+        ])
+        .map_err(|_| {
+            CircuitError::ParameterError(
+                "Error generating hash commitment when building withdraw inputs".to_string(),
+            )
+        })?;
+        mint_commitment_hashes.push(hash);
+        total_value += mint_inputs.token_values[i];
+        indices.push(V::ScalarField::from(i as u64));
+    }
+    let commitment_tree = Tree::<V::ScalarField, D>::from_leaves(mint_commitment_hashes);
+    let mut commitment_paths: Vec<MembershipPath<_>> = Vec::new();
+    for j in 0..N {
+        commitment_paths.push(commitment_tree.membership_witness(j).ok_or(
+            CircuitError::ParameterError("Error computing membership witness".to_string()),
+        )?);
+    }
+
+    // Withdraw the value of the first note and keep the rest as change
+    let withdraw_value = mint_inputs.token_values[0];
+    let change_value = total_value - withdraw_value;
+
+    let circuit_inputs = CircuitInputs::new()
+        .add_old_token_values(mint_inputs.token_values)
+        .add_old_token_salts(mint_inputs.token_salts)
+        .add_membership_path(commitment_paths)
+        .add_membership_path_index(indices)
+        .add_commitment_tree_root(vec![commitment_tree.root(); N])
+        .add_token_values(vec![withdraw_value, change_value])
+        .add_token_salts(vec![V::ScalarField::rand(&mut rng)])
+        .add_token_ids(vec![mint_inputs.token_ids[0]])
+        .add_root_key(root_key)
+        .add_withdraw_address(V::ScalarField::rand(&mut rng))
+        .build();
+    check_inputs::<P, V>(&circuit_inputs, N, D)?;
+
+    Ok(circuit_inputs)
+}
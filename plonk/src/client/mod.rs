@@ -1,5 +1,6 @@
 use crate::client::structs::ClientPubInput;
 use crate::primitives::circuits::kem_dem::KemDemParams;
+use crate::setup::{universal_setup, UniversalSrs};
 use ark_ec::{
     pairing::Pairing,
     short_weierstrass::{Affine, Projective, SWCurveConfig},
@@ -16,8 +17,6 @@ use jf_relation::constraint_system::PlonkCircuit;
 use jf_relation::errors::CircuitError;
 use jf_relation::gadgets::ecc::SWToTEConParam;
 use jf_relation::Arithmetization;
-use rand::SeedableRng;
-use rand_chacha::ChaChaRng;
 use zk_macros::client_bounds;
 
 pub mod circuits;
@@ -48,6 +47,16 @@ pub trait ClientPlonkCircuit<P, V, VSW>: Send + Sync + 'static + std::fmt::Debug
         generate_keys_from_plonk::<P, V, VSW>(&mut circuit)
     }
 
+    // Keys against a shared setup, so they match keys derived by other processes
+    fn generate_keys_with_srs(
+        &self,
+        srs: &UniversalSrs<V>,
+    ) -> Result<(ProvingKey<V>, VerifyingKey<V>), CircuitError> {
+        let inputs = self.generate_random_inputs(None)?;
+        let circuit = self.to_plonk_circuit(inputs)?;
+        PlonkIpaSnark::<V>::preprocess(srs, &circuit)
+    }
+
     fn to_plonk_circuit(
         &self,
         circuit_inputs: CircuitInputs<P>,
@@ -66,9 +75,7 @@ pub fn generate_keys_from_plonk<P, V, VSW>(
     circuit: &mut PlonkCircuit<V::ScalarField>,
 ) -> Result<(ProvingKey<V>, VerifyingKey<V>), CircuitError> {
     let srs_size = circuit.srs_size()?;
-    let mut rng = ChaChaRng::from_entropy();
-    let srs =
-        <PlonkIpaSnark<V> as UniversalSNARK<V>>::universal_setup_for_testing(srs_size, &mut rng)?;
+    let srs = universal_setup::<V, VSW>(srs_size)?;
 
     let (pk, vk) = PlonkIpaSnark::<V>::preprocess(&srs, circuit)?;
     Ok((pk, vk))
@@ -2,7 +2,6 @@ use crate::client::PoseidonParams;
 use ark_ec::{pairing::Pairing, short_weierstrass::SWCurveConfig, CurveGroup};
 use ark_ff::Field;
 use common::structs::Transaction;
-use trees::{AppendTree, Tree};
 
 #[derive(Clone, Debug)]
 pub struct ClientPubInput<F: Field> {
@@ -40,6 +39,19 @@ impl<F: Field> ClientPubInput<F> {
             ciphertexts: value[ciph_offset..ciph_offset + 3].to_vec(),
         })
     }
+
+    // Inverse of `new`: the public inputs in circuit order
+    pub fn to_vec(&self) -> Vec<F> {
+        let swap_field = if self.swap_field { F::one() } else { F::zero() };
+        std::iter::once(swap_field)
+            .chain(self.commitment_root.iter().cloned())
+            .chain(self.nullifiers.iter().cloned())
+            .chain(self.commitments.iter().cloned())
+            .chain(self.ephemeral_public_key.iter().cloned())
+            .chain(self.ciphertexts.iter().cloned())
+            .collect()
+    }
+
     fn from_transaction<V>(
         transaction: &Transaction<V>,
     ) -> ClientPubInput<<V as Pairing>::ScalarField>
@@ -58,14 +70,13 @@ impl<F: Field> ClientPubInput<F> {
             .iter()
             .map(|n| n.0)
             .collect::<Vec<_>>();
-        let commitments_tree = Tree::<V::ScalarField, 8>::from_leaves(commitments.clone());
         ClientPubInput {
             swap_field: transaction.swap_field,
             ciphertexts: transaction.ciphertexts.clone(),
             ephemeral_public_key: transaction.eph_pub_key.clone(),
             commitments,
             nullifiers,
-            commitment_root: vec![commitments_tree.root(); transaction.nullifiers.len()],
+            commitment_root: transaction.commitment_root.clone(),
         }
     }
 }
@@ -1,4 +1,5 @@
 pub mod client;
 pub mod primitives;
 pub mod rollup;
+pub mod setup;
 pub mod utils;
@@ -1,4 +1,5 @@
 mod kem_dem_constants;
+pub mod native;
 
 use ark_ec::short_weierstrass::SWCurveConfig;
 
@@ -96,11 +97,10 @@ where
 
 #[cfg(test)]
 mod test {
+    use super::native::{decrypt, encrypt};
     use super::*;
-    use ark_ec::CurveGroup;
     use ark_std::UniformRand;
-    use common::crypto::poseidon::Poseidon;
-    use curves::pallas::{Affine, Fq, Fr, PallasConfig};
+    use curves::pallas::{Fq, Fr, PallasConfig};
     use jf_utils::field_switching;
 
     #[test]
@@ -108,57 +108,6 @@ mod test {
         test_kem_dem_gadget_helper();
     }
 
-    fn dem<F: KemDemParams>(enc_key: Fq, plain_texts: Vec<Fq>) -> Vec<Fq> {
-        let domain_dem = Fq::from_le_bytes_mod_order(F::DOMAIN_DEM);
-        let mut ciphertexts = Vec::new();
-        for (i, plain_text) in plain_texts.iter().enumerate() {
-            let poseidon: Poseidon<Fq> = Poseidon::new();
-            let hash = poseidon
-                .hash(vec![enc_key, domain_dem, Fq::from(i as u32)])
-                .unwrap();
-            ciphertexts.push(hash + plain_text);
-        }
-        ciphertexts
-    }
-
-    fn undo_dem<F: KemDemParams>(enc_key: Fq, cipher_texts: Vec<Fq>) -> Vec<Fq> {
-        let domain_dem = Fq::from_le_bytes_mod_order(F::DOMAIN_DEM);
-        let mut plain_texts = Vec::new();
-        for (i, cipher_text) in cipher_texts.iter().enumerate() {
-            let hash = Poseidon::<Fq>::new()
-                .hash(vec![enc_key, domain_dem, Fq::from(i as u32)])
-                .unwrap();
-            plain_texts.push(cipher_text - &hash);
-        }
-        plain_texts
-    }
-
-    fn kem<F: KemDemParams>(private_key: Fr, recipient: Affine) -> Fq {
-        let domain_kem = Fq::from_le_bytes_mod_order(F::DOMAIN_KEM);
-        let shared_secret = (recipient * private_key).into_affine();
-        Poseidon::<Fq>::new()
-            .hash(vec![shared_secret.x, shared_secret.y, domain_kem])
-            .unwrap()
-    }
-
-    fn encrypt<F: KemDemParams>(
-        ephemeral_key: Fr,
-        recipient: Affine,
-        plain_texts: Vec<Fq>,
-    ) -> Vec<Fq> {
-        let enc_key = kem::<F>(ephemeral_key, recipient);
-        dem::<F>(enc_key, plain_texts)
-    }
-
-    fn decrypt<F: KemDemParams>(
-        recipient_private_key: Fr,
-        ephemeral_pub: Affine,
-        cipher_texts: Vec<Fq>,
-    ) -> Vec<Fq> {
-        let enc_key = kem::<F>(recipient_private_key, ephemeral_pub);
-        undo_dem::<F>(enc_key, cipher_texts)
-    }
-
     fn test_kem_dem_gadget_helper() {
         let mut rng = ark_std::test_rng();
         let rand_plaintexts = (0..3).map(|_| Fq::rand(&mut rng)).collect::<Vec<_>>();
@@ -183,12 +132,14 @@ mod test {
         let ephemeral_key_var = circuit.create_variable(ephemeral_key).unwrap();
 
         let expected_ciphertexts =
-            encrypt::<Fq>(ephemeral_key_fr, recipient.into(), rand_plaintexts.clone());
-        let decrypted_plaintexts = decrypt::<Fq>(
+            encrypt::<PallasConfig, Fq>(ephemeral_key_fr, recipient.into(), &rand_plaintexts)
+                .unwrap();
+        let decrypted_plaintexts = decrypt::<PallasConfig, Fq>(
             recipient_private_key_fr,
             ephemeral_pub.into(),
-            expected_ciphertexts.clone(),
-        );
+            &expected_ciphertexts,
+        )
+        .unwrap();
 
         let circuit_results = KemDemGadget::<PlainTextVars<3>, PallasConfig, Fq>::kem_dem(
             &mut circuit,
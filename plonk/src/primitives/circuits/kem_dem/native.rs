@@ -0,0 +1,108 @@
+use ark_ec::{
+    short_weierstrass::{Affine, SWCurveConfig},
+    CurveGroup,
+};
+use ark_ff::PrimeField;
+use common::crypto::{crypto_errors::CryptoError, poseidon::Poseidon};
+
+use super::KemDemParams;
+
+// Native counterparts of KemDemGadget. A ciphertext produced in circuit with
+// ephemeral key `k` for recipient `R` can be opened with the recipient private
+// key `r` and the ephemeral public key `k * G`, since r * (k * G) = k * R.
+
+pub fn kem<E, F>(private_key: E::ScalarField, public_key: Affine<E>) -> Result<F, CryptoError>
+where
+    E: SWCurveConfig<BaseField = F>,
+    F: PrimeField + KemDemParams<Field = F>,
+{
+    let domain_kem = F::from_le_bytes_mod_order(F::DOMAIN_KEM);
+    let shared_secret = (public_key * private_key).into_affine();
+    Poseidon::<F>::new().hash(vec![shared_secret.x, shared_secret.y, domain_kem])
+}
+
+pub fn dem<F>(encryption_key: F, plaintexts: &[F]) -> Result<Vec<F>, CryptoError>
+where
+    F: PrimeField + KemDemParams<Field = F>,
+{
+    let poseidon = Poseidon::<F>::new();
+    let domain_dem = F::from_le_bytes_mod_order(F::DOMAIN_DEM);
+    plaintexts
+        .iter()
+        .enumerate()
+        .map(|(i, plaintext)| {
+            let hash = poseidon.hash(vec![encryption_key, domain_dem, F::from(i as u32)])?;
+            Ok(hash + plaintext)
+        })
+        .collect()
+}
+
+pub fn undo_dem<F>(encryption_key: F, ciphertexts: &[F]) -> Result<Vec<F>, CryptoError>
+where
+    F: PrimeField + KemDemParams<Field = F>,
+{
+    let poseidon = Poseidon::<F>::new();
+    let domain_dem = F::from_le_bytes_mod_order(F::DOMAIN_DEM);
+    ciphertexts
+        .iter()
+        .enumerate()
+        .map(|(i, ciphertext)| {
+            let hash = poseidon.hash(vec![encryption_key, domain_dem, F::from(i as u32)])?;
+            Ok(*ciphertext - hash)
+        })
+        .collect()
+}
+
+pub fn encrypt<E, F>(
+    ephemeral_key: E::ScalarField,
+    recipient: Affine<E>,
+    plaintexts: &[F],
+) -> Result<Vec<F>, CryptoError>
+where
+    E: SWCurveConfig<BaseField = F>,
+    F: PrimeField + KemDemParams<Field = F>,
+{
+    let encryption_key = kem::<E, F>(ephemeral_key, recipient)?;
+    dem(encryption_key, plaintexts)
+}
+
+pub fn decrypt<E, F>(
+    recipient_private_key: E::ScalarField,
+    ephemeral_public_key: Affine<E>,
+    ciphertexts: &[F],
+) -> Result<Vec<F>, CryptoError>
+where
+    E: SWCurveConfig<BaseField = F>,
+    F: PrimeField + KemDemParams<Field = F>,
+{
+    let encryption_key = kem::<E, F>(recipient_private_key, ephemeral_public_key)?;
+    undo_dem(encryption_key, ciphertexts)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ark_std::UniformRand;
+    use curves::pallas::{Fq, Fr, PallasConfig};
+
+    #[test]
+    fn test_native_encrypt_decrypt() {
+        let mut rng = ark_std::test_rng();
+        let plaintexts = (0..3).map(|_| Fq::rand(&mut rng)).collect::<Vec<_>>();
+        let ephemeral_key = Fr::rand(&mut rng);
+        let ephemeral_pub = (PallasConfig::GENERATOR * ephemeral_key).into_affine();
+        let recipient_private_key = Fr::rand(&mut rng);
+        let recipient = (PallasConfig::GENERATOR * recipient_private_key).into_affine();
+
+        let ciphertexts =
+            encrypt::<PallasConfig, Fq>(ephemeral_key, recipient, &plaintexts).unwrap();
+        let decrypted =
+            decrypt::<PallasConfig, Fq>(recipient_private_key, ephemeral_pub, &ciphertexts)
+                .unwrap();
+        assert_eq!(decrypted, plaintexts);
+
+        let wrong_key = Fr::rand(&mut rng);
+        let garbage = decrypt::<PallasConfig, Fq>(wrong_key, ephemeral_pub, &ciphertexts).unwrap();
+        assert_ne!(garbage, plaintexts);
+    }
+}
@@ -0,0 +1,762 @@
+use ark_ec::{
+    pairing::Pairing,
+    short_weierstrass::{Affine, SWCurveConfig},
+    CurveGroup,
+};
+use ark_ff::{PrimeField, Zero};
+use ark_poly::{univariate::DensePolynomial, Polynomial};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Compress, Validate};
+use common::crypto::poseidon::{constants::PoseidonParams, Poseidon};
+use common::hash::content_hash;
+use common::structs::{
+    canonical_hex, transactions_root, Block, BlockTransaction, CircuitType, Withdrawal,
+};
+use jf_plonk::{
+    nightfall::{
+        ipa_structs::{CommitKey, Proof, VerifyingKey},
+        PlonkIpaSnark, UnivariateIpaPCS,
+    },
+    transcript::RescueTranscript,
+};
+use jf_primitives::{pcs::PolynomialCommitmentScheme, rescue::RescueParameter};
+use jf_relation::{
+    errors::CircuitError,
+    gadgets::ecc::{short_weierstrass::SWPoint, SWToTEConParam},
+};
+use jf_utils::field_switching;
+
+use crate::client::circuits::mint::constants::{CIPHERTEXT_LEN, EPHEMERAL_KEY_LEN};
+
+use super::circuits::{
+    base::BasePublicVarIndex,
+    structs::{AccInstance, GlobalPublicInputs, SubTrees},
+};
+
+// Rollup proof of a block together with the public inputs it was proven
+// against. `I` is the curve of the accumulated client proofs. The root of a
+// block is either a base rollup, or a merge rollup when the block needed more
+// than one base proof. A merge also passes through the accumulators of the two
+// proofs it merged, which a base does not have.
+#[derive(CanonicalSerialize, CanonicalDeserialize, Clone)]
+pub struct RollupProof<E, I>
+where
+    E: Pairing,
+    <E::G1 as CurveGroup>::Config: SWCurveConfig,
+    I: Pairing<BaseField = E::ScalarField>,
+{
+    pub proof: Proof<E>,
+    pub global_public_inputs: GlobalPublicInputs<E::ScalarField>,
+    pub subtrees: SubTrees<E::ScalarField>,
+    pub instance: AccInstance<I>,
+    pub passthrough: Vec<AccInstance<E>>,
+}
+
+// Public inputs of a merge circuit: the global state, 4 values per passthrough
+// accumulator, the accumulator and the subtrees
+const MERGE_PUBLIC_INPUTS_LEN: usize = 5 + 2 * 4 + 4 + 2;
+
+impl<E, I> RollupProof<E, I>
+where
+    E: Pairing,
+    <E::G1 as CurveGroup>::Config: SWCurveConfig,
+    I: Pairing<BaseField = E::ScalarField>,
+{
+    // Splits the public inputs of a base rollup circuit
+    pub fn new(proof: Proof<E>, public_inputs: &[E::ScalarField]) -> Result<Self, CircuitError> {
+        if public_inputs.len() <= BasePublicVarIndex::AccumulatorInstancePoint as usize {
+            return Err(CircuitError::ParameterError(format!(
+                "Rollup proof expects {} public inputs, got {}",
+                BasePublicVarIndex::AccumulatorInstancePoint as usize + 1,
+                public_inputs.len()
+            )));
+        }
+        let global_public_inputs = GlobalPublicInputs::from_vec(public_inputs.to_vec());
+        let subtrees = SubTrees::from_vec(
+            public_inputs[BasePublicVarIndex::CommitmentSubteeRoot as usize
+                ..=BasePublicVarIndex::NullifierSubtreeRoot as usize]
+                .to_vec(),
+        );
+        let x = public_inputs[BasePublicVarIndex::AccumulatorCommitmentX as usize];
+        let y = public_inputs[BasePublicVarIndex::AccumulatorCommitmentY as usize];
+        let instance = AccInstance {
+            comm: SWPoint(x, y, x.is_zero()),
+            // The evaluations are scalars of the accumulated curve, the switch is lossless
+            eval: field_switching(
+                &public_inputs[BasePublicVarIndex::AccumulatorInstanceValue as usize],
+            ),
+            eval_point: field_switching(
+                &public_inputs[BasePublicVarIndex::AccumulatorInstancePoint as usize],
+            ),
+        };
+        Ok(Self {
+            proof,
+            global_public_inputs,
+            subtrees,
+            instance,
+            passthrough: vec![],
+        })
+    }
+
+    // Splits the public inputs of a merge circuit, `passthrough` are the
+    // accumulators of the two merged proofs
+    pub fn from_merge(
+        proof: Proof<E>,
+        public_inputs: &[E::ScalarField],
+        passthrough: [AccInstance<E>; 2],
+    ) -> Result<Self, CircuitError> {
+        let n = public_inputs.len();
+        if n != MERGE_PUBLIC_INPUTS_LEN {
+            return Err(CircuitError::ParameterError(format!(
+                "Merge proof expects {MERGE_PUBLIC_INPUTS_LEN} public inputs, got {n}"
+            )));
+        }
+        let global_public_inputs = GlobalPublicInputs::from_vec(public_inputs.to_vec());
+        let subtrees = SubTrees::from_vec(public_inputs[n - 2..].to_vec());
+        let instance = AccInstance {
+            comm: SWPoint(
+                public_inputs[n - 6],
+                public_inputs[n - 5],
+                public_inputs[n - 6].is_zero(),
+            ),
+            eval: field_switching(&public_inputs[n - 4]),
+            eval_point: field_switching(&public_inputs[n - 3]),
+        };
+        Ok(Self {
+            proof,
+            global_public_inputs,
+            subtrees,
+            instance,
+            passthrough: passthrough.to_vec(),
+        })
+    }
+
+    pub fn circuit_type(&self) -> CircuitType {
+        if self.passthrough.is_empty() {
+            CircuitType::BaseRollup
+        } else {
+            CircuitType::MergeRollup
+        }
+    }
+
+    pub fn public_inputs(&self) -> Vec<E::ScalarField> {
+        let mut public_inputs = self.global_public_inputs.to_vec();
+        if self.passthrough.is_empty() {
+            public_inputs.extend(self.subtrees.to_vec());
+            public_inputs.extend(self.instance.to_vec());
+        } else {
+            for acc in self.passthrough.iter() {
+                public_inputs.extend(acc.to_vec_switch::<E::ScalarField>());
+            }
+            public_inputs.extend(self.instance.to_vec());
+            public_inputs.extend(self.subtrees.to_vec());
+        }
+        public_inputs
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, CircuitError> {
+        let mut bytes = Vec::new();
+        self.serialize_with_mode(&mut bytes, Compress::Yes)
+            .map_err(|e| CircuitError::ParameterError(format!("Invalid rollup proof: {e}")))?;
+        Ok(bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CircuitError> {
+        Self::deserialize_with_mode(bytes, Compress::Yes, Validate::Yes)
+            .map_err(|e| CircuitError::ParameterError(format!("Invalid rollup proof: {e}")))
+    }
+}
+
+// Polynomials of the accumulators a rollup proof outputs, published alongside
+// it so that the accumulators can be decided. `instance` is the polynomial of
+// `RollupProof::instance`, `passthrough` those of `RollupProof::passthrough`.
+#[derive(CanonicalSerialize, CanonicalDeserialize, Clone)]
+pub struct RollupAccumulators<E: Pairing, I: Pairing> {
+    pub instance: DensePolynomial<I::ScalarField>,
+    pub passthrough: Vec<DensePolynomial<E::ScalarField>>,
+}
+
+impl<E: Pairing, I: Pairing> RollupAccumulators<E, I> {
+    pub fn to_bytes(&self) -> Result<Vec<u8>, CircuitError> {
+        let mut bytes = Vec::new();
+        self.serialize_with_mode(&mut bytes, Compress::Yes)
+            .map_err(|e| CircuitError::ParameterError(format!("Invalid accumulators: {e}")))?;
+        Ok(bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CircuitError> {
+        Self::deserialize_with_mode(bytes, Compress::Yes, Validate::Yes)
+            .map_err(|e| CircuitError::ParameterError(format!("Invalid accumulators: {e}")))
+    }
+}
+
+// Hash a block uses to reference the verifying key of its rollup proof
+pub fn verifying_key_hash<E: Pairing>(vk: &VerifyingKey<E>) -> Result<String, CircuitError> {
+    let mut bytes = Vec::new();
+    vk.serialize_with_mode(&mut bytes, Compress::Yes)
+        .map_err(|e| CircuitError::ParameterError(format!("Invalid verifying key: {e}")))?;
+    Ok(content_hash(&bytes))
+}
+
+// Fields of a block header its rollup proof doesn't attest to. The rollup
+// circuits don't update the nullifier tree yet and don't take the commitment
+// root before the block, so these are only as trustworthy as the sequencer and
+// must be checked against the chain the block is applied to. The circuit types
+// of the transactions aren't bound by the proof either, only their public
+// inputs are.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnprovenState {
+    pub previous_commitment_root: String,
+    pub new_nullifier_root: String,
+    pub new_nullifier_leaf_count: u64,
+}
+
+// Checks the rollup proof published with `block` against `vk`. The proof must
+// have been generated for the header and the transactions of `block`, and the
+// accumulators it outputs are decided with `commit_key` for those of the
+// merged proofs and `instance_commit_key` for the one of the client proofs.
+// The verification is partial, the header fields the proof doesn't cover are
+// returned for the caller to check.
+pub fn verify_block<E, I, F>(
+    block: &Block<F>,
+    vk: &VerifyingKey<E>,
+    commit_key: &CommitKey<E>,
+    instance_commit_key: &CommitKey<I>,
+) -> Result<UnprovenState, CircuitError>
+where
+    E: Pairing<G1Affine = Affine<<<E as Pairing>::G1 as CurveGroup>::Config>>,
+    <<E as Pairing>::G1 as CurveGroup>::Config: SWCurveConfig<BaseField = E::BaseField>,
+    <E as Pairing>::BaseField: PrimeField + RescueParameter + SWToTEConParam,
+    <E as Pairing>::ScalarField: PoseidonParams<Field = E::ScalarField>,
+    I: Pairing<
+        BaseField = E::ScalarField,
+        G1Affine = Affine<<<I as Pairing>::G1 as CurveGroup>::Config>,
+    >,
+    <<I as Pairing>::G1 as CurveGroup>::Config: SWCurveConfig<BaseField = I::BaseField>,
+    <I as Pairing>::BaseField: PrimeField + RescueParameter + SWToTEConParam,
+    F: PrimeField,
+{
+    let block_proof = block.proof.as_ref().ok_or_else(|| {
+        CircuitError::ParameterError(format!("Block {} has no rollup proof", block.block_number))
+    })?;
+    if block_proof.vk_hash != verifying_key_hash(vk)? {
+        return Err(CircuitError::ParameterError(format!(
+            "Block {} was proven with another verifying key",
+            block.block_number
+        )));
+    }
+    let rollup_proof = RollupProof::<E, I>::from_bytes(&block_proof.rollup_proof)?;
+    if block_proof.circuit_type != rollup_proof.circuit_type() {
+        return Err(CircuitError::ParameterError(format!(
+            "Block {} declares a {:?} proof, got a {:?} proof",
+            block.block_number,
+            block_proof.circuit_type,
+            rollup_proof.circuit_type()
+        )));
+    }
+    check_public_inputs(block, &rollup_proof, block_proof.base_rollups)?;
+
+    let accumulators = RollupAccumulators::<E, I>::from_bytes(&block_proof.accumulators)?;
+    let decided = accumulators.passthrough.len() == rollup_proof.passthrough.len()
+        && decide(
+            instance_commit_key,
+            &rollup_proof.instance,
+            &accumulators.instance,
+        )?
+        && rollup_proof
+            .passthrough
+            .iter()
+            .zip(accumulators.passthrough.iter())
+            .try_fold(true, |decided, (instance, pi_star)| {
+                Ok::<_, CircuitError>(decided && decide(commit_key, instance, pi_star)?)
+            })?;
+    if !decided {
+        return Err(CircuitError::ParameterError(format!(
+            "Invalid accumulators for block {}",
+            block.block_number
+        )));
+    }
+
+    PlonkIpaSnark::<E>::verify::<RescueTranscript<E::BaseField>>(
+        vk,
+        &rollup_proof.public_inputs(),
+        &rollup_proof.proof,
+        None,
+    )
+    .map_err(|e| {
+        CircuitError::ParameterError(format!(
+            "Invalid rollup proof for block {}: {e:?}",
+            block.block_number
+        ))
+    })?;
+    Ok(UnprovenState {
+        previous_commitment_root: block.header.previous_state.commitment_root.clone(),
+        new_nullifier_root: block.header.new_state.nullifier_root.clone(),
+        new_nullifier_leaf_count: block.header.new_state.nullifier_leaf_count,
+    })
+}
+
+// Binds the public inputs of `rollup_proof` to the header and the transactions
+// of `block`. The commitment subtree commits to the public inputs of every
+// client proof but their commitment roots, so recomputing it binds the
+// transactions, from which the commitments, nullifiers and withdrawals of the
+// block are derived. The rollup circuits don't update the nullifier root yet,
+// both nullifier roots they output are the one before the block. The nullifier
+// subtree hashes the low nullifiers of the block, which need the nullifier tree
+// before the block, so it isn't recomputed. See `UnprovenState` for the header
+// fields left unchecked.
+fn check_public_inputs<E, I, F>(
+    block: &Block<F>,
+    rollup_proof: &RollupProof<E, I>,
+    base_rollups: u64,
+) -> Result<(), CircuitError>
+where
+    E: Pairing,
+    <E::G1 as CurveGroup>::Config: SWCurveConfig,
+    <E as Pairing>::ScalarField: PoseidonParams<Field = E::ScalarField>,
+    I: Pairing<BaseField = E::ScalarField>,
+    F: PrimeField,
+{
+    let header = &block.header;
+    let mismatch = |what: &str| {
+        CircuitError::ParameterError(format!(
+            "Rollup proof of block {} doesn't match its {what}",
+            block.block_number
+        ))
+    };
+    if header.block_number != block.block_number
+        || header.transactions_root != transactions_root(&block.transactions)
+    {
+        return Err(mismatch("header"));
+    }
+    if block.commitments != block_commitments(&block.transactions)
+        || block.nullifiers != block_nullifiers(&block.transactions)
+    {
+        return Err(mismatch("transactions"));
+    }
+    let withdrawals = block
+        .transactions
+        .iter()
+        .filter_map(Withdrawal::from_block_transaction)
+        .collect::<Vec<_>>();
+    if block.withdrawals != withdrawals {
+        return Err(mismatch("withdrawals"));
+    }
+    let global_public_inputs = rollup_proof.global_public_inputs.to_vec();
+    let [commitment_root, vk_root, initial_nullifier_root, initial_leaf_count, new_nullifier_root] =
+        global_public_inputs[..]
+    else {
+        return Err(mismatch("global state"));
+    };
+    if canonical_hex(&commitment_root) != header.new_state.commitment_root {
+        return Err(mismatch("commitment root"));
+    }
+    if canonical_hex(&vk_root) != header.vk_root {
+        return Err(mismatch("verifying key root"));
+    }
+    if canonical_hex(&initial_nullifier_root) != header.previous_state.nullifier_root
+        || canonical_hex(&new_nullifier_root) != header.previous_state.nullifier_root
+        || initial_leaf_count != E::ScalarField::from(header.previous_state.nullifier_leaf_count)
+    {
+        return Err(mismatch("nullifier root"));
+    }
+    if commitment_subtree::<E::ScalarField, F>(&block.transactions, base_rollups)?
+        != rollup_proof.subtrees.commitment_subtree
+    {
+        return Err(mismatch("commitments"));
+    }
+    Ok(())
+}
+
+// Commitments a block appends to the commitment tree: the non zero ones of its
+// transactions, only the outgoing one for a swap half
+fn block_commitments<F: PrimeField>(transactions: &[BlockTransaction<F>]) -> Vec<F> {
+    transactions
+        .iter()
+        .flat_map(|transaction| {
+            let n_commitments = if transaction.swap_field {
+                1
+            } else {
+                transaction.commitments.len()
+            };
+            transaction.commitments.iter().take(n_commitments).copied()
+        })
+        .filter(|commitment| !commitment.is_zero())
+        .collect()
+}
+
+fn block_nullifiers<F: PrimeField>(transactions: &[BlockTransaction<F>]) -> Vec<F> {
+    transactions
+        .iter()
+        .flat_map(|transaction| transaction.nullifiers.iter().copied())
+        .filter(|nullifier| !nullifier.is_zero())
+        .collect()
+}
+
+// Commitment subtree the rollup circuits compute over `transactions`: one leaf
+// per transaction, see `transaction_leaf`, the hash of the leaves of each base
+// rollup, or its leaf when it has a single transaction, then the hash of every
+// merged pair
+fn commitment_subtree<S, F>(
+    transactions: &[BlockTransaction<F>],
+    base_rollups: u64,
+) -> Result<S, CircuitError>
+where
+    S: PrimeField + PoseidonParams<Field = S>,
+    F: PrimeField,
+{
+    let n_base = base_rollups as usize;
+    if !n_base.is_power_of_two() || transactions.is_empty() || transactions.len() % n_base != 0 {
+        return Err(CircuitError::ParameterError(format!(
+            "Cannot split {} transactions in {n_base} base rollups",
+            transactions.len()
+        )));
+    }
+    let poseidon = Poseidon::<S>::new();
+    let hash = |inputs: Vec<S>| {
+        poseidon
+            .hash(inputs)
+            .map_err(|e| CircuitError::ParameterError(format!("Invalid commitment subtree: {e}")))
+    };
+    let leaves = transactions
+        .iter()
+        .map(|transaction| transaction_leaf(transaction, &hash))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut level = leaves
+        .chunks(transactions.len() / n_base)
+        .map(|base| match base {
+            [leaf] => Ok(*leaf),
+            _ => hash(base.to_vec()),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| hash(pair.to_vec()))
+            .collect::<Result<Vec<_>, _>>()?;
+    }
+    Ok(level[0])
+}
+
+// Leaf of a transaction in the commitment subtree: the hash of its swap field,
+// its commitments, its nullifiers, its ephemeral key and its ciphertexts. The
+// commitments are hashed together unless there's a single one or the
+// transaction is a swap half, and so are the nullifiers unless there's a
+// single one.
+fn transaction_leaf<S, F>(
+    transaction: &BlockTransaction<F>,
+    hash: &impl Fn(Vec<S>) -> Result<S, CircuitError>,
+) -> Result<S, CircuitError>
+where
+    S: PrimeField,
+    F: PrimeField,
+{
+    let switch = |values: &[F]| values.iter().map(field_switching).collect::<Vec<S>>();
+    let commitments = switch(&transaction.commitments);
+    let commitment_leaf = match commitments.first() {
+        Some(first) if commitments.len() == 1 || transaction.swap_field => *first,
+        Some(_) => hash(commitments)?,
+        None => {
+            return Err(CircuitError::ParameterError(
+                "Transaction without commitments".to_string(),
+            ))
+        }
+    };
+    let nullifiers = switch(&transaction.nullifiers);
+    let nullifier_leaf = match nullifiers.as_slice() {
+        [nullifier] => *nullifier,
+        [] => {
+            return Err(CircuitError::ParameterError(
+                "Transaction without nullifiers".to_string(),
+            ))
+        }
+        _ => hash(nullifiers)?,
+    };
+    if transaction.eph_pub_key.len() != EPHEMERAL_KEY_LEN
+        || transaction.ciphertexts.len() != CIPHERTEXT_LEN
+    {
+        return Err(CircuitError::ParameterError(
+            "Transaction with a malformed ephemeral key or ciphertext".to_string(),
+        ));
+    }
+    let mut inputs = vec![
+        S::from(transaction.swap_field),
+        commitment_leaf,
+        nullifier_leaf,
+    ];
+    inputs.extend(switch(&transaction.eph_pub_key));
+    inputs.extend(switch(&transaction.ciphertexts));
+    hash(inputs)
+}
+
+// Decides `instance` with its polynomial: the polynomial must commit to the
+// accumulated commitment and evaluate to the accumulated value at its point
+fn decide<C>(
+    commit_key: &CommitKey<C>,
+    instance: &AccInstance<C>,
+    pi_star: &DensePolynomial<C::ScalarField>,
+) -> Result<bool, CircuitError>
+where
+    C: Pairing<G1Affine = Affine<<<C as Pairing>::G1 as CurveGroup>::Config>>,
+    <<C as Pairing>::G1 as CurveGroup>::Config: SWCurveConfig<BaseField = C::BaseField>,
+    <C as Pairing>::BaseField: PrimeField + RescueParameter + SWToTEConParam,
+{
+    let comm = UnivariateIpaPCS::<C>::commit(commit_key, pi_star)
+        .map_err(|e| CircuitError::ParameterError(format!("Invalid accumulator: {e:?}")))?;
+    let comm: SWPoint<C::BaseField> = comm.0.into();
+    Ok(comm == instance.comm && pi_star.evaluate(&instance.eval_point) == instance.eval)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::circuits::mint::MintCircuit;
+    use crate::client::ClientPlonkCircuit;
+    use crate::rollup::circuits::base::base_rollup_circuit;
+    use crate::utils::bench::{
+        base::{build_client_inputs, build_commit_keys},
+        generate_rollup_circuit_artifacts_and_verify,
+        tree::tree_generator_from_client_inputs,
+    };
+    use common::structs::{BlockHeader, BlockProof, GlobalState};
+    use curves::pallas::PallasConfig;
+    use curves::vesta::{Fr, VestaConfig};
+    use jf_relation::Circuit;
+    use trees::{non_membership_tree::IndexedMerkleTree, AppendTree};
+
+    // A block of two mints proven by a base rollup, with the commit keys that
+    // decide its accumulator
+    fn proven_block() -> (
+        Block<Fr>,
+        VerifyingKey<PallasConfig>,
+        CommitKey<PallasConfig>,
+        CommitKey<VestaConfig>,
+    ) {
+        let circuits: [Box<dyn ClientPlonkCircuit<PallasConfig, VestaConfig, VestaConfig>>; 2] = [
+            Box::new(MintCircuit::<1>::new()),
+            Box::new(MintCircuit::<2>::new()),
+        ];
+        let mut client_inputs = vec![];
+        let mut nullifier_tree = IndexedMerkleTree::<curves::pallas::Fr, 32>::new();
+        let mut global_comm_roots = vec![];
+        let mut g_polys = vec![];
+        for circuit in circuits.iter() {
+            build_client_inputs(
+                &mut client_inputs,
+                &mut nullifier_tree,
+                &mut global_comm_roots,
+                &mut g_polys,
+                &**circuit,
+                None,
+            )
+            .unwrap();
+        }
+        let zk_trees =
+            tree_generator_from_client_inputs::<8>(&mut client_inputs, &global_comm_roots).unwrap();
+        let (vesta_commit_key, pallas_commit_key) = build_commit_keys().unwrap();
+        let initial_nullifier_tree = IndexedMerkleTree::<curves::pallas::Fr, 32>::new();
+        let (circuit, pi_star) = base_rollup_circuit::<VestaConfig, PallasConfig, 8>(
+            client_inputs.clone(),
+            zk_trees.vk_tree.root(),
+            initial_nullifier_tree.root(),
+            initial_nullifier_tree.leaf_count().into(),
+            zk_trees.global_root_tree.root(),
+            g_polys,
+            vesta_commit_key.clone(),
+        )
+        .unwrap();
+        let artifacts =
+            generate_rollup_circuit_artifacts_and_verify::<PallasConfig, VestaConfig, _, _>(
+                &circuit, false,
+            )
+            .unwrap();
+        let rollup_proof = RollupProof::<PallasConfig, VestaConfig>::new(
+            artifacts.proof,
+            &circuit.public_input().unwrap(),
+        )
+        .unwrap();
+        let accumulators = RollupAccumulators::<PallasConfig, VestaConfig> {
+            instance: pi_star,
+            passthrough: vec![],
+        };
+
+        let transactions = client_inputs
+            .iter()
+            .zip(circuits.iter())
+            .map(|(input, circuit)| BlockTransaction {
+                commitments: input.commitments.clone(),
+                nullifiers: input.nullifiers.clone(),
+                eph_pub_key: input.eph_pub_key.iter().map(field_switching).collect(),
+                ciphertexts: input.ciphertext.to_vec(),
+                swap_field: false,
+                circuit_type: circuit.get_circuit_type(),
+            })
+            .collect::<Vec<_>>();
+        let nullifier_root = initial_nullifier_tree.root();
+        let header = BlockHeader {
+            block_number: 0,
+            parent_hash: String::new(),
+            previous_state: GlobalState::new(&curves::pallas::Fr::zero(), &nullifier_root, 0),
+            new_state: GlobalState::new(&zk_trees.global_root_tree.root(), &nullifier_root, 0),
+            vk_root: canonical_hex(&zk_trees.vk_tree.root()),
+            transactions_root: transactions_root(&transactions),
+        };
+        let block = Block::<Fr> {
+            header,
+            commitments: block_commitments(&transactions),
+            nullifiers: block_nullifiers(&transactions),
+            transactions,
+            proof: Some(BlockProof {
+                circuit_type: CircuitType::BaseRollup,
+                vk_hash: verifying_key_hash(&artifacts.vk).unwrap(),
+                rollup_proof: rollup_proof.to_bytes().unwrap(),
+                accumulators: accumulators.to_bytes().unwrap(),
+                base_rollups: 1,
+            }),
+            ..Default::default()
+        };
+        (block, artifacts.vk, pallas_commit_key, vesta_commit_key)
+    }
+
+    #[test]
+    fn verify_block_test() {
+        let (block, vk, pallas_commit_key, vesta_commit_key) = proven_block();
+        let verify = |block: &Block<Fr>| {
+            verify_block::<PallasConfig, VestaConfig, _>(
+                block,
+                &vk,
+                &pallas_commit_key,
+                &vesta_commit_key,
+            )
+        };
+        let block_proof = block.proof.clone().unwrap();
+        assert!(verify(&block).is_ok());
+
+        // Public inputs the proof was not generated for
+        let mut tampered_proof =
+            RollupProof::<PallasConfig, VestaConfig>::from_bytes(&block_proof.rollup_proof)
+                .unwrap();
+        tampered_proof.subtrees.commitment_subtree += curves::pallas::Fr::from(1u64);
+        let tampered_block = Block::<Fr> {
+            proof: Some(BlockProof {
+                rollup_proof: tampered_proof.to_bytes().unwrap(),
+                ..block_proof.clone()
+            }),
+            ..block.clone()
+        };
+        assert!(verify(&tampered_block).is_err());
+
+        // Accumulator polynomial that doesn't decide the accumulator
+        let mut accumulators =
+            RollupAccumulators::<PallasConfig, VestaConfig>::from_bytes(&block_proof.accumulators)
+                .unwrap();
+        accumulators.instance.coeffs[0] += curves::vesta::Fr::from(1u64);
+        let undecided_block = Block::<Fr> {
+            proof: Some(BlockProof {
+                accumulators: accumulators.to_bytes().unwrap(),
+                ..block_proof.clone()
+            }),
+            ..block.clone()
+        };
+        assert!(verify(&undecided_block).is_err());
+
+        // Verifying key the block does not reference
+        let other_block = Block::<Fr> {
+            proof: Some(BlockProof {
+                vk_hash: "other".to_string(),
+                ..block_proof.clone()
+            }),
+            ..block.clone()
+        };
+        assert!(verify(&other_block).is_err());
+        assert!(verify(&Block::<Fr>::default()).is_err());
+    }
+
+    #[test]
+    fn transplanted_proof_test() {
+        let (block, vk, pallas_commit_key, vesta_commit_key) = proven_block();
+        let verify = |block: &Block<Fr>| {
+            verify_block::<PallasConfig, VestaConfig, _>(
+                block,
+                &vk,
+                &pallas_commit_key,
+                &vesta_commit_key,
+            )
+        };
+
+        // Another block with other commitments and a consistent header
+        let mut other_block = block.clone();
+        other_block.transactions[0].commitments[0] += Fr::from(1u64);
+        other_block.header.transactions_root = transactions_root(&other_block.transactions);
+        assert!(verify(&other_block).is_err());
+
+        // The same transactions on top of another state
+        let mut other_block = block.clone();
+        other_block.header.previous_state.nullifier_root =
+            canonical_hex(&curves::pallas::Fr::from(1u64));
+        assert!(verify(&other_block).is_err());
+
+        let mut other_block = block.clone();
+        other_block.header.previous_state.nullifier_leaf_count = 1;
+        assert!(verify(&other_block).is_err());
+
+        let mut other_block = block.clone();
+        other_block.header.new_state.commitment_root =
+            canonical_hex(&curves::pallas::Fr::from(1u64));
+        assert!(verify(&other_block).is_err());
+
+        // A header that doesn't commit to the transactions of the block
+        let mut other_block = block.clone();
+        other_block.transactions.swap(0, 1);
+        assert!(verify(&other_block).is_err());
+    }
+
+    #[test]
+    fn rewritten_transactions_test() {
+        let (block, vk, pallas_commit_key, vesta_commit_key) = proven_block();
+        let verify = |block: &Block<Fr>| {
+            verify_block::<PallasConfig, VestaConfig, _>(
+                block,
+                &vk,
+                &pallas_commit_key,
+                &vesta_commit_key,
+            )
+        };
+        let unproven = verify(&block).unwrap();
+        assert_eq!(
+            unproven.new_nullifier_root,
+            block.header.new_state.nullifier_root
+        );
+
+        // Every public input of a transaction is bound, even with a consistent header
+        let rewrites: [fn(&mut BlockTransaction<Fr>); 3] = [
+            |transaction| transaction.nullifiers[0] += Fr::from(1u64),
+            |transaction| transaction.eph_pub_key[0] += Fr::from(1u64),
+            |transaction| transaction.ciphertexts[2] += Fr::from(1u64),
+        ];
+        for rewrite in rewrites {
+            let mut other_block = block.clone();
+            rewrite(&mut other_block.transactions[0]);
+            other_block.header.transactions_root = transactions_root(&other_block.transactions);
+            other_block.nullifiers = block_nullifiers(&other_block.transactions);
+            assert!(verify(&other_block).is_err());
+        }
+
+        // Block data that doesn't match its transactions
+        let mut other_block = block.clone();
+        other_block.nullifiers.push(Fr::from(1u64));
+        assert!(verify(&other_block).is_err());
+
+        let mut other_block = block.clone();
+        other_block.commitments.reverse();
+        assert!(verify(&other_block).is_err());
+
+        // A withdrawal no transaction of the block made
+        let mut other_block = block.clone();
+        other_block.withdrawals.push(Withdrawal {
+            value: Fr::from(1u64),
+            token_id: Fr::from(1u64),
+            recipient: Fr::from(1u64),
+        });
+        assert!(verify(&other_block).is_err());
+    }
+}
@@ -34,7 +34,7 @@ use jf_utils::{field_switching, fr_to_fq};
 use super::base_helpers::*;
 
 const VK_PATHS_LEN: usize = 8;
-const MAX_N_BASE_TRANSACTIONS: usize = 8;
+pub const MAX_N_BASE_TRANSACTIONS: usize = 8;
 const MAX_N_COMMITMENTS: usize = 4;
 const MAX_N_NULLIFIERS: usize = 4;
 
@@ -71,7 +71,8 @@ where
         ));
     }
 
-    if I == 0 || I % 2 == 1 || I > MAX_N_BASE_TRANSACTIONS {
+    // A single transaction is rolled up alone so that it isn't kept waiting for another one
+    if I == 0 || (I % 2 == 1 && I != 1) || I > MAX_N_BASE_TRANSACTIONS {
         return Err(CircuitError::ParameterError(
             "Incorrect number of client inputs".to_string(),
         ));
@@ -369,12 +370,23 @@ where
         // Step 7: Hash the output commitments pairwise
         // This loop creates 2 subtrees of depth 1 (but really log2(C))
         //  This is only if transaction has > 1 commitment
-        if C == 1 || input.swap_field {
-            leaf_hashes.push(commitments_var[0]);
+        let commitment_leaf = if C == 1 || input.swap_field {
+            commitments_var[0]
         } else {
-            let subtree_leaf = poseidon_gadget::<C1, C2>(&mut circuit, &commitments_var, C)?;
-            leaf_hashes.push(subtree_leaf);
-        }
+            poseidon_gadget::<C1, C2>(&mut circuit, &commitments_var, C)?
+        };
+        let nullifier_leaf = if N == 1 {
+            nullifiers_fq[0]
+        } else {
+            poseidon_gadget::<C1, C2>(&mut circuit, &nullifiers_fq, N)?
+        };
+        // The leaf binds every public input of the client proof but its commitment
+        // tree roots, so that a block can't be published with other nullifiers,
+        // ephemeral keys or ciphertexts than the ones that were proven
+        let mut leaf_inputs = vec![swap_var.into(), commitment_leaf, nullifier_leaf];
+        leaf_inputs.extend(eph_pub_key_vars.iter().chain(ciphertext_vars.iter()));
+        let leaf = poseidon_gadget::<C1, C2>(&mut circuit, &leaf_inputs, leaf_inputs.len())?;
+        leaf_hashes.push(leaf);
 
         if N == 1 {
             nullifier_leaf_hashes.push(input_nullifier_hashes[0]);
@@ -393,22 +405,27 @@ where
 
     // Step 8: Swap Checks
     // TODO enforce I == 2 if swap in circuit
-    let same_swap_fields = circuit.is_equal(swap_vars[0].into(), swap_vars[1].into())?;
-    circuit.enforce_true(same_swap_fields.into())?;
-    /*
-       if C == 1 {
-           // fix out of bounds err
-           // TODO make better if C = 1, since there is no swap
-           out_commitments[0].push(0);
-           out_commitments[1].push(0);
-       }
-    */
-    let out_1_match = circuit.is_equal(out_commitments[0][0], out_commitments[1][1])?;
-    let out_2_match = circuit.is_equal(out_commitments[1][0], out_commitments[0][1])?;
-    let both_match = circuit.logic_and(out_2_match, out_1_match)?;
-    // Using swap[0] as we have already enforced equality across swap vars
-    let check = circuit.conditional_select(swap_vars[0], 1, both_match.into())?;
-    circuit.enforce_true(check)?;
+    if I == 1 {
+        // A swap half is only rolled up with its counterparty
+        circuit.enforce_equal(swap_vars[0].into(), circuit.zero())?;
+    } else {
+        let same_swap_fields = circuit.is_equal(swap_vars[0].into(), swap_vars[1].into())?;
+        circuit.enforce_true(same_swap_fields.into())?;
+        /*
+           if C == 1 {
+               // fix out of bounds err
+               // TODO make better if C = 1, since there is no swap
+               out_commitments[0].push(0);
+               out_commitments[1].push(0);
+           }
+        */
+        let out_1_match = circuit.is_equal(out_commitments[0][0], out_commitments[1][1])?;
+        let out_2_match = circuit.is_equal(out_commitments[1][0], out_commitments[0][1])?;
+        let both_match = circuit.logic_and(out_2_match, out_1_match)?;
+        // Using swap[0] as we have already enforced equality across swap vars
+        let check = circuit.conditional_select(swap_vars[0], 1, both_match.into())?;
+        circuit.enforce_true(check)?;
+    }
 
     let prover = AccProver::new();
     // 1 SW Point + 2 Field element made public here
@@ -417,11 +434,17 @@ where
         .unwrap();
 
     circuit.set_variable_public(nullifier_new_root)?;
-    // Bag the roots of the subtrees created previously assuming I == 2
-    let commitment_subtree_root = poseidon_gadget::<C1, C2>(&mut circuit, &leaf_hashes, I)?;
+    // Bag the roots of the subtrees created previously, the leaf is the root of a
+    // single transaction
+    let (commitment_subtree_root, nullifier_subtree_root) = if I == 1 {
+        (leaf_hashes[0], nullifier_leaf_hashes[0])
+    } else {
+        (
+            poseidon_gadget::<C1, C2>(&mut circuit, &leaf_hashes, I)?,
+            poseidon_gadget::<C1, C2>(&mut circuit, &nullifier_leaf_hashes, I)?,
+        )
+    };
     circuit.set_variable_public(commitment_subtree_root)?;
-    let nullifier_subtree_root =
-        poseidon_gadget::<C1, C2>(&mut circuit, &nullifier_leaf_hashes, I)?;
     circuit.set_variable_public(nullifier_subtree_root)?;
 
     // nullfier_leaf_hash [left_n, right_n]
@@ -1,7 +1,7 @@
 pub mod base_helpers;
 pub mod circuit;
 pub use base_helpers::BasePublicVarIndex;
-pub use circuit::base_rollup_circuit;
+pub use circuit::{base_rollup_circuit, MAX_N_BASE_TRANSACTIONS};
 
 #[cfg(test)]
 mod tests;
@@ -1,7 +1,8 @@
 #[cfg(test)]
 use super::base_rollup_circuit;
+use crate::client::circuits::circuit_inputs::CircuitInputs;
 use crate::client::circuits::mint::MintCircuit;
-use crate::client::circuits::swap::swap_circuit;
+use crate::client::circuits::swap::SwapCircuit;
 use crate::client::circuits::transfer::TransferCircuit;
 use crate::client::ClientPlonkCircuit;
 use crate::rollup::circuits::client_input::{self, LowNullifierInfo};
@@ -10,12 +11,12 @@ use crate::utils::bench;
 use crate::utils::bench::base;
 use crate::utils::bench::tree::tree_generator_from_client_inputs;
 use ark_ec::pairing::Pairing;
-use ark_ec::short_weierstrass::SWCurveConfig;
-use ark_ec::CurveGroup;
 use ark_ff::Zero;
 use ark_std::UniformRand;
 use client_input::ClientInput;
 use common::crypto::poseidon::Poseidon;
+use common::derived_keys::DerivedKeys;
+use common::keypair::PublicKey;
 use curves::pallas::Affine as PAffine;
 use curves::pallas::{Fq, Fr, PallasConfig};
 use curves::vesta::VestaConfig;
@@ -24,12 +25,17 @@ use jf_plonk::proof_system::UniversalSNARK;
 use jf_plonk::transcript::RescueTranscript;
 use jf_relation::PlonkCircuit;
 use jf_relation::{Arithmetization, Circuit};
-use jf_utils::{field_switching, fq_to_fr_with_mask, test_rng};
-use std::str::FromStr;
+use jf_utils::{field_switching, test_rng};
 use trees::membership_tree::{MembershipTree, Tree};
 use trees::non_membership_tree::IndexedMerkleTree;
 use trees::non_membership_tree::NonMembershipTree;
 use trees::tree::AppendTree;
+use trees::MembershipPath;
+
+#[test]
+fn test_base_circuit_1_transaction() {
+    test_base_rollup_helper::<8>(&[Box::new(TransferCircuit::<1, 1, 8>::new())], 1);
+}
 
 #[test]
 fn test_base_circuit_2_transactions() {
@@ -111,8 +117,6 @@ pub fn test_base_rollup_helper<const D: usize>(
 fn test_base_rollup_helper_swap<const D: usize>() -> StoredProof<PallasConfig, VestaConfig> {
     const I: usize = 2;
     let mut rng = test_rng();
-    let private_key_domain = Fq::from_str("1").unwrap();
-    let nullifier_key_domain = Fq::from_str("2").unwrap();
 
     let mut client_inputs = vec![];
     let mut g_polys = vec![];
@@ -133,12 +137,9 @@ fn test_base_rollup_helper_swap<const D: usize>() -> StoredProof<PallasConfig, V
         let token_id = Fq::from((i + 10) as u64);
         let nonce = Fq::from((i + 100) as u64);
 
-        let private_key: Fq = Poseidon::<Fq>::new()
-            .hash(vec![root_key, private_key_domain])
-            .unwrap();
-        let private_key_trunc: Fr = fq_to_fr_with_mask(&private_key);
-
-        let token_owner = (PallasConfig::GENERATOR * private_key_trunc).into_affine();
+        let token_owner = DerivedKeys::<PallasConfig>::new(root_key)
+            .unwrap()
+            .public_key;
 
         let old_commitment_hash = Poseidon::<Fq>::new()
             .hash(vec![value, token_id, nonce, token_owner.x, token_owner.y])
@@ -155,7 +156,7 @@ fn test_base_rollup_helper_swap<const D: usize>() -> StoredProof<PallasConfig, V
         // other half of swap index
         let j = (i + 1) % 2;
         let old_sib_path = comm_tree.membership_witness(i).unwrap().try_into().unwrap();
-        let (mut swap_circuit, (nullifier, commitments, eph_pub_key, ciphertext)) =
+        let (swap_circuit, (nullifier, commitments, eph_pub_key, ciphertext)) =
             swap_circuit_helper_generator(
                 values[i],
                 token_ids[i],
@@ -167,11 +168,8 @@ fn test_base_rollup_helper_swap<const D: usize>() -> StoredProof<PallasConfig, V
                 i as u64,
                 j as u64,
                 root_keys[i],
-                private_key_domain,
-                nullifier_key_domain,
                 public_keys[j],
             );
-        swap_circuit.finalize_for_arithmetization().unwrap();
         let swap_ipa_srs = <PlonkIpaSnark<VestaConfig> as UniversalSNARK<VestaConfig>>::universal_setup_for_testing(
             swap_circuit.srs_size().unwrap(),
             &mut rng,
@@ -314,28 +312,27 @@ fn swap_circuit_helper_generator(
     old_leaf_index: u64,
     new_leaf_index: u64, // = expected incoming commitment nonce
     root_key: Fq,        // required here as we need to keep keys consistent
-    private_key_domain: Fq,
-    nullifier_key_domain: Fq,
     recipient_public_key: PAffine,
 ) -> (PlonkCircuit<Fq>, (Fq, [Fq; 2], [Fq; 2], [Fq; 3])) {
     let ephemeral_key = Fq::rand(&mut test_rng());
-    let circuit = swap_circuit::<PallasConfig, VestaConfig, 8>(
-        old_value,
-        old_nonce,
-        old_sib_path.try_into().unwrap(),
-        old_leaf_index.try_into().unwrap(),
-        root,
-        old_token_id,
-        new_value,
-        new_leaf_index.try_into().unwrap(),
-        new_token_id,
-        recipient_public_key,
-        root_key,
-        ephemeral_key,
-        private_key_domain,
-        nullifier_key_domain,
-    )
-    .unwrap();
+    let circuit_inputs = CircuitInputs::<PallasConfig>::new()
+        .add_old_token_values(vec![old_value])
+        .add_old_token_salts(vec![old_nonce])
+        .add_old_token_ids(vec![old_token_id])
+        .add_membership_path(MembershipPath::from_array([old_sib_path]))
+        .add_membership_path_index(vec![Fq::from(old_leaf_index)])
+        .add_commitment_tree_root(vec![root])
+        .add_token_values(vec![new_value])
+        .add_token_salts(vec![Fq::from(new_leaf_index)])
+        .add_token_ids(vec![new_token_id])
+        .add_recipients(vec![PublicKey::from_affine(recipient_public_key)])
+        .add_root_key(root_key)
+        .add_ephemeral_key(ephemeral_key)
+        .build();
+    let circuit = SwapCircuit::<8>::new()
+        .as_circuit::<PallasConfig, VestaConfig, VestaConfig>()
+        .to_plonk_circuit(circuit_inputs)
+        .unwrap();
 
     let public_inputs = circuit.public_input().unwrap();
     let len = public_inputs.len();
@@ -45,7 +45,14 @@ pub fn bounce_merge_circuit<C1, C2>(
     // This is the acc calculated in bounce
     bounce_accs: [AccInstance<C1>; 2],
     bounce_pi_stars: [DensePolynomial<C1::ScalarField>; 2],
-) -> Result<(PlonkCircuit<C2::ScalarField>, Vec<C1::ScalarField>), CircuitError>
+) -> Result<
+    (
+        PlonkCircuit<C2::ScalarField>,
+        Vec<C1::ScalarField>,
+        DensePolynomial<C1::ScalarField>,
+    ),
+    CircuitError,
+>
 where
     C1: Pairing<G1Affine = Affine<<<C1 as Pairing>::G1 as CurveGroup>::Config>>,
     <<C1 as Pairing>::G1 as CurveGroup>::Config: SWCurveConfig<BaseField = C1::BaseField>,
@@ -140,7 +147,8 @@ where
     // Partial prove accumulation of all instances
     let prover = AccProver::new();
 
-    let (acc, _) = prover
+    // pi_star is needed to accumulate this acc again in a later bounce merge
+    let (acc, pi_star) = prover
         .prove_accumulation(&commit_key, &instances, &g_polys)
         .unwrap();
 
@@ -194,7 +202,7 @@ where
     circuit.check_circuit_satisfiability(&circuit.public_input()?)?;
     circuit.finalize_for_arithmetization()?;
 
-    Ok((circuit, public_outputs))
+    Ok((circuit, public_outputs, pi_star))
 }
 
 #[cfg(test)]
@@ -231,7 +239,7 @@ pub mod bounce_merge_test {
         let (global_public_inputs, subtree_public_inputs, passthrough_instance, bounce_accs) =
             stored_proof_merge.pub_inputs;
 
-        let (mut bounce_circuit, public_outputs) =
+        let (mut bounce_circuit, public_outputs, _) =
             bounce_merge_circuit::<PallasConfig, VestaConfig>(
                 stored_proof_merge.vk,
                 global_public_inputs.clone(),
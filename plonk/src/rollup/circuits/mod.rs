@@ -5,6 +5,12 @@ pub mod client_input;
 pub mod merge;
 pub mod structs;
 
+// Version of the base, bounce, merge and bounce merge circuits. Rollup keys are
+// cached under it since preprocessing them needs a full rollup, so it must be
+// bumped whenever a change to those circuits or their gadgets changes their
+// constraints.
+pub const ROLLUP_CIRCUITS_VERSION: u32 = 2;
+
 pub mod utils {
     use super::structs::{AccInstance, GlobalPublicInputs, SubTrees};
     use crate::rollup::circuits::base::BasePublicVarIndex;
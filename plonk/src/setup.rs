@@ -0,0 +1,144 @@
+use ark_ec::{
+    pairing::Pairing,
+    short_weierstrass::{Affine, Projective, SWCurveConfig},
+    AffineRepr,
+};
+use ark_ff::PrimeField;
+use ark_serialize::CanonicalSerialize;
+use jf_plonk::{nightfall::PlonkIpaSnark, proof_system::UniversalSNARK};
+use jf_primitives::{pcs::prelude::UnivariateUniversalIpaParams, rescue::RescueParameter};
+use jf_relation::{errors::CircuitError, gadgets::ecc::SWToTEConParam};
+use sha2::{Digest, Sha512};
+
+// Degree of the shared setup. It bounds every client circuit and is also the
+// size of the commit keys the rollup circuits accumulate client proofs with.
+pub const SRS_DEGREE: usize = 2usize.pow(21);
+
+// Public domain string the commit key bases are hashed from. There is no
+// trapdoor: anyone can recompute the bases from it and compare.
+pub const SETUP_DOMAIN: &[u8] = b"zk-engine/ipa-commit-key/v1";
+
+pub type UniversalSrs<E> = <PlonkIpaSnark<E> as UniversalSNARK<E>>::UniversalSRS;
+
+// Transparent setup for the IPA scheme. The i-th base is hashed from
+// (domain, "g", i) and the blinding base from (domain, "h", 0), so a setup of
+// a smaller degree is a prefix of a larger one.
+pub fn universal_setup<E, SW>(max_degree: usize) -> Result<UniversalSrs<E>, CircuitError>
+where
+    E: Pairing<G1Affine = Affine<SW>, G1 = Projective<SW>>,
+    <E as Pairing>::BaseField: RescueParameter + SWToTEConParam,
+    SW: SWCurveConfig<BaseField = E::BaseField>,
+{
+    let g_bases = (0..=max_degree as u64)
+        .map(|i| hash_to_curve::<SW>(SETUP_DOMAIN, b"g", i))
+        .collect::<Result<Vec<_>, _>>()?;
+    let h = hash_to_curve::<SW>(SETUP_DOMAIN, b"h", 0)?;
+    Ok(UnivariateUniversalIpaParams { g_bases, h })
+}
+
+// Recomputes every base of `srs` from the public domain string.
+pub fn verify_universal_setup<E, SW>(srs: &UniversalSrs<E>) -> bool
+where
+    E: Pairing<G1Affine = Affine<SW>, G1 = Projective<SW>>,
+    <E as Pairing>::BaseField: RescueParameter + SWToTEConParam,
+    SW: SWCurveConfig<BaseField = E::BaseField>,
+{
+    let bases_match = srs
+        .g_bases
+        .iter()
+        .enumerate()
+        .all(|(i, g)| hash_to_curve::<SW>(SETUP_DOMAIN, b"g", i as u64).ok() == Some(*g));
+    bases_match && hash_to_curve::<SW>(SETUP_DOMAIN, b"h", 0).ok() == Some(srs.h)
+}
+
+// Try-and-increment hash to curve. The curve generator is part of the hashed
+// data so each curve gets independent bases from the same domain string.
+pub fn hash_to_curve<SW>(
+    domain: &[u8],
+    label: &[u8],
+    index: u64,
+) -> Result<Affine<SW>, CircuitError>
+where
+    SW: SWCurveConfig,
+    SW::BaseField: PrimeField,
+{
+    let mut generator = Vec::new();
+    SW::GENERATOR
+        .serialize_compressed(&mut generator)
+        .map_err(|e| CircuitError::ParameterError(format!("Invalid curve generator: {e}")))?;
+    for counter in 0u64.. {
+        let digest = Sha512::new()
+            .chain_update((domain.len() as u64).to_le_bytes())
+            .chain_update(domain)
+            .chain_update(&generator)
+            .chain_update((label.len() as u64).to_le_bytes())
+            .chain_update(label)
+            .chain_update(index.to_le_bytes())
+            .chain_update(counter.to_le_bytes())
+            .finalize();
+        let x = SW::BaseField::from_le_bytes_mod_order(&digest);
+        // Of the two points with this x coordinate, take the one with the smaller y
+        if let Some(point) = Affine::<SW>::get_point_from_x_unchecked(x, false) {
+            let point = point.clear_cofactor();
+            if !point.is_zero() {
+                return Ok(point);
+            }
+        }
+    }
+    unreachable!("hash to curve always finds a point")
+}
+
+// Setup of degree `SRS_DEGREE`. It is derived from the public domain string
+// alone, so every client and sequencer gets the same one without sharing any
+// artifact. It isn't read from a file: checking a stored setup means deriving
+// every base again, which costs as much as deriving it.
+pub fn shared_setup<E, SW>() -> Result<UniversalSrs<E>, CircuitError>
+where
+    E: Pairing<G1Affine = Affine<SW>, G1 = Projective<SW>>,
+    <E as Pairing>::BaseField: RescueParameter + SWToTEConParam,
+    SW: SWCurveConfig<BaseField = E::BaseField>,
+{
+    universal_setup::<E, SW>(SRS_DEGREE)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use curves::vesta::VestaConfig;
+
+    #[test]
+    fn universal_setup_is_deterministic() {
+        let srs_1 = universal_setup::<VestaConfig, VestaConfig>(2usize.pow(8)).unwrap();
+        let srs_2 = universal_setup::<VestaConfig, VestaConfig>(2usize.pow(8)).unwrap();
+        assert_eq!(srs_1, srs_2);
+
+        // Smaller setups are a prefix of larger ones
+        let srs_3 = universal_setup::<VestaConfig, VestaConfig>(2usize.pow(9)).unwrap();
+        assert_eq!(srs_1.g_bases[..], srs_3.g_bases[..srs_1.g_bases.len()]);
+        assert_eq!(srs_1.h, srs_3.h);
+    }
+
+    #[test]
+    fn verify_universal_setup_test() {
+        let mut srs = universal_setup::<VestaConfig, VestaConfig>(2usize.pow(8)).unwrap();
+        assert!(verify_universal_setup::<VestaConfig, VestaConfig>(&srs));
+
+        // A base with a known discrete log relation is rejected
+        srs.g_bases[1] = (srs.g_bases[0] + srs.g_bases[0]).into();
+        assert!(!verify_universal_setup::<VestaConfig, VestaConfig>(&srs));
+    }
+
+    #[test]
+    fn hash_to_curve_test() {
+        let g_0 = hash_to_curve::<VestaConfig>(SETUP_DOMAIN, b"g", 0).unwrap();
+        let g_1 = hash_to_curve::<VestaConfig>(SETUP_DOMAIN, b"g", 1).unwrap();
+        let h = hash_to_curve::<VestaConfig>(SETUP_DOMAIN, b"h", 0).unwrap();
+        assert!(g_0.is_on_curve() && g_0.is_in_correct_subgroup_assuming_on_curve());
+        assert_ne!(g_0, g_1);
+        assert_ne!(g_0, h);
+        assert_ne!(
+            g_0,
+            hash_to_curve::<VestaConfig>(b"another domain", b"g", 0).unwrap()
+        );
+    }
+}
@@ -7,6 +7,7 @@ use crate::rollup::circuits::base;
 use crate::rollup::circuits::client_input;
 use crate::rollup::circuits::client_input::ClientInput;
 use crate::rollup::circuits::utils::StoredProof;
+use crate::setup::{universal_setup, SRS_DEGREE};
 use ark_ec::{
     pairing::Pairing,
     short_weierstrass::{Affine, Projective, SWCurveConfig},
@@ -141,8 +142,7 @@ fn generate_client_circuit_artifacts_and_verify<P, V, VSW>(
         .srs_size()
         .map_err(|_| "Couldnt extract Client Circuit SRS Size".to_string())?;
     let ipa_srs =
-        <PlonkIpaSnark<V> as UniversalSNARK<V>>::universal_setup_for_testing(srs_size, &mut rng)
-            .map_err(|_| "Couldnt compute Client Circuit SRS")?;
+        universal_setup::<V, VSW>(srs_size).map_err(|_| "Couldnt compute Client Circuit SRS")?;
     let (ipa_pk, ipa_vk) = PlonkIpaSnark::<V>::preprocess(&ipa_srs, circuit)
         .map_err(|_| "Couldn't compute Client Circuit PK/VK".to_string())?;
 
@@ -232,22 +232,17 @@ pub fn build_client_inputs(
 }
 
 pub fn build_commit_keys() -> Result<(CommitKey<VestaConfig>, CommitKey<PallasConfig>), String> {
-    let mut rng = ChaChaRng::from_entropy();
-    let vesta_srs =
-        <PlonkIpaSnark<VestaConfig> as UniversalSNARK<VestaConfig>>::universal_setup_for_testing(
-            2usize.pow(21),
-            &mut rng,
-        )
-        .unwrap();
-    let (vesta_commit_key, _) = vesta_srs.trim(2usize.pow(21)).unwrap();
-
-    let pallas_srs =
-        <PlonkIpaSnark<PallasConfig> as UniversalSNARK<PallasConfig>>::universal_setup_for_testing(
-            2usize.pow(21),
-            &mut rng,
-        )
-        .unwrap();
-    let (pallas_commit_key, _) = pallas_srs.trim(2usize.pow(21)).unwrap();
+    let vesta_srs = universal_setup::<VestaConfig, VestaConfig>(SRS_DEGREE)
+        .map_err(|e| format!("Vesta setup failed: {e}"))?;
+    let (vesta_commit_key, _) = vesta_srs
+        .trim(SRS_DEGREE)
+        .map_err(|e| format!("Vesta commit key failed: {e}"))?;
+
+    let pallas_srs = universal_setup::<PallasConfig, PallasConfig>(SRS_DEGREE)
+        .map_err(|e| format!("Pallas setup failed: {e}"))?;
+    let (pallas_commit_key, _) = pallas_srs
+        .trim(SRS_DEGREE)
+        .map_err(|e| format!("Pallas commit key failed: {e}"))?;
 
     Ok((vesta_commit_key, pallas_commit_key))
 }
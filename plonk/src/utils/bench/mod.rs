@@ -1,3 +1,4 @@
+use crate::setup::universal_setup;
 use ark_ec::{
     pairing::Pairing,
     short_weierstrass::{Affine, Projective, SWCurveConfig},
@@ -58,14 +59,11 @@ pub fn generate_rollup_circuit_artifacts_and_verify<P, V, SW, VSW>(
 pub fn generate_rollup_circuit_pks<P, V, SW, VSW>(
     rollup_circuit: &PlonkCircuit<P::ScalarField>,
 ) -> Result<(ProvingKey<P>, VerifyingKey<P>), String> {
-    let mut rng = ChaChaRng::from_entropy();
-
     let srs_size = rollup_circuit
         .srs_size()
         .map_err(|_| "Couldnt extract rollup Circuit SRS Size".to_string())?;
     let rollup_ipa_srs =
-        <PlonkIpaSnark<P> as UniversalSNARK<P>>::universal_setup_for_testing(srs_size, &mut rng)
-            .map_err(|_| "Couldnt compute rollup Circuit SRS")?;
+        universal_setup::<P, SW>(srs_size).map_err(|_| "Couldnt compute rollup Circuit SRS")?;
 
     let (rollup_ipa_pk, rollup_ipa_vk) =
         PlonkIpaSnark::<P>::preprocess(&rollup_ipa_srs, rollup_circuit)
@@ -1,2 +1,5 @@
+pub mod blocks;
 pub mod sequence;
+pub mod state;
+pub mod subscriptions;
 pub mod transactions;
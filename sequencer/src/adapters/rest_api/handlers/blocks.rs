@@ -0,0 +1,45 @@
+use crate::adapters::rest_api::sequencer_api::SequencerState;
+use crate::ports::storage::BlockStorage;
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use common::structs::Block;
+use serde::Deserialize;
+
+// Most blocks returned by a single range request
+pub const MAX_BLOCK_RANGE: u64 = 100;
+
+#[derive(Debug, Deserialize)]
+pub struct BlockRange {
+    pub from: Option<u64>,
+    // Exclusive
+    pub to: Option<u64>,
+}
+
+#[tracing::instrument(name = "Requested Block", skip(db))]
+pub async fn get_block(
+    State(db): State<SequencerState>,
+    Path(block_number): Path<u64>,
+) -> Result<Json<Block<curves::vesta::Fr>>, StatusCode> {
+    let db = db.state_db.lock().await;
+    db.get_block(block_number)
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+#[tracing::instrument(name = "Requested Blocks", skip(db))]
+pub async fn get_blocks(
+    State(db): State<SequencerState>,
+    Query(range): Query<BlockRange>,
+) -> Result<Json<Vec<Block<curves::vesta::Fr>>>, StatusCode> {
+    let from = range.from.unwrap_or(0);
+    let max_to = from.saturating_add(MAX_BLOCK_RANGE);
+    let to = range.to.map_or(max_to, |to| to.min(max_to));
+    if to < from {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let db = db.state_db.lock().await;
+    Ok(Json(db.get_blocks(from, to)))
+}
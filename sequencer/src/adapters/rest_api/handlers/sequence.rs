@@ -6,7 +6,10 @@ use common::structs::Block;
 impl From<BuildBlockError> for StatusCode {
     fn from(value: BuildBlockError) -> Self {
         match value {
-            BuildBlockError::BlockError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            BuildBlockError::BlockError(_) | BuildBlockError::ProverError(_) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+            BuildBlockError::StaleBlock => StatusCode::CONFLICT,
             _ => StatusCode::BAD_REQUEST,
         }
     }
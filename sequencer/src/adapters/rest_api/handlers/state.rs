@@ -0,0 +1,64 @@
+use crate::adapters::rest_api::sequencer_api::SequencerState;
+use crate::ports::storage::{GlobalStateStorage, TransactionStorage};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use curves::pallas::Fr;
+use jf_utils::{canonical, field_switching};
+use serde::Serialize;
+use std::str::FromStr;
+
+#[derive(Debug, Serialize)]
+pub struct GlobalRootsResponse {
+    #[serde(with = "canonical")]
+    pub commitment_root: Fr,
+    #[serde(with = "canonical")]
+    pub nullifier_root: Fr,
+    pub nullifier_leaf_count: u64,
+    #[serde(with = "canonical")]
+    pub vk_root: Fr,
+}
+
+#[derive(Debug, Serialize)]
+pub struct NullifierResponse {
+    pub spent: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MempoolResponse {
+    pub size: usize,
+}
+
+#[tracing::instrument(name = "Requested Global Roots", skip(db))]
+pub async fn get_roots(State(db): State<SequencerState>) -> Json<GlobalRootsResponse> {
+    let db = db.state_db.lock().await;
+    let roots = db.get_global_roots();
+    Json(GlobalRootsResponse {
+        commitment_root: roots.commitment_root,
+        nullifier_root: roots.nullifier_root,
+        nullifier_leaf_count: roots.nullifier_leaf_count,
+        vk_root: roots.vk_root,
+    })
+}
+
+// Nullifiers are given in decimal, as transactions carry them
+#[tracing::instrument(name = "Requested Nullifier", skip(db))]
+pub async fn get_nullifier(
+    State(db): State<SequencerState>,
+    Path(nullifier): Path<String>,
+) -> Result<Json<NullifierResponse>, StatusCode> {
+    let nullifier = curves::vesta::Fr::from_str(&nullifier).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let db = db.state_db.lock().await;
+    let spent = db.is_nullifier_spent(field_switching(&nullifier));
+    Ok(Json(NullifierResponse { spent }))
+}
+
+#[tracing::instrument(name = "Requested Mempool", skip(db))]
+pub async fn get_mempool(State(db): State<SequencerState>) -> Json<MempoolResponse> {
+    let db = db.state_db.lock().await;
+    Json(MempoolResponse {
+        size: db.get_mempool_size(),
+    })
+}
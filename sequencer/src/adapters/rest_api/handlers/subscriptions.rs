@@ -0,0 +1,52 @@
+use crate::adapters::rest_api::sequencer_api::SequencerState;
+use crate::domain::subscription::{Subscription, SubscriptionError};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct SubscriptionRequest {
+    pub callback_url: String,
+}
+
+impl IntoResponse for SubscriptionError {
+    fn into_response(self) -> Response {
+        let status = match self {
+            SubscriptionError::InvalidCallbackUrl(_) => StatusCode::BAD_REQUEST,
+            SubscriptionError::AlreadySubscribed(_) => StatusCode::CONFLICT,
+            SubscriptionError::TooManySubscribers(_) => StatusCode::SERVICE_UNAVAILABLE,
+            SubscriptionError::UnknownSubscription(_) => StatusCode::NOT_FOUND,
+        };
+        (status, Json(self)).into_response()
+    }
+}
+
+#[tracing::instrument(name = "Received Subscription", skip(db))]
+pub async fn subscribe(
+    State(db): State<SequencerState>,
+    Json(request): Json<SubscriptionRequest>,
+) -> Result<(StatusCode, Json<Subscription>), SubscriptionError> {
+    let mut notifier = db.notifier.lock().await;
+    let subscription = notifier.subscribe(request.callback_url)?;
+    Ok((StatusCode::CREATED, Json(subscription)))
+}
+
+#[tracing::instrument(name = "Requested Subscriptions", skip(db))]
+pub async fn get_subscriptions(State(db): State<SequencerState>) -> Json<Vec<String>> {
+    let notifier = db.notifier.lock().await;
+    Json(notifier.callback_urls())
+}
+
+#[tracing::instrument(name = "Received Unsubscription", skip(db))]
+pub async fn unsubscribe(
+    State(db): State<SequencerState>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, SubscriptionError> {
+    let mut notifier = db.notifier.lock().await;
+    notifier.unsubscribe(&id)?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -1,18 +1,46 @@
 use crate::adapters::rest_api::sequencer_api::SequencerState;
+use crate::domain::transaction::TransactionStatus;
 use crate::ports::storage::TransactionStorage;
-use axum::{extract::State, http::StatusCode};
+use crate::usecase::{self, transaction::TransactionError};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
 use axum_serde::Cbor;
 use common::structs::Transaction;
 use curves::vesta::VestaConfig;
+use serde::Serialize;
+
+#[derive(Debug, Serialize)]
+pub struct TransactionResponse {
+    pub status: TransactionStatus,
+    // Rejected transactions aren't kept
+    pub transaction: Option<Transaction<VestaConfig>>,
+}
+
+impl IntoResponse for TransactionError {
+    fn into_response(self) -> Response {
+        let status = match self {
+            TransactionError::UnknownCircuit(_)
+            | TransactionError::InvalidPublicInputs(_)
+            | TransactionError::InvalidProof => StatusCode::BAD_REQUEST,
+            // The transaction may be submitted again once blocks drain the mempool
+            TransactionError::MempoolFull(_) => StatusCode::SERVICE_UNAVAILABLE,
+            TransactionError::StorageFailure(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            _ => StatusCode::CONFLICT,
+        };
+        (status, Json(self)).into_response()
+    }
+}
 
 #[tracing::instrument(name = "Received Transaction", skip(db, tx))]
 pub async fn handle_tx(
     State(db): State<SequencerState>,
     Cbor(tx): Cbor<Transaction<VestaConfig>>,
-) -> Result<StatusCode, StatusCode> {
-    let mut db = db.state_db.lock().await;
-    // Assume tx is valid here
-    db.insert_transaction(tx);
+) -> Result<StatusCode, TransactionError> {
+    usecase::transaction::submit_transaction_process(db.state_db, db.prover, tx).await?;
     Ok(StatusCode::CREATED)
 }
 
@@ -23,3 +51,18 @@ pub async fn get_tx(
     let db = db.state_db.lock().await;
     Ok(Cbor(db.get_all_transactions()))
 }
+
+#[tracing::instrument(name = "Requested Transaction By Hash", skip(db))]
+pub async fn get_tx_by_hash(
+    State(db): State<SequencerState>,
+    Path(hash): Path<String>,
+) -> Result<Json<TransactionResponse>, StatusCode> {
+    let db = db.state_db.lock().await;
+    let status = db
+        .get_transaction_status(&hash)
+        .ok_or(StatusCode::NOT_FOUND)?;
+    Ok(Json(TransactionResponse {
+        status,
+        transaction: db.get_transaction(&hash),
+    }))
+}
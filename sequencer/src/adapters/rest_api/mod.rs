@@ -1,31 +1,32 @@
 pub mod handlers;
 
 pub mod sequencer_api {
-    use crate::adapters::rest_api::handlers::{sequence, transactions};
+    use crate::adapters::rest_api::handlers::{
+        blocks, sequence, state, subscriptions, transactions,
+    };
     use crate::services::{
-        prover::in_mem_sequencer_prover::InMemProver,
-        storage::in_mem_sequencer_storage::InMemStorage,
+        notifier::block_notifier::BlockNotifier, prover::in_mem_sequencer_prover::InMemProver,
+        storage::sequencer_storage::SequencerStorage,
     };
+    use crate::usecase::block::producer::run_block_producer;
     use anyhow::anyhow;
     use axum::{
         extract::DefaultBodyLimit,
         http::StatusCode,
-        routing::{get, post},
+        routing::{delete, get, post},
         Router,
     };
     use common::configuration::ApplicationSettings;
-    use common::services::notifier::HttpNotifier;
-    use common::structs::Block;
     use curves::{pallas::PallasConfig, vesta::VestaConfig};
     use dotenvy::dotenv;
     use std::sync::Arc;
     use tokio::sync::Mutex;
     use tracing_log::log;
 
-    type SequencerDB = Arc<Mutex<InMemStorage>>;
+    type SequencerDB = Arc<Mutex<SequencerStorage>>;
     type SequencerProve =
         Arc<Mutex<InMemProver<VestaConfig, VestaConfig, PallasConfig, PallasConfig>>>;
-    type SequencerNotifier = Arc<Mutex<HttpNotifier<Block<curves::vesta::Fr>>>>;
+    type SequencerNotifier = Arc<Mutex<BlockNotifier<curves::vesta::Fr>>>;
 
     #[derive(Clone)]
     pub struct SequencerState {
@@ -60,6 +61,15 @@ pub mod sequencer_api {
                 run_api(listener, db.clone(), prover.clone(), notifier.clone()).await;
             log::trace!("Launching server at {}:{}", configuration.host, port);
 
+            if configuration.block_production.enabled {
+                tokio::spawn(run_block_producer(
+                    db.clone(),
+                    prover.clone(),
+                    notifier.clone(),
+                    configuration.block_production.clone(),
+                ));
+            }
+
             Ok(Application {
                 server,
                 port,
@@ -96,6 +106,15 @@ pub mod sequencer_api {
             .route("/health", get(|| async { StatusCode::OK }))
             .route("/transactions", post(transactions::handle_tx))
             .route("/transactions", get(transactions::get_tx))
+            .route("/transactions/:hash", get(transactions::get_tx_by_hash))
+            .route("/blocks", get(blocks::get_blocks))
+            .route("/blocks/:block_number", get(blocks::get_block))
+            .route("/nullifiers/:nullifier", get(state::get_nullifier))
+            .route("/roots", get(state::get_roots))
+            .route("/mempool", get(state::get_mempool))
+            .route("/subscriptions", post(subscriptions::subscribe))
+            .route("/subscriptions", get(subscriptions::get_subscriptions))
+            .route("/subscriptions/:id", delete(subscriptions::unsubscribe))
             .route("/sequence", post(sequence::make_block))
             .layer(DefaultBodyLimit::disable())
             .with_state(state);
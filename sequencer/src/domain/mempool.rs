@@ -0,0 +1,226 @@
+use std::collections::{HashMap, VecDeque};
+
+use ark_ec::{pairing::Pairing, short_weierstrass::SWCurveConfig, CurveGroup};
+use ark_ff::Zero;
+use common::structs::Transaction;
+use serde::Serialize;
+
+pub use common::structs::transaction_hash;
+
+pub const DEFAULT_MEMPOOL_CAPACITY: usize = 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "reason", content = "detail", rename_all = "snake_case")]
+pub enum MempoolError {
+    DuplicatedTransaction(String),
+    NullifierConflict(String),
+    MempoolFull(usize),
+    // The transaction couldn't be stored durably
+    StorageFailure(String),
+}
+
+// Pending transactions in arrival order, indexed by transaction hash and by the
+// nullifiers they spend. No two transactions in the mempool spend the same
+// nullifier, so any selection of them can be rolled up together. Transactions
+// are refused while the mempool is full, pending ones are never dropped.
+#[derive(Debug, Clone)]
+pub struct Mempool<P: Pairing>
+where
+    <<P as Pairing>::G1 as CurveGroup>::Config: SWCurveConfig,
+{
+    capacity: usize,
+    transactions: HashMap<String, Transaction<P>>,
+    order: VecDeque<String>,
+    nullifiers: HashMap<P::ScalarField, String>,
+}
+
+impl<P: Pairing> Default for Mempool<P>
+where
+    <<P as Pairing>::G1 as CurveGroup>::Config: SWCurveConfig,
+{
+    fn default() -> Self {
+        Self::new(DEFAULT_MEMPOOL_CAPACITY)
+    }
+}
+
+impl<P: Pairing> Mempool<P>
+where
+    <<P as Pairing>::G1 as CurveGroup>::Config: SWCurveConfig,
+{
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            transactions: HashMap::new(),
+            order: VecDeque::new(),
+            nullifiers: HashMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+
+    pub fn contains(&self, hash: &str) -> bool {
+        self.transactions.contains_key(hash)
+    }
+
+    pub fn get(&self, hash: &str) -> Option<&Transaction<P>> {
+        self.transactions.get(hash)
+    }
+
+    // Checks whether `transaction` could be inserted, without inserting it
+    pub fn check(&self, transaction: &Transaction<P>) -> Result<String, MempoolError> {
+        let hash = transaction_hash(transaction);
+        if self.contains(&hash) {
+            return Err(MempoolError::DuplicatedTransaction(hash));
+        }
+        if let Some(nullifier) =
+            spent_nullifiers(transaction).find(|nullifier| self.nullifiers.contains_key(nullifier))
+        {
+            return Err(MempoolError::NullifierConflict(nullifier.to_string()));
+        }
+        if self.len() >= self.capacity {
+            return Err(MempoolError::MempoolFull(self.capacity));
+        }
+        Ok(hash)
+    }
+
+    pub fn insert(&mut self, transaction: Transaction<P>) -> Result<String, MempoolError> {
+        let hash = self.check(&transaction)?;
+        for nullifier in spent_nullifiers(&transaction) {
+            self.nullifiers.insert(nullifier, hash.clone());
+        }
+        self.order.push_back(hash.clone());
+        self.transactions.insert(hash.clone(), transaction);
+        Ok(hash)
+    }
+
+    pub fn remove(&mut self, hash: &str) -> Option<Transaction<P>> {
+        let transaction = self.transactions.remove(hash)?;
+        self.order.retain(|h| h != hash);
+        for nullifier in spent_nullifiers(&transaction) {
+            self.nullifiers.remove(&nullifier);
+        }
+        Some(transaction)
+    }
+
+    // Pending transactions, oldest first
+    pub fn transactions(&self) -> Vec<Transaction<P>> {
+        self.order
+            .iter()
+            .filter_map(|hash| self.transactions.get(hash))
+            .cloned()
+            .collect()
+    }
+}
+
+// Mints fill their nullifier slots with zero
+fn spent_nullifiers<P>(transaction: &Transaction<P>) -> impl Iterator<Item = P::ScalarField> + '_
+where
+    P: Pairing,
+    <<P as Pairing>::G1 as CurveGroup>::Config: SWCurveConfig,
+{
+    transaction
+        .nullifiers
+        .iter()
+        .map(|n| n.0)
+        .filter(|n| !n.is_zero())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_transactions::{spending, MINT};
+    use curves::vesta::{Fr, VestaConfig};
+
+    fn transfer(nullifier: u64) -> Transaction<VestaConfig> {
+        spending(&[nullifier], Fr::from(0u64))
+    }
+
+    #[test]
+    fn test_full_mempool_rejects_transactions() {
+        let mut mempool = Mempool::<VestaConfig>::new(2);
+        let first = mempool.insert(transfer(1)).unwrap();
+        let second = mempool.insert(transfer(2)).unwrap();
+
+        assert_eq!(
+            mempool.insert(transfer(3)),
+            Err(MempoolError::MempoolFull(2))
+        );
+        assert_eq!(
+            mempool.check(&transfer(3)),
+            Err(MempoolError::MempoolFull(2))
+        );
+        // Pending transactions are kept
+        assert!(mempool.contains(&first));
+        assert!(mempool.contains(&second));
+        assert_eq!(mempool.len(), 2);
+
+        // Room is made by removing transactions, not by inserting
+        mempool.remove(&first);
+        assert!(mempool.insert(transfer(3)).is_ok());
+    }
+
+    #[test]
+    fn test_transactions_are_kept_in_arrival_order() {
+        let mut mempool = Mempool::<VestaConfig>::default();
+        let transactions = [transfer(3), MINT.1.clone(), transfer(1), transfer(2)];
+        for transaction in transactions.iter() {
+            mempool.insert(transaction.clone()).unwrap();
+        }
+        assert_eq!(mempool.transactions(), transactions.to_vec());
+
+        mempool.remove(&transaction_hash(&transactions[1]));
+        assert_eq!(
+            mempool.transactions(),
+            vec![
+                transactions[0].clone(),
+                transactions[2].clone(),
+                transactions[3].clone()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_duplicated_transactions_are_rejected() {
+        let mut mempool = Mempool::<VestaConfig>::default();
+        let hash = mempool.insert(transfer(1)).unwrap();
+
+        assert_eq!(
+            mempool.insert(transfer(1)),
+            Err(MempoolError::DuplicatedTransaction(hash))
+        );
+        assert_eq!(mempool.len(), 1);
+    }
+
+    #[test]
+    fn test_conflicting_nullifiers_are_rejected() {
+        let mut mempool = Mempool::<VestaConfig>::default();
+        mempool.insert(spending(&[1, 2], Fr::from(0u64))).unwrap();
+
+        assert_eq!(
+            mempool.insert(spending(&[2, 3], Fr::from(0u64))),
+            Err(MempoolError::NullifierConflict(Fr::from(2u64).to_string()))
+        );
+        // Mints spend no nullifier, so they never conflict
+        assert!(mempool.insert(MINT.1.clone()).is_ok());
+    }
+
+    #[test]
+    fn test_removal_releases_hash_and_nullifiers() {
+        let mut mempool = Mempool::<VestaConfig>::default();
+        let hash = mempool.insert(transfer(1)).unwrap();
+
+        assert_eq!(mempool.remove(&hash), Some(transfer(1)));
+        assert!(mempool.is_empty());
+        assert!(mempool.get(&hash).is_none());
+        assert_eq!(mempool.remove(&hash), None);
+
+        // Both the transaction and its nullifier can be submitted again
+        assert_eq!(mempool.insert(transfer(1)), Ok(hash));
+    }
+}
@@ -1,11 +1,17 @@
+pub mod mempool;
+pub mod subscription;
+pub mod transaction;
+
 use ark_ec::{
     pairing::Pairing,
     short_weierstrass::{Affine, Projective, SWCurveConfig},
     CurveGroup,
 };
 use ark_ff::PrimeField;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
 use common::crypto::poseidon::constants::PoseidonParams;
-use jf_plonk::nightfall::ipa_structs::{CommitKey, ProvingKey};
+use common::structs::CircuitType;
+use jf_plonk::nightfall::ipa_structs::{CommitKey, ProvingKey, VerifyingKey};
 use jf_primitives::rescue::RescueParameter;
 use jf_relation::gadgets::ecc::SWToTEConParam;
 use zk_macros::sequencer_bounds;
@@ -27,13 +33,39 @@ impl<V, VSW, P, SW> Clone for RollupCommitKeys<V, VSW, P, SW> {
     }
 }
 
+// Circuit types of the transactions in a base rollup, in order. The rollup
+// circuits depend on it, so each shape has its own rollup keys.
+pub type RollupShape = Vec<CircuitType>;
+
+// Transactions in a block are bounded by the depth 8 trees of commitments and
+// commitment roots it fills, a transaction having at most 4 commitments
+pub const MAX_BLOCK_TRANSACTIONS: usize = 64;
+
+// Number of merge levels of the largest block of base rollups of `shape`. A
+// pair of swap halves is always rolled up on its own.
+pub fn max_rollup_depth(shape: &RollupShape) -> usize {
+    if shape.contains(&CircuitType::Swap) {
+        return 0;
+    }
+    match MAX_BLOCK_TRANSACTIONS / shape.len().max(1) {
+        0 => 0,
+        n_base => n_base.ilog2() as usize,
+    }
+}
+
+// Each level of the aggregation tree verifies the proofs of the level below,
+// so its merge and bounce merge circuits differ from those of the other levels.
+// `merge_keys[i]` are the keys of the merges of level `i + 1` and
+// `bounce_merge_keys[i]` those of their bounces, the root merge isn't bounced.
 #[sequencer_bounds]
 #[derive(Debug)]
 pub struct RollupProvingKeys<V, VSW, P, SW> {
     pub base_proving_key: ProvingKey<P>,
+    pub base_verifying_key: VerifyingKey<P>,
     pub bounce_proving_key: ProvingKey<V>,
-    pub merge_proving_key: ProvingKey<P>,
-    pub bounce_merge_proving_key: ProvingKey<V>,
+    pub bounce_verifying_key: VerifyingKey<V>,
+    pub merge_keys: Vec<(ProvingKey<P>, VerifyingKey<P>)>,
+    pub bounce_merge_keys: Vec<(ProvingKey<V>, VerifyingKey<V>)>,
 }
 
 #[sequencer_bounds]
@@ -41,9 +73,44 @@ impl<V, VSW, P, SW> Clone for RollupProvingKeys<V, VSW, P, SW> {
     fn clone(&self) -> Self {
         Self {
             base_proving_key: self.base_proving_key.clone(),
+            base_verifying_key: self.base_verifying_key.clone(),
             bounce_proving_key: self.bounce_proving_key.clone(),
-            merge_proving_key: self.merge_proving_key.clone(),
-            bounce_merge_proving_key: self.bounce_merge_proving_key.clone(),
+            bounce_verifying_key: self.bounce_verifying_key.clone(),
+            merge_keys: self.merge_keys.clone(),
+            bounce_merge_keys: self.bounce_merge_keys.clone(),
         }
     }
 }
+
+#[sequencer_bounds]
+impl<V, VSW, P, SW> RollupProvingKeys<V, VSW, P, SW> {
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SerializationError> {
+        let mut bytes = Vec::new();
+        (
+            (
+                (&self.base_proving_key, &self.base_verifying_key),
+                (&self.bounce_proving_key, &self.bounce_verifying_key),
+            ),
+            (&self.merge_keys, &self.bounce_merge_keys),
+        )
+            .serialize_uncompressed(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    // Keys are only read back from a store that checked their content hash,
+    // so points are not validated again
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SerializationError> {
+        let (
+            ((base_proving_key, base_verifying_key), (bounce_proving_key, bounce_verifying_key)),
+            (merge_keys, bounce_merge_keys),
+        ) = CanonicalDeserialize::deserialize_uncompressed_unchecked(bytes)?;
+        Ok(Self {
+            base_proving_key,
+            base_verifying_key,
+            bounce_proving_key,
+            bounce_verifying_key,
+            merge_keys,
+            bounce_merge_keys,
+        })
+    }
+}
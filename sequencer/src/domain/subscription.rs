@@ -0,0 +1,39 @@
+use common::hash::to_hex;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "reason", content = "detail", rename_all = "snake_case")]
+pub enum SubscriptionError {
+    InvalidCallbackUrl(String),
+    AlreadySubscribed(String),
+    TooManySubscribers(usize),
+    UnknownSubscription(String),
+}
+
+// A client that is sent every new block at `callback_url`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Subscription {
+    pub id: String,
+    pub callback_url: String,
+}
+
+impl Subscription {
+    pub fn new(callback_url: String) -> Result<Self, SubscriptionError> {
+        let url = reqwest::Url::parse(&callback_url)
+            .map_err(|_| SubscriptionError::InvalidCallbackUrl(callback_url.clone()))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(SubscriptionError::InvalidCallbackUrl(callback_url));
+        }
+        Ok(Self {
+            id: subscription_id(),
+            callback_url,
+        })
+    }
+}
+
+// The id is the only credential needed to unsubscribe, so it is a random secret
+// handed out once to whoever subscribed
+pub fn subscription_id() -> String {
+    to_hex(&rand::thread_rng().gen::<[u8; 32]>())
+}
@@ -0,0 +1,55 @@
+use std::collections::{HashMap, VecDeque};
+
+pub use common::structs::TransactionStatus;
+
+pub const DEFAULT_REJECTED_CAPACITY: usize = 1024;
+
+// Position of an included transaction among the past transactions and the
+// block that included it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IncludedTransaction {
+    pub index: usize,
+    pub block_number: u64,
+}
+
+// Reasons the latest rejected transactions were refused, by transaction hash.
+// Only the most recent ones are kept, so a flood of invalid transactions can't
+// grow it without bound.
+#[derive(Debug, Clone)]
+pub struct RejectedTransactions {
+    capacity: usize,
+    reasons: HashMap<String, String>,
+    order: VecDeque<String>,
+}
+
+impl Default for RejectedTransactions {
+    fn default() -> Self {
+        Self::new(DEFAULT_REJECTED_CAPACITY)
+    }
+}
+
+impl RejectedTransactions {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            reasons: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    pub fn insert(&mut self, hash: String, reason: String) {
+        if self.reasons.insert(hash.clone(), reason).is_some() {
+            return;
+        }
+        self.order.push_back(hash);
+        while self.order.len() > self.capacity {
+            if let Some(evicted) = self.order.pop_front() {
+                self.reasons.remove(&evicted);
+            }
+        }
+    }
+
+    pub fn get(&self, hash: &str) -> Option<&String> {
+        self.reasons.get(hash)
+    }
+}
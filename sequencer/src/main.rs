@@ -1,13 +1,19 @@
-use common::services::notifier::HttpNotifier;
 use common::{configuration, telemetry};
 use curves::{pallas::PallasConfig, vesta::VestaConfig};
 use plonk_prover::client::ClientPlonkCircuit;
+use plonk_prover::setup::shared_setup;
 use sequencer::adapters::rest_api::sequencer_api::Application;
+use sequencer::domain::mempool::DEFAULT_MEMPOOL_CAPACITY;
 use sequencer::services::{
-    prover::in_mem_sequencer_prover::InMemProver, storage::in_mem_sequencer_storage::InMemStorage,
+    notifier::block_notifier::{configured_subscribers, BlockNotifier},
+    prover::in_mem_sequencer_prover::InMemProver,
+    storage::sequencer_storage::SequencerStorage,
 };
 use sequencer::services::{
-    prover::{generate_and_store_cks, generate_and_store_client_circuit_vks},
+    prover::{
+        generate_and_store_cks, generate_and_store_client_circuit_vks,
+        generate_and_store_rollup_pks, key_store::RollupKeyStore,
+    },
     storage::generate_and_store_vk_tree,
 };
 use std::sync::Arc;
@@ -21,19 +27,43 @@ fn main() {
         std::io::stdout,
     );
     log::trace!("Initializing");
-    let mut db: InMemStorage = InMemStorage::new();
+    let mut db = SequencerStorage::from_settings(
+        &configuration.sequencer.storage,
+        configuration
+            .sequencer
+            .mempool_capacity
+            .unwrap_or(DEFAULT_MEMPOOL_CAPACITY),
+    )
+    .expect("Failed to open sequencer storage");
     let mut prover = InMemProver::<VestaConfig, VestaConfig, PallasConfig, PallasConfig>::new();
-    let notifier = HttpNotifier::new(configuration.client);
+    let notifier = BlockNotifier::new(&configuration.sequencer);
 
     let client_circuit_info: Vec<
         Box<dyn ClientPlonkCircuit<PallasConfig, VestaConfig, VestaConfig>>,
     > = sequencer::utils::circuits::select_client_circuits_sequencer();
+    ark_std::println!("Deriving setup");
+    let vesta_srs =
+        shared_setup::<VestaConfig, VestaConfig>().expect("Failed to derive Vesta setup");
+    let pallas_srs =
+        shared_setup::<PallasConfig, PallasConfig>().expect("Failed to derive Pallas setup");
     ark_std::println!("Generating Keys");
-    let vks = generate_and_store_client_circuit_vks(&mut prover, &client_circuit_info);
+    let vks = generate_and_store_client_circuit_vks(&mut prover, &client_circuit_info, &vesta_srs);
     generate_and_store_vk_tree(&mut db, vks);
-    ark_std::println!("Generating srs_1");
-    generate_and_store_cks(&mut prover);
+    generate_and_store_cks(&mut prover, &vesta_srs, &pallas_srs);
     ark_std::println!("Ck ready");
+    let key_store = configuration
+        .sequencer
+        .key_store_path
+        .as_ref()
+        .map(|path| RollupKeyStore::open(path).expect("Failed to open rollup key store"));
+    generate_and_store_rollup_pks(
+        &mut prover,
+        &client_circuit_info,
+        &configuration.sequencer.rollup_shapes,
+        key_store.as_ref(),
+    )
+    .expect("Failed to generate rollup keys");
+    ark_std::println!("Rollup keys ready");
 
     let thread_safe_db = std::sync::Arc::new(tokio::sync::Mutex::new(db));
     let thread_safe_prover = std::sync::Arc::new(tokio::sync::Mutex::new(prover));
@@ -45,6 +75,14 @@ fn main() {
         .build()
         .unwrap();
     async_rt.block_on(async {
+        {
+            let mut notifier = thread_safe_notifier.lock().await;
+            for callback_url in configured_subscribers(&configuration) {
+                notifier
+                    .subscribe(callback_url)
+                    .expect("Invalid subscriber in configuration");
+            }
+        }
         let application = Application::build(
             thread_safe_db,
             thread_safe_prover,
@@ -9,28 +9,33 @@ use common::crypto::poseidon::constants::PoseidonParams;
 use jf_plonk::nightfall::ipa_structs::{Proof, VerifyingKey};
 use jf_primitives::rescue::RescueParameter;
 use jf_relation::{errors::CircuitError, gadgets::ecc::SWToTEConParam};
+use plonk_prover::rollup::block::{RollupAccumulators, RollupProof};
 use plonk_prover::rollup::circuits::client_input::ClientInput;
 use zk_macros::sequencer_bounds;
 
-use crate::domain::{RollupCommitKeys, RollupProvingKeys};
+use crate::domain::{RollupCommitKeys, RollupProvingKeys, RollupShape};
 use common::structs::CircuitType;
 
 #[sequencer_bounds]
 pub trait SequencerProver<V, VSW, P, SW> {
+    // `client_inputs` and `g_polys` hold one batch per base rollup
     #[allow(clippy::too_many_arguments)]
     fn rollup_proof(
-        client_inputs: Vec<ClientInput<V>>,
+        client_inputs: Vec<Vec<ClientInput<V>>>,
         global_vk_root: P::ScalarField,
         global_nullifier_root: P::ScalarField,
         global_nullifier_leaf_count: P::ScalarField,
         global_commitment_root: P::ScalarField,
-        g_polys: Vec<DensePolynomial<<V as Pairing>::ScalarField>>,
+        g_polys: Vec<Vec<DensePolynomial<<V as Pairing>::ScalarField>>>,
         commit_keys: RollupCommitKeys<V, VSW, P, SW>,
-        proving_keys: Option<RollupProvingKeys<V, VSW, P, SW>>,
-    ) -> Result<Proof<P>, CircuitError>;
+        proving_keys: RollupProvingKeys<V, VSW, P, SW>,
+    ) -> Result<(RollupProof<P, V>, VerifyingKey<P>, RollupAccumulators<P, V>), CircuitError>;
 
-    fn store_pks(&mut self, pks: RollupProvingKeys<V, VSW, P, SW>);
-    fn get_pks(&self) -> Option<RollupProvingKeys<V, VSW, P, SW>>;
+    fn verify(vk: VerifyingKey<V>, public_inputs: Vec<V::ScalarField>, proof: Proof<V>) -> bool;
+
+    fn store_pks(&mut self, shape: RollupShape, pks: RollupProvingKeys<V, VSW, P, SW>);
+    fn get_pks(&self, shape: &RollupShape) -> Option<RollupProvingKeys<V, VSW, P, SW>>;
+    fn get_rollup_shapes(&self) -> Vec<RollupShape>;
 
     fn store_vk(&mut self, circuit_type: CircuitType, vk_info: (VerifyingKey<V>, usize));
     fn get_vk(&self, circuit_type: CircuitType) -> Option<(VerifyingKey<V>, usize)>;
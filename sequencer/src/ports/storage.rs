@@ -1,3 +1,4 @@
+use crate::domain::{mempool::MempoolError, transaction::TransactionStatus};
 use ark_ec::{
     pairing::Pairing,
     short_weierstrass::{Affine, Projective, SWCurveConfig},
@@ -20,18 +21,57 @@ where
     P: Pairing,
     <<P as Pairing>::G1 as CurveGroup>::Config: SWCurveConfig,
 {
-    fn get_transaction(&self) -> Option<Transaction<P>>;
-    fn insert_transaction(&mut self, transaction: Transaction<P>);
+    // Pending or included transaction with the given hash
+    fn get_transaction(&self, hash: &str) -> Option<Transaction<P>>;
+    fn get_transaction_status(&self, hash: &str) -> Option<TransactionStatus>;
+    // Remembers why a transaction was refused admission to the mempool
+    fn reject_transaction(&mut self, hash: String, reason: String);
+    fn check_transaction(&self, transaction: &Transaction<P>) -> Result<(), MempoolError>;
+    fn insert_transaction(&mut self, transaction: Transaction<P>) -> Result<String, MempoolError>;
+    // Drops a pending transaction from the mempool and rejects it
+    fn evict_transaction(&mut self, hash: &str, reason: String) -> Option<Transaction<P>>;
+    // Mempool transactions, oldest first
     fn get_mempool_transactions(&self) -> Vec<Transaction<P>>;
-    fn get_block_transaction(&self) -> Vec<Transaction<P>>;
+    fn get_mempool_size(&self) -> usize;
+    // Transactions included in the block, in inclusion order
+    fn get_block_transactions(&self, block_number: u64) -> Vec<Transaction<P>>;
     fn get_all_transactions(&self) -> Vec<Transaction<P>>;
-    fn flush_mempool_transactions(&mut self);
+    // Moves the given transactions out of the mempool once they are in a block
+    fn flush_mempool_transactions(&mut self, block_number: u64, transactions: &[Transaction<P>]);
 }
 
 pub trait BlockStorage<F: PrimeField> {
     fn get_block(&self, blocknumber: u64) -> Option<Block<F>>;
     fn insert_block(&mut self, block: Block<F>);
     fn get_block_count(&self) -> u64;
+
+    // Stored blocks numbered from `from` up to, but excluding, `to`
+    fn get_blocks(&self, from: u64, to: u64) -> Vec<Block<F>> {
+        (from..to.min(self.get_block_count()))
+            .filter_map(|n| self.get_block(n))
+            .collect()
+    }
+
+    // Whether some block published this commitment root. Checked for every
+    // submitted transaction, so storages index the roots of their blocks.
+    fn has_commitment_root(&self, root: F) -> bool;
+}
+
+// Global trees as a block leaves them. They are staged while the block is
+// built and only applied once its rollup proof succeeded and the block is
+// stored, so a failed build leaves the global state untouched.
+pub struct StateTransition<C, N> {
+    pub commitment_tree: C,
+    pub nullifier_tree: N,
+}
+
+// Roots of the global trees as of the last stored block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GlobalRoots<F> {
+    pub commitment_root: F,
+    pub nullifier_root: F,
+    pub nullifier_leaf_count: u64,
+    pub vk_root: F,
 }
 
 pub trait GlobalStateStorage {
@@ -41,8 +81,53 @@ pub trait GlobalStateStorage {
     fn get_global_commitment_tree(&self) -> Self::CommitmentTree;
     fn store_global_commitment_tree(&mut self, new_tree: Self::CommitmentTree);
     fn get_global_nullifier_tree(&self) -> Self::NullifierTree;
+    fn store_global_nullifier_tree(&mut self, new_tree: Self::NullifierTree);
     fn get_vk_tree(&self) -> Self::VkTree;
     fn store_vk_tree(&mut self, vk_tree: Self::VkTree);
+    fn get_global_roots(&self) -> GlobalRoots<<Self::CommitmentTree as AppendTree<8>>::F>;
+
+    // Whether a stored block spent the nullifier. Storages that can query the
+    // nullifier tree in place should avoid the copy.
+    fn is_nullifier_spent(
+        &self,
+        nullifier: <Self::NullifierTree as MembershipTree<32>>::Field,
+    ) -> bool {
+        self.get_global_nullifier_tree()
+            .non_membership_witness(nullifier)
+            .is_none()
+    }
+
+    // Applies every tree a block changed in one step
+    fn apply_state_transition(
+        &mut self,
+        transition: StateTransition<Self::CommitmentTree, Self::NullifierTree>,
+    ) {
+        self.store_global_commitment_tree(transition.commitment_tree);
+        self.store_global_nullifier_tree(transition.nullifier_tree);
+    }
+}
+
+// Stores a built block together with everything it changed: the global trees it
+// staged and the transactions it moved out of the mempool. Durable storages
+// apply it in a single write, so a restart never sees half a block.
+pub trait BlockCommitStorage<P>:
+    TransactionStorage<P> + BlockStorage<P::ScalarField> + GlobalStateStorage
+where
+    P: Pairing,
+    <<P as Pairing>::G1 as CurveGroup>::Config: SWCurveConfig,
+{
+    fn commit_block(
+        &mut self,
+        block: Block<P::ScalarField>,
+        transition: StateTransition<Self::CommitmentTree, Self::NullifierTree>,
+        transactions: &[Transaction<P>],
+    ) -> Option<()> {
+        let block_number = block.block_number;
+        self.insert_block(block);
+        self.apply_state_transition(transition);
+        self.flush_mempool_transactions(block_number, transactions);
+        Some(())
+    }
 }
 
 #[client_bounds]
@@ -1,2 +1,3 @@
+pub mod notifier;
 pub mod prover;
 pub mod storage;
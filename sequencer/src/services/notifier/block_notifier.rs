@@ -0,0 +1,239 @@
+use crate::domain::subscription::{Subscription, SubscriptionError};
+use ark_ff::PrimeField;
+use async_trait::async_trait;
+use common::configuration::{ApplicationSettings, NotificationSettings, Settings};
+use common::ports::notifier::Notifier;
+use common::structs::Block;
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tracing_log::log;
+
+#[derive(Clone, Debug)]
+struct Subscriber<F: PrimeField> {
+    subscription: Subscription,
+    queue: mpsc::Sender<Block<F>>,
+}
+
+// Sends every new block to all the subscribed clients. Each subscriber is served
+// by its own task from a bounded queue, so a slow or unreachable client delays
+// neither block production nor the other subscribers. Blocks that don't fit in
+// a full queue are dropped for that subscriber, which catches up by syncing
+// them from the sequencer.
+#[derive(Clone, Debug)]
+pub struct BlockNotifier<F: PrimeField> {
+    settings: NotificationSettings,
+    timeout: Duration,
+    subscribers: HashMap<String, Subscriber<F>>,
+}
+
+impl<F: PrimeField> BlockNotifier<F> {
+    pub fn new(settings: &ApplicationSettings) -> Self {
+        Self {
+            settings: settings.notifications.clone(),
+            timeout: settings.timeout(),
+            subscribers: HashMap::new(),
+        }
+    }
+
+    // Only the callback urls, the ids are kept secret
+    pub fn callback_urls(&self) -> Vec<String> {
+        self.subscribers
+            .values()
+            .map(|subscriber| subscriber.subscription.callback_url.clone())
+            .collect()
+    }
+
+    // Starts the delivery task of a new subscriber. Must be called from within
+    // the tokio runtime.
+    pub fn subscribe(&mut self, callback_url: String) -> Result<Subscription, SubscriptionError> {
+        let subscription = Subscription::new(callback_url)?;
+        if self
+            .subscribers
+            .values()
+            .any(|subscriber| subscriber.subscription.callback_url == subscription.callback_url)
+        {
+            return Err(SubscriptionError::AlreadySubscribed(
+                subscription.callback_url,
+            ));
+        }
+        if self.subscribers.len() >= self.settings.max_subscribers {
+            return Err(SubscriptionError::TooManySubscribers(
+                self.settings.max_subscribers,
+            ));
+        }
+        let (queue, blocks) = mpsc::channel(self.settings.queue_capacity.max(1));
+        tokio::spawn(deliver_blocks(
+            subscription.callback_url.clone(),
+            blocks,
+            self.settings.clone(),
+            self.timeout,
+        ));
+        log::info!("Subscribed {}", subscription.callback_url);
+        self.subscribers.insert(
+            subscription.id.clone(),
+            Subscriber {
+                subscription: subscription.clone(),
+                queue,
+            },
+        );
+        Ok(subscription)
+    }
+
+    // Blocks already queued for the subscriber are still delivered
+    pub fn unsubscribe(&mut self, id: &str) -> Result<Subscription, SubscriptionError> {
+        let subscriber = self
+            .subscribers
+            .remove(id)
+            .ok_or_else(|| SubscriptionError::UnknownSubscription(id.to_string()))?;
+        log::info!("Unsubscribed {}", subscriber.subscription.callback_url);
+        Ok(subscriber.subscription)
+    }
+}
+
+// The subscribers of the configuration or, when there are none, the configured
+// client, so that blocks reach it without it having to subscribe
+pub fn configured_subscribers(settings: &Settings) -> Vec<String> {
+    let subscribers = &settings.sequencer.notifications.subscribers;
+    if subscribers.is_empty() {
+        vec![format!("{}/block", settings.client.base_url)]
+    } else {
+        subscribers.clone()
+    }
+}
+
+#[async_trait]
+impl<F> Notifier for BlockNotifier<F>
+where
+    F: PrimeField,
+{
+    type Info = Block<F>;
+
+    #[tracing::instrument(name = "Send notification", skip(self, block))]
+    async fn send_info(&self, block: Block<F>) -> anyhow::Result<()> {
+        for subscriber in self.subscribers.values() {
+            match subscriber.queue.try_send(block.clone()) {
+                Ok(()) => {}
+                Err(TrySendError::Full(_)) => log::warn!(
+                    "Notification queue of {} is full, dropping block {}",
+                    subscriber.subscription.callback_url,
+                    block.block_number
+                ),
+                Err(TrySendError::Closed(_)) => log::error!(
+                    "Notification task of {} stopped, dropping block {}",
+                    subscriber.subscription.callback_url,
+                    block.block_number
+                ),
+            }
+        }
+        Ok(())
+    }
+}
+
+async fn deliver_blocks<F: PrimeField>(
+    callback_url: String,
+    mut blocks: mpsc::Receiver<Block<F>>,
+    settings: NotificationSettings,
+    timeout: Duration,
+) {
+    let client = match reqwest::Client::builder().timeout(timeout).build() {
+        Ok(client) => client,
+        Err(e) => {
+            log::error!("Unable to notify {}: {}", callback_url, e);
+            return;
+        }
+    };
+    while let Some(block) = blocks.recv().await {
+        let mut backoff = settings.retry_backoff();
+        for attempt in 0..=settings.max_retries {
+            match post_block(&client, &callback_url, &block).await {
+                Ok(()) => break,
+                Err(e) if attempt < settings.max_retries && is_retryable(&e) => {
+                    log::warn!(
+                        "Delivery of block {} to {} failed, retrying in {:?}: {}",
+                        block.block_number,
+                        callback_url,
+                        backoff,
+                        e
+                    );
+                    tokio::time::sleep(backoff).await;
+                    backoff *= 2;
+                }
+                Err(e) => {
+                    log::error!(
+                        "Unable to deliver block {} to {}: {}",
+                        block.block_number,
+                        callback_url,
+                        e
+                    );
+                    break;
+                }
+            }
+        }
+    }
+    log::debug!("Stopped notifying {}", callback_url);
+}
+
+async fn post_block<F: PrimeField>(
+    client: &reqwest::Client,
+    callback_url: &str,
+    block: &Block<F>,
+) -> Result<(), reqwest::Error> {
+    client
+        .post(callback_url)
+        .json(block)
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+// Clients answer with a client error to blocks they can't apply, sending them
+// again won't help
+fn is_retryable(error: &reqwest::Error) -> bool {
+    !matches!(error.status(), Some(status) if status.is_client_error())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::configuration::get_configuration;
+    use curves::vesta::Fr;
+
+    #[test]
+    fn test_configured_client_is_subscribed_by_default() {
+        let mut settings = get_configuration().unwrap();
+        settings.client.base_url = "http://127.0.0.1:8000".to_string();
+        settings.sequencer.notifications.subscribers = vec![];
+        assert_eq!(
+            configured_subscribers(&settings),
+            vec!["http://127.0.0.1:8000/block".to_string()]
+        );
+
+        settings.sequencer.notifications.subscribers = vec!["http://other/block".to_string()];
+        assert_eq!(
+            configured_subscribers(&settings),
+            vec!["http://other/block".to_string()]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_subscription_ids_are_secret() {
+        let mut notifier = BlockNotifier::<Fr>::new(&get_configuration().unwrap().sequencer);
+        let callback_url = "http://127.0.0.1:8000/block".to_string();
+
+        let subscription = notifier.subscribe(callback_url.clone()).unwrap();
+        assert_ne!(
+            subscription.id,
+            Subscription::new(callback_url.clone()).unwrap().id
+        );
+        assert_eq!(
+            notifier.subscribe(callback_url.clone()),
+            Err(SubscriptionError::AlreadySubscribed(callback_url.clone()))
+        );
+        assert_eq!(notifier.callback_urls(), vec![callback_url]);
+
+        assert!(notifier.unsubscribe(&subscription.id).is_ok());
+        assert!(notifier.callback_urls().is_empty());
+    }
+}
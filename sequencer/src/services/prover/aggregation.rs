@@ -0,0 +1,541 @@
+use crate::domain::{RollupCommitKeys, RollupProvingKeys};
+use ark_ec::{
+    pairing::Pairing,
+    short_weierstrass::{Affine, Projective, SWCurveConfig},
+    CurveGroup,
+};
+use ark_ff::{PrimeField, Zero};
+use ark_poly::univariate::DensePolynomial;
+use common::crypto::poseidon::constants::PoseidonParams;
+use jf_plonk::nightfall::ipa_structs::{Proof, ProvingKey, VerifyingKey};
+use jf_plonk::{
+    nightfall::PlonkIpaSnark, proof_system::UniversalSNARK, transcript::RescueTranscript,
+};
+use jf_primitives::rescue::RescueParameter;
+use jf_relation::errors::CircuitError;
+use jf_relation::gadgets::ecc::{short_weierstrass::SWPoint, SWToTEConParam};
+use jf_relation::gadgets::EmulationConfig;
+use jf_relation::{Arithmetization, Circuit, PlonkCircuit};
+use jf_utils::field_switching;
+use plonk_prover::rollup::block::{verifying_key_hash, RollupAccumulators, RollupProof};
+use plonk_prover::rollup::circuits::{
+    base::base_rollup_circuit,
+    bounce::bounce_circuit,
+    bounce_merge::bounce_merge_circuit,
+    client_input::ClientInput,
+    merge::merge_circuit,
+    structs::{AccInstance, GlobalPublicInputs, SubTrees},
+};
+use plonk_prover::setup::universal_setup;
+use rand::SeedableRng;
+use rand_chacha::ChaChaRng;
+use std::time::Instant;
+use tracing_log::log;
+use zk_macros::sequencer_bounds;
+
+// A base rollup, or a merge of two bounced proofs, over Pallas. `level` is 0
+// for a base rollup and the number of merges below and including a merge.
+struct PallasNode<P, V>
+where
+    P: Pairing,
+    <P::G1 as CurveGroup>::Config: SWCurveConfig,
+    V: Pairing<BaseField = P::ScalarField>,
+{
+    level: usize,
+    rollup: RollupProof<P, V>,
+    vk: VerifyingKey<P>,
+    g_poly: DensePolynomial<P::ScalarField>,
+    // pi_star of the Vesta accumulator in `rollup.instance`
+    pi_star: DensePolynomial<V::ScalarField>,
+    // pi_stars of the Pallas accumulators in `rollup.passthrough`
+    passthrough_pi_stars: Vec<DensePolynomial<P::ScalarField>>,
+}
+
+// A bounce of a base rollup, or a bounce merge of a merge, over Vesta. Both
+// have the same public inputs so either can be merged.
+#[derive(Clone)]
+struct VestaNode<P: Pairing, V: Pairing> {
+    // Level of the bounced proof
+    level: usize,
+    proof: Proof<V>,
+    vk: VerifyingKey<V>,
+    g_poly: DensePolynomial<V::ScalarField>,
+    subtrees: SubTrees<P::ScalarField>,
+    // Vesta accumulator of the bounced proof, accumulated by the next merge
+    passthrough: AccInstance<V>,
+    passthrough_pi_star: DensePolynomial<V::ScalarField>,
+    // Pallas accumulator of this proof, accumulated by the next bounce merge
+    instance: AccInstance<P>,
+    pi_star: DensePolynomial<P::ScalarField>,
+}
+
+// Keys of each rollup circuit, taken from the keys stored for the shape of a
+// block, with a merge and a bounce merge per level of the aggregation tree.
+// When generating the keys of a new shape they start empty and each circuit is
+// preprocessed the first time it is proven.
+#[allow(clippy::type_complexity)]
+struct RollupKeys<P: Pairing, V: Pairing> {
+    base: Option<(ProvingKey<P>, VerifyingKey<P>)>,
+    bounce: Option<(ProvingKey<V>, VerifyingKey<V>)>,
+    merge: Vec<Option<(ProvingKey<P>, VerifyingKey<P>)>>,
+    bounce_merge: Vec<Option<(ProvingKey<V>, VerifyingKey<V>)>>,
+}
+
+// Proves `client_inputs`, one batch per base rollup, with a binary tree of
+// rollup proofs and returns its root. A single batch is proven by a base rollup
+// alone. Otherwise each level of the tree bounces every proof to Vesta and
+// merges them in pairs back on Pallas, so the number of batches must be a power
+// of two, at most 2 to the number of merge levels `proving_keys` has keys for,
+// and the root is a merge. Every base rollup is proven against the
+// global state before the block, which the merge circuit requires both of its
+// proofs to share. The polynomials of the accumulators of the root are
+// returned with it.
+#[sequencer_bounds]
+#[allow(clippy::too_many_arguments)]
+pub fn rollup_proof<V, VSW, P, SW>(
+    client_inputs: Vec<Vec<ClientInput<V>>>,
+    global_vk_root: P::ScalarField,
+    global_nullifier_root: P::ScalarField,
+    global_nullifier_leaf_count: P::ScalarField,
+    global_commitment_root: P::ScalarField,
+    g_polys: Vec<Vec<DensePolynomial<V::ScalarField>>>,
+    commit_keys: &RollupCommitKeys<V, VSW, P, SW>,
+    proving_keys: RollupProvingKeys<V, VSW, P, SW>,
+) -> Result<(RollupProof<P, V>, VerifyingKey<P>, RollupAccumulators<P, V>), CircuitError>
+where
+    <P as Pairing>::ScalarField: EmulationConfig<<P as Pairing>::BaseField>,
+{
+    let n_base = client_inputs.len();
+    if !n_base.is_power_of_two() || n_base != g_polys.len() {
+        return Err(CircuitError::ParameterError(format!(
+            "Cannot aggregate {n_base} base rollups"
+        )));
+    }
+    if n_base.ilog2() as usize > proving_keys.merge_keys.len() {
+        return Err(CircuitError::ParameterError(format!(
+            "No rollup keys to merge {n_base} base rollups"
+        )));
+    }
+    log::debug!("Rolling up {n_base} base rollups");
+    let mut keys = RollupKeys {
+        base: Some((
+            proving_keys.base_proving_key,
+            proving_keys.base_verifying_key,
+        )),
+        bounce: Some((
+            proving_keys.bounce_proving_key,
+            proving_keys.bounce_verifying_key,
+        )),
+        merge: proving_keys.merge_keys.into_iter().map(Some).collect(),
+        bounce_merge: proving_keys
+            .bounce_merge_keys
+            .into_iter()
+            .map(Some)
+            .collect(),
+    };
+
+    let mut level = client_inputs
+        .into_iter()
+        .zip(g_polys)
+        .map(|(client_inputs, g_polys)| {
+            base::<V, VSW, P, SW>(
+                client_inputs,
+                global_vk_root,
+                global_nullifier_root,
+                global_nullifier_leaf_count,
+                global_commitment_root,
+                g_polys,
+                commit_keys,
+                &mut keys,
+            )
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let global_state = level[0].rollup.global_public_inputs.clone();
+
+    while level.len() > 1 {
+        let mut bounced = level
+            .into_iter()
+            .map(|node| bounce::<V, VSW, P, SW>(node, &global_state, commit_keys, &mut keys))
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter();
+        level = Vec::new();
+        while let (Some(left), Some(right)) = (bounced.next(), bounced.next()) {
+            level.push(merge::<V, VSW, P, SW>(
+                left,
+                right,
+                &global_state,
+                commit_keys,
+                &mut keys,
+            )?);
+        }
+    }
+
+    let root = level.remove(0);
+    let accumulators = RollupAccumulators {
+        instance: root.pi_star,
+        passthrough: root.passthrough_pi_stars,
+    };
+    Ok((root.rollup, root.vk, accumulators))
+}
+
+// Generates the keys of every rollup circuit for blocks of up to 2^`depth`
+// base rollups with the shape of `client_inputs`. Proves the base rollup, then
+// at each level bounces the proof and merges the bounce with itself,
+// preprocessing each circuit on the way.
+#[sequencer_bounds]
+#[allow(clippy::too_many_arguments)]
+pub fn rollup_keys<V, VSW, P, SW>(
+    depth: usize,
+    client_inputs: Vec<ClientInput<V>>,
+    global_vk_root: P::ScalarField,
+    global_nullifier_root: P::ScalarField,
+    global_nullifier_leaf_count: P::ScalarField,
+    global_commitment_root: P::ScalarField,
+    g_polys: Vec<DensePolynomial<V::ScalarField>>,
+    commit_keys: &RollupCommitKeys<V, VSW, P, SW>,
+) -> Result<RollupProvingKeys<V, VSW, P, SW>, CircuitError>
+where
+    <P as Pairing>::ScalarField: EmulationConfig<<P as Pairing>::BaseField>,
+{
+    let mut keys = RollupKeys {
+        base: None,
+        bounce: None,
+        merge: (0..depth).map(|_| None).collect(),
+        bounce_merge: (1..depth).map(|_| None).collect(),
+    };
+    let mut node = base::<V, VSW, P, SW>(
+        client_inputs,
+        global_vk_root,
+        global_nullifier_root,
+        global_nullifier_leaf_count,
+        global_commitment_root,
+        g_polys,
+        commit_keys,
+        &mut keys,
+    )?;
+    let global_state = node.rollup.global_public_inputs.clone();
+    for _ in 0..depth {
+        let bounced = bounce::<V, VSW, P, SW>(node, &global_state, commit_keys, &mut keys)?;
+        node = merge::<V, VSW, P, SW>(
+            bounced.clone(),
+            bounced,
+            &global_state,
+            commit_keys,
+            &mut keys,
+        )?;
+    }
+
+    let not_preprocessed =
+        || CircuitError::ParameterError("Not every rollup circuit was preprocessed".to_string());
+    let (base_proving_key, base_verifying_key) = keys.base.ok_or_else(not_preprocessed)?;
+    let (bounce_proving_key, bounce_verifying_key) = keys.bounce.ok_or_else(not_preprocessed)?;
+    Ok(RollupProvingKeys {
+        base_proving_key,
+        base_verifying_key,
+        bounce_proving_key,
+        bounce_verifying_key,
+        merge_keys: keys
+            .merge
+            .into_iter()
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(not_preprocessed)?,
+        bounce_merge_keys: keys
+            .bounce_merge
+            .into_iter()
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(not_preprocessed)?,
+    })
+}
+
+#[sequencer_bounds]
+#[allow(clippy::too_many_arguments)]
+fn base<V, VSW, P, SW>(
+    client_inputs: Vec<ClientInput<V>>,
+    global_vk_root: P::ScalarField,
+    global_nullifier_root: P::ScalarField,
+    global_nullifier_leaf_count: P::ScalarField,
+    global_commitment_root: P::ScalarField,
+    g_polys: Vec<DensePolynomial<V::ScalarField>>,
+    commit_keys: &RollupCommitKeys<V, VSW, P, SW>,
+    keys: &mut RollupKeys<P, V>,
+) -> Result<PallasNode<P, V>, CircuitError> {
+    let (circuit, pi_star) = base_rollup_circuit::<V, P, 8>(
+        client_inputs,
+        global_vk_root,
+        global_nullifier_root,
+        global_nullifier_leaf_count,
+        global_commitment_root,
+        g_polys,
+        commit_keys.vesta_commit_key.clone(),
+    )?;
+    let (proof, g_poly, vk) = prove::<P, SW>(&circuit, &mut keys.base)?;
+    Ok(PallasNode {
+        level: 0,
+        rollup: RollupProof::new(proof, &circuit.public_input()?)?,
+        vk,
+        g_poly,
+        pi_star,
+        passthrough_pi_stars: vec![],
+    })
+}
+
+// Moves a Pallas proof to Vesta: a base rollup with a bounce, a merge with a
+// bounce merge
+#[sequencer_bounds]
+fn bounce<V, VSW, P, SW>(
+    node: PallasNode<P, V>,
+    global_state: &GlobalPublicInputs<P::ScalarField>,
+    commit_keys: &RollupCommitKeys<V, VSW, P, SW>,
+    keys: &mut RollupKeys<P, V>,
+) -> Result<VestaNode<P, V>, CircuitError>
+where
+    <P as Pairing>::ScalarField: EmulationConfig<<P as Pairing>::BaseField>,
+{
+    let PallasNode {
+        level,
+        rollup,
+        vk,
+        g_poly,
+        pi_star,
+        passthrough_pi_stars,
+    } = node;
+
+    let (circuit, public_outputs, instance_pi_star, slot) = if rollup.passthrough.is_empty() {
+        let (circuit, public_outputs) = bounce_circuit::<P, V>(
+            vk,
+            global_state.clone(),
+            rollup.subtrees.clone(),
+            rollup.proof,
+            rollup.instance.clone(),
+        )?;
+        // Bounce only partially verifies the base proof, so its g_poly is the pi_star
+        (circuit, public_outputs, g_poly, &mut keys.bounce)
+    } else {
+        let (circuit, public_outputs, bounce_merge_pi_star) = bounce_merge_circuit::<P, V>(
+            vk,
+            global_state.clone(),
+            rollup.subtrees.clone(),
+            rollup.proof,
+            g_poly,
+            rollup.instance.clone(),
+            commit_keys.pallas_commit_key.clone(),
+            [rollup.passthrough[0].clone(), rollup.passthrough[1].clone()],
+            [
+                passthrough_pi_stars[0].clone(),
+                passthrough_pi_stars[1].clone(),
+            ],
+        )?;
+        let slot = level
+            .checked_sub(1)
+            .and_then(|i| keys.bounce_merge.get_mut(i))
+            .ok_or_else(|| {
+                CircuitError::ParameterError(format!("No bounce merge keys for level {level}"))
+            })?;
+        (circuit, public_outputs, bounce_merge_pi_star, slot)
+    };
+    let (proof, g_poly, vk) = prove::<V, VSW>(&circuit, slot)?;
+
+    // The Pallas accumulator is the last 4 public outputs
+    let n = public_outputs.len();
+    let instance = AccInstance {
+        comm: SWPoint(
+            field_switching(&public_outputs[n - 4]),
+            field_switching(&public_outputs[n - 3]),
+            public_outputs[n - 4].is_zero(),
+        ),
+        eval: public_outputs[n - 2],
+        eval_point: public_outputs[n - 1],
+    };
+    Ok(VestaNode {
+        level,
+        proof,
+        vk,
+        g_poly,
+        subtrees: rollup.subtrees,
+        passthrough: rollup.instance,
+        passthrough_pi_star: pi_star,
+        instance,
+        pi_star: instance_pi_star,
+    })
+}
+
+#[sequencer_bounds]
+fn merge<V, VSW, P, SW>(
+    left: VestaNode<P, V>,
+    right: VestaNode<P, V>,
+    global_state: &GlobalPublicInputs<P::ScalarField>,
+    commit_keys: &RollupCommitKeys<V, VSW, P, SW>,
+    keys: &mut RollupKeys<P, V>,
+) -> Result<PallasNode<P, V>, CircuitError>
+where
+    <P as Pairing>::ScalarField: EmulationConfig<<P as Pairing>::BaseField>,
+{
+    // The merge circuit verifies both proofs with a single verifying key
+    if verifying_key_hash(&left.vk)? != verifying_key_hash(&right.vk)? {
+        return Err(CircuitError::ParameterError(
+            "Merged proofs were not generated by the same circuit".to_string(),
+        ));
+    }
+    let (circuit, pi_star) = merge_circuit::<V, P>(
+        left.vk,
+        global_state.clone(),
+        [left.subtrees, right.subtrees],
+        [left.proof, right.proof],
+        [left.g_poly, right.g_poly],
+        [left.instance.clone(), right.instance.clone()],
+        commit_keys.vesta_commit_key.clone(),
+        [left.passthrough, right.passthrough],
+        [left.passthrough_pi_star, right.passthrough_pi_star],
+    )?;
+    let level = left.level + 1;
+    let slot = keys
+        .merge
+        .get_mut(left.level)
+        .ok_or_else(|| CircuitError::ParameterError(format!("No merge keys for level {level}")))?;
+    let (proof, g_poly, vk) = prove::<P, SW>(&circuit, slot)?;
+
+    Ok(PallasNode {
+        level,
+        rollup: RollupProof::from_merge(
+            proof,
+            &circuit.public_input()?,
+            [left.instance, right.instance],
+        )?,
+        vk,
+        g_poly,
+        pi_star,
+        passthrough_pi_stars: vec![left.pi_star, right.pi_star],
+    })
+}
+
+// Proves `circuit` with the keys in `slot`, preprocessing them first when
+// generating keys
+#[allow(clippy::type_complexity)]
+fn prove<E, S>(
+    circuit: &PlonkCircuit<E::ScalarField>,
+    slot: &mut Option<(ProvingKey<E>, VerifyingKey<E>)>,
+) -> Result<(Proof<E>, DensePolynomial<E::ScalarField>, VerifyingKey<E>), CircuitError>
+where
+    E: Pairing<G1Affine = Affine<S>, G1 = Projective<S>>,
+    <E as Pairing>::BaseField: RescueParameter + SWToTEConParam,
+    S: SWCurveConfig<BaseField = E::BaseField>,
+{
+    let mut rng = ChaChaRng::from_entropy();
+    log::debug!("Constraint count: {}", circuit.num_gates());
+
+    if slot.is_none() {
+        let now = Instant::now();
+        let srs = universal_setup::<E, S>(circuit.srs_size()?)?;
+        *slot = Some(PlonkIpaSnark::<E>::preprocess(&srs, circuit)?);
+        log::debug!("Preprocess done: {:?}", now.elapsed());
+    }
+    let (pk, vk) = slot.as_ref().expect("Keys are preprocessed when missing");
+
+    let now = Instant::now();
+    let (proof, g_poly, _) =
+        PlonkIpaSnark::<E>::prove_for_partial::<_, _, RescueTranscript<<E as Pairing>::BaseField>>(
+            &mut rng, circuit, pk, None,
+        )?;
+    log::debug!("Proof done: {:?}", now.elapsed());
+    Ok((proof, g_poly, vk.clone()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_std::UniformRand;
+    use curves::pallas::{Fq, Fr, PallasConfig};
+    use curves::vesta::VestaConfig;
+    use plonk_prover::client::circuits::transfer::TransferCircuit;
+    use plonk_prover::utils::bench::{
+        base::{build_client_inputs, build_commit_keys},
+        tree::tree_generator_from_client_inputs,
+    };
+    use trees::{non_membership_tree::IndexedMerkleTree, AppendTree};
+
+    // Proves a block of `n_base` base rollups of two transfers each
+    fn block_with_spends(n_base: usize) {
+        let circuit = TransferCircuit::<1, 1, 8>::new();
+        let token_id = Some(Fq::rand(&mut ChaChaRng::from_entropy()));
+        let mut client_inputs = vec![];
+        let mut global_comm_roots = vec![];
+        let mut g_polys = vec![];
+        // Every base is proven against the nullifier tree before the block, so
+        // each batch is witnessed against it
+        for _ in 0..n_base {
+            let mut batch_nullifier_tree = IndexedMerkleTree::<Fr, 32>::new();
+            for _ in 0..2 {
+                build_client_inputs(
+                    &mut client_inputs,
+                    &mut batch_nullifier_tree,
+                    &mut global_comm_roots,
+                    &mut g_polys,
+                    &circuit,
+                    token_id,
+                )
+                .unwrap();
+            }
+        }
+        let zk_trees =
+            tree_generator_from_client_inputs::<8>(&mut client_inputs, &global_comm_roots).unwrap();
+        let (vesta_commit_key, pallas_commit_key) = build_commit_keys().unwrap();
+        let commit_keys = RollupCommitKeys {
+            pallas_commit_key,
+            vesta_commit_key,
+        };
+        let nullifier_tree = IndexedMerkleTree::<Fr, 32>::new();
+        let batches = client_inputs
+            .chunks(2)
+            .map(|batch| batch.to_vec())
+            .collect::<Vec<_>>();
+        let g_polys = g_polys
+            .chunks(2)
+            .map(|batch| batch.to_vec())
+            .collect::<Vec<_>>();
+
+        let proving_keys = rollup_keys::<VestaConfig, VestaConfig, PallasConfig, PallasConfig>(
+            n_base.ilog2() as usize,
+            batches[0].clone(),
+            zk_trees.vk_tree.root(),
+            nullifier_tree.root(),
+            nullifier_tree.leaf_count().into(),
+            zk_trees.global_root_tree.root(),
+            g_polys[0].clone(),
+            &commit_keys,
+        )
+        .unwrap();
+        let (rollup, vk, _) = rollup_proof::<VestaConfig, VestaConfig, PallasConfig, PallasConfig>(
+            batches,
+            zk_trees.vk_tree.root(),
+            nullifier_tree.root(),
+            nullifier_tree.leaf_count().into(),
+            zk_trees.global_root_tree.root(),
+            g_polys,
+            &commit_keys,
+            proving_keys,
+        )
+        .unwrap();
+
+        assert_eq!(
+            rollup.circuit_type(),
+            common::structs::CircuitType::MergeRollup
+        );
+        PlonkIpaSnark::<PallasConfig>::verify::<RescueTranscript<Fq>>(
+            &vk,
+            &rollup.public_inputs(),
+            &rollup.proof,
+            None,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_two_base_block_with_spends() {
+        block_with_spends(2);
+    }
+
+    #[test]
+    fn test_four_base_block_with_spends() {
+        block_with_spends(4);
+    }
+}
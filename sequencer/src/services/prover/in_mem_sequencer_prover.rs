@@ -1,5 +1,6 @@
+use super::aggregation;
 use crate::{
-    domain::{RollupCommitKeys, RollupProvingKeys},
+    domain::{RollupCommitKeys, RollupProvingKeys, RollupShape},
     ports::prover::SequencerProver,
 };
 use ark_ec::{
@@ -13,24 +14,19 @@ use common::crypto::poseidon::constants::PoseidonParams;
 use common::structs::CircuitType;
 use jf_plonk::nightfall::ipa_structs::Proof;
 use jf_plonk::nightfall::ipa_structs::VerifyingKey;
-use jf_plonk::{
-    nightfall::PlonkIpaSnark, proof_system::UniversalSNARK, transcript::RescueTranscript,
-};
+use jf_plonk::{nightfall::PlonkIpaSnark, transcript::RescueTranscript};
 use jf_primitives::rescue::RescueParameter;
 use jf_relation::errors::CircuitError;
-use jf_relation::gadgets::ecc::SWToTEConParam;
-use jf_relation::{Arithmetization, Circuit};
-use plonk_prover::rollup::circuits::base::base_rollup_circuit;
+use jf_relation::gadgets::{ecc::SWToTEConParam, EmulationConfig};
+use plonk_prover::rollup::block::{RollupAccumulators, RollupProof};
 use plonk_prover::rollup::circuits::client_input::ClientInput;
-use rand::SeedableRng;
-use rand_chacha::ChaChaRng;
 use std::{collections::HashMap, time::Instant};
 use tracing_log::log;
 use zk_macros::sequencer_bounds;
 
 #[sequencer_bounds]
 pub struct InMemProver<V, VSW, P, SW> {
-    pub proving_key_store: Option<RollupProvingKeys<V, VSW, P, SW>>,
+    pub proving_key_store: HashMap<RollupShape, RollupProvingKeys<V, VSW, P, SW>>,
     pub commit_key_store: Option<RollupCommitKeys<V, VSW, P, SW>>,
     pub verifying_key_store: HashMap<CircuitType, (VerifyingKey<V>, usize)>,
 }
@@ -39,7 +35,7 @@ pub struct InMemProver<V, VSW, P, SW> {
 impl<V, VSW, P, SW> InMemProver<V, VSW, P, SW> {
     pub fn new() -> Self {
         Self {
-            proving_key_store: None,
+            proving_key_store: HashMap::new(),
             commit_key_store: None,
             verifying_key_store: HashMap::new(),
         }
@@ -54,63 +50,56 @@ impl<V, VSW, P, SW> Default for InMemProver<V, VSW, P, SW> {
 }
 
 #[sequencer_bounds]
-impl<V, VSW, P, SW> SequencerProver<V, VSW, P, SW> for InMemProver<V, VSW, P, SW> {
+impl<V, VSW, P, SW> SequencerProver<V, VSW, P, SW> for InMemProver<V, VSW, P, SW>
+where
+    <P as Pairing>::ScalarField: EmulationConfig<<P as Pairing>::BaseField>,
+{
     fn rollup_proof(
-        client_inputs: Vec<ClientInput<V>>,
+        client_inputs: Vec<Vec<ClientInput<V>>>,
         global_vk_root: <V as Pairing>::BaseField,
         global_nullifier_root: <V as Pairing>::BaseField,
         global_nullifier_leaf_count: <V as Pairing>::BaseField,
         global_commitment_root: <V as Pairing>::BaseField,
-        g_polys: Vec<DensePolynomial<<V as Pairing>::ScalarField>>,
+        g_polys: Vec<Vec<DensePolynomial<<V as Pairing>::ScalarField>>>,
         commit_key: RollupCommitKeys<V, VSW, P, SW>,
-        proving_keys: Option<RollupProvingKeys<V, VSW, P, SW>>,
-    ) -> Result<Proof<P>, CircuitError> {
-        let mut rng = ChaChaRng::from_entropy();
+        proving_keys: RollupProvingKeys<V, VSW, P, SW>,
+    ) -> Result<(RollupProof<P, V>, VerifyingKey<P>, RollupAccumulators<P, V>), CircuitError> {
         log::debug!("Start rollup circuit");
-        let (circuit, _pi_star) = base_rollup_circuit::<V, P, 8>(
+        let now = Instant::now();
+        let rollup_proof = aggregation::rollup_proof::<V, VSW, P, SW>(
             client_inputs,
             global_vk_root,
             global_nullifier_root,
             global_nullifier_leaf_count,
             global_commitment_root,
             g_polys,
-            commit_key.vesta_commit_key,
+            &commit_key,
+            proving_keys,
         )?;
+        log::debug!("Rollup proof done: {:?}", now.elapsed());
+        Ok(rollup_proof)
+    }
 
-        log::debug!("Constraint count: {}", circuit.num_gates());
-
-        let now = Instant::now();
-        let pk = if let Some(pkey) = proving_keys {
-            pkey.base_proving_key
-        } else {
-            let srs = <PlonkIpaSnark<P> as UniversalSNARK<P>>::universal_setup_for_testing(
-                circuit.srs_size()?,
-                &mut rng,
-            )?;
-            log::debug!("SRS size {} done: {:?}", circuit.srs_size()?, now.elapsed());
-            let now = Instant::now();
-            let (pk, _vk) = PlonkIpaSnark::<P>::preprocess(&srs, &circuit)?;
-            log::debug!("Preprocess done: {:?}", now.elapsed());
-            pk
-        };
-
-        let now = Instant::now();
+    fn verify(vk: VerifyingKey<V>, public_inputs: Vec<V::ScalarField>, proof: Proof<V>) -> bool {
+        PlonkIpaSnark::<V>::verify::<RescueTranscript<<V as Pairing>::BaseField>>(
+            &vk,
+            &public_inputs,
+            &proof,
+            None,
+        )
+        .is_ok()
+    }
 
-        let (proof, _g_poly, _) = PlonkIpaSnark::<P>::prove_for_partial::<
-            _,
-            _,
-            RescueTranscript<<P as Pairing>::BaseField>,
-        >(&mut rng, &circuit, &pk, None)?;
-        log::debug!("Proof done: {:?}", now.elapsed());
-        Ok(proof)
+    fn store_pks(&mut self, shape: RollupShape, pks: RollupProvingKeys<V, VSW, P, SW>) {
+        self.proving_key_store.insert(shape, pks);
     }
 
-    fn store_pks(&mut self, pks: RollupProvingKeys<V, VSW, P, SW>) {
-        self.proving_key_store = Some(pks);
+    fn get_pks(&self, shape: &RollupShape) -> Option<RollupProvingKeys<V, VSW, P, SW>> {
+        self.proving_key_store.get(shape).cloned()
     }
 
-    fn get_pks(&self) -> Option<RollupProvingKeys<V, VSW, P, SW>> {
-        self.proving_key_store.clone()
+    fn get_rollup_shapes(&self) -> Vec<RollupShape> {
+        self.proving_key_store.keys().cloned().collect()
     }
 
     fn store_vk(&mut self, circuit_type: CircuitType, vk_info: (VerifyingKey<V>, usize)) {
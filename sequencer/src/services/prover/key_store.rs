@@ -0,0 +1,77 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::anyhow;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Compress, Validate};
+use common::{files::write_atomically, hash::content_hash, structs::CircuitType};
+use tracing_log::log;
+
+use crate::domain::RollupShape;
+
+// Rollup keys stored on disk, one file per rollup shape. As in the client key
+// store, each record carries the hash of what the keys were generated from, so
+// they are regenerated when it changes, and a hash of its own bytes, so a
+// truncated or corrupted file is discarded instead of deserialized.
+#[derive(Clone, Debug)]
+pub struct RollupKeyStore {
+    path: PathBuf,
+}
+
+// (keys hash, content hash, serialized rollup keys)
+type KeyRecord = (String, String, Vec<u8>);
+
+impl RollupKeyStore {
+    pub fn open(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        fs::create_dir_all(&path)?;
+        Ok(Self { path })
+    }
+
+    // Serialized rollup keys of `shape`, as written by `store`
+    pub fn load(&self, shape: &RollupShape, keys_hash: &str) -> Option<Vec<u8>> {
+        let path = self.key_path(shape);
+        let bytes = fs::read(&path).ok()?;
+        let (stored_keys_hash, stored_content_hash, keys) =
+            match <KeyRecord as CanonicalDeserialize>::deserialize_with_mode(
+                &*bytes,
+                Compress::No,
+                Validate::Yes,
+            ) {
+                Ok(record) => record,
+                Err(e) => {
+                    log::warn!("Discarding unreadable rollup keys {:?}: {e}", path);
+                    return None;
+                }
+            };
+        if stored_keys_hash != keys_hash {
+            log::info!("Rollup keys for {:?} are stale", shape);
+            return None;
+        }
+        if stored_content_hash != content_hash(&keys) {
+            log::warn!("Discarding corrupted rollup keys {:?}", path);
+            return None;
+        }
+        Some(keys)
+    }
+
+    pub fn store(&self, shape: &RollupShape, keys_hash: &str, keys: Vec<u8>) -> anyhow::Result<()> {
+        let record: KeyRecord = (keys_hash.to_string(), content_hash(&keys), keys);
+        let mut bytes = Vec::new();
+        record
+            .serialize_with_mode(&mut bytes, Compress::No)
+            .map_err(|e| anyhow!("Failed to serialize rollup keys record: {e}"))?;
+        write_atomically(&self.key_path(shape), &bytes)?;
+        Ok(())
+    }
+
+    fn key_path(&self, shape: &RollupShape) -> PathBuf {
+        let name = shape
+            .iter()
+            .map(CircuitType::key_name)
+            .collect::<Vec<_>>()
+            .join("-");
+        self.path.join(format!("rollup-{name}.pk"))
+    }
+}
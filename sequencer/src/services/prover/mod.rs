@@ -1,43 +1,48 @@
-use crate::domain::RollupCommitKeys;
+use crate::domain::{max_rollup_depth, RollupCommitKeys, RollupProvingKeys, RollupShape};
 use crate::ports::prover::SequencerProver;
+use anyhow::anyhow;
 use ark_ec::pairing::Pairing;
 use ark_ec::short_weierstrass::{Affine, Projective, SWCurveConfig};
 use ark_ec::{CurveConfig, CurveGroup};
 use ark_ff::PrimeField;
+use ark_serialize::CanonicalSerialize;
+use ark_std::UniformRand;
 use common::crypto::poseidon::constants::PoseidonParams;
+use common::hash::content_hash;
+use curves::pallas::{Fq, Fr, PallasConfig};
+use curves::vesta::VestaConfig;
 use jf_plonk::nightfall::ipa_structs::VerifyingKey;
-use jf_plonk::nightfall::PlonkIpaSnark;
-use jf_plonk::proof_system::UniversalSNARK;
 use jf_primitives::pcs::StructuredReferenceString;
 use jf_primitives::rescue::RescueParameter;
 use jf_relation::gadgets::ecc::SWToTEConParam;
+use key_store::RollupKeyStore;
 use plonk_prover::client::ClientPlonkCircuit;
 use plonk_prover::primitives::circuits::kem_dem::KemDemParams;
+use plonk_prover::rollup::circuits::ROLLUP_CIRCUITS_VERSION;
+use plonk_prover::setup::{UniversalSrs, SETUP_DOMAIN, SRS_DEGREE};
+use plonk_prover::utils::bench::{
+    base::build_client_inputs, tree::tree_generator_from_client_inputs,
+};
 use rand::SeedableRng;
 use rand_chacha::ChaChaRng;
+use tracing_log::log;
+use trees::{non_membership_tree::IndexedMerkleTree, AppendTree};
 use zk_macros::{prover_bounds, sequencer_bounds};
 
+pub mod aggregation;
 pub mod in_mem_sequencer_prover;
+pub mod key_store;
 
 #[sequencer_bounds]
-pub fn generate_and_store_cks<V, VSW, P, SW, Prover>(prover: &mut Prover)
-where
+pub fn generate_and_store_cks<V, VSW, P, SW, Prover>(
+    prover: &mut Prover,
+    vesta_srs: &UniversalSrs<V>,
+    pallas_srs: &UniversalSrs<P>,
+) where
     Prover: SequencerProver<V, VSW, P, SW>,
 {
-    let mut rng = ChaChaRng::from_entropy();
-    let vesta_srs = <PlonkIpaSnark<V> as UniversalSNARK<V>>::universal_setup_for_testing(
-        2usize.pow(21),
-        &mut rng,
-    )
-    .unwrap();
-    let (vesta_commit_key, _) = vesta_srs.trim(2usize.pow(21)).unwrap();
-
-    let pallas_srs = <PlonkIpaSnark<P> as UniversalSNARK<P>>::universal_setup_for_testing(
-        2usize.pow(21),
-        &mut rng,
-    )
-    .unwrap();
-    let (pallas_commit_key, _) = pallas_srs.trim(2usize.pow(21)).unwrap();
+    let (vesta_commit_key, _) = vesta_srs.trim(SRS_DEGREE).unwrap();
+    let (pallas_commit_key, _) = pallas_srs.trim(SRS_DEGREE).unwrap();
     let rollup_commit_keys = RollupCommitKeys {
         pallas_commit_key,
         vesta_commit_key,
@@ -49,6 +54,7 @@ where
 pub fn generate_and_store_client_circuit_vks<P, V, SW, VSW, Prover>(
     prover: &mut Prover,
     circuit_info: &[Box<dyn ClientPlonkCircuit<P, V, VSW>>],
+    srs: &UniversalSrs<V>,
 ) -> Vec<VerifyingKey<V>>
 where
     Prover: SequencerProver<V, VSW, P, SW>,
@@ -57,9 +63,165 @@ where
         .iter()
         .enumerate()
         .map(|(idx, c)| {
-            let keys = c.generate_keys().unwrap();
+            let keys = c.generate_keys_with_srs(srs).unwrap();
             prover.store_vk(c.get_circuit_type(), (keys.1.clone(), idx));
             keys.1
         })
         .collect::<Vec<VerifyingKey<_>>>()
 }
+
+// Loads the rollup keys of every shape in `shapes` from `key_store`, or
+// generates and persists them, and stores them in the prover. Blocks are only
+// built for these shapes, so this must run before the sequencer starts, after
+// the commit keys and client verifying keys are stored. Every client circuit
+// must appear in some shape, otherwise its transactions would be admitted but
+// never rolled up.
+pub fn generate_and_store_rollup_pks<Prover>(
+    prover: &mut Prover,
+    circuit_info: &[Box<dyn ClientPlonkCircuit<PallasConfig, VestaConfig, VestaConfig>>],
+    shapes: &[RollupShape],
+    key_store: Option<&RollupKeyStore>,
+) -> anyhow::Result<()>
+where
+    Prover: SequencerProver<VestaConfig, VestaConfig, PallasConfig, PallasConfig>,
+{
+    check_shapes(circuit_info, shapes)?;
+    let commit_keys = prover
+        .get_cks()
+        .ok_or_else(|| anyhow!("Commit keys are needed to generate rollup keys"))?;
+    for shape in shapes {
+        let keys_hash = rollup_keys_hash(prover, shape)?;
+        let stored_keys = key_store
+            .and_then(|store| store.load(shape, &keys_hash))
+            .and_then(|bytes| {
+                RollupProvingKeys::from_bytes(&bytes)
+                    .map_err(|e| log::warn!("Discarding undecodable rollup keys: {e}"))
+                    .ok()
+            });
+        let keys = match stored_keys {
+            Some(keys) => {
+                log::debug!("Loaded rollup keys for {:?}", shape);
+                keys
+            }
+            None => {
+                log::info!("Generating rollup keys for {:?}", shape);
+                let keys = generate_rollup_keys(circuit_info, shape, &commit_keys)?;
+                if let Some(store) = key_store {
+                    let bytes = keys
+                        .to_bytes()
+                        .map_err(|e| anyhow!("Failed to serialize rollup keys: {e}"))?;
+                    store.store(shape, &keys_hash, bytes)?;
+                }
+                keys
+            }
+        };
+        prover.store_pks(shape.clone(), keys);
+    }
+    Ok(())
+}
+
+fn check_shapes(
+    circuit_info: &[Box<dyn ClientPlonkCircuit<PallasConfig, VestaConfig, VestaConfig>>],
+    shapes: &[RollupShape],
+) -> anyhow::Result<()> {
+    for circuit in circuit_info {
+        let circuit_type = circuit.get_circuit_type();
+        if !shapes.iter().any(|shape| shape.contains(&circuit_type)) {
+            return Err(anyhow!("No rollup shape for {:?}", circuit_type));
+        }
+    }
+    Ok(())
+}
+
+// Persisted rollup keys are only valid for the rollup circuits they were
+// preprocessed for, the number of merge levels they cover, the client circuits
+// of their shape and the setup they were derived from, so all of them are part
+// of their hash
+fn rollup_keys_hash<Prover>(prover: &Prover, shape: &RollupShape) -> anyhow::Result<String>
+where
+    Prover: SequencerProver<VestaConfig, VestaConfig, PallasConfig, PallasConfig>,
+{
+    let mut bytes = format!(
+        "{:?}:{}:{}",
+        shape,
+        ROLLUP_CIRCUITS_VERSION,
+        max_rollup_depth(shape)
+    )
+    .into_bytes();
+    bytes.extend_from_slice(SETUP_DOMAIN);
+    for circuit_type in shape {
+        let (vk, _) = prover
+            .get_vk(circuit_type.clone())
+            .ok_or_else(|| anyhow!("No verifying key for {:?}", circuit_type))?;
+        vk.serialize_compressed(&mut bytes)
+            .map_err(|e| anyhow!("Failed to serialize verifying key: {e}"))?;
+    }
+    Ok(content_hash(&bytes))
+}
+
+// Rollup circuits only depend on the shape of their inputs, so the keys are
+// preprocessed from a rollup of random client transactions of that shape, up
+// to the largest block of that shape
+fn generate_rollup_keys(
+    circuit_info: &[Box<dyn ClientPlonkCircuit<PallasConfig, VestaConfig, VestaConfig>>],
+    shape: &RollupShape,
+    commit_keys: &RollupCommitKeys<VestaConfig, VestaConfig, PallasConfig, PallasConfig>,
+) -> anyhow::Result<RollupProvingKeys<VestaConfig, VestaConfig, PallasConfig, PallasConfig>> {
+    let mut rng = ChaChaRng::from_entropy();
+    let token_id = Some(Fq::rand(&mut rng));
+    let mut client_inputs = vec![];
+    let mut nullifier_tree = IndexedMerkleTree::<Fr, 32>::new();
+    let mut global_comm_roots = vec![];
+    let mut g_polys = vec![];
+    for circuit_type in shape {
+        let circuit = circuit_info
+            .iter()
+            .find(|c| c.get_circuit_type() == *circuit_type)
+            .ok_or_else(|| anyhow!("No client circuit for {:?}", circuit_type))?;
+        build_client_inputs(
+            &mut client_inputs,
+            &mut nullifier_tree,
+            &mut global_comm_roots,
+            &mut g_polys,
+            &**circuit,
+            token_id,
+        )
+        .map_err(|e| anyhow!("Failed to build {:?} inputs: {e}", circuit_type))?;
+    }
+    let zk_trees = tree_generator_from_client_inputs::<8>(&mut client_inputs, &global_comm_roots)
+        .map_err(|e| anyhow!("Failed to build rollup trees: {e}"))?;
+    let initial_nullifier_tree = IndexedMerkleTree::<Fr, 32>::new();
+
+    aggregation::rollup_keys::<VestaConfig, VestaConfig, PallasConfig, PallasConfig>(
+        max_rollup_depth(shape),
+        client_inputs,
+        zk_trees.vk_tree.root(),
+        initial_nullifier_tree.root(),
+        initial_nullifier_tree.leaf_count().into(),
+        zk_trees.global_root_tree.root(),
+        g_polys,
+        commit_keys,
+    )
+    .map_err(|e| anyhow!("Failed to generate rollup keys for {:?}: {e}", shape))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::circuits::select_client_circuits_sequencer;
+    use common::{configuration::get_configuration, structs::CircuitType};
+
+    #[test]
+    fn test_configured_shapes_roll_up_every_client_circuit() {
+        let configuration = get_configuration().unwrap();
+        let circuit_info = select_client_circuits_sequencer();
+        assert!(check_shapes(&circuit_info, &configuration.sequencer.rollup_shapes).is_ok());
+    }
+
+    #[test]
+    fn test_client_circuit_without_shape() {
+        let circuit_info = select_client_circuits_sequencer();
+        let shapes = vec![vec![CircuitType::Mint(1), CircuitType::Mint(1)]];
+        assert!(check_shapes(&circuit_info, &shapes).is_err());
+    }
+}
@@ -0,0 +1,568 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::anyhow;
+use common::files::write_atomically;
+use common::structs::{Block, Transaction};
+use curves::pallas::Fr;
+use curves::vesta::VestaConfig;
+use jf_utils::canonical;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tracing_log::log;
+use trees::{
+    membership_tree::Tree,
+    non_membership_tree::{IndexedMerkleTree, IndexedNode, SortedIndexedNode},
+    tree::{AppendTree, Position},
+};
+
+use super::in_mem_sequencer_storage::InMemStorage;
+use crate::domain::mempool::{transaction_hash, MempoolError};
+use crate::domain::transaction::{IncludedTransaction, TransactionStatus};
+use crate::ports::storage::{
+    BlockCommitStorage, BlockStorage, GlobalRoots, GlobalStateStorage, StateTransition,
+    TransactionStorage,
+};
+
+pub const STATE_RECORD_VERSION: u32 = 3;
+pub const BLOCK_RECORD_VERSION: u32 = 1;
+pub const MEMPOOL_RECORD_VERSION: u32 = 1;
+
+const STATE_FILE: &str = "state.json";
+const BLOCKS_DIR: &str = "blocks";
+const MEMPOOL_FILE: &str = "mempool.json";
+
+#[derive(Serialize, Deserialize)]
+struct VersionedRecord<T> {
+    version: u32,
+    record: T,
+}
+
+// Snapshot of the global trees as of the first `block_count` blocks. Blocks
+// are stored in their own files, written before the snapshot that counts them,
+// so a block file past `block_count` is a block whose commit didn't complete.
+#[derive(Serialize, Deserialize)]
+struct StateRecord {
+    block_count: u64,
+    #[serde(with = "canonical")]
+    commitment_tree: Vec<Fr>,
+    #[serde(with = "canonical")]
+    vk_tree: Vec<Fr>,
+    nullifier_tree: NullifierTreeRecord,
+}
+
+// A block and the transactions it included, in inclusion order
+#[derive(Serialize, Deserialize)]
+struct BlockRecord {
+    block: Block<curves::vesta::Fr>,
+    transactions: Vec<Transaction<VestaConfig>>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct NullifierTreeRecord {
+    leaf_count: u64,
+    nodes: Vec<IndexedNodeRecord>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct IndexedNodeRecord {
+    tree_index: usize,
+    #[serde(with = "canonical")]
+    value: Fr,
+    next_index: u64,
+    #[serde(with = "canonical")]
+    next_value: Fr,
+}
+
+/// Durable sequencer storage. Records live in an in memory cache that is
+/// written through to a directory of versioned JSON files: the pending
+/// transactions in one, each block with its transactions in a file of its own,
+/// and a snapshot of the global trees in another. Storing a block only writes
+/// that block and the trees. Files are replaced atomically so a crash leaves
+/// either the previous or the new snapshot.
+pub struct FileStorage {
+    path: PathBuf,
+    cache: InMemStorage,
+}
+
+impl FileStorage {
+    /// Opens the storage at `path`, creating the directory if needed, and
+    /// restores the state of the last stored block.
+    pub fn open<P: AsRef<Path>>(path: P, mempool_capacity: usize) -> anyhow::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        fs::create_dir_all(path.join(BLOCKS_DIR))?;
+        let mut cache = InMemStorage::new().with_mempool_capacity(mempool_capacity);
+
+        if let Some(state) =
+            read_record::<StateRecord>(&path.join(STATE_FILE), STATE_RECORD_VERSION)?
+        {
+            for block_number in 0..state.block_count {
+                let stored = read_record::<BlockRecord>(
+                    &block_path(&path, block_number),
+                    BLOCK_RECORD_VERSION,
+                )?
+                .ok_or(anyhow!("Stored block {block_number} is missing"))?;
+                cache.insert_block(stored.block);
+                for transaction in stored.transactions {
+                    let included = IncludedTransaction {
+                        index: cache.past_txs.len(),
+                        block_number,
+                    };
+                    cache
+                        .included_txs
+                        .insert(transaction_hash(&transaction), included);
+                    cache.past_txs.push(transaction);
+                }
+            }
+            cache.commitment_tree = Tree::from_leaves(state.commitment_tree);
+            cache.vk_tree = Tree::from_leaves(state.vk_tree);
+            cache.nullifier_tree = nullifier_tree_from_record(state.nullifier_tree)?;
+        }
+
+        let mempool = read_record::<Vec<Transaction<VestaConfig>>>(
+            &path.join(MEMPOOL_FILE),
+            MEMPOOL_RECORD_VERSION,
+        )?
+        .unwrap_or_default();
+        // The mempool is written after the state, so it may still hold
+        // transactions of the last block if the sequencer stopped in between
+        for transaction in mempool {
+            if cache
+                .included_txs
+                .contains_key(&transaction_hash(&transaction))
+            {
+                continue;
+            }
+            if let Err(e) = cache.mempool.insert(transaction) {
+                log::warn!("Dropping stored mempool transaction: {:?}", e);
+            }
+        }
+
+        log::info!(
+            "Restored {} blocks and {} pending transactions from {:?}",
+            cache.blocks.len(),
+            cache.mempool.len(),
+            path
+        );
+        Ok(Self { path, cache })
+    }
+
+    fn persist_state(&self) -> anyhow::Result<()> {
+        let state = state_record(
+            self.cache.get_block_count(),
+            &self.cache.commitment_tree,
+            &self.cache.vk_tree,
+            &self.cache.nullifier_tree,
+        );
+        write_record(&self.path.join(STATE_FILE), STATE_RECORD_VERSION, state)
+    }
+
+    fn persist_block(&self, block_number: u64) -> anyhow::Result<()> {
+        let block = self
+            .cache
+            .get_block(block_number)
+            .ok_or(anyhow!("Unknown block {block_number}"))?;
+        let record = BlockRecord {
+            block,
+            transactions: self.cache.get_block_transactions(block_number),
+        };
+        write_record(
+            &block_path(&self.path, block_number),
+            BLOCK_RECORD_VERSION,
+            record,
+        )
+    }
+
+    fn persist_mempool(&self) -> anyhow::Result<()> {
+        write_record(
+            &self.path.join(MEMPOOL_FILE),
+            MEMPOOL_RECORD_VERSION,
+            self.cache.mempool.transactions(),
+        )
+    }
+}
+
+fn block_path(path: &Path, block_number: u64) -> PathBuf {
+    path.join(BLOCKS_DIR)
+        .join(format!("{block_number:012}.json"))
+}
+
+fn state_record(
+    block_count: u64,
+    commitment_tree: &Tree<Fr, 8>,
+    vk_tree: &Tree<Fr, 8>,
+    nullifier_tree: &IndexedMerkleTree<Fr, 32>,
+) -> StateRecord {
+    StateRecord {
+        block_count,
+        commitment_tree: tree_leaves(commitment_tree),
+        vk_tree: tree_leaves(vk_tree),
+        nullifier_tree: nullifier_tree_record(nullifier_tree),
+    }
+}
+
+fn tree_leaves(tree: &Tree<Fr, 8>) -> Vec<Fr> {
+    (0..tree.leaf_count() as usize)
+        .map(|i| tree.get_node(Position::new(i, 0)))
+        .collect()
+}
+
+fn nullifier_tree_record(tree: &IndexedMerkleTree<Fr, 32>) -> NullifierTreeRecord {
+    NullifierTreeRecord {
+        leaf_count: tree.leaf_count(),
+        nodes: tree
+            .nodes()
+            .into_iter()
+            .map(|sorted| IndexedNodeRecord {
+                tree_index: sorted.tree_index,
+                value: sorted.node.value(),
+                next_index: sorted.node.next_index(),
+                next_value: sorted.node.next_value(),
+            })
+            .collect(),
+    }
+}
+
+fn nullifier_tree_from_record(
+    record: NullifierTreeRecord,
+) -> anyhow::Result<IndexedMerkleTree<Fr, 32>> {
+    let nodes = record
+        .nodes
+        .into_iter()
+        .map(|node| SortedIndexedNode {
+            tree_index: node.tree_index,
+            node: IndexedNode::new(node.value, node.next_index as usize, node.next_value),
+        })
+        .collect();
+    IndexedMerkleTree::from_nodes(nodes, record.leaf_count)
+        .ok_or(anyhow!("Stored nullifier tree is missing leaves"))
+}
+
+fn read_record<T: DeserializeOwned>(
+    path: &Path,
+    current_version: u32,
+) -> anyhow::Result<Option<T>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let stored: VersionedRecord<serde_json::Value> = serde_json::from_slice(&fs::read(path)?)?;
+    if stored.version != current_version {
+        return Err(anyhow!(
+            "Unsupported record version {} in {:?}. Expected {}",
+            stored.version,
+            path,
+            current_version
+        ));
+    }
+    Ok(Some(serde_json::from_value(stored.record)?))
+}
+
+fn write_record<T: Serialize>(path: &Path, version: u32, record: T) -> anyhow::Result<()> {
+    let bytes = serde_json::to_vec(&VersionedRecord { version, record })?;
+    write_atomically(path, &bytes)?;
+    Ok(())
+}
+
+impl TransactionStorage<VestaConfig> for FileStorage {
+    fn get_transaction(&self, hash: &str) -> Option<Transaction<VestaConfig>> {
+        self.cache.get_transaction(hash)
+    }
+
+    fn get_transaction_status(&self, hash: &str) -> Option<TransactionStatus> {
+        self.cache.get_transaction_status(hash)
+    }
+
+    // Rejections only matter to clients polling a recent submission, so they
+    // aren't persisted
+    fn reject_transaction(&mut self, hash: String, reason: String) {
+        self.cache.reject_transaction(hash, reason)
+    }
+
+    fn check_transaction(
+        &self,
+        transaction: &Transaction<VestaConfig>,
+    ) -> Result<(), MempoolError> {
+        self.cache.check_transaction(transaction)
+    }
+
+    fn insert_transaction(
+        &mut self,
+        transaction: Transaction<VestaConfig>,
+    ) -> Result<String, MempoolError> {
+        let hash = self.cache.insert_transaction(transaction)?;
+        if let Err(e) = self.persist_mempool() {
+            log::error!("Couldn't persist transaction {}: {e}", hash);
+            self.cache.mempool.remove(&hash);
+            return Err(MempoolError::StorageFailure(e.to_string()));
+        }
+        Ok(hash)
+    }
+
+    fn evict_transaction(
+        &mut self,
+        hash: &str,
+        reason: String,
+    ) -> Option<Transaction<VestaConfig>> {
+        let transaction = self.cache.evict_transaction(hash, reason)?;
+        if let Err(e) = self.persist_mempool() {
+            log::error!("Couldn't persist eviction of {}: {e}", hash);
+        }
+        Some(transaction)
+    }
+
+    fn get_mempool_transactions(&self) -> Vec<Transaction<VestaConfig>> {
+        self.cache.get_mempool_transactions()
+    }
+
+    fn get_mempool_size(&self) -> usize {
+        self.cache.get_mempool_size()
+    }
+
+    fn get_block_transactions(&self, block_number: u64) -> Vec<Transaction<VestaConfig>> {
+        self.cache.get_block_transactions(block_number)
+    }
+
+    fn get_all_transactions(&self) -> Vec<Transaction<VestaConfig>> {
+        self.cache.get_all_transactions()
+    }
+
+    fn flush_mempool_transactions(
+        &mut self,
+        block_number: u64,
+        transactions: &[Transaction<VestaConfig>],
+    ) {
+        self.cache
+            .flush_mempool_transactions(block_number, transactions);
+        if let Err(e) = self
+            .persist_block(block_number)
+            .and_then(|_| self.persist_mempool())
+        {
+            log::error!("Couldn't persist flushed transactions: {e}");
+        }
+    }
+}
+
+impl BlockStorage<curves::vesta::Fr> for FileStorage {
+    fn get_block(&self, blocknumber: u64) -> Option<Block<curves::vesta::Fr>> {
+        self.cache.get_block(blocknumber)
+    }
+
+    fn insert_block(&mut self, block: Block<curves::vesta::Fr>) {
+        let block_number = block.block_number;
+        self.cache.insert_block(block);
+        if let Err(e) = self
+            .persist_block(block_number)
+            .and_then(|_| self.persist_state())
+        {
+            log::error!("Couldn't persist block {block_number}: {e}");
+        }
+    }
+
+    fn get_block_count(&self) -> u64 {
+        self.cache.get_block_count()
+    }
+
+    fn has_commitment_root(&self, root: curves::vesta::Fr) -> bool {
+        self.cache.has_commitment_root(root)
+    }
+}
+
+impl GlobalStateStorage for FileStorage {
+    type CommitmentTree = Tree<Fr, 8>;
+    type VkTree = Tree<Fr, 8>;
+    type NullifierTree = IndexedMerkleTree<Fr, 32>;
+    fn get_global_commitment_tree(&self) -> Self::CommitmentTree {
+        self.cache.get_global_commitment_tree()
+    }
+    fn store_global_commitment_tree(&mut self, new_tree: Self::CommitmentTree) {
+        self.cache.store_global_commitment_tree(new_tree);
+        if let Err(e) = self.persist_state() {
+            log::error!("Couldn't persist commitment tree: {e}");
+        }
+    }
+    fn get_global_nullifier_tree(&self) -> Self::NullifierTree {
+        self.cache.get_global_nullifier_tree()
+    }
+    fn store_global_nullifier_tree(&mut self, new_tree: Self::NullifierTree) {
+        self.cache.store_global_nullifier_tree(new_tree);
+        if let Err(e) = self.persist_state() {
+            log::error!("Couldn't persist nullifier tree: {e}");
+        }
+    }
+    fn get_vk_tree(&self) -> Self::VkTree {
+        self.cache.get_vk_tree()
+    }
+    fn store_vk_tree(&mut self, vk_tree: Self::VkTree) {
+        self.cache.store_vk_tree(vk_tree);
+        if let Err(e) = self.persist_state() {
+            log::error!("Couldn't persist vk tree: {e}");
+        }
+    }
+    fn get_global_roots(&self) -> GlobalRoots<Fr> {
+        self.cache.get_global_roots()
+    }
+    fn is_nullifier_spent(&self, nullifier: Fr) -> bool {
+        self.cache.is_nullifier_spent(nullifier)
+    }
+    fn apply_state_transition(
+        &mut self,
+        transition: StateTransition<Self::CommitmentTree, Self::NullifierTree>,
+    ) {
+        self.cache.apply_state_transition(transition);
+        if let Err(e) = self.persist_state() {
+            log::error!("Couldn't persist global state: {e}");
+        }
+    }
+}
+
+// The block and the trees it produced are written before the cache is
+// updated, so a failed write leaves the storage as it was
+impl BlockCommitStorage<VestaConfig> for FileStorage {
+    fn commit_block(
+        &mut self,
+        block: Block<curves::vesta::Fr>,
+        transition: StateTransition<Self::CommitmentTree, Self::NullifierTree>,
+        transactions: &[Transaction<VestaConfig>],
+    ) -> Option<()> {
+        let block_number = block.block_number;
+        // Only pending transactions are included, as in the cache
+        let record = BlockRecord {
+            block: block.clone(),
+            transactions: transactions
+                .iter()
+                .filter(|transaction| self.cache.mempool.contains(&transaction_hash(transaction)))
+                .cloned()
+                .collect(),
+        };
+        let state = state_record(
+            self.cache.get_block_count() + 1,
+            &transition.commitment_tree,
+            &self.cache.vk_tree,
+            &transition.nullifier_tree,
+        );
+        let persisted = write_record(
+            &block_path(&self.path, block_number),
+            BLOCK_RECORD_VERSION,
+            record,
+        )
+        .and_then(|_| write_record(&self.path.join(STATE_FILE), STATE_RECORD_VERSION, state));
+        if let Err(e) = persisted {
+            log::error!("Couldn't persist block {block_number}: {e}");
+            return None;
+        }
+        self.cache.commit_block(block, transition, transactions)?;
+        // Included transactions left in the stored mempool are dropped on open
+        if let Err(e) = self.persist_mempool() {
+            log::error!("Couldn't persist mempool after block {block_number}: {e}");
+        }
+        Some(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_transactions::{spending, MINT};
+    use tempfile::TempDir;
+    use trees::NonMembershipTree;
+
+    // Block `block_number` spending `nullifier` on top of the stored trees
+    fn commit(db: &mut FileStorage, transaction: &Transaction<VestaConfig>, nullifier: u64) {
+        let block_number = db.get_block_count();
+        let mut commitment_tree = db.get_global_commitment_tree();
+        commitment_tree.append_leaf(Fr::from(block_number + 1));
+        let mut nullifier_tree = db.get_global_nullifier_tree();
+        nullifier_tree.update_low_nullifier(Fr::from(nullifier));
+        let block = Block {
+            block_number,
+            commitment_root: commitment_tree.root(),
+            ..Default::default()
+        };
+        let transition = StateTransition {
+            commitment_tree,
+            nullifier_tree,
+        };
+        db.commit_block(block, transition, &[transaction.clone()])
+            .unwrap();
+    }
+
+    #[test]
+    fn test_blocks_survive_reopen() {
+        let dir = TempDir::new().unwrap();
+        let first = MINT.1.clone();
+        let second = spending(&[1], Fr::from(0u64));
+        let pending = spending(&[2], Fr::from(0u64));
+        let (roots, blocks) = {
+            let mut db = FileStorage::open(dir.path(), 8).unwrap();
+            for transaction in [&first, &second, &pending] {
+                db.insert_transaction(transaction.clone()).unwrap();
+            }
+            commit(&mut db, &first, 1);
+            commit(&mut db, &second, 2);
+            (
+                db.get_global_roots(),
+                vec![db.get_block(0).unwrap(), db.get_block(1).unwrap()],
+            )
+        };
+
+        let db = FileStorage::open(dir.path(), 8).unwrap();
+        assert_eq!(db.get_global_roots(), roots);
+        assert_eq!(db.get_block_count(), 2);
+        for (block_number, (block, transaction)) in blocks.iter().zip([&first, &second]).enumerate()
+        {
+            let block_number = block_number as u64;
+            assert_eq!(
+                db.get_block(block_number).unwrap().commitment_root,
+                block.commitment_root
+            );
+            assert_eq!(
+                db.get_block_transactions(block_number),
+                vec![transaction.clone()]
+            );
+        }
+        assert_eq!(db.get_mempool_transactions(), vec![pending]);
+        assert!(db.is_nullifier_spent(Fr::from(2u64)));
+    }
+
+    #[test]
+    fn test_failed_block_write_keeps_the_previous_state() {
+        let dir = TempDir::new().unwrap();
+        let mut db = FileStorage::open(dir.path(), 8).unwrap();
+        db.insert_transaction(MINT.1.clone()).unwrap();
+        let roots = db.get_global_roots();
+        // Replacing a directory with the new snapshot fails
+        fs::create_dir(dir.path().join(STATE_FILE)).unwrap();
+
+        let transition = StateTransition {
+            commitment_tree: Tree::from_leaves(vec![Fr::from(1u64)]),
+            nullifier_tree: db.get_global_nullifier_tree(),
+        };
+        assert!(db
+            .commit_block(Block::default(), transition, &[MINT.1.clone()])
+            .is_none());
+        assert_eq!(db.get_block_count(), 0);
+        assert_eq!(db.get_global_roots(), roots);
+        assert_eq!(db.get_mempool_transactions(), vec![MINT.1.clone()]);
+
+        // The block file written before the snapshot isn't counted
+        fs::remove_dir(dir.path().join(STATE_FILE)).unwrap();
+        let db = FileStorage::open(dir.path(), 8).unwrap();
+        assert_eq!(db.get_block_count(), 0);
+        assert_eq!(db.get_mempool_transactions(), vec![MINT.1.clone()]);
+    }
+
+    #[test]
+    fn test_failed_mempool_write_refuses_the_transaction() {
+        let dir = TempDir::new().unwrap();
+        let mut db = FileStorage::open(dir.path(), 8).unwrap();
+        fs::create_dir(dir.path().join(MEMPOOL_FILE)).unwrap();
+
+        assert!(matches!(
+            db.insert_transaction(MINT.1.clone()),
+            Err(MempoolError::StorageFailure(_))
+        ));
+        assert_eq!(db.get_mempool_size(), 0);
+        assert_eq!(db.get_transaction_status(&transaction_hash(&MINT.1)), None);
+    }
+}
@@ -1,15 +1,27 @@
-use crate::ports::storage::{BlockStorage, GlobalStateStorage, TransactionStorage};
+use std::collections::{HashMap, HashSet};
+
+use crate::domain::mempool::{transaction_hash, Mempool, MempoolError};
+use crate::domain::transaction::{IncludedTransaction, RejectedTransactions, TransactionStatus};
+use crate::ports::storage::{
+    BlockCommitStorage, BlockStorage, GlobalRoots, GlobalStateStorage, TransactionStorage,
+};
 use common::structs::{Block, Transaction};
 use curves::pallas::Fr;
 use curves::vesta::VestaConfig;
 use trees::membership_tree::Tree;
-use trees::non_membership_tree::IndexedMerkleTree;
+use trees::non_membership_tree::{IndexedMerkleTree, NonMembershipTree};
+use trees::tree::AppendTree;
 
 #[derive(Clone, Default)]
 pub struct InMemStorage {
     pub blocks: Vec<Block<curves::vesta::Fr>>,
-    pub mempool: Vec<Transaction<VestaConfig>>,
+    // Commitment roots published by `blocks`
+    pub commitment_roots: HashSet<curves::vesta::Fr>,
+    pub mempool: Mempool<VestaConfig>,
     pub past_txs: Vec<Transaction<VestaConfig>>,
+    // Where each past transaction sits in `past_txs`, by transaction hash
+    pub included_txs: HashMap<String, IncludedTransaction>,
+    pub rejected_txs: RejectedTransactions,
     pub nullifier_tree: IndexedMerkleTree<Fr, 32>,
     pub commitment_tree: Tree<Fr, 8>,
     pub vk_tree: Tree<Fr, 8>,
@@ -19,31 +31,111 @@ impl InMemStorage {
     pub fn new() -> Self {
         Default::default()
     }
+
+    pub fn with_mempool_capacity(mut self, capacity: usize) -> Self {
+        self.mempool = Mempool::new(capacity);
+        self
+    }
 }
 
 impl TransactionStorage<VestaConfig> for InMemStorage {
-    fn get_transaction(&self) -> Option<Transaction<VestaConfig>> {
-        todo!()
+    fn get_transaction(&self, hash: &str) -> Option<Transaction<VestaConfig>> {
+        if let Some(transaction) = self.mempool.get(hash) {
+            return Some(transaction.clone());
+        }
+        self.included_txs
+            .get(hash)
+            .and_then(|included| self.past_txs.get(included.index))
+            .cloned()
+    }
+
+    fn get_transaction_status(&self, hash: &str) -> Option<TransactionStatus> {
+        if self.mempool.contains(hash) {
+            return Some(TransactionStatus::Pending);
+        }
+        if let Some(included) = self.included_txs.get(hash) {
+            return Some(TransactionStatus::Included {
+                block_number: included.block_number,
+            });
+        }
+        self.rejected_txs
+            .get(hash)
+            .map(|reason| TransactionStatus::Rejected {
+                reason: reason.clone(),
+            })
     }
 
-    fn insert_transaction(&mut self, transaction: Transaction<VestaConfig>) {
-        self.mempool.push(transaction);
+    fn reject_transaction(&mut self, hash: String, reason: String) {
+        self.rejected_txs.insert(hash, reason);
+    }
+
+    fn check_transaction(
+        &self,
+        transaction: &Transaction<VestaConfig>,
+    ) -> Result<(), MempoolError> {
+        self.mempool.check(transaction).map(|_| ())
+    }
+
+    fn insert_transaction(
+        &mut self,
+        transaction: Transaction<VestaConfig>,
+    ) -> Result<String, MempoolError> {
+        self.mempool.insert(transaction)
+    }
+
+    fn evict_transaction(
+        &mut self,
+        hash: &str,
+        reason: String,
+    ) -> Option<Transaction<VestaConfig>> {
+        let transaction = self.mempool.remove(hash)?;
+        self.rejected_txs.insert(hash.to_string(), reason);
+        Some(transaction)
     }
 
     fn get_mempool_transactions(&self) -> Vec<Transaction<VestaConfig>> {
-        self.mempool.clone()
+        self.mempool.transactions()
+    }
+
+    fn get_mempool_size(&self) -> usize {
+        self.mempool.len()
     }
 
-    fn get_block_transaction(&self) -> Vec<Transaction<VestaConfig>> {
-        todo!()
+    fn get_block_transactions(&self, block_number: u64) -> Vec<Transaction<VestaConfig>> {
+        let mut indices = self
+            .included_txs
+            .values()
+            .filter(|included| included.block_number == block_number)
+            .map(|included| included.index)
+            .collect::<Vec<_>>();
+        indices.sort_unstable();
+        indices
+            .into_iter()
+            .filter_map(|index| self.past_txs.get(index))
+            .cloned()
+            .collect()
     }
     fn get_all_transactions(&self) -> Vec<Transaction<VestaConfig>> {
         let past_txs = self.past_txs.clone();
-        let mempool_txs = self.mempool.clone();
+        let mempool_txs = self.mempool.transactions();
         past_txs.into_iter().chain(mempool_txs).collect()
     }
-    fn flush_mempool_transactions(&mut self) {
-        self.past_txs.append(&mut self.mempool);
+    fn flush_mempool_transactions(
+        &mut self,
+        block_number: u64,
+        transactions: &[Transaction<VestaConfig>],
+    ) {
+        for transaction in transactions {
+            let hash = transaction_hash(transaction);
+            if let Some(flushed) = self.mempool.remove(&hash) {
+                let included = IncludedTransaction {
+                    index: self.past_txs.len(),
+                    block_number,
+                };
+                self.included_txs.insert(hash, included);
+                self.past_txs.push(flushed);
+            }
+        }
     }
 }
 
@@ -53,12 +145,17 @@ impl BlockStorage<curves::vesta::Fr> for InMemStorage {
     }
 
     fn insert_block(&mut self, block: Block<curves::vesta::Fr>) {
+        self.commitment_roots.insert(block.commitment_root);
         self.blocks.push(block);
     }
 
     fn get_block_count(&self) -> u64 {
         self.blocks.len() as u64
     }
+
+    fn has_commitment_root(&self, root: curves::vesta::Fr) -> bool {
+        self.commitment_roots.contains(&root)
+    }
 }
 
 impl GlobalStateStorage for InMemStorage {
@@ -74,10 +171,28 @@ impl GlobalStateStorage for InMemStorage {
     fn get_global_nullifier_tree(&self) -> Self::NullifierTree {
         self.nullifier_tree.clone()
     }
+    fn store_global_nullifier_tree(&mut self, new_tree: Self::NullifierTree) {
+        self.nullifier_tree = new_tree;
+    }
     fn get_vk_tree(&self) -> Self::VkTree {
         self.vk_tree.clone()
     }
     fn store_vk_tree(&mut self, vk_tree: Self::VkTree) {
         self.vk_tree = vk_tree;
     }
+    fn get_global_roots(&self) -> GlobalRoots<Fr> {
+        GlobalRoots {
+            commitment_root: self.commitment_tree.root(),
+            nullifier_root: self.nullifier_tree.root(),
+            nullifier_leaf_count: self.nullifier_tree.leaf_count(),
+            vk_root: self.vk_tree.root(),
+        }
+    }
+    fn is_nullifier_spent(&self, nullifier: Fr) -> bool {
+        self.nullifier_tree
+            .non_membership_witness(nullifier)
+            .is_none()
+    }
 }
+
+impl BlockCommitStorage<VestaConfig> for InMemStorage {}
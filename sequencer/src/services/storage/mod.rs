@@ -9,7 +9,9 @@ use jf_plonk::proof_system::structs::VK;
 use trees::MembershipTree;
 use trees::{membership_tree::Tree, tree::AppendTree};
 
+pub mod file_sequencer_storage;
 pub mod in_mem_sequencer_storage;
+pub mod sequencer_storage;
 
 pub fn generate_and_store_vk_tree<V, Storage, const H: usize>(
     db: &mut Storage,
@@ -0,0 +1,248 @@
+use anyhow::anyhow;
+use common::configuration::{StorageBackend, StorageSettings};
+use common::structs::{Block, Transaction};
+use curves::pallas::Fr;
+use curves::vesta::VestaConfig;
+use trees::{membership_tree::Tree, non_membership_tree::IndexedMerkleTree};
+
+use super::{file_sequencer_storage::FileStorage, in_mem_sequencer_storage::InMemStorage};
+use crate::domain::{mempool::MempoolError, transaction::TransactionStatus};
+use crate::ports::storage::{
+    BlockCommitStorage, BlockStorage, GlobalRoots, GlobalStateStorage, StateTransition,
+    TransactionStorage,
+};
+
+/// Storage backend selected at startup from `StorageSettings`.
+pub enum SequencerStorage {
+    InMem(InMemStorage),
+    File(FileStorage),
+}
+
+impl SequencerStorage {
+    pub fn from_settings(
+        settings: &StorageSettings,
+        mempool_capacity: usize,
+    ) -> anyhow::Result<Self> {
+        match settings.backend {
+            StorageBackend::InMemory => Ok(Self::InMem(
+                InMemStorage::new().with_mempool_capacity(mempool_capacity),
+            )),
+            StorageBackend::File => {
+                let path = settings
+                    .path
+                    .as_ref()
+                    .ok_or(anyhow!("File storage requires a storage path"))?;
+                Ok(Self::File(FileStorage::open(path, mempool_capacity)?))
+            }
+        }
+    }
+}
+
+impl TransactionStorage<VestaConfig> for SequencerStorage {
+    fn get_transaction(&self, hash: &str) -> Option<Transaction<VestaConfig>> {
+        match self {
+            Self::InMem(db) => db.get_transaction(hash),
+            Self::File(db) => db.get_transaction(hash),
+        }
+    }
+
+    fn get_transaction_status(&self, hash: &str) -> Option<TransactionStatus> {
+        match self {
+            Self::InMem(db) => db.get_transaction_status(hash),
+            Self::File(db) => db.get_transaction_status(hash),
+        }
+    }
+
+    fn reject_transaction(&mut self, hash: String, reason: String) {
+        match self {
+            Self::InMem(db) => db.reject_transaction(hash, reason),
+            Self::File(db) => db.reject_transaction(hash, reason),
+        }
+    }
+
+    fn check_transaction(
+        &self,
+        transaction: &Transaction<VestaConfig>,
+    ) -> Result<(), MempoolError> {
+        match self {
+            Self::InMem(db) => db.check_transaction(transaction),
+            Self::File(db) => db.check_transaction(transaction),
+        }
+    }
+
+    fn insert_transaction(
+        &mut self,
+        transaction: Transaction<VestaConfig>,
+    ) -> Result<String, MempoolError> {
+        match self {
+            Self::InMem(db) => db.insert_transaction(transaction),
+            Self::File(db) => db.insert_transaction(transaction),
+        }
+    }
+
+    fn evict_transaction(
+        &mut self,
+        hash: &str,
+        reason: String,
+    ) -> Option<Transaction<VestaConfig>> {
+        match self {
+            Self::InMem(db) => db.evict_transaction(hash, reason),
+            Self::File(db) => db.evict_transaction(hash, reason),
+        }
+    }
+
+    fn get_mempool_transactions(&self) -> Vec<Transaction<VestaConfig>> {
+        match self {
+            Self::InMem(db) => db.get_mempool_transactions(),
+            Self::File(db) => db.get_mempool_transactions(),
+        }
+    }
+
+    fn get_mempool_size(&self) -> usize {
+        match self {
+            Self::InMem(db) => db.get_mempool_size(),
+            Self::File(db) => db.get_mempool_size(),
+        }
+    }
+
+    fn get_block_transactions(&self, block_number: u64) -> Vec<Transaction<VestaConfig>> {
+        match self {
+            Self::InMem(db) => db.get_block_transactions(block_number),
+            Self::File(db) => db.get_block_transactions(block_number),
+        }
+    }
+
+    fn get_all_transactions(&self) -> Vec<Transaction<VestaConfig>> {
+        match self {
+            Self::InMem(db) => db.get_all_transactions(),
+            Self::File(db) => db.get_all_transactions(),
+        }
+    }
+
+    fn flush_mempool_transactions(
+        &mut self,
+        block_number: u64,
+        transactions: &[Transaction<VestaConfig>],
+    ) {
+        match self {
+            Self::InMem(db) => db.flush_mempool_transactions(block_number, transactions),
+            Self::File(db) => db.flush_mempool_transactions(block_number, transactions),
+        }
+    }
+}
+
+impl BlockStorage<curves::vesta::Fr> for SequencerStorage {
+    fn get_block(&self, blocknumber: u64) -> Option<Block<curves::vesta::Fr>> {
+        match self {
+            Self::InMem(db) => db.get_block(blocknumber),
+            Self::File(db) => db.get_block(blocknumber),
+        }
+    }
+
+    fn insert_block(&mut self, block: Block<curves::vesta::Fr>) {
+        match self {
+            Self::InMem(db) => db.insert_block(block),
+            Self::File(db) => db.insert_block(block),
+        }
+    }
+
+    fn get_block_count(&self) -> u64 {
+        match self {
+            Self::InMem(db) => db.get_block_count(),
+            Self::File(db) => db.get_block_count(),
+        }
+    }
+
+    fn has_commitment_root(&self, root: curves::vesta::Fr) -> bool {
+        match self {
+            Self::InMem(db) => db.has_commitment_root(root),
+            Self::File(db) => db.has_commitment_root(root),
+        }
+    }
+}
+
+impl GlobalStateStorage for SequencerStorage {
+    type CommitmentTree = Tree<Fr, 8>;
+    type VkTree = Tree<Fr, 8>;
+    type NullifierTree = IndexedMerkleTree<Fr, 32>;
+
+    fn get_global_commitment_tree(&self) -> Self::CommitmentTree {
+        match self {
+            Self::InMem(db) => db.get_global_commitment_tree(),
+            Self::File(db) => db.get_global_commitment_tree(),
+        }
+    }
+
+    fn store_global_commitment_tree(&mut self, new_tree: Self::CommitmentTree) {
+        match self {
+            Self::InMem(db) => db.store_global_commitment_tree(new_tree),
+            Self::File(db) => db.store_global_commitment_tree(new_tree),
+        }
+    }
+
+    fn get_global_nullifier_tree(&self) -> Self::NullifierTree {
+        match self {
+            Self::InMem(db) => db.get_global_nullifier_tree(),
+            Self::File(db) => db.get_global_nullifier_tree(),
+        }
+    }
+
+    fn store_global_nullifier_tree(&mut self, new_tree: Self::NullifierTree) {
+        match self {
+            Self::InMem(db) => db.store_global_nullifier_tree(new_tree),
+            Self::File(db) => db.store_global_nullifier_tree(new_tree),
+        }
+    }
+
+    fn get_vk_tree(&self) -> Self::VkTree {
+        match self {
+            Self::InMem(db) => db.get_vk_tree(),
+            Self::File(db) => db.get_vk_tree(),
+        }
+    }
+
+    fn store_vk_tree(&mut self, vk_tree: Self::VkTree) {
+        match self {
+            Self::InMem(db) => db.store_vk_tree(vk_tree),
+            Self::File(db) => db.store_vk_tree(vk_tree),
+        }
+    }
+
+    fn get_global_roots(&self) -> GlobalRoots<Fr> {
+        match self {
+            Self::InMem(db) => db.get_global_roots(),
+            Self::File(db) => db.get_global_roots(),
+        }
+    }
+
+    fn is_nullifier_spent(&self, nullifier: Fr) -> bool {
+        match self {
+            Self::InMem(db) => db.is_nullifier_spent(nullifier),
+            Self::File(db) => db.is_nullifier_spent(nullifier),
+        }
+    }
+
+    fn apply_state_transition(
+        &mut self,
+        transition: StateTransition<Self::CommitmentTree, Self::NullifierTree>,
+    ) {
+        match self {
+            Self::InMem(db) => db.apply_state_transition(transition),
+            Self::File(db) => db.apply_state_transition(transition),
+        }
+    }
+}
+
+impl BlockCommitStorage<VestaConfig> for SequencerStorage {
+    fn commit_block(
+        &mut self,
+        block: Block<curves::vesta::Fr>,
+        transition: StateTransition<Self::CommitmentTree, Self::NullifierTree>,
+        transactions: &[Transaction<VestaConfig>],
+    ) -> Option<()> {
+        match self {
+            Self::InMem(db) => db.commit_block(block, transition, transactions),
+            Self::File(db) => db.commit_block(block, transition, transactions),
+        }
+    }
+}
@@ -5,21 +5,20 @@ use ark_ec::{
 };
 use ark_ff::PrimeField;
 use ark_poly::univariate::DensePolynomial;
-use common::{crypto::poseidon::constants::PoseidonParams, structs::Block};
+use common::{
+    crypto::poseidon::constants::PoseidonParams,
+    structs::{Block, BlockHeader, BlockProof, BlockTransaction, Withdrawal},
+};
 use jf_primitives::rescue::RescueParameter;
 use jf_relation::gadgets::ecc::SWToTEConParam;
+use plonk_prover::rollup::block::verifying_key_hash;
 use plonk_prover::rollup::circuits::client_input::ClientInput;
-use trees::{membership_tree::Tree, non_membership_tree::IndexedMerkleTree, tree::AppendTree};
 use zk_macros::sequencer_bounds;
 
 use crate::{
     domain::{RollupCommitKeys, RollupProvingKeys},
-    ports::{
-        prover::SequencerProver,
-        storage::{BlockStorage, GlobalStateStorage},
-    },
+    ports::{prover::SequencerProver, storage::GlobalRoots},
 };
-use tokio::sync::MutexGuard;
 use tracing_log::log;
 
 use super::BuildBlockError;
@@ -47,61 +46,81 @@ use super::BuildBlockError;
 // }
 //
 
+// Proves a block without holding the storage or the prover, `roots` being the
+// global state before the block that every base rollup is proven against
 #[sequencer_bounds]
-pub async fn build_block<P, V, SW, VSW, Storage, Prover>(
-    db_locked: &mut MutexGuard<'_, Storage>,
+pub async fn build_block<P, V, SW, VSW, Prover>(
+    roots: GlobalRoots<V::BaseField>,
+    header: BlockHeader,
+    // Root of the global commitment tree staged with the commitments of this block
+    global_commitment_tree_root: V::BaseField,
     client_inputs: Vec<ClientInput<V>>,
+    transactions: Vec<BlockTransaction<V::ScalarField>>,
     nullifiers: Vec<V::ScalarField>,
     commitments: Vec<V::ScalarField>,
     g_polys: Vec<DensePolynomial<V::ScalarField>>,
     local_commitment_root: V::ScalarField,
+    base_size: usize,
     commit_keys: RollupCommitKeys<V, VSW, P, SW>,
-    proving_keys: Option<RollupProvingKeys<V, VSW, P, SW>>,
+    proving_keys: RollupProvingKeys<V, VSW, P, SW>,
 ) -> Result<Block<V::ScalarField>, BuildBlockError>
 where
-    Storage: GlobalStateStorage<
-            CommitmentTree = Tree<V::BaseField, 8>,
-            VkTree = Tree<V::BaseField, 8>,
-            NullifierTree = IndexedMerkleTree<V::BaseField, 32>,
-        > + BlockStorage<V::ScalarField>,
     Prover: SequencerProver<V, VSW, P, SW>,
 {
-    // client_inputs: [ClientInput<V, 1, 1>; 2],
-    // global_vk_root: P::ScalarField,
-    // global_nullifier_root: P::ScalarField,
-    // global_nullifier_leaf_count: P::ScalarField,
-    // global_commitment_root: P::ScalarField,
-    // g_polys: [DensePolynomial<P::BaseField>; 2],
-    // commit_key: CommitKey<V>,
-    let vk_tree_root = db_locked.get_vk_tree().root();
-    // Global nullifier tree, updated with the latest transactions to be processed in this block
-    let global_nullifier_tree = db_locked.get_global_nullifier_tree();
-    let global_nullifier_tree_root = global_nullifier_tree.root();
-    let global_nullifier_tree_leaf_count = V::BaseField::from(global_nullifier_tree.leaf_count());
-    // Global commitment tree updated with root formed with all commitments processed in this block
-    let global_commitment_tree_root = db_locked.get_global_commitment_tree().root();
-    let block_count = db_locked.get_block_count();
+    let block_count = header.block_number;
+
+    // One batch per base rollup, each with `base_size` transactions
+    let client_inputs = client_inputs
+        .chunks(base_size)
+        .map(|batch| batch.to_vec())
+        .collect::<Vec<_>>();
+    let g_polys = g_polys
+        .chunks(base_size)
+        .map(|batch| batch.to_vec())
+        .collect::<Vec<_>>();
+
+    let base_rollups = client_inputs.len() as u64;
 
     log::debug!("build_block");
     let block =
         tokio::task::spawn_blocking(move || -> Result<Block<V::ScalarField>, BuildBlockError> {
-            Prover::rollup_proof(
+            let (rollup_proof, vk, accumulators) = Prover::rollup_proof(
                 client_inputs,
-                vk_tree_root,
-                global_nullifier_tree_root,
-                global_nullifier_tree_leaf_count,
+                roots.vk_root,
+                roots.nullifier_root,
+                V::BaseField::from(roots.nullifier_leaf_count),
                 global_commitment_tree_root,
                 g_polys,
                 commit_keys,
                 proving_keys,
             )
-            .map_err(|e| BuildBlockError::BlockError(e.to_string()))?;
+            .map_err(|e| BuildBlockError::ProverError(e.to_string()))?;
+            let proof = BlockProof {
+                circuit_type: rollup_proof.circuit_type(),
+                vk_hash: verifying_key_hash(&vk)
+                    .map_err(|e| BuildBlockError::ProverError(e.to_string()))?,
+                rollup_proof: rollup_proof
+                    .to_bytes()
+                    .map_err(|e| BuildBlockError::ProverError(e.to_string()))?,
+                accumulators: accumulators
+                    .to_bytes()
+                    .map_err(|e| BuildBlockError::ProverError(e.to_string()))?,
+                base_rollups,
+            };
 
+            let withdrawals = transactions
+                .iter()
+                .filter_map(Withdrawal::from_block_transaction)
+                .collect();
             Ok(Block {
                 block_number: block_count,
+                header,
                 commitments,
                 nullifiers,
                 commitment_root: local_commitment_root,
+                transactions,
+                withdrawals,
+                proof: Some(proof),
             })
         })
         .await;
@@ -109,15 +128,13 @@ where
     let block = match block {
         Ok(inner_result) => inner_result?,
         Err(e) => {
-            return Err(BuildBlockError::BlockError(format!(
+            return Err(BuildBlockError::ProverError(format!(
                 "Task panicked: {:?}",
                 e
             )));
         }
     };
 
-    db_locked.insert_block(block.clone());
-
     Ok(block)
 
     // Given transaction list, produce a block
@@ -48,6 +48,12 @@ use super::BuildBlockError;
 // }
 //
 
+// Builds the client inputs of `transactions`, one batch of `base_size` per base
+// rollup. Every base is proven against the global state before the block, so
+// the low nullifier witnesses of a batch are built against the nullifier tree
+// before the block updated only with the nullifiers of that batch. The
+// returned nullifier tree is the one staged with every nullifier of the block,
+// which also rejects a nullifier spent twice across batches.
 #[prover_bounds]
 pub async fn build_client_inputs_and_update_nullifier_tree<
     P,
@@ -65,58 +71,71 @@ pub async fn build_client_inputs_and_update_nullifier_tree<
     db_locked: &MutexGuard<'_, Storage>,
     prover: &MutexGuard<'_, Proof>,
     transactions: &[Transaction<V>],
-) -> Result<Vec<ClientInput<V>>, BuildBlockError> {
+    base_size: usize,
+) -> Result<(Vec<ClientInput<V>>, IndexedMerkleTree<V::BaseField, 32>), BuildBlockError> {
     let vk_tree = db_locked.get_vk_tree();
     let mut client_inputs = Vec::new();
     let mut nullifier_tree = db_locked.get_global_nullifier_tree();
     // global_comm_roots is a vector with the roots of all transaction nullified commitments, one element per transaction
     let mut global_comm_roots: Vec<<P as Pairing>::ScalarField> = Vec::new();
-    for (idx, transaction) in transactions.iter().enumerate() {
-        let transaction_type = &transaction.circuit_type;
-        let vk_info = prover
-            .get_vk(transaction_type.clone())
-            .ok_or(BuildBlockError::VksNotFound)?;
-        let public_input: ClientPubInput<<V as Pairing>::ScalarField> = transaction.into();
-        let low_nullifier_info = client_input::update_nullifier_tree::<V, 32>(
-            &mut nullifier_tree,
-            &public_input.nullifiers,
-        )
-        .map_err(|_| BuildBlockError::InvalidNullifier)?;
-        let (vk, vk_idx) = vk_info;
-        let mut client_input = ClientInput::<V>::new(
-            transaction.proof.clone(),
-            vk.clone(),
-            public_input.commitments.len(),
-            public_input.nullifiers.len(),
-        );
-        client_input
-            .set_eph_pub_key(
-                client_input::to_eph_key_array::<V>(public_input.ephemeral_public_key.clone())
-                    .unwrap(),
+    for batch in transactions.chunks(base_size) {
+        let mut batch_nullifier_tree = db_locked.get_global_nullifier_tree();
+        for transaction in batch {
+            let transaction_type = &transaction.circuit_type;
+            let vk_info = prover
+                .get_vk(transaction_type.clone())
+                .ok_or(BuildBlockError::VksNotFound)?;
+            let public_input: ClientPubInput<<V as Pairing>::ScalarField> = transaction.into();
+            client_input::update_nullifier_tree::<V, 32>(
+                &mut nullifier_tree,
+                &public_input.nullifiers,
             )
-            .set_ciphertext(
-                client_input::to_ciphertext_array::<V>(public_input.ciphertexts.clone()).unwrap(),
+            .map_err(|_| BuildBlockError::InvalidNullifier)?;
+            let low_nullifier_info = client_input::update_nullifier_tree::<V, 32>(
+                &mut batch_nullifier_tree,
+                &public_input.nullifiers,
             )
-            .set_nullifiers(&public_input.nullifiers)
-            .set_commitments(&public_input.commitments)
-            .set_commitment_tree_root(&public_input.commitment_root);
-        client_input.vk_paths = vk_tree
-            .membership_witness(vk_idx)
-            .ok_or(BuildBlockError::VksNotFound)?
-            .as_vec();
-        client_input.vk_path_index = V::BaseField::from(vk_idx as u32);
-        if let Some(info) = low_nullifier_info {
-            global_comm_roots.push(field_switching(&public_input.commitment_root[0]));
+            .map_err(|_| BuildBlockError::InvalidNullifier)?;
+            let (vk, vk_idx) = vk_info;
+            let mut client_input = ClientInput::<V>::new(
+                transaction.proof.clone(),
+                vk.clone(),
+                public_input.commitments.len(),
+                public_input.nullifiers.len(),
+            );
             client_input
-                .set_commitment_path_index(idx)
-                .set_low_nullifier_info(&info);
+                .set_swap_field(transaction.swap_field)
+                .set_eph_pub_key(
+                    client_input::to_eph_key_array::<V>(public_input.ephemeral_public_key.clone())
+                        .unwrap(),
+                )
+                .set_ciphertext(
+                    client_input::to_ciphertext_array::<V>(public_input.ciphertexts.clone())
+                        .unwrap(),
+                )
+                .set_nullifiers(&public_input.nullifiers)
+                .set_commitments(&public_input.commitments)
+                .set_commitment_tree_root(&public_input.commitment_root);
+            client_input.vk_paths = vk_tree
+                .membership_witness(vk_idx)
+                .ok_or(BuildBlockError::VksNotFound)?
+                .as_vec();
+            client_input.vk_path_index = V::BaseField::from(vk_idx as u32);
+            if let Some(info) = low_nullifier_info {
+                // Every base shares the global commitment root, so the path is
+                // to the root of the spent roots of the whole block
+                client_input
+                    .set_commitment_path_index(global_comm_roots.len())
+                    .set_low_nullifier_info(&info);
+                global_comm_roots.push(field_switching(&public_input.commitment_root[0]));
+            }
+            client_inputs.push(client_input);
         }
-        client_inputs.push(client_input);
     }
     let global_root_tree: Tree<V::BaseField, 8> = Tree::from_leaves(global_comm_roots.clone());
     for client_input in &mut client_inputs {
         client_input.set_commitment_path(&global_root_tree);
     }
 
-    Ok(client_inputs)
+    Ok((client_inputs, nullifier_tree))
 }
@@ -1,6 +1,8 @@
-use crate::domain::{RollupCommitKeys, RollupProvingKeys};
+use crate::domain::RollupCommitKeys;
 use crate::ports::prover::SequencerProver;
-use crate::ports::storage::{BlockStorage, GlobalStateStorage, TransactionStorage};
+use crate::ports::storage::{
+    BlockCommitStorage, BlockStorage, GlobalStateStorage, StateTransition, TransactionStorage,
+};
 use ark_ec::{
     pairing::Pairing,
     short_weierstrass::{Affine, Projective, SWCurveConfig},
@@ -10,7 +12,10 @@ use ark_ff::{PrimeField, Zero};
 use ark_poly::univariate::DensePolynomial;
 use common::crypto::poseidon::constants::PoseidonParams;
 use common::ports::notifier::Notifier;
-use common::structs::{Block, Transaction};
+use common::structs::{
+    canonical_hex, transaction_hash, transactions_root, Block, BlockHeader, BlockTransaction,
+    GlobalState, Transaction,
+};
 use jf_primitives::rescue::RescueParameter;
 use jf_relation::gadgets::ecc::SWToTEConParam;
 use jf_utils::field_switching;
@@ -23,36 +28,68 @@ use zk_macros::prover_bounds;
 
 mod build;
 pub mod inputs;
+mod pairing;
+pub mod producer;
 
 #[derive(Debug)]
 pub enum BuildBlockError {
     VksNotFound,
     CommitKeysNotFound,
+    ProvingKeysNotFound,
     BlockError(String),
     InvalidNullifierPath,
     InvalidNullifier,
     NotifierError,
     DispatcherNotFound,
+    NotEnoughTransactions,
+    // The rollup proof failed, which says nothing about the transactions
+    ProverError(String),
+    // Another block was stored while this one was proven
+    StaleBlock,
+}
+
+impl BuildBlockError {
+    // Failures caused by the selected transactions rather than by the sequencer
+    // setup. Selecting the same transactions again would fail the same way.
+    fn is_batch_error(&self) -> bool {
+        matches!(
+            self,
+            BuildBlockError::InvalidNullifierPath | BuildBlockError::InvalidNullifier
+        )
+    }
+}
+
+// Rejects the transactions of a batch that can't be rolled up, so that neither
+// the producer nor the REST API keep selecting them. Their senders see them
+// rejected and may submit them again.
+fn reject_batch<V, Storage>(
+    db_locked: &mut MutexGuard<'_, Storage>,
+    transactions: &[Transaction<V>],
+    error: BuildBlockError,
+) -> BuildBlockError
+where
+    V: Pairing,
+    <<V as Pairing>::G1 as CurveGroup>::Config: SWCurveConfig,
+    Storage: TransactionStorage<V>,
+{
+    if error.is_batch_error() {
+        for transaction in transactions {
+            let hash = transaction_hash(transaction);
+            log::warn!("Rejecting transaction {} of a failed block", hash);
+            db_locked.evict_transaction(&hash, format!("Block production failed: {:?}", error));
+        }
+    }
+    error
 }
 
 #[prover_bounds]
 fn get_keys<P, V, SW, VSW, Proof>(
     prover: &MutexGuard<'_, Proof>,
-) -> Result<
-    (
-        Option<RollupProvingKeys<V, VSW, P, SW>>,
-        RollupCommitKeys<V, VSW, P, SW>,
-    ),
-    BuildBlockError,
->
+) -> Result<RollupCommitKeys<V, VSW, P, SW>, BuildBlockError>
 where
     Proof: SequencerProver<V, VSW, P, SW>,
 {
-    let proving_keys = prover.get_pks();
-    let commit_keys = prover
-        .get_cks()
-        .ok_or(BuildBlockError::CommitKeysNotFound)?;
-    Ok((proving_keys, commit_keys))
+    prover.get_cks().ok_or(BuildBlockError::CommitKeysNotFound)
 }
 
 fn get_g_polys<V>(transactions: &[Transaction<V>]) -> Vec<DensePolynomial<V::ScalarField>>
@@ -66,10 +103,12 @@ where
         .collect::<Vec<_>>()
 }
 
-fn get_commitments_and_update_tree<V, Storage>(
-    db_locked: &mut MutexGuard<'_, Storage>,
+// Appends the commitment roots of `transactions` to a copy of the global
+// commitment tree, which is returned alongside the block commitments and their root
+fn get_commitments_and_staged_tree<V, Storage>(
+    db_locked: &MutexGuard<'_, Storage>,
     transactions: &[Transaction<V>],
-) -> (Vec<V::ScalarField>, V::ScalarField)
+) -> (Vec<V::ScalarField>, V::ScalarField, Tree<V::BaseField, 8>)
 where
     V: Pairing,
     <V as Pairing>::BaseField: PoseidonParams<Field = V::BaseField>,
@@ -85,9 +124,16 @@ where
     let commitments = transactions
         .iter()
         .flat_map(|tx| {
+            // The incoming commitment of a swap half is published by its counterparty
+            let n_commitments = if tx.swap_field {
+                1
+            } else {
+                tx.commitments.len()
+            };
             let tx_commitments: Vec<_> = tx
                 .commitments
                 .iter()
+                .take(n_commitments)
                 .map(|c| c.0)
                 .filter(|&c| c != V::ScalarField::zero())
                 .collect();
@@ -101,9 +147,54 @@ where
 
     let local_commitment_tree: Tree<V::ScalarField, 8> = Tree::from_leaves(commitments.clone());
     let local_commitment_tree_root = local_commitment_tree.root();
-    db_locked.store_global_commitment_tree(global_commitment_tree);
 
-    (commitments, local_commitment_tree_root)
+    (
+        commitments,
+        local_commitment_tree_root,
+        global_commitment_tree,
+    )
+}
+
+// Header of the next block: its parent, the global state before the block and
+// the staged state after it
+fn get_block_header<V, Storage>(
+    db_locked: &MutexGuard<'_, Storage>,
+    transition: &StateTransition<Tree<V::BaseField, 8>, IndexedMerkleTree<V::BaseField, 32>>,
+    transactions: &[BlockTransaction<V::ScalarField>],
+) -> BlockHeader
+where
+    V: Pairing,
+    <V as Pairing>::BaseField: PoseidonParams<Field = V::BaseField>,
+    <<V as Pairing>::G1 as CurveGroup>::Config: SWCurveConfig,
+    Storage: GlobalStateStorage<
+            CommitmentTree = Tree<V::BaseField, 8>,
+            VkTree = Tree<V::BaseField, 8>,
+            NullifierTree = IndexedMerkleTree<V::BaseField, 32>,
+        > + BlockStorage<V::ScalarField>,
+{
+    let block_number = db_locked.get_block_count();
+    let parent_hash = block_number
+        .checked_sub(1)
+        .and_then(|n| db_locked.get_block(n))
+        .map(|parent| parent.hash())
+        .unwrap_or_default();
+    let roots = db_locked.get_global_roots();
+    BlockHeader {
+        block_number,
+        parent_hash,
+        previous_state: GlobalState::new(
+            &roots.commitment_root,
+            &roots.nullifier_root,
+            roots.nullifier_leaf_count,
+        ),
+        new_state: GlobalState::new(
+            &transition.commitment_tree.root(),
+            &transition.nullifier_tree.root(),
+            transition.nullifier_tree.leaf_count(),
+        ),
+        vk_root: canonical_hex(&roots.vk_root),
+        transactions_root: transactions_root(transactions),
+    }
 }
 
 fn get_nullifiers<V>(transactions: &[Transaction<V>]) -> Vec<V::ScalarField>
@@ -136,7 +227,8 @@ pub async fn build_block_process<
             CommitmentTree = Tree<V::BaseField, 8>,
             VkTree = Tree<V::BaseField, 8>,
             NullifierTree = IndexedMerkleTree<V::BaseField, 32>,
-        > + BlockStorage<V::ScalarField>,
+        > + BlockStorage<V::ScalarField>
+        + BlockCommitStorage<V>,
     Comms: Notifier<Info = Block<V::ScalarField>>,
 >(
     db: Arc<Mutex<Storage>>,
@@ -147,36 +239,86 @@ pub async fn build_block_process<
     let prover = prover.lock().await;
     let mut db_locked = db.lock().await;
 
-    let (proving_keys, commit_keys) = get_keys(&prover)?;
-    let transactions = db_locked.get_mempool_transactions();
+    let commit_keys = get_keys(&prover)?;
+    let (shape, transactions) = pairing::select_block_transactions(
+        db_locked.get_mempool_transactions(),
+        &prover.get_rollup_shapes(),
+    )
+    .ok_or(BuildBlockError::NotEnoughTransactions)?;
+    // Keys are generated at startup, never while building a block
+    let proving_keys = prover
+        .get_pks(&shape)
+        .ok_or(BuildBlockError::ProvingKeysNotFound)?;
     let g_polys = get_g_polys(&transactions);
     let nullifiers = get_nullifiers(&transactions);
+    let block_transactions = transactions
+        .iter()
+        .map(BlockTransaction::from)
+        .collect::<Vec<_>>();
 
-    let inputs =
-        inputs::build_client_inputs_and_update_nullifier_tree::<P, V, SW, VSW, Storage, Proof>(
-            &db_locked,
-            &prover,
-            &transactions,
-        )
-        .await?;
+    let (inputs, nullifier_tree) = match inputs::build_client_inputs_and_update_nullifier_tree::<
+        P,
+        V,
+        SW,
+        VSW,
+        Storage,
+        Proof,
+    >(&db_locked, &prover, &transactions, shape.len())
+    .await
+    {
+        Ok(built) => built,
+        Err(e) => return Err(reject_batch(&mut db_locked, &transactions, e)),
+    };
     // root stores the root of the tree formed by all commitments in all transactions submitted
-    let (commitments, commitments_root) =
-        get_commitments_and_update_tree(&mut db_locked, &transactions);
+    let (commitments, commitments_root, commitment_tree) =
+        get_commitments_and_staged_tree(&db_locked, &transactions);
+    // Nothing is stored until the rollup proof succeeds
+    let transition = StateTransition {
+        commitment_tree,
+        nullifier_tree,
+    };
+    let header = get_block_header::<V, _>(&db_locked, &transition, &block_transactions);
+    let roots = db_locked.get_global_roots();
+    // The block is proven from this snapshot, so transactions are admitted and
+    // the state is queried meanwhile
+    drop(db_locked);
+    drop(prover);
 
-    let block = build::build_block::<P, V, SW, VSW, Storage, Proof>(
-        &mut db_locked,
+    let block = build::build_block::<P, V, SW, VSW, Proof>(
+        roots,
+        header,
+        transition.commitment_tree.root(),
         inputs,
+        block_transactions,
         nullifiers,
         commitments,
         g_polys,
         commitments_root,
+        shape.len(),
         commit_keys,
         proving_keys,
     )
     .await
-    .map_err(|_| BuildBlockError::BlockError("Unable to build block".to_string()))?;
+    .map_err(|e| {
+        log::error!("Discarding block: {:?}", e);
+        e
+    })?;
 
-    db_locked.flush_mempool_transactions();
+    // The block, the global trees it changed and the mempool are updated together while
+    // the storage is locked, so no one observes a partially applied block. A block built
+    // alongside this one may have been stored first, then this one no longer applies.
+    let mut db_locked = db.lock().await;
+    if db_locked.get_block_count() != block.block_number || db_locked.get_global_roots() != roots {
+        log::warn!(
+            "Discarding block {}, another block was stored first",
+            block.block_number
+        );
+        return Err(BuildBlockError::StaleBlock);
+    }
+    db_locked
+        .commit_block(block.clone(), transition, &transactions)
+        .ok_or_else(|| BuildBlockError::BlockError("Unable to store block".to_string()))?;
+    drop(db_locked);
 
     let notifier = notifier.lock().await;
     notifier
@@ -186,3 +328,65 @@ pub async fn build_block_process<
 
     Ok(block)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::transaction::TransactionStatus;
+    use crate::services::storage::in_mem_sequencer_storage::InMemStorage;
+    use crate::utils::test_transactions::{spending, MINT};
+    use curves::vesta::{Fr, VestaConfig};
+
+    fn storage_with(transactions: &[Transaction<VestaConfig>]) -> Mutex<InMemStorage> {
+        let mut db = InMemStorage::new();
+        for transaction in transactions {
+            db.insert_transaction(transaction.clone()).unwrap();
+        }
+        Mutex::new(db)
+    }
+
+    #[tokio::test]
+    async fn test_failed_batch_is_rejected() {
+        let batch = [MINT.1.clone(), spending(&[1], Fr::from(0u64))];
+        let other = spending(&[2], Fr::from(0u64));
+        let db = storage_with(&[batch[0].clone(), batch[1].clone(), other.clone()]);
+        let mut db_locked = db.lock().await;
+
+        reject_batch(&mut db_locked, &batch, BuildBlockError::InvalidNullifier);
+
+        assert_eq!(db_locked.get_mempool_transactions(), vec![other]);
+        for transaction in batch.iter() {
+            assert!(matches!(
+                db_locked.get_transaction_status(&transaction_hash(transaction)),
+                Some(TransactionStatus::Rejected { .. })
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_setup_failures_keep_the_batch_pending() {
+        let batch = [MINT.1.clone(), spending(&[1], Fr::from(0u64))];
+        let db = storage_with(&batch);
+        let mut db_locked = db.lock().await;
+
+        reject_batch(&mut db_locked, &batch, BuildBlockError::VksNotFound);
+
+        assert_eq!(db_locked.get_mempool_transactions(), batch.to_vec());
+    }
+
+    #[tokio::test]
+    async fn test_prover_failures_keep_the_batch_pending() {
+        let batch = [MINT.1.clone(), spending(&[1], Fr::from(0u64))];
+        let db = storage_with(&batch);
+        let mut db_locked = db.lock().await;
+
+        reject_batch(
+            &mut db_locked,
+            &batch,
+            BuildBlockError::ProverError("Unsatisfied circuit".to_string()),
+        );
+        reject_batch(&mut db_locked, &batch, BuildBlockError::StaleBlock);
+
+        assert_eq!(db_locked.get_mempool_transactions(), batch.to_vec());
+    }
+}
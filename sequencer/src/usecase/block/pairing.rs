@@ -0,0 +1,289 @@
+use ark_ec::{pairing::Pairing, short_weierstrass::SWCurveConfig, CurveGroup};
+use ark_ff::PrimeField;
+use common::structs::{CircuitType, Commitment, Transaction};
+
+use crate::domain::{RollupShape, MAX_BLOCK_TRANSACTIONS};
+
+/// Two swap halves match when each one sends its commitment to the other as its expected
+/// incoming commitment.
+fn is_swap_pair<V>(a: &Transaction<V>, b: &Transaction<V>) -> bool
+where
+    V: Pairing,
+    <<V as Pairing>::G1 as CurveGroup>::Config: SWCurveConfig,
+{
+    a.swap_field && b.swap_field && swap_commitments_match(&a.commitments, &b.commitments)
+}
+
+/// A swap half outputs the commitment it sends, then the one it expects in return.
+fn swap_commitments_match<F: PrimeField>(a: &[Commitment<F>], b: &[Commitment<F>]) -> bool {
+    a.len() == 2 && b.len() == 2 && a[0] == b[1] && b[0] == a[1]
+}
+
+/// Selects the transactions included in the next block from the mempool, oldest first, together
+/// with the shape of its base rollups. Only shapes in `shapes` have rollup keys. The base rollup
+/// checks a swap across its two inputs, so a matched pair of swap halves is rolled up on its own.
+/// Otherwise the block is the largest one some shape can prove, see `fill_shape`. Shapes of a
+/// single transaction let any other transaction be included without waiting for a second one.
+/// Unmatched swap halves wait for their counterparty.
+pub(crate) fn select_block_transactions<V>(
+    mempool: Vec<Transaction<V>>,
+    shapes: &[RollupShape],
+) -> Option<(RollupShape, Vec<Transaction<V>>)>
+where
+    V: Pairing,
+    <<V as Pairing>::G1 as CurveGroup>::Config: SWCurveConfig,
+{
+    let swap_shape = vec![CircuitType::Swap, CircuitType::Swap];
+    if shapes.contains(&swap_shape) {
+        let pair = (0..mempool.len()).find_map(|i| {
+            (i + 1..mempool.len())
+                .find(|&j| is_swap_pair(&mempool[i], &mempool[j]))
+                .map(|j| (i, j))
+        });
+        if let Some((i, j)) = pair {
+            return Some((swap_shape, vec![mempool[i].clone(), mempool[j].clone()]));
+        }
+    }
+
+    let pending = mempool
+        .into_iter()
+        .filter(|tx| !tx.swap_field)
+        .collect::<Vec<_>>();
+    shapes
+        .iter()
+        .filter(|shape| !shape.is_empty() && !shape.contains(&CircuitType::Swap))
+        .filter_map(|shape| {
+            let indices = fill_shape(&pending, shape);
+            // Prefer the largest block, then the one including the oldest transaction,
+            // then the one with the fewest base rollups to prove
+            let oldest = *indices.iter().min()?;
+            let key = (indices.len(), std::cmp::Reverse(oldest), shape.len());
+            Some((key, shape, indices))
+        })
+        .max_by_key(|(key, _, _)| *key)
+        .map(|(_, shape, indices)| {
+            let transactions = indices.into_iter().map(|i| pending[i].clone()).collect();
+            (shape.clone(), transactions)
+        })
+}
+
+/// Indices of the transactions in `pending` filling the most base rollups of `shape`, each base
+/// rollup taking the oldest remaining transaction of every circuit type in the shape, in order.
+/// Base rollups are merged in pairs, so only a power of two of them, up to
+/// `MAX_BLOCK_TRANSACTIONS` transactions, are kept.
+fn fill_shape<V>(pending: &[Transaction<V>], shape: &RollupShape) -> Vec<usize>
+where
+    V: Pairing,
+    <<V as Pairing>::G1 as CurveGroup>::Config: SWCurveConfig,
+{
+    let mut used = vec![false; pending.len()];
+    let mut batches = Vec::new();
+    'batches: while (batches.len() + 1) * shape.len() <= MAX_BLOCK_TRANSACTIONS {
+        let mut batch = Vec::with_capacity(shape.len());
+        for circuit_type in shape.iter() {
+            let next = (0..pending.len()).find(|&i| {
+                !used[i] && !batch.contains(&i) && pending[i].circuit_type == *circuit_type
+            });
+            match next {
+                Some(i) => batch.push(i),
+                None => break 'batches,
+            }
+        }
+        for &i in batch.iter() {
+            used[i] = true;
+        }
+        batches.push(batch);
+    }
+    if batches.is_empty() {
+        return vec![];
+    }
+    batches.truncate(1 << batches.len().ilog2());
+    batches.into_iter().flatten().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::test_transactions::{spending, MINT};
+    use curves::vesta::{Fr, VestaConfig};
+
+    fn commitments(values: &[u64]) -> Vec<Commitment<Fr>> {
+        values.iter().map(|v| Commitment(Fr::from(*v))).collect()
+    }
+
+    fn transfers(nullifiers: std::ops::Range<u64>) -> Vec<Transaction<VestaConfig>> {
+        nullifiers.map(|n| spending(&[n], Fr::from(0u64))).collect()
+    }
+
+    fn swap_half(sent: u64, expected: u64) -> Transaction<VestaConfig> {
+        let mut transaction = MINT.1.clone();
+        transaction.circuit_type = CircuitType::Swap;
+        transaction.swap_field = true;
+        transaction.commitments = commitments(&[sent, expected]);
+        transaction
+    }
+
+    fn shape(circuit_type: CircuitType, len: usize) -> RollupShape {
+        vec![circuit_type; len]
+    }
+
+    #[test]
+    fn test_selects_the_largest_block() {
+        let mut mempool = vec![MINT.1.clone(), MINT.1.clone()];
+        mempool.extend(transfers(1..5));
+        let shapes = [
+            shape(CircuitType::Mint(1), 2),
+            shape(CircuitType::Transfer(1, 1), 2),
+        ];
+
+        let (selected, transactions) = select_block_transactions(mempool, &shapes).unwrap();
+
+        assert_eq!(selected, shapes[1]);
+        assert_eq!(transactions, transfers(1..5));
+    }
+
+    #[test]
+    fn test_equal_blocks_include_the_oldest_transaction() {
+        let mut mempool = transfers(1..3);
+        mempool.extend([MINT.1.clone(), MINT.1.clone()]);
+        let shapes = [
+            shape(CircuitType::Mint(1), 2),
+            shape(CircuitType::Transfer(1, 1), 2),
+        ];
+
+        let (selected, transactions) = select_block_transactions(mempool, &shapes).unwrap();
+
+        assert_eq!(selected, shapes[1]);
+        assert_eq!(transactions, transfers(1..3));
+    }
+
+    #[test]
+    fn test_base_rollups_are_truncated_to_a_power_of_two() {
+        // Three base rollups can be filled, only the two oldest are kept
+        let shapes = [shape(CircuitType::Transfer(1, 1), 2)];
+
+        let (_, transactions) = select_block_transactions(transfers(1..8), &shapes).unwrap();
+
+        assert_eq!(transactions, transfers(1..5));
+    }
+
+    #[test]
+    fn test_block_is_bounded_by_max_block_transactions() {
+        let shapes = [shape(CircuitType::Transfer(1, 1), 1)];
+        let mempool = transfers(1..(MAX_BLOCK_TRANSACTIONS as u64 + 10));
+
+        let (_, transactions) = select_block_transactions(mempool, &shapes).unwrap();
+
+        assert_eq!(
+            transactions,
+            transfers(1..(MAX_BLOCK_TRANSACTIONS as u64 + 1))
+        );
+    }
+
+    #[test]
+    fn test_no_block_without_a_filled_shape() {
+        let shapes = [shape(CircuitType::Transfer(1, 1), 2)];
+
+        assert!(select_block_transactions(transfers(1..2), &shapes).is_none());
+        assert!(select_block_transactions(vec![MINT.1.clone()], &shapes).is_none());
+    }
+
+    #[test]
+    fn test_lone_transaction_is_sequenced() {
+        let shapes = [
+            shape(CircuitType::Transfer(1, 1), 2),
+            shape(CircuitType::Transfer(1, 1), 1),
+            shape(CircuitType::Mint(1), 2),
+            shape(CircuitType::Mint(1), 1),
+        ];
+
+        let (selected, transactions) = select_block_transactions(transfers(1..2), &shapes).unwrap();
+        assert_eq!(selected, shapes[1]);
+        assert_eq!(transactions, transfers(1..2));
+
+        // One transaction of each type, the oldest goes first
+        let mut mempool = transfers(1..2);
+        mempool.push(MINT.1.clone());
+        let (selected, transactions) = select_block_transactions(mempool, &shapes).unwrap();
+        assert_eq!(selected, shapes[1]);
+        assert_eq!(transactions, transfers(1..2));
+    }
+
+    #[test]
+    fn test_pairs_are_preferred_to_single_base_rollups() {
+        let shapes = [
+            shape(CircuitType::Transfer(1, 1), 1),
+            shape(CircuitType::Transfer(1, 1), 2),
+        ];
+
+        let (selected, transactions) = select_block_transactions(transfers(1..3), &shapes).unwrap();
+
+        assert_eq!(selected, shapes[1]);
+        assert_eq!(transactions, transfers(1..3));
+    }
+
+    #[test]
+    fn test_matched_swap_halves_are_rolled_up_alone() {
+        let mut mempool = transfers(1..5);
+        mempool.extend([swap_half(10, 11), swap_half(11, 10)]);
+        let shapes = [
+            shape(CircuitType::Transfer(1, 1), 2),
+            shape(CircuitType::Swap, 2),
+        ];
+
+        let (selected, transactions) = select_block_transactions(mempool, &shapes).unwrap();
+
+        assert_eq!(selected, shapes[1]);
+        assert_eq!(transactions, vec![swap_half(10, 11), swap_half(11, 10)]);
+    }
+
+    #[test]
+    fn test_unmatched_swap_halves_are_skipped() {
+        let mut mempool = vec![swap_half(10, 11), swap_half(12, 13)];
+        mempool.extend(transfers(1..3));
+        let shapes = [
+            shape(CircuitType::Transfer(1, 1), 2),
+            shape(CircuitType::Swap, 2),
+        ];
+
+        let (selected, transactions) = select_block_transactions(mempool, &shapes).unwrap();
+
+        assert_eq!(selected, shapes[0]);
+        assert_eq!(transactions, transfers(1..3));
+        assert!(
+            select_block_transactions(vec![swap_half(10, 11), swap_half(12, 13)], &shapes)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_matching_swap_halves() {
+        let a = commitments(&[1, 2]);
+        let b = commitments(&[2, 1]);
+        assert!(swap_commitments_match(&a, &b));
+        assert!(swap_commitments_match(&b, &a));
+    }
+
+    #[test]
+    fn test_non_matching_swap_halves() {
+        // Only one side of the exchange agrees
+        assert!(!swap_commitments_match(
+            &commitments(&[1, 2]),
+            &commitments(&[2, 3])
+        ));
+        // Both halves expect the same commitment they send
+        assert!(!swap_commitments_match(
+            &commitments(&[1, 2]),
+            &commitments(&[1, 2])
+        ));
+        // Swap halves have exactly two commitments
+        assert!(!swap_commitments_match(
+            &commitments(&[1, 2, 3]),
+            &commitments(&[2, 1, 3])
+        ));
+        assert!(!swap_commitments_match(
+            &commitments(&[]),
+            &commitments(&[])
+        ));
+    }
+}
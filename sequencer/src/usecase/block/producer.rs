@@ -0,0 +1,236 @@
+use crate::ports::prover::SequencerProver;
+use crate::ports::storage::{
+    BlockCommitStorage, BlockStorage, GlobalStateStorage, TransactionStorage,
+};
+use ark_ec::{
+    pairing::Pairing,
+    short_weierstrass::{Affine, Projective, SWCurveConfig},
+    CurveConfig,
+};
+use ark_ff::PrimeField;
+use common::configuration::BlockProductionSettings;
+use common::crypto::poseidon::constants::PoseidonParams;
+use common::ports::notifier::Notifier;
+use common::structs::Block;
+use jf_primitives::rescue::RescueParameter;
+use jf_relation::gadgets::ecc::SWToTEConParam;
+use plonk_prover::primitives::circuits::kem_dem::KemDemParams;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio::time::{Instant, MissedTickBehavior};
+use tracing_log::log;
+use trees::{IndexedMerkleTree, Tree};
+use zk_macros::prover_bounds;
+
+use super::{build_block_process, BuildBlockError};
+
+// When the producer next builds a block
+#[derive(Debug, Default)]
+struct Schedule {
+    // When the producer first saw the current pending transactions
+    pending_since: Option<Instant>,
+    // Builds that failed for reasons other than their transactions, such as
+    // missing keys, are not retried before this
+    retry_at: Option<Instant>,
+}
+
+impl Schedule {
+    fn is_due(&mut self, pending: usize, now: Instant, settings: &BlockProductionSettings) -> bool {
+        if pending == 0 {
+            self.pending_since = None;
+            return false;
+        }
+        if self.retry_at.is_some_and(|retry_at| now < retry_at) {
+            return false;
+        }
+        let since = *self.pending_since.get_or_insert(now);
+        pending >= settings.batch_size || now.duration_since(since) >= settings.max_latency()
+    }
+
+    fn record<T>(
+        &mut self,
+        result: &Result<T, BuildBlockError>,
+        now: Instant,
+        settings: &BlockProductionSettings,
+    ) {
+        self.retry_at = None;
+        match result {
+            Ok(_) => self.pending_since = None,
+            // Only unmatched swap halves or a single transaction are pending
+            Err(BuildBlockError::NotEnoughTransactions) => self.pending_since = Some(now),
+            // The failed batch was rejected, what is left can be built right away
+            Err(e) if e.is_batch_error() => self.pending_since = None,
+            // Its transactions are still pending and go in the next block
+            Err(BuildBlockError::StaleBlock) => self.pending_since = None,
+            Err(_) => {
+                self.pending_since = None;
+                self.retry_at = Some(now + settings.max_latency());
+            }
+        }
+    }
+}
+
+// Builds blocks in the background whenever the mempool holds `batch_size`
+// transactions or has held some for `max_latency`. Blocks requested through
+// the REST API go through the same `build_block_process`, which only stores a
+// block if no other block was stored while it was proven, so neither producer
+// includes transactions the other already included. A batch with invalid
+// witnesses is rejected, so it is never retried. A failed proof leaves the
+// batch pending and is retried after `max_latency`.
+#[prover_bounds]
+pub async fn run_block_producer<
+    P,
+    V,
+    SW,
+    VSW,
+    Proof: SequencerProver<V, VSW, P, SW>,
+    Storage: TransactionStorage<V>
+        + GlobalStateStorage<
+            CommitmentTree = Tree<V::BaseField, 8>,
+            VkTree = Tree<V::BaseField, 8>,
+            NullifierTree = IndexedMerkleTree<V::BaseField, 32>,
+        > + BlockStorage<V::ScalarField>
+        + BlockCommitStorage<V>,
+    Comms: Notifier<Info = Block<V::ScalarField>>,
+>(
+    db: Arc<Mutex<Storage>>,
+    prover: Arc<Mutex<Proof>>,
+    notifier: Arc<Mutex<Comms>>,
+    settings: BlockProductionSettings,
+) {
+    let mut interval = tokio::time::interval(settings.poll_interval());
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let mut schedule = Schedule::default();
+
+    loop {
+        interval.tick().await;
+        let pending = db.lock().await.get_mempool_size();
+        if !schedule.is_due(pending, Instant::now(), &settings) {
+            continue;
+        }
+
+        let result = build_block_process::<P, V, SW, VSW, Proof, Storage, Comms>(
+            db.clone(),
+            prover.clone(),
+            notifier.clone(),
+        )
+        .await;
+        match &result {
+            Ok(block) => log::info!("Produced block {}", block.block_number),
+            Err(BuildBlockError::NotEnoughTransactions) => {}
+            Err(e) => log::error!("Block production failed: {:?}", e),
+        }
+        schedule.record(&result, Instant::now(), &settings);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn settings() -> BlockProductionSettings {
+        BlockProductionSettings {
+            enabled: true,
+            batch_size: 4,
+            max_latency_milliseconds: 1000,
+            poll_interval_milliseconds: 100,
+        }
+    }
+
+    #[test]
+    fn test_full_batch_is_built_right_away() {
+        let mut schedule = Schedule::default();
+        assert!(schedule.is_due(4, Instant::now(), &settings()));
+    }
+
+    #[test]
+    fn test_partial_batch_waits_for_max_latency() {
+        let settings = settings();
+        let mut schedule = Schedule::default();
+        let start = Instant::now();
+
+        assert!(!schedule.is_due(1, start, &settings));
+        assert!(!schedule.is_due(2, start + Duration::from_millis(999), &settings));
+        assert!(schedule.is_due(2, start + settings.max_latency(), &settings));
+
+        // An empty mempool restarts the wait
+        assert!(!schedule.is_due(0, start + settings.max_latency(), &settings));
+        assert!(!schedule.is_due(1, start + settings.max_latency(), &settings));
+    }
+
+    #[test]
+    fn test_rejected_batch_is_not_waited_on() {
+        let settings = settings();
+        let mut schedule = Schedule::default();
+        let now = Instant::now();
+
+        assert!(schedule.is_due(4, now, &settings));
+        schedule.record::<()>(&Err(BuildBlockError::InvalidNullifier), now, &settings);
+        // The rejected transactions left the mempool, another full batch is built
+        assert!(schedule.is_due(4, now, &settings));
+    }
+
+    #[test]
+    fn test_setup_failures_are_retried_after_max_latency() {
+        let settings = settings();
+        let mut schedule = Schedule::default();
+        let now = Instant::now();
+
+        assert!(schedule.is_due(4, now, &settings));
+        schedule.record::<()>(&Err(BuildBlockError::ProvingKeysNotFound), now, &settings);
+        assert!(!schedule.is_due(4, now + Duration::from_millis(999), &settings));
+        assert!(schedule.is_due(4, now + settings.max_latency(), &settings));
+    }
+
+    #[test]
+    fn test_prover_failures_are_retried_after_max_latency() {
+        let settings = settings();
+        let mut schedule = Schedule::default();
+        let now = Instant::now();
+
+        assert!(schedule.is_due(4, now, &settings));
+        let failed = Err(BuildBlockError::ProverError(
+            "Unsatisfied circuit".to_string(),
+        ));
+        schedule.record::<()>(&failed, now, &settings);
+        assert!(!schedule.is_due(4, now + Duration::from_millis(999), &settings));
+        assert!(schedule.is_due(4, now + settings.max_latency(), &settings));
+    }
+
+    #[test]
+    fn test_stale_block_is_rebuilt_right_away() {
+        let settings = settings();
+        let mut schedule = Schedule::default();
+        let now = Instant::now();
+
+        assert!(schedule.is_due(4, now, &settings));
+        schedule.record::<()>(&Err(BuildBlockError::StaleBlock), now, &settings);
+        assert!(schedule.is_due(4, now, &settings));
+    }
+
+    #[test]
+    fn test_unmatched_transactions_wait_for_max_latency() {
+        let settings = settings();
+        let mut schedule = Schedule::default();
+        let now = Instant::now();
+
+        assert!(schedule.is_due(4, now, &settings));
+        schedule.record::<()>(&Err(BuildBlockError::NotEnoughTransactions), now, &settings);
+        assert!(!schedule.is_due(1, now + Duration::from_millis(999), &settings));
+        assert!(schedule.is_due(1, now + settings.max_latency(), &settings));
+    }
+
+    #[test]
+    fn test_built_block_restarts_the_wait() {
+        let settings = settings();
+        let mut schedule = Schedule::default();
+        let start = Instant::now();
+
+        assert!(!schedule.is_due(1, start, &settings));
+        let later = start + settings.max_latency();
+        assert!(schedule.is_due(1, later, &settings));
+        schedule.record(&Ok(()), later, &settings);
+        assert!(!schedule.is_due(1, later, &settings));
+    }
+}
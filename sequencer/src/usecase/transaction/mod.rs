@@ -0,0 +1,392 @@
+use crate::domain::mempool::{transaction_hash, MempoolError};
+use crate::ports::prover::SequencerProver;
+use crate::ports::storage::{BlockStorage, GlobalStateStorage, TransactionStorage};
+use crate::utils::circuits::select_client_circuits_sequencer;
+use ark_ec::{
+    pairing::Pairing,
+    short_weierstrass::{Affine, Projective, SWCurveConfig},
+    CurveConfig,
+};
+use ark_ff::{PrimeField, Zero};
+use common::crypto::poseidon::constants::PoseidonParams;
+use common::structs::{CircuitType, Transaction};
+use jf_plonk::nightfall::ipa_structs::VerifyingKey;
+use jf_primitives::rescue::RescueParameter;
+use jf_relation::gadgets::ecc::SWToTEConParam;
+use jf_utils::field_switching;
+use plonk_prover::client::structs::ClientPubInput;
+use plonk_prover::primitives::circuits::kem_dem::KemDemParams;
+use serde::Serialize;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tracing_log::log;
+use trees::{IndexedMerkleTree, Tree};
+use zk_macros::{client_bounds, prover_bounds};
+
+// Reasons a transaction is refused admission to the mempool
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "reason", content = "detail", rename_all = "snake_case")]
+pub enum TransactionError {
+    UnknownCircuit(CircuitType),
+    InvalidPublicInputs(String),
+    InvalidProof,
+    DuplicatedTransaction(String),
+    DuplicatedNullifier(String),
+    NullifierAlreadySpent(String),
+    NullifierPending(String),
+    UnknownCommitmentRoot(String),
+    MempoolFull(usize),
+    StorageFailure(String),
+}
+
+impl From<MempoolError> for TransactionError {
+    fn from(value: MempoolError) -> Self {
+        match value {
+            MempoolError::DuplicatedTransaction(hash) => {
+                TransactionError::DuplicatedTransaction(hash)
+            }
+            MempoolError::NullifierConflict(nullifier) => {
+                TransactionError::NullifierPending(nullifier)
+            }
+            MempoolError::MempoolFull(capacity) => TransactionError::MempoolFull(capacity),
+            MempoolError::StorageFailure(e) => TransactionError::StorageFailure(e),
+        }
+    }
+}
+
+#[prover_bounds]
+pub async fn submit_transaction_process<P, V, SW, VSW, Proof, Storage>(
+    db: Arc<Mutex<Storage>>,
+    prover: Arc<Mutex<Proof>>,
+    transaction: Transaction<V>,
+) -> Result<(), TransactionError>
+where
+    Proof: SequencerProver<V, VSW, P, SW>,
+    Storage: TransactionStorage<V>
+        + GlobalStateStorage<
+            CommitmentTree = Tree<V::BaseField, 8>,
+            VkTree = Tree<V::BaseField, 8>,
+            NullifierTree = IndexedMerkleTree<V::BaseField, 32>,
+        > + BlockStorage<V::ScalarField>,
+{
+    match admit_transaction::<P, V, SW, VSW, Proof, Storage>(&db, &prover, transaction.clone())
+        .await
+    {
+        Ok(hash) => {
+            log::debug!("Transaction {} added to the mempool", hash);
+            Ok(())
+        }
+        Err(e) => {
+            log::debug!("Rejected transaction: {:?}", e);
+            // A duplicate shares its hash with the transaction already known
+            if !matches!(e, TransactionError::DuplicatedTransaction(_)) {
+                db.lock()
+                    .await
+                    .reject_transaction(transaction_hash(&transaction), format!("{:?}", e));
+            }
+            Err(e)
+        }
+    }
+}
+
+// Verifying the proof takes long enough to stall block production and other
+// submissions, so it runs without holding any lock. The state checks are run
+// again before inserting, as a block may have been stored meanwhile.
+#[prover_bounds]
+async fn admit_transaction<P, V, SW, VSW, Proof, Storage>(
+    db: &Arc<Mutex<Storage>>,
+    prover: &Arc<Mutex<Proof>>,
+    transaction: Transaction<V>,
+) -> Result<String, TransactionError>
+where
+    Proof: SequencerProver<V, VSW, P, SW>,
+    Storage: TransactionStorage<V>
+        + GlobalStateStorage<
+            CommitmentTree = Tree<V::BaseField, 8>,
+            VkTree = Tree<V::BaseField, 8>,
+            NullifierTree = IndexedMerkleTree<V::BaseField, 32>,
+        > + BlockStorage<V::ScalarField>,
+{
+    let vk = get_verifying_key::<P, V, SW, VSW, Proof>(&*prover.lock().await, &transaction)?;
+    let public_input = get_public_input::<P, V, VSW>(&transaction)?;
+    check_transaction_state::<P, V, SW, VSW, Storage>(
+        &*db.lock().await,
+        &transaction,
+        &public_input,
+    )?;
+
+    if !Proof::verify(vk, public_input.to_vec(), transaction.proof.clone()) {
+        return Err(TransactionError::InvalidProof);
+    }
+
+    let mut db = db.lock().await;
+    check_transaction_state::<P, V, SW, VSW, Storage>(&*db, &transaction, &public_input)?;
+    Ok(db.insert_transaction(transaction)?)
+}
+
+#[prover_bounds]
+fn get_verifying_key<P, V, SW, VSW, Proof>(
+    prover: &Proof,
+    transaction: &Transaction<V>,
+) -> Result<VerifyingKey<V>, TransactionError>
+where
+    Proof: SequencerProver<V, VSW, P, SW>,
+{
+    let circuit_type = &transaction.circuit_type;
+    let (vk, _) = prover
+        .get_vk(circuit_type.clone())
+        .ok_or(TransactionError::UnknownCircuit(circuit_type.clone()))?;
+    Ok(vk)
+}
+
+#[client_bounds]
+fn get_public_input<P, V, VSW>(
+    transaction: &Transaction<V>,
+) -> Result<ClientPubInput<V::ScalarField>, TransactionError> {
+    let circuit_type = &transaction.circuit_type;
+    let commitment_nullifier_count = select_client_circuits_sequencer::<P, V, VSW>()
+        .iter()
+        .find(|c| c.get_circuit_type() == *circuit_type)
+        .map(|c| c.get_commitment_and_nullifier_count())
+        .ok_or(TransactionError::UnknownCircuit(circuit_type.clone()))?;
+
+    let public_inputs = ClientPubInput::from(transaction).to_vec();
+    ClientPubInput::new(public_inputs, commitment_nullifier_count)
+        .map_err(|e| TransactionError::InvalidPublicInputs(e.to_string()))
+}
+
+// Checks against the mempool and the global state, cheap enough to run while
+// holding the storage lock
+#[prover_bounds]
+fn check_transaction_state<P, V, SW, VSW, Storage>(
+    db: &Storage,
+    transaction: &Transaction<V>,
+    public_input: &ClientPubInput<V::ScalarField>,
+) -> Result<(), TransactionError>
+where
+    Storage: TransactionStorage<V>
+        + GlobalStateStorage<
+            CommitmentTree = Tree<V::BaseField, 8>,
+            VkTree = Tree<V::BaseField, 8>,
+            NullifierTree = IndexedMerkleTree<V::BaseField, 32>,
+        > + BlockStorage<V::ScalarField>,
+{
+    db.check_transaction(transaction)?;
+
+    for (i, nullifier) in public_input.nullifiers.iter().enumerate() {
+        // Mints fill their nullifier slot with zero
+        if nullifier.is_zero() {
+            continue;
+        }
+        if public_input.nullifiers[..i].contains(nullifier) {
+            return Err(TransactionError::DuplicatedNullifier(nullifier.to_string()));
+        }
+        if db.is_nullifier_spent(field_switching(nullifier)) {
+            return Err(TransactionError::NullifierAlreadySpent(
+                nullifier.to_string(),
+            ));
+        }
+        let root = public_input.commitment_root[i];
+        if !db.has_commitment_root(root) {
+            return Err(TransactionError::UnknownCommitmentRoot(root.to_string()));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::transaction::TransactionStatus;
+    use crate::services::prover::in_mem_sequencer_prover::InMemProver;
+    use crate::services::storage::in_mem_sequencer_storage::InMemStorage;
+    use crate::utils::test_transactions::{spending, MINT};
+    use common::structs::{Block, Commitment};
+    use curves::pallas::PallasConfig;
+    use curves::vesta::{Fr, VestaConfig};
+    use trees::NonMembershipTree;
+
+    type TestProver = InMemProver<VestaConfig, VestaConfig, PallasConfig, PallasConfig>;
+
+    // Transfers reuse the mint verifying key: every check on their nullifiers
+    // runs before the proof is verified
+    fn prover() -> TestProver {
+        let mut prover = TestProver::new();
+        for circuit_type in [
+            CircuitType::Mint(1),
+            CircuitType::Transfer(1, 1),
+            CircuitType::Transfer(1, 2),
+        ] {
+            prover.store_vk(circuit_type, (MINT.0.clone(), 0));
+        }
+        prover
+    }
+
+    fn storage_with_root(root: Fr) -> InMemStorage {
+        let mut db = InMemStorage::new();
+        db.insert_block(Block {
+            commitment_root: root,
+            ..Default::default()
+        });
+        db
+    }
+
+    async fn submit(
+        db: InMemStorage,
+        prover: TestProver,
+        transaction: &Transaction<VestaConfig>,
+    ) -> (Result<(), TransactionError>, InMemStorage) {
+        let db = Arc::new(Mutex::new(db));
+        let result = submit_transaction_process::<
+            PallasConfig,
+            VestaConfig,
+            PallasConfig,
+            VestaConfig,
+            _,
+            _,
+        >(
+            db.clone(),
+            Arc::new(Mutex::new(prover)),
+            transaction.clone(),
+        )
+        .await;
+        let db = Arc::try_unwrap(db).ok().unwrap().into_inner();
+        (result, db)
+    }
+
+    // Refused transactions are remembered as rejected
+    fn assert_rejected(db: &InMemStorage, transaction: &Transaction<VestaConfig>) {
+        assert!(matches!(
+            db.get_transaction_status(&transaction_hash(transaction)),
+            Some(TransactionStatus::Rejected { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_valid_transaction() {
+        let (result, db) = submit(InMemStorage::new(), prover(), &MINT.1).await;
+        assert_eq!(result, Ok(()));
+        assert_eq!(db.get_mempool_size(), 1);
+        assert_eq!(
+            db.get_transaction_status(&transaction_hash(&MINT.1)),
+            Some(TransactionStatus::Pending)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unknown_circuit() {
+        let (result, db) = submit(InMemStorage::new(), TestProver::new(), &MINT.1).await;
+        assert_eq!(
+            result,
+            Err(TransactionError::UnknownCircuit(CircuitType::Mint(1)))
+        );
+        assert_rejected(&db, &MINT.1);
+        assert_eq!(db.get_mempool_size(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_public_inputs() {
+        let mut transaction = MINT.1.clone();
+        transaction.ciphertexts.pop();
+        let (result, db) = submit(InMemStorage::new(), prover(), &transaction).await;
+        assert!(matches!(
+            result,
+            Err(TransactionError::InvalidPublicInputs(_))
+        ));
+        assert_rejected(&db, &transaction);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_proof() {
+        let mut transaction = MINT.1.clone();
+        transaction.commitments[0] = Commitment(transaction.commitments[0].0 + Fr::from(1u64));
+        let (result, db) = submit(InMemStorage::new(), prover(), &transaction).await;
+        assert_eq!(result, Err(TransactionError::InvalidProof));
+        assert_rejected(&db, &transaction);
+        assert_eq!(db.get_mempool_size(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_duplicated_transaction() {
+        let mut db = InMemStorage::new();
+        let hash = db.insert_transaction(MINT.1.clone()).unwrap();
+        let (result, db) = submit(db, prover(), &MINT.1).await;
+        assert_eq!(
+            result,
+            Err(TransactionError::DuplicatedTransaction(hash.clone()))
+        );
+        // The pending transaction with the same hash is not marked rejected
+        assert_eq!(
+            db.get_transaction_status(&hash),
+            Some(TransactionStatus::Pending)
+        );
+        assert_eq!(db.get_mempool_size(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_duplicated_nullifier() {
+        let root = Fr::from(7u64);
+        let transaction = spending(&[5, 5], root);
+        let (result, db) = submit(storage_with_root(root), prover(), &transaction).await;
+        assert_eq!(
+            result,
+            Err(TransactionError::DuplicatedNullifier(
+                Fr::from(5u64).to_string()
+            ))
+        );
+        assert_rejected(&db, &transaction);
+    }
+
+    #[tokio::test]
+    async fn test_nullifier_already_spent() {
+        let root = Fr::from(7u64);
+        let mut db = storage_with_root(root);
+        db.nullifier_tree
+            .update_low_nullifier(curves::pallas::Fr::from(5u64));
+        let transaction = spending(&[5], root);
+        let (result, db) = submit(db, prover(), &transaction).await;
+        assert_eq!(
+            result,
+            Err(TransactionError::NullifierAlreadySpent(
+                Fr::from(5u64).to_string()
+            ))
+        );
+        assert_rejected(&db, &transaction);
+    }
+
+    #[tokio::test]
+    async fn test_nullifier_pending() {
+        let root = Fr::from(7u64);
+        let mut db = storage_with_root(root);
+        let pending = db.insert_transaction(spending(&[5], root)).unwrap();
+
+        let mut transaction = spending(&[5], root);
+        transaction.ciphertexts[0] += Fr::from(1u64);
+        let (result, db) = submit(db, prover(), &transaction).await;
+        assert_eq!(
+            result,
+            Err(TransactionError::NullifierPending(
+                Fr::from(5u64).to_string()
+            ))
+        );
+        assert_rejected(&db, &transaction);
+        assert_eq!(
+            db.get_transaction_status(&pending),
+            Some(TransactionStatus::Pending)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unknown_commitment_root() {
+        let root = Fr::from(7u64);
+        let transaction = spending(&[5], Fr::from(8u64));
+        let (result, db) = submit(storage_with_root(root), prover(), &transaction).await;
+        assert_eq!(
+            result,
+            Err(TransactionError::UnknownCommitmentRoot(
+                Fr::from(8u64).to_string()
+            ))
+        );
+        assert_rejected(&db, &transaction);
+    }
+}
@@ -8,7 +8,9 @@ use common::crypto::poseidon::constants::PoseidonParams;
 use jf_primitives::rescue::RescueParameter;
 use jf_relation::gadgets::ecc::SWToTEConParam;
 use plonk_prover::client::{
-    circuits::{mint::MintCircuit, transfer::TransferCircuit},
+    circuits::{
+        mint::MintCircuit, swap::SwapCircuit, transfer::TransferCircuit, withdraw::WithdrawCircuit,
+    },
     ClientPlonkCircuit,
 };
 use plonk_prover::primitives::circuits::kem_dem::KemDemParams;
@@ -23,9 +25,13 @@ pub fn select_client_circuits_sequencer<P, V, VSW>() -> Vec<Box<dyn ClientPlonkC
         Box::new(MintCircuit::<1>::new()),
         Box::new(MintCircuit::<2>::new()),
         Box::new(TransferCircuit::<1, 1, DEPTH>::new()),
+        Box::new(TransferCircuit::<2, 1, DEPTH>::new()),
         Box::new(TransferCircuit::<1, 2, DEPTH>::new()),
         Box::new(TransferCircuit::<2, 2, DEPTH>::new()),
         Box::new(TransferCircuit::<2, 3, DEPTH>::new()),
+        Box::new(SwapCircuit::<DEPTH>::new()),
+        Box::new(WithdrawCircuit::<1, DEPTH>::new()),
+        Box::new(WithdrawCircuit::<2, DEPTH>::new()),
     ];
     circuit_info
 }
@@ -1 +1,3 @@
 pub mod circuits;
+#[cfg(test)]
+pub(crate) mod test_transactions;
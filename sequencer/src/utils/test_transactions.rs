@@ -0,0 +1,62 @@
+use ark_ec::pairing::Pairing;
+use common::structs::{CircuitType, Nullifier, Transaction};
+use curves::pallas::PallasConfig;
+use curves::vesta::{Fr, VestaConfig};
+use jf_plonk::{
+    nightfall::{ipa_structs::VerifyingKey, PlonkIpaSnark},
+    transcript::RescueTranscript,
+};
+use jf_relation::Circuit;
+use once_cell::sync::Lazy;
+use plonk_prover::client::circuits::mint::{self, MintCircuit};
+use plonk_prover::client::structs::ClientPubInput;
+use rand::SeedableRng;
+use rand_chacha::ChaChaRng;
+
+// A proven mint, shared by the tests as proving is slow
+pub(crate) static MINT: Lazy<(VerifyingKey<VestaConfig>, Transaction<VestaConfig>)> =
+    Lazy::new(|| {
+        let circuit = MintCircuit::<1>::new().as_circuit::<PallasConfig, VestaConfig, _>();
+        let (pk, vk) = circuit.generate_keys().unwrap();
+        let inputs =
+            mint::utils::build_random_inputs::<PallasConfig, VestaConfig, _, 1>(None).unwrap();
+        let plonk_circuit = circuit.to_plonk_circuit(inputs).unwrap();
+        let (proof, g_polys, _) =
+            PlonkIpaSnark::<VestaConfig>::prove_for_partial::<
+                _,
+                _,
+                RescueTranscript<<VestaConfig as Pairing>::BaseField>,
+            >(&mut ChaChaRng::from_entropy(), &plonk_circuit, &pk, None)
+            .unwrap();
+        let public_input =
+            ClientPubInput::new(plonk_circuit.public_input().unwrap(), (1, 1)).unwrap();
+        let mut transaction = Transaction::new(
+            public_input
+                .commitments
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+            public_input
+                .nullifiers
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+            public_input.ciphertexts,
+            proof,
+            g_polys,
+            public_input.ephemeral_public_key,
+            public_input.swap_field,
+            CircuitType::Mint(1),
+        );
+        transaction.set_commitment_root(public_input.commitment_root);
+        (vk, transaction)
+    });
+
+// The mint turned into a transfer spending `nullifiers` against `root`
+pub(crate) fn spending(nullifiers: &[u64], root: Fr) -> Transaction<VestaConfig> {
+    let mut transaction = MINT.1.clone();
+    transaction.circuit_type = CircuitType::Transfer(1, nullifiers.len());
+    transaction.nullifiers = nullifiers.iter().map(|n| Nullifier(Fr::from(*n))).collect();
+    transaction.commitment_root = vec![root; nullifiers.len()];
+    transaction
+}
@@ -119,6 +119,32 @@ impl<F: PrimeField + PoseidonParams<Field = F>, const H: usize> IndexedMerkleTre
     pub fn root(&self) -> F {
         self.root
     }
+
+    // Indexed nodes of the tree, sorted by value. Together with the leaf count
+    // they are all `from_nodes` needs to rebuild the tree.
+    pub fn nodes(&self) -> Vec<SortedIndexedNode<F>> {
+        self.sorted_vec.clone()
+    }
+
+    // Rebuilds a tree from the nodes returned by `nodes`. Every leaf below
+    // `leaf_count` must have a node.
+    pub fn from_nodes(nodes: Vec<SortedIndexedNode<F>>, leaf_count: u64) -> Option<Self> {
+        let mut leaf_hashes = vec![None; leaf_count as usize];
+        for node in nodes.iter() {
+            *leaf_hashes.get_mut(node.tree_index)? = Some(Self::leaf_hash(node.node));
+        }
+        let leaf_hashes = leaf_hashes.into_iter().collect::<Option<Vec<_>>>()?;
+        let mut sorted_vec = nodes;
+        sorted_vec.sort_by(|a, b| a.node.value.cmp(&b.node.value));
+        let mut tree = Self {
+            inner: HashMap::with_capacity(leaf_count as usize * 2 + 1),
+            sorted_vec,
+            leaf_count,
+            root: Default::default(),
+        };
+        tree.root = tree.add_leaves(leaf_hashes);
+        Some(tree)
+    }
 }
 
 pub trait NonMembershipTree<const H: usize>: MembershipTree<H> {
@@ -97,4 +97,23 @@ mod test {
                 });
         assert_eq!(witness_hash, indexed_tree.root);
     }
+
+    #[test]
+    fn test_from_nodes() {
+        let mut tree = IndexedMerkleTree::<ark_bn254::Fr, 8>::new();
+        for value in [5u64, 2, 9] {
+            tree.append_leaf(ark_bn254::Fr::from(value));
+        }
+        tree.update_low_nullifier(ark_bn254::Fr::from(7u64));
+
+        let rebuilt =
+            IndexedMerkleTree::<ark_bn254::Fr, 8>::from_nodes(tree.nodes(), tree.leaf_count())
+                .unwrap();
+        assert_eq!(rebuilt.root(), tree.root());
+        assert_eq!(
+            rebuilt.non_membership_witness(ark_bn254::Fr::from(6u64)),
+            tree.non_membership_witness(ark_bn254::Fr::from(6u64))
+        );
+        assert!(IndexedMerkleTree::<ark_bn254::Fr, 8>::from_nodes(tree.nodes(), 5).is_none());
+    }
 }